@@ -0,0 +1,107 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Round trip latency and CPU time over loopback TCP, with and without busy polling.
+//!
+//! ```ignore
+//! cargo bench --bench busy_poll
+//! ```
+//!
+//! `SO_BUSY_POLL` is only raised with `CAP_NET_ADMIN`. Without it, the user space spinning
+//! is still measured. Both ends spin on threads of their own, so results only mean something
+//! with at least two idle cores. On one, a spinning end just keeps the other from running.
+
+#![cfg_attr(not(unix), allow(dead_code, unused_imports))]
+
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use simple_stream::frame::{SimpleFrame, SimpleFrameBuilder};
+#[cfg(unix)]
+use simple_stream::TcpOptions;
+use simple_stream::{Blocking, Plain};
+
+const ROUND_TRIPS: usize = 20_000;
+
+#[cfg(unix)]
+fn main() {
+    for busy_poll in [
+        None,
+        Some(Duration::from_micros(50)),
+        Some(Duration::from_micros(200)),
+    ] {
+        run(busy_poll);
+    }
+}
+
+#[cfg(not(unix))]
+fn main() {
+    println!("busy_poll only runs on unix");
+}
+
+#[cfg(unix)]
+fn run(busy_poll: Option<Duration>) {
+    let options = TcpOptions {
+        busy_poll,
+        ..TcpOptions::default()
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let echo = thread::spawn(move || {
+        let (socket, _) = listener.accept().unwrap();
+        socket.set_nodelay(true).unwrap();
+        let mut stream = Plain::<TcpStream, SimpleFrameBuilder>::new(socket);
+        stream.set_tcp_options(options);
+        for _ in 0..ROUND_TRIPS {
+            let frame = stream.b_recv().unwrap();
+            stream.b_send(&*frame).unwrap();
+        }
+    });
+
+    let socket = TcpStream::connect(addr).unwrap();
+    socket.set_nodelay(true).unwrap();
+    let mut stream = Plain::<TcpStream, SimpleFrameBuilder>::new(socket);
+    stream.set_tcp_options(options);
+
+    let frame = SimpleFrame::new(&[0xAB; 64]);
+    let mut samples = Vec::<Duration>::with_capacity(ROUND_TRIPS);
+    let cpu_before = cpu_time();
+    let started = Instant::now();
+    for _ in 0..ROUND_TRIPS {
+        let sent_at = Instant::now();
+        stream.b_send(&frame).unwrap();
+        stream.b_recv().unwrap();
+        samples.push(sent_at.elapsed());
+    }
+    let elapsed = started.elapsed();
+    let cpu = cpu_time() - cpu_before;
+    echo.join().unwrap();
+
+    samples.sort_unstable();
+    let percentile = |p: usize| samples[(samples.len() - 1) * p / 100];
+    println!(
+        "busy_poll {:>8}: p50 {:>9.1?}  p99 {:>9.1?}  cpu {:>5.1}%",
+        busy_poll.map_or("off".to_string(), |b| format!("{:?}", b)),
+        percentile(50),
+        percentile(99),
+        // Both ends run in this process, so this is out of two cores
+        cpu.as_secs_f64() / elapsed.as_secs_f64() * 100.0
+    );
+}
+
+/// Returns the user and system CPU time used by this process so far.
+#[cfg(unix)]
+fn cpu_time() -> Duration {
+    let mut usage: libc::rusage = unsafe { std::mem::zeroed() };
+    unsafe { libc::getrusage(libc::RUSAGE_SELF, &mut usage) };
+
+    let as_duration =
+        |tv: libc::timeval| Duration::new(tv.tv_sec as u64, (tv.tv_usec as u32) * 1000);
+    as_duration(usage.ru_utime) + as_duration(usage.ru_stime)
+}
@@ -0,0 +1,109 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Heap allocations made by non-blocking calls that would block, polled at 100k calls a
+//! second, against building the `WouldBlock` error with a message as streams used to.
+//!
+//! ```ignore
+//! cargo bench --bench would_block
+//! ```
+//!
+//! Every allocation in the process is counted, so the numbers are exact. The pacing only
+//! matters for the allocation rate reported; a poll that allocates does so at any rate.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::hint::black_box;
+use std::io;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use simple_stream::frame::{Frame, SimpleFrame, SimpleFrameBuilder};
+use simple_stream::testing::{MockStep, MockStream};
+use simple_stream::{NonBlocking, Plain};
+
+const POLLS_PER_SEC: u32 = 100_000;
+const POLLS: u32 = POLLS_PER_SEC;
+
+/// The system allocator, counting every allocation made through it.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(layout.size(), Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        ALLOCATED_BYTES.fetch_add(new_size, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn main() {
+    run("io::Error::new(WouldBlock, ..)", || {
+        black_box(io::Error::new(io::ErrorKind::WouldBlock, "WouldBlock"));
+    });
+
+    let (local, _remote) = MockStream::pair();
+    let mut stream = Plain::<_, SimpleFrameBuilder>::new(local);
+    run("nb_recv", || {
+        black_box(stream.nb_recv()).unwrap_err();
+    });
+
+    let mut frames = Vec::<Box<dyn Frame>>::with_capacity(8);
+    run("nb_recv_into", || {
+        black_box(stream.nb_recv_into(&mut frames)).unwrap_err();
+    });
+
+    // Leaves a frame queued behind a socket that never becomes writable
+    let steps = vec![MockStep::WouldBlock; POLLS as usize + 1];
+    stream.get_mut().script_writes(&steps);
+    stream.nb_send(&SimpleFrame::new(&[0xAB; 64])).unwrap_err();
+    run("nb_flush", || {
+        assert!(!black_box(stream.nb_flush()).unwrap());
+    });
+}
+
+/// Calls `poll` `POLLS` times, paced to `POLLS_PER_SEC`, and prints what it allocated.
+fn run<F: FnMut()>(name: &str, mut poll: F) {
+    let interval = Duration::from_secs(1) / POLLS_PER_SEC;
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed);
+
+    let started = Instant::now();
+    let mut next_poll = started;
+    for _ in 0..POLLS {
+        while Instant::now() < next_poll {
+            std::hint::spin_loop();
+        }
+        poll();
+        next_poll += interval;
+    }
+    let elapsed = started.elapsed();
+
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - allocations;
+    let bytes = ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes;
+    println!(
+        "{:<32} {:>5.2} allocs/poll  {:>7.1} bytes/poll  {:>9.0} allocs/sec",
+        name,
+        allocations as f64 / POLLS as f64,
+        bytes as f64 / POLLS as f64,
+        allocations as f64 / elapsed.as_secs_f64()
+    );
+}
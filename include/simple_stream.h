@@ -0,0 +1,79 @@
+/*
+ * Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+ *
+ * This Source Code Form is subject to the terms of the
+ * Mozilla Public License, v. 2.0. If a copy of the MPL was not
+ * distributed with this file, You can obtain one at
+ * http://mozilla.org/MPL/2.0/.
+ */
+
+/*
+ * C interface to the simple-stream framing core, built with:
+ *
+ *     cargo rustc --release --features ffi --crate-type cdylib
+ *
+ * Framings are selected by name: "simple", "checksum32", "headered", "length_prefixed32",
+ * "varint", "newline", "crlf", "json", "cobs" or "websocket".
+ */
+
+#ifndef SIMPLE_STREAM_H
+#define SIMPLE_STREAM_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SS_ABI_VERSION 2
+
+/* Returned by ss_decoder_error: why the bytes fed can never become a frame. */
+#define SS_OK 0
+#define SS_BAD_GUARD 1
+#define SS_BAD_CHECKSUM 2
+#define SS_MALFORMED 3
+
+typedef struct SsDecoder ss_decoder;
+typedef struct SsFrame ss_frame;
+
+/* Returns the ABI version the library was built with. */
+uint32_t ss_abi_version(void);
+
+/* Creates a decoder for the named framing, or returns NULL if the name is not known. */
+ss_decoder *ss_decoder_new(const char *framing);
+void ss_decoder_free(ss_decoder *decoder);
+
+/* Buffers len bytes at data. Returns 0 on success, -1 on invalid arguments or once the
+ * decoder failed. */
+int ss_decoder_feed(ss_decoder *decoder, const uint8_t *data, size_t len);
+
+/* Returns the next complete frame, or NULL until more bytes are fed. Also returns NULL once
+ * the bytes fed can never become a frame, e.g. because a checksum is wrong. The decoder then
+ * drops them, fails from then on, and ss_decoder_error reports why. */
+ss_frame *ss_decoder_next(ss_decoder *decoder);
+
+/* Returns SS_OK, or why the decoder failed: SS_BAD_GUARD, SS_BAD_CHECKSUM or SS_MALFORMED.
+ * Returns -1 if decoder is NULL. */
+int ss_decoder_error(const ss_decoder *decoder);
+
+/* Returns how many fed bytes are not part of a decoded frame yet. */
+size_t ss_decoder_buffered(const ss_decoder *decoder);
+
+/* Return the frame's payload, and the frame as it was on the wire. Both live as long as
+ * the frame. */
+const uint8_t *ss_frame_payload(const ss_frame *frame, size_t *len);
+const uint8_t *ss_frame_bytes(const ss_frame *frame, size_t *len);
+void ss_frame_free(ss_frame *frame);
+
+/* Encodes len bytes at payload in the named framing. Returns NULL if the name is not
+ * known, or the payload is longer than the framing allows or contains its delimiter. The
+ * result is freed with ss_buffer_free, passing the length stored in out_len. */
+uint8_t *ss_encode(const char *framing, const uint8_t *payload, size_t len, size_t *out_len);
+void ss_buffer_free(uint8_t *buf, size_t len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SIMPLE_STREAM_H */
@@ -0,0 +1,306 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Async counterparts of `Plain` and `Secure` for use with Tokio, enabled by the `tokio`
+//! feature.
+//!
+//! ```ignore
+//! use simple_stream::frame::{SimpleFrame, SimpleFrameBuilder};
+//! use simple_stream::AsyncPlain;
+//! use tokio::net::TcpStream;
+//!
+//! let stream = TcpStream::connect("127.0.0.1:9000").await?;
+//! let mut stream = AsyncPlain::<_, SimpleFrameBuilder>::new(stream);
+//!
+//! stream.send(&SimpleFrame::new(&[1, 2, 3, 4])).await?;
+//! let frame = stream.recv().await?;
+//! ```
+
+use std::io::{self, Error, ErrorKind};
+use std::marker::PhantomData;
+use std::pin::Pin;
+
+use openssl::ssl::{self, Ssl};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio_openssl::SslStream;
+
+use crate::buffer::RecvBuffer;
+use crate::close::CloseReason;
+use crate::frame::{reserve_frame, BuilderDecoder, Frame, FrameBuilder};
+use crate::resync::decode_unsynced;
+
+const BUF_SIZE: usize = 1024;
+
+/// Plain text stream over an `AsyncRead + AsyncWrite` transport.
+pub struct AsyncPlain<S, FB>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    FB: FrameBuilder,
+{
+    inner: S,
+    rx_buf: RecvBuffer,
+    close_reason: Option<CloseReason>,
+    phantom: PhantomData<FB>,
+}
+
+/// Encrypted stream over an `AsyncRead + AsyncWrite` transport, using OpenSSL.
+pub struct AsyncSecure<S, FB>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    FB: FrameBuilder,
+{
+    inner: AsyncPlain<SslStream<S>, FB>,
+}
+
+impl<S, FB> AsyncPlain<S, FB>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    FB: FrameBuilder,
+{
+    /// Creates a new plain text stream.
+    pub fn new(stream: S) -> AsyncPlain<S, FB> {
+        AsyncPlain {
+            inner: stream,
+            rx_buf: RecvBuffer::with_capacity(BUF_SIZE),
+            close_reason: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns why the connection terminated, or `None` while it is still open.
+    pub fn close_reason(&self) -> Option<&CloseReason> {
+        self.close_reason.as_ref()
+    }
+
+    /// Returns the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns the underlying stream mutably. Reading from or writing to it directly corrupts
+    /// the framing.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Waits until a complete frame has been read, or an `std::io::Error` has occurred.
+    ///
+    /// Cancel safe: if the future is dropped before completing, no bytes are lost and the
+    /// next call picks up where this one left off.
+    pub async fn recv(&mut self) -> io::Result<Box<dyn Frame>> {
+        // Empty anything that is in our buffer already from any previous reads
+        if let Some(boxed_frame) = self.decode()? {
+            debug!("Complete frame read: {}", boxed_frame.fmt_summary());
+            return Ok(boxed_frame);
+        }
+
+        self.ensure_open()?;
+
+        loop {
+            let mut buf = [0u8; BUF_SIZE];
+            let num_read = match self.inner.read(&mut buf).await {
+                Ok(0) => return Err(self.close(CloseReason::PeerClosed)),
+                Ok(num_read) => num_read,
+                Err(e) => return Err(self.fail(e)),
+            };
+            trace!("Read {} byte(s)", num_read);
+            self.rx_buf.extend_from_slice(&buf[0..num_read]);
+            reserve_frame(&BuilderDecoder::of::<FB>(), &mut self.rx_buf);
+
+            if let Some(boxed_frame) = self.decode()? {
+                debug!("Complete frame read: {}", boxed_frame.fmt_summary());
+                return Ok(boxed_frame);
+            }
+        }
+    }
+
+    /// Waits until `frame` has been written and the underlying stream flushed, or an
+    /// `std::io::Error` has occurred.
+    ///
+    /// Not cancel safe: if the future is dropped before completing, part of the frame may
+    /// have been written, and the stream should be closed.
+    pub async fn send(&mut self, frame: &dyn Frame) -> io::Result<()> {
+        self.ensure_open()?;
+
+        let buf = frame.to_bytes();
+        if let Err(e) = self.inner.write_all(&buf[..]).await {
+            return Err(self.fail(e));
+        }
+        if let Err(e) = self.inner.flush().await {
+            return Err(self.fail(e));
+        }
+
+        trace!("Wrote {} byte(s)", buf.len());
+        Ok(())
+    }
+
+    /// Shuts down the write half of the underlying stream. Every later call fails with
+    /// `CloseReason::LocalShutdown`.
+    pub async fn shutdown(&mut self) -> io::Result<()> {
+        if self.close_reason.is_some() {
+            return Ok(());
+        }
+
+        self.close_reason = Some(CloseReason::LocalShutdown);
+        self.inner.shutdown().await
+    }
+
+    /// Decodes the next frame from `rx_buf`, closing the stream if it holds bytes that can
+    /// never become a frame.
+    fn decode(&mut self) -> io::Result<Option<Box<dyn Frame>>> {
+        decode_unsynced(&mut BuilderDecoder::of::<FB>(), &mut self.rx_buf).inspect_err(|_| {
+            self.close(CloseReason::ProtocolError);
+        })
+    }
+
+    fn ensure_open(&self) -> Result<(), Error> {
+        match self.close_reason {
+            Some(ref reason) => Err(reason.to_io_error()),
+            None => Ok(()),
+        }
+    }
+
+    fn close(&mut self, reason: CloseReason) -> Error {
+        debug!("Stream closed: {}", reason);
+        let err = reason.to_io_error();
+        self.close_reason = Some(reason);
+        err
+    }
+
+    /// Closes the stream if `e` terminated the connection, returning the error to report.
+    fn fail(&mut self, e: Error) -> Error {
+        match e.kind() {
+            ErrorKind::Interrupted => e,
+            _ => self.close(CloseReason::TransportError(e)),
+        }
+    }
+}
+
+impl<S, FB> AsyncSecure<S, FB>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    FB: FrameBuilder,
+{
+    /// Performs the client side of the TLS handshake over `stream`.
+    pub async fn connect(ssl: Ssl, stream: S) -> io::Result<AsyncSecure<S, FB>> {
+        let mut stream = SslStream::new(ssl, stream).map_err(io::Error::other)?;
+        Pin::new(&mut stream)
+            .connect()
+            .await
+            .map_err(from_ssl_error)?;
+
+        Ok(AsyncSecure {
+            inner: AsyncPlain::new(stream),
+        })
+    }
+
+    /// Performs the server side of the TLS handshake over `stream`.
+    pub async fn accept(ssl: Ssl, stream: S) -> io::Result<AsyncSecure<S, FB>> {
+        let mut stream = SslStream::new(ssl, stream).map_err(io::Error::other)?;
+        Pin::new(&mut stream)
+            .accept()
+            .await
+            .map_err(from_ssl_error)?;
+
+        Ok(AsyncSecure {
+            inner: AsyncPlain::new(stream),
+        })
+    }
+
+    /// Returns why the connection terminated, or `None` while it is still open.
+    pub fn close_reason(&self) -> Option<&CloseReason> {
+        self.inner.close_reason()
+    }
+
+    /// Returns the TLS session.
+    pub fn ssl(&self) -> &ssl::SslRef {
+        self.inner.get_ref().ssl()
+    }
+
+    /// Returns the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        self.inner.get_ref().get_ref()
+    }
+
+    /// Same as `AsyncPlain::recv`. A close_notify alert from the peer is reported as
+    /// `CloseReason::PeerClosed`, as is the transport closing without one.
+    pub async fn recv(&mut self) -> io::Result<Box<dyn Frame>> {
+        self.inner.recv().await
+    }
+
+    /// Same as `AsyncPlain::send`.
+    pub async fn send(&mut self, frame: &dyn Frame) -> io::Result<()> {
+        self.inner.send(frame).await
+    }
+
+    /// Sends a close_notify alert to the peer. Every later call fails with
+    /// `CloseReason::LocalShutdown`.
+    pub async fn shutdown(&mut self) -> io::Result<()> {
+        self.inner.shutdown().await
+    }
+}
+
+fn from_ssl_error(e: ssl::Error) -> io::Error {
+    match e.into_io_error() {
+        Ok(e) => e,
+        Err(e) => io::Error::new(ErrorKind::InvalidData, e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    use crate::frame::{SimpleFrame, SimpleFrameBuilder};
+
+    /// Polls `future` until it completes. Only for futures over in-memory transports, which
+    /// make progress on every poll.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn frames_arrive_until_the_peer_closes() {
+        let (a, b) = tokio::io::duplex(4096);
+        let mut local = AsyncPlain::<_, SimpleFrameBuilder>::new(a);
+        let mut remote = AsyncPlain::<_, SimpleFrameBuilder>::new(b);
+
+        block_on(remote.send(&SimpleFrame::new(b"one"))).unwrap();
+        block_on(remote.send(&SimpleFrame::new(b"two"))).unwrap();
+        assert_eq!(block_on(local.recv()).unwrap().payload(), b"one");
+        assert_eq!(block_on(local.recv()).unwrap().payload(), b"two");
+
+        drop(remote);
+        assert!(block_on(local.recv()).is_err());
+        assert!(matches!(
+            local.close_reason(),
+            Some(CloseReason::PeerClosed)
+        ));
+    }
+
+    #[test]
+    fn bytes_that_are_not_a_frame_close_the_stream() {
+        let (a, mut b) = tokio::io::duplex(4096);
+        let mut local = AsyncPlain::<_, SimpleFrameBuilder>::new(a);
+
+        block_on(b.write_all(&[0xff; 8])).unwrap();
+        let e = block_on(local.recv()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::InvalidData);
+        assert!(matches!(
+            local.close_reason(),
+            Some(CloseReason::ProtocolError)
+        ));
+    }
+}
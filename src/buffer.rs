@@ -0,0 +1,181 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! The receive buffer streams decode frames from.
+//!
+//! ```ignore
+//!           consumed           unconsumed            spare
+//! +------------------------+------------------+-----------------+
+//! |  frames already taken  | bytes to decode  |                 |
+//! +------------------------+------------------+-----------------+
+//!                          ^ cursor
+//! ```
+//!
+//! Taking a frame only moves the cursor. The consumed bytes are reclaimed by moving the
+//! unconsumed ones to the front once they are no more than what was consumed, so every byte is
+//! moved a bounded number of times however many frames each read carries.
+
+use std::mem;
+
+/// Bytes received but not yet decoded into frames, consumed from the front with a cursor.
+///
+/// `FrameBuilder::from_buffer` decodes from `as_slice()` and calls `consume` with the length of
+/// what it decoded, instead of removing it from a `Vec`.
+#[derive(Clone, Debug, Default)]
+pub struct RecvBuffer {
+    buf: Vec<u8>,
+    start: usize,
+}
+
+impl RecvBuffer {
+    /// Creates an empty buffer with room for `capacity` bytes.
+    pub fn with_capacity(capacity: usize) -> RecvBuffer {
+        RecvBuffer {
+            buf: Vec::with_capacity(capacity),
+            start: 0,
+        }
+    }
+
+    /// Returns the bytes not consumed yet.
+    pub fn as_slice(&self) -> &[u8] {
+        &self.buf[self.start..]
+    }
+
+    /// Returns how many bytes are not consumed yet.
+    pub fn len(&self) -> usize {
+        self.buf.len() - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Marks the first `len` unconsumed bytes as consumed.
+    ///
+    /// # Panics
+    ///
+    /// If fewer than `len` bytes are unconsumed.
+    pub fn consume(&mut self, len: usize) {
+        assert!(len <= self.len(), "Consumed more bytes than buffered");
+        self.start += len;
+        if self.start == self.buf.len() {
+            self.buf.clear();
+            self.start = 0;
+        }
+    }
+
+    /// Puts `bytes` back in front of the unconsumed bytes, e.g. those of a frame decoded
+    /// before finding out something after it has not arrived yet.
+    pub fn unconsume(&mut self, bytes: &[u8]) {
+        if self.start >= bytes.len() {
+            self.start -= bytes.len();
+            self.buf[self.start..(self.start + bytes.len())].copy_from_slice(bytes);
+        } else {
+            self.buf
+                .splice(self.start..self.start, bytes.iter().copied());
+        }
+    }
+
+    /// Consumes every byte.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.start = 0;
+    }
+
+    /// Appends `bytes` after the unconsumed bytes.
+    pub fn extend_from_slice(&mut self, bytes: &[u8]) {
+        if self.buf.len() + bytes.len() > self.buf.capacity() {
+            self.reclaim();
+        }
+        self.buf.extend_from_slice(bytes);
+    }
+
+    /// Makes room for at least `additional` more bytes.
+    pub fn reserve(&mut self, additional: usize) {
+        self.reclaim();
+        self.buf.reserve(additional);
+    }
+
+    /// Returns the unconsumed bytes as a `Vec`, for code removing decoded frames from the
+    /// front itself, as `FrameBuilder::from_bytes` does. The consumed bytes are dropped first.
+    pub fn as_vec_mut(&mut self) -> &mut Vec<u8> {
+        self.compact();
+        &mut self.buf
+    }
+
+    /// Takes the unconsumed bytes out, leaving the buffer empty.
+    pub fn take(&mut self) -> Vec<u8> {
+        self.compact();
+        mem::take(&mut self.buf)
+    }
+
+    /// Moves the unconsumed bytes to the front if no more of them than consumed ones remain,
+    /// which keeps the copying linear in the bytes received.
+    fn reclaim(&mut self) {
+        if self.start > 0 && self.start >= self.len() {
+            self.compact();
+        }
+    }
+
+    fn compact(&mut self) {
+        if self.start > 0 {
+            self.buf.drain(..self.start);
+            self.start = 0;
+        }
+    }
+}
+
+impl From<Vec<u8>> for RecvBuffer {
+    /// Creates a buffer holding `buf` unconsumed.
+    fn from(buf: Vec<u8>) -> RecvBuffer {
+        RecvBuffer { buf, start: 0 }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn consumed_bytes_are_reclaimed_before_growing() {
+        let mut buf = RecvBuffer::with_capacity(8);
+        buf.extend_from_slice(b"abcdef");
+        buf.consume(4);
+        assert_eq!(buf.as_slice(), b"ef");
+
+        // The two unconsumed bytes move to the front instead of the buffer reallocating
+        buf.extend_from_slice(b"ghijkl");
+        assert_eq!(buf.as_slice(), b"efghijkl");
+        assert_eq!(buf.start, 0);
+        assert_eq!(buf.buf.capacity(), 8);
+
+        buf.consume(8);
+        assert!(buf.is_empty());
+        assert_eq!(buf.buf.len(), 0);
+    }
+
+    #[test]
+    fn unconsumed_bytes_go_back_in_front() {
+        let mut buf = RecvBuffer::from(b"abcdef".to_vec());
+        buf.consume(3);
+        buf.unconsume(b"xy");
+        assert_eq!(buf.as_slice(), b"xydef");
+
+        // More than was consumed has to be spliced in
+        buf.unconsume(b"1234");
+        assert_eq!(buf.as_slice(), b"1234xydef");
+        assert_eq!(buf.take(), b"1234xydef");
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "Consumed more bytes than buffered")]
+    fn consuming_past_the_end_panics() {
+        let mut buf = RecvBuffer::from(vec![1, 2]);
+        buf.consume(3);
+    }
+}
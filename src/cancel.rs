@@ -0,0 +1,252 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::io;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Interrupts blocking calls from another thread.
+///
+/// Clones share the same state, so a token can be handed to a stream with
+/// `set_cancellation_token` and cancelled from any thread holding a clone. Once cancelled,
+/// every blocking receive, send, or `connect_cancellable` observing the token returns
+/// `ErrorKind::Interrupted` promptly, including those already waiting on the socket. The
+/// stream itself is left open. Cancellation cannot be undone.
+#[derive(Clone, Debug)]
+pub struct CancellationToken {
+    inner: Arc<Wakeup>,
+}
+
+/// A cancelled flag and a pipe that becomes readable once the flag is set, so threads
+/// waiting in `poll(2)` wake up.
+#[derive(Debug)]
+struct Wakeup {
+    cancelled: AtomicBool,
+    read_fd: RawFd,
+    write_fd: RawFd,
+}
+
+/// Readiness to wait for on a socket before a blocking call.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Interest {
+    Readable,
+    Writable,
+}
+
+/// A token attached to the socket a stream performs its blocking calls on.
+#[derive(Clone, Debug)]
+pub(crate) struct Cancellable {
+    fd: RawFd,
+    token: CancellationToken,
+}
+
+impl CancellationToken {
+    /// Creates a token that has not been cancelled.
+    pub fn new() -> io::Result<CancellationToken> {
+        let mut fds = [0 as RawFd; 2];
+        let result = unsafe { libc::pipe(fds.as_mut_ptr()) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let wakeup = Wakeup {
+            cancelled: AtomicBool::new(false),
+            read_fd: fds[0],
+            write_fd: fds[1],
+        };
+        for &fd in fds.iter() {
+            set_fd_flags(fd)?;
+        }
+
+        Ok(CancellationToken {
+            inner: Arc::new(wakeup),
+        })
+    }
+
+    /// Cancels every blocking call observing this token, now and in the future.
+    pub fn cancel(&self) {
+        if self.inner.cancelled.swap(true, Ordering::SeqCst) {
+            return;
+        }
+
+        // The pipe is never drained, so a single byte keeps it readable for good
+        let byte = 1u8;
+        let result = unsafe {
+            libc::write(
+                self.inner.write_fd,
+                &byte as *const u8 as *const libc::c_void,
+                1,
+            )
+        };
+        if result < 0 {
+            error!(
+                "Error waking cancelled calls: {}",
+                io::Error::last_os_error()
+            );
+        }
+
+        debug!("Cancellation requested");
+    }
+
+    /// Returns `true` once `cancel` has been called on this token or any of its clones.
+    pub fn is_cancelled(&self) -> bool {
+        self.inner.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// Waits until `fd` is ready for `interest`, failing with `ErrorKind::Interrupted` if the
+    /// token is cancelled first.
+    pub(crate) fn wait(&self, fd: RawFd, interest: Interest) -> io::Result<()> {
+        wait_ready(fd, interest, Some(self), None).map(|_| ())
+    }
+}
+
+impl Drop for Wakeup {
+    fn drop(&mut self) {
+        unsafe {
+            libc::close(self.read_fd);
+            libc::close(self.write_fd);
+        }
+    }
+}
+
+impl Cancellable {
+    pub(crate) fn new(fd: RawFd, token: CancellationToken) -> Cancellable {
+        Cancellable { fd, token }
+    }
+
+    /// Waits until the socket is ready for `interest` or the token is cancelled.
+    pub(crate) fn wait(&self, interest: Interest) -> io::Result<()> {
+        self.token.wait(self.fd, interest)
+    }
+
+    pub(crate) fn token(&self) -> &CancellationToken {
+        &self.token
+    }
+}
+
+/// Waits until `fd` is ready for `interest`, for up to `timeout` if set. Returns `false` if
+/// the timeout passed first, and fails with `ErrorKind::Interrupted` if `token` is cancelled
+/// first.
+pub(crate) fn wait_ready(
+    fd: RawFd,
+    interest: Interest,
+    token: Option<&CancellationToken>,
+    timeout: Option<Duration>,
+) -> io::Result<bool> {
+    let events = match interest {
+        Interest::Readable => libc::POLLIN,
+        Interest::Writable => libc::POLLOUT,
+    };
+    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+
+    loop {
+        if token.is_some_and(|token| token.is_cancelled()) {
+            return Err(cancelled());
+        }
+
+        let mut fds = [
+            libc::pollfd {
+                fd,
+                events,
+                revents: 0,
+            },
+            libc::pollfd {
+                fd: token.map_or(-1, |token| token.inner.read_fd),
+                events: libc::POLLIN,
+                revents: 0,
+            },
+        ];
+        // Rounded up, so the wait does not end just short of the deadline
+        let timeout_ms = match deadline {
+            Some(deadline) => deadline
+                .saturating_duration_since(Instant::now())
+                .as_nanos()
+                .div_ceil(1_000_000)
+                .min(libc::c_int::MAX as u128) as libc::c_int,
+            None => -1,
+        };
+        let result = unsafe { libc::poll(fds.as_mut_ptr(), 2, timeout_ms) };
+        if result < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+
+        if fds[1].revents != 0 {
+            return Err(cancelled());
+        }
+        // Errors and hangups are left for the following call to report
+        if fds[0].revents != 0 {
+            return Ok(true);
+        }
+        if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            return Ok(false);
+        }
+    }
+}
+
+fn cancelled() -> io::Error {
+    io::Error::new(io::ErrorKind::Interrupted, "Operation cancelled")
+}
+
+/// Makes `fd` non-blocking and close-on-exec.
+fn set_fd_flags(fd: RawFd) -> io::Result<()> {
+    unsafe {
+        let flags = libc::fcntl(fd, libc::F_GETFL);
+        if flags < 0 || libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        if libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+    use std::thread;
+
+    #[test]
+    fn cancelling_wakes_a_waiting_thread() {
+        let (local, _remote) = UnixStream::pair().unwrap();
+        let token = CancellationToken::new().unwrap();
+        let waiter = {
+            let token = token.clone();
+            thread::spawn(move || token.wait(local.as_raw_fd(), Interest::Readable))
+        };
+
+        thread::sleep(Duration::from_millis(20));
+        token.cancel();
+        let e = waiter.join().unwrap().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::Interrupted);
+        assert!(token.is_cancelled());
+    }
+
+    #[test]
+    fn waits_end_when_ready_or_timed_out() {
+        let (local, mut remote) = UnixStream::pair().unwrap();
+        let fd = local.as_raw_fd();
+        let token = CancellationToken::new().unwrap();
+
+        let timeout = Some(Duration::from_millis(10));
+        assert!(!wait_ready(fd, Interest::Readable, Some(&token), timeout).unwrap());
+        assert!(wait_ready(fd, Interest::Writable, Some(&token), timeout).unwrap());
+
+        remote.write_all(b"hi").unwrap();
+        assert!(wait_ready(fd, Interest::Readable, None, timeout).unwrap());
+    }
+}
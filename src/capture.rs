@@ -0,0 +1,311 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Records every frame a stream sends and receives to a file, and reads recordings back, so a
+//! framing problem seen once can be replayed offline or kept as a regression test.
+//!
+//! ```ignore
+//! stream.set_capture(Some(Capture::create("session.sscap")?));
+//!
+//! // Later, somewhere else
+//! let mut reader = CaptureReader::open("session.sscap")?;
+//! let frames = reader.replay::<SimpleFrameBuilder>(Direction::Received)?;
+//! ```
+//!
+//! ## Format
+//!
+//! A capture starts with the 5 bytes `SSCAP` and a version byte, currently 1, followed by
+//! one record per frame. Each record is, with integers big endian:
+//!
+//! * `u64`: when the frame was sent or received, in microseconds since the UNIX epoch.
+//! * `u8`: 0 if the frame was sent, 1 if it was received.
+//! * `u32`: the length of the frame.
+//! * The frame, encoded as it is on the wire before any TLS, and before receive transforms
+//!   were applied.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, IoSlice, Read, Write};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::frame::{Frame, FrameBuilder};
+
+const MAGIC: &[u8; 5] = b"SSCAP";
+const VERSION: u8 = 1;
+/// Length of a record before the frame.
+const RECORD_HEADER_LEN: usize = 13;
+
+/// Whether a captured frame was sent or received.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Sent,
+    Received,
+}
+
+/// A single captured frame.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CaptureRecord {
+    /// When the frame was queued to be sent, or decoded after being received.
+    pub timestamp: SystemTime,
+    pub direction: Direction,
+    /// The encoded frame.
+    pub bytes: Vec<u8>,
+}
+
+/// Handle to a capture file, set on a stream with `set_capture` on `Plain` or `Secure`.
+///
+/// Clones write to the same file, so several streams can share one capture. Once a write
+/// fails the error is logged and nothing more is recorded.
+#[derive(Clone)]
+pub struct Capture {
+    inner: Arc<Mutex<CaptureWriter>>,
+}
+
+struct CaptureWriter {
+    out: Box<dyn Write + Send>,
+    failed: bool,
+}
+
+/// Reads the records of a capture, in the order they were written.
+pub struct CaptureReader<R> {
+    inner: R,
+}
+
+impl Capture {
+    /// Creates a capture at `path`, replacing any file there.
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Capture> {
+        Capture::new(BufWriter::new(File::create(path)?))
+    }
+
+    /// Creates a capture written to `out`.
+    pub fn new<W: Write + Send + 'static>(mut out: W) -> io::Result<Capture> {
+        out.write_all(MAGIC)?;
+        out.write_all(&[VERSION])?;
+        Ok(Capture {
+            inner: Arc::new(Mutex::new(CaptureWriter {
+                out: Box::new(out),
+                failed: false,
+            })),
+        })
+    }
+
+    /// Flushes records buffered so far. They are also flushed once every handle is dropped.
+    pub fn flush(&self) -> io::Result<()> {
+        let mut writer = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        writer.out.flush()
+    }
+
+    pub(crate) fn sent(&self, buf: &[u8]) {
+        self.record(Direction::Sent, &[IoSlice::new(buf)]);
+    }
+
+    pub(crate) fn sent_vectored(&self, bufs: &[IoSlice]) {
+        self.record(Direction::Sent, bufs);
+    }
+
+    pub(crate) fn received(&self, frame: &dyn Frame) {
+        self.record(Direction::Received, &[IoSlice::new(&frame.to_bytes()[..])]);
+    }
+
+    fn record(&self, direction: Direction, bufs: &[IoSlice]) {
+        let mut writer = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        if writer.failed {
+            return;
+        }
+
+        let len: usize = bufs.iter().map(|buf| buf.len()).sum();
+        let mut header = [0u8; RECORD_HEADER_LEN];
+        let micros = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+        header[0..8].copy_from_slice(&micros.to_be_bytes());
+        header[8] = match direction {
+            Direction::Sent => 0,
+            Direction::Received => 1,
+        };
+        header[9..13].copy_from_slice(&(len as u32).to_be_bytes());
+
+        let mut result = writer.out.write_all(&header);
+        for buf in bufs {
+            result = result.and_then(|()| writer.out.write_all(buf));
+        }
+        if let Err(e) = result {
+            error!("Capture write failed, no longer recording: {}", e);
+            writer.failed = true;
+        }
+    }
+}
+
+impl fmt::Debug for Capture {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Capture").finish_non_exhaustive()
+    }
+}
+
+impl CaptureReader<BufReader<File>> {
+    /// Opens the capture at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> io::Result<CaptureReader<BufReader<File>>> {
+        CaptureReader::new(BufReader::new(File::open(path)?))
+    }
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Reads a capture from `inner`. Fails with `ErrorKind::InvalidData` if it does not start
+    /// like one, or was written in a version this crate does not read.
+    pub fn new(mut inner: R) -> io::Result<CaptureReader<R>> {
+        let mut header = [0u8; 6];
+        inner.read_exact(&mut header)?;
+        if header[0..5] != MAGIC[..] {
+            return Err(invalid("Not a capture"));
+        }
+        if header[5] != VERSION {
+            return Err(invalid(format!("Unknown capture version {}", header[5])));
+        }
+
+        Ok(CaptureReader { inner })
+    }
+
+    /// Returns the next record, or `None` at the end of the capture. Fails with
+    /// `ErrorKind::UnexpectedEof` if the capture ends part way through a record.
+    pub fn next_record(&mut self) -> io::Result<Option<CaptureRecord>> {
+        let mut header = [0u8; RECORD_HEADER_LEN];
+        let num_read = read_full(&mut self.inner, &mut header)?;
+        if num_read == 0 {
+            return Ok(None);
+        }
+        if num_read < header.len() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        let micros = u64::from_be_bytes(header[0..8].try_into().unwrap());
+        let direction = match header[8] {
+            0 => Direction::Sent,
+            1 => Direction::Received,
+            other => return Err(invalid(format!("Unknown direction {}", other))),
+        };
+        // The length is not trusted until the bytes are there, so a corrupt header only
+        // costs what the capture actually holds
+        let len = u32::from_be_bytes(header[9..13].try_into().unwrap()) as usize;
+        let mut bytes = Vec::new();
+        (&mut self.inner).take(len as u64).read_to_end(&mut bytes)?;
+        if bytes.len() < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        Ok(Some(CaptureRecord {
+            timestamp: UNIX_EPOCH + Duration::from_micros(micros),
+            direction,
+            bytes,
+        }))
+    }
+
+    /// Feeds the bytes of the remaining records going in `direction` to `FB`, as a stream
+    /// would receive them, and returns the frames it decodes. Fails with
+    /// `ErrorKind::InvalidData` if bytes are left over that `FB` does not decode as a frame.
+    pub fn replay<FB: FrameBuilder>(
+        &mut self,
+        direction: Direction,
+    ) -> io::Result<Vec<Box<dyn Frame>>> {
+        let mut buf = Vec::<u8>::new();
+        let mut frames = Vec::<Box<dyn Frame>>::new();
+        while let Some(record) = self.next_record()? {
+            if record.direction != direction {
+                continue;
+            }
+
+            buf.extend_from_slice(&record.bytes[..]);
+            while let Some(frame) = FB::from_bytes(&mut buf) {
+                frames.push(frame);
+            }
+        }
+
+        if !buf.is_empty() {
+            return Err(invalid(format!(
+                "{} byte(s) after frame {} do not decode",
+                buf.len(),
+                frames.len()
+            )));
+        }
+        Ok(frames)
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = io::Result<CaptureRecord>;
+
+    fn next(&mut self) -> Option<io::Result<CaptureRecord>> {
+        self.next_record().transpose()
+    }
+}
+
+/// Reads until `buf` is full or the reader ends, returning how many bytes were read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> io::Result<usize> {
+    let mut num_read = 0;
+    while num_read < buf.len() {
+        match reader.read(&mut buf[num_read..]) {
+            Ok(0) => break,
+            Ok(n) => num_read += n,
+            Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(num_read)
+}
+
+fn invalid<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn capture(records: &[u8]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.push(VERSION);
+        bytes.extend_from_slice(records);
+        bytes
+    }
+
+    #[test]
+    fn huge_announced_lengths_only_read_what_is_there() {
+        let mut record = vec![0u8; 8];
+        record.push(1);
+        record.extend_from_slice(&u32::MAX.to_be_bytes());
+        record.extend_from_slice(b"abc");
+
+        let mut reader = CaptureReader::new(io::Cursor::new(capture(&record))).unwrap();
+        let err = reader.next_record().unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn records_read_back_whole() {
+        let mut record = 42u64.to_be_bytes().to_vec();
+        record.push(0);
+        record.extend_from_slice(&3u32.to_be_bytes());
+        record.extend_from_slice(b"abc");
+
+        let mut reader = CaptureReader::new(io::Cursor::new(capture(&record))).unwrap();
+        let read = reader.next_record().unwrap().unwrap();
+        assert_eq!(read.timestamp, UNIX_EPOCH + Duration::from_micros(42));
+        assert_eq!(read.direction, Direction::Sent);
+        assert_eq!(read.bytes, b"abc");
+        assert!(reader.next_record().unwrap().is_none());
+    }
+}
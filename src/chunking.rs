@@ -0,0 +1,424 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Checks a `FrameBuilder` against every way its input can be torn across reads.
+//!
+//! ```ignore
+//! use simple_stream::frame::{Frame, SimpleFrame, SimpleFrameBuilder};
+//! use simple_stream::verify_chunking;
+//!
+//! let mut encoded = SimpleFrame::new(b"hello").to_bytes();
+//! encoded.extend(SimpleFrame::new(b"world").to_bytes());
+//!
+//! // Splits the 18 bytes into up to 4 reads, in every possible way
+//! let checked = verify_chunking::<SimpleFrameBuilder>(&encoded[..], 4).unwrap();
+//! ```
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::io::{self, Read, Write};
+
+use crate::frame::FrameBuilder;
+use crate::{Blocking, NonBlocking, Plain};
+
+/// Iterates every way to split a byte sequence into consecutive, non-empty chunks, up to a
+/// maximum number of chunks. Each item is the list of chunk lengths.
+#[derive(Clone, Debug)]
+pub struct Partitions {
+    len: usize,
+    max_chunks: usize,
+    /// Offsets the sequence is cut at, strictly increasing and within `1..len`.
+    cuts: Vec<usize>,
+    done: bool,
+}
+
+/// A transport replaying a script of chunks, one per read. Writes are discarded.
+///
+/// When `would_block` is set, every chunk is followed by an `ErrorKind::WouldBlock` read, as a
+/// non-blocking socket would between arrivals. Once the script runs out, reads return end of
+/// file.
+#[derive(Clone, Debug, Default)]
+pub struct ScriptedTransport {
+    chunks: VecDeque<Vec<u8>>,
+    would_block: bool,
+    blocked: bool,
+}
+
+/// A split of the input that decoded to different frames than the input as a whole.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ChunkingMismatch {
+    /// Lengths of the chunks the input was read in.
+    pub chunks: Vec<usize>,
+    /// Whether the frames were read with `nb_recv` rather than `b_recv`.
+    pub non_blocking: bool,
+    /// Encoded frames decoded from the input in a single piece.
+    pub expected: Vec<Vec<u8>>,
+    /// Encoded frames decoded from the chunks.
+    pub actual: Vec<Vec<u8>>,
+}
+
+/// Returns every split of `len` bytes into at most `max_chunks` chunks.
+pub fn partitions(len: usize, max_chunks: usize) -> Partitions {
+    Partitions {
+        len,
+        max_chunks,
+        cuts: Vec::new(),
+        done: max_chunks == 0,
+    }
+}
+
+/// Feeds `encoded` to a `Plain` stream using `FB` in every split of up to `max_chunks` reads,
+/// through both `b_recv` and `nb_recv`, and checks that each decodes to the same frames as
+/// `encoded` does in a single piece. Returns the number of splits checked.
+///
+/// The number of splits grows quickly with both the input length and `max_chunks`.
+pub fn verify_chunking<FB: FrameBuilder>(
+    encoded: &[u8],
+    max_chunks: usize,
+) -> Result<usize, ChunkingMismatch> {
+    let mut buf = encoded.to_vec();
+    let mut expected = Vec::<Vec<u8>>::new();
+    while let Some(frame) = FB::from_bytes(&mut buf) {
+        expected.push(frame.to_bytes());
+    }
+
+    let mut num_checked = 0;
+    for chunks in partitions(encoded.len(), max_chunks) {
+        for &non_blocking in [false, true].iter() {
+            let transport = ScriptedTransport::split(encoded, &chunks[..], non_blocking);
+            let actual = decode_all::<FB>(transport, non_blocking);
+            if actual != expected {
+                return Err(ChunkingMismatch {
+                    chunks,
+                    non_blocking,
+                    expected,
+                    actual,
+                });
+            }
+        }
+        num_checked += 1;
+    }
+
+    trace!("Checked {} split(s)", num_checked);
+    Ok(num_checked)
+}
+
+/// Reads frames off `transport` until it runs out, returning them encoded.
+fn decode_all<FB: FrameBuilder>(transport: ScriptedTransport, non_blocking: bool) -> Vec<Vec<u8>> {
+    let mut stream = Plain::<ScriptedTransport, FB>::new(transport);
+    let mut frames = Vec::<Vec<u8>>::new();
+    if non_blocking {
+        loop {
+            match stream.nb_recv() {
+                Ok(received) => frames.extend(received.iter().map(|frame| frame.to_bytes())),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(_) => break,
+            }
+        }
+    } else {
+        while let Ok(frame) = stream.b_recv() {
+            frames.push(frame.to_bytes());
+        }
+    }
+
+    frames
+}
+
+impl Iterator for Partitions {
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Vec<usize>> {
+        if self.done {
+            return None;
+        }
+
+        let mut chunks = Vec::<usize>::with_capacity(self.cuts.len() + 1);
+        let mut start = 0;
+        for &cut in self.cuts.iter().chain(Some(&self.len)) {
+            chunks.push(cut - start);
+            start = cut;
+        }
+        if self.len == 0 {
+            chunks.clear();
+        }
+
+        self.advance();
+        Some(chunks)
+    }
+}
+
+impl Partitions {
+    /// Moves to the next set of cuts, in lexicographic order within each number of cuts.
+    fn advance(&mut self) {
+        let num_cuts = self.cuts.len();
+        for i in (0..num_cuts).rev() {
+            // The last position cut i can take and still leave room for the cuts after it
+            let limit = self.len - (num_cuts - i);
+            if self.cuts[i] < limit {
+                self.cuts[i] += 1;
+                for j in (i + 1)..num_cuts {
+                    self.cuts[j] = self.cuts[j - 1] + 1;
+                }
+                return;
+            }
+        }
+
+        let num_cuts = num_cuts + 1;
+        if num_cuts >= self.max_chunks || num_cuts >= self.len {
+            self.done = true;
+            return;
+        }
+        self.cuts = (1..=num_cuts).collect();
+    }
+}
+
+impl ScriptedTransport {
+    /// Creates a transport returning `chunks` in order.
+    pub fn new(chunks: Vec<Vec<u8>>, would_block: bool) -> ScriptedTransport {
+        ScriptedTransport {
+            chunks: chunks.into(),
+            would_block,
+            blocked: false,
+        }
+    }
+
+    /// Creates a transport returning `buf` in chunks of the passed lengths.
+    pub fn split(buf: &[u8], chunk_lens: &[usize], would_block: bool) -> ScriptedTransport {
+        let mut chunks = Vec::<Vec<u8>>::with_capacity(chunk_lens.len());
+        let mut start = 0;
+        for &len in chunk_lens {
+            chunks.push(buf[start..(start + len)].to_vec());
+            start += len;
+        }
+
+        ScriptedTransport::new(chunks, would_block)
+    }
+}
+
+impl Read for ScriptedTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.blocked {
+            self.blocked = false;
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+
+        let mut chunk = match self.chunks.pop_front() {
+            Some(chunk) => chunk,
+            None => return Ok(0),
+        };
+
+        // A chunk larger than the read buffer is returned over several reads
+        let num_read = chunk.len().min(buf.len());
+        buf[..num_read].copy_from_slice(&chunk[..num_read]);
+        if num_read < chunk.len() {
+            self.chunks.push_front(chunk.split_off(num_read));
+        } else {
+            self.blocked = self.would_block;
+        }
+
+        Ok(num_read)
+    }
+}
+
+impl Write for ScriptedTransport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl fmt::Display for ChunkingMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Reading in chunks of {:?} with {} decoded {} frame(s), expected {}",
+            self.chunks,
+            if self.non_blocking {
+                "nb_recv"
+            } else {
+                "b_recv"
+            },
+            self.actual.len(),
+            self.expected.len()
+        )
+    }
+}
+
+impl Error for ChunkingMismatch {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem;
+
+    use crate::frame::*;
+    use crate::preamble::Compression;
+
+    /// Encodes `frames` back to back.
+    fn encode(frames: &[&dyn Frame]) -> Vec<u8> {
+        frames.iter().flat_map(|frame| frame.to_bytes()).collect()
+    }
+
+    /// Checks every split of `frames`, encoded, into up to three reads.
+    fn assert_chunking<FB: FrameBuilder>(frames: &[&dyn Frame]) {
+        let encoded = encode(frames);
+        let mut buf = encoded.clone();
+        let mut num_frames = 0;
+        while FB::from_bytes(&mut buf).is_some() {
+            num_frames += 1;
+        }
+        assert_eq!(num_frames, frames.len(), "{}", std::any::type_name::<FB>());
+
+        if let Err(mismatch) = verify_chunking::<FB>(&encoded, 3) {
+            panic!("{}", mismatch);
+        }
+    }
+
+    /// A `Mac` tagging data with the sum of its bytes.
+    #[derive(Default)]
+    struct SumMac;
+
+    impl Mac for SumMac {
+        const TAG_LEN: usize = 4;
+
+        fn sign(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+            let sum = data.iter().fold(0u32, |sum, &b| sum.wrapping_add(b as u32));
+            Ok(sum.to_be_bytes().to_vec())
+        }
+    }
+
+    /// Decodes whatever has been read so far as a single frame, as parsers that assume a read
+    /// returns whole frames do.
+    struct WholeReadBuilder;
+
+    impl FrameBuilder for WholeReadBuilder {
+        fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+            if buf.is_empty() {
+                return None;
+            }
+            Some(Box::new(SimpleFrame::new(&mem::take(buf)[..])))
+        }
+    }
+
+    #[test]
+    fn partitions_cover_every_split() {
+        let all: Vec<Vec<usize>> = partitions(4, 3).collect();
+        assert_eq!(
+            all,
+            vec![
+                vec![4],
+                vec![1, 3],
+                vec![2, 2],
+                vec![3, 1],
+                vec![1, 1, 2],
+                vec![1, 2, 1],
+                vec![2, 1, 1],
+            ]
+        );
+        assert_eq!(partitions(0, 3).count(), 1);
+        assert_eq!(partitions(4, 0).count(), 0);
+    }
+
+    #[test]
+    fn torn_reads_are_caught() {
+        let mismatch = verify_chunking::<WholeReadBuilder>(b"torn", 2).unwrap_err();
+        assert_eq!(mismatch.chunks, vec![1, 3]);
+        assert_eq!(mismatch.expected.len(), 1);
+        assert_eq!(mismatch.actual.len(), 2);
+    }
+
+    #[test]
+    fn built_in_builders_survive_every_split() {
+        assert_chunking::<SimpleFrameBuilder>(&[
+            &SimpleFrame::new(b"hello"),
+            &SimpleFrame::new(b""),
+            &SimpleFrame::new(b"world"),
+        ]);
+        assert_chunking::<Checksum32FrameBuilder>(&[
+            &Checksum32Frame::new(b"hello"),
+            &Checksum32Frame::new(b"world"),
+        ]);
+        assert_chunking::<LengthPrefixed32FrameBuilder>(&[
+            &LengthPrefixed32Frame::new(b"hello"),
+            &LengthPrefixed32Frame::new(b"world"),
+        ]);
+        assert_chunking::<VarintFrameBuilder>(&[
+            &VarintFrame::new(&[0xAB; 130]),
+            &VarintFrame::new(b"world"),
+        ]);
+        let mut headered = HeaderedFrame::new(b"hello");
+        headered.set_header(APPLICATION_HEADER_KEYS, b"key");
+        assert_chunking::<HeaderedFrameBuilder>(&[&headered, &HeaderedFrame::new(b"world")]);
+        assert_chunking::<TlvFrameBuilder>(&[&TlvFrame::new(1, b"hello"), &TlvFrame::new(2, b"")]);
+        assert_chunking::<TlvFrameBuilder<Tag16>>(&[
+            &TlvFrame::new_wide(0x1234, b"hello"),
+            &TlvFrame::new_wide(2, b"world"),
+        ]);
+        assert_chunking::<DelimitedFrameBuilder>(&[
+            &DelimitedFrame::new(b"hello"),
+            &DelimitedFrame::new(b"world"),
+        ]);
+        assert_chunking::<DelimitedFrameBuilder<CrLf>>(&[
+            &DelimitedFrame::with_delimiter::<CrLf>(b"hello"),
+            &DelimitedFrame::with_delimiter::<CrLf>(b"world"),
+        ]);
+        assert_chunking::<DelimitedFrameBuilder<EscapedNewline>>(&[
+            &DelimitedFrame::with_delimiter::<EscapedNewline>(b"hel\nlo"),
+            &DelimitedFrame::with_delimiter::<EscapedNewline>(b"world"),
+        ]);
+        assert_chunking::<JsonFrameBuilder>(&[
+            &JsonFrame::new(br#"{"a":"}"}"#),
+            &JsonFrame::new(b"[1,2]"),
+        ]);
+        assert_chunking::<CobsFrameBuilder>(&[&CobsFrame::new(b"he\0llo"), &CobsFrame::new(b"\0")]);
+    }
+
+    #[test]
+    fn websocket_builders_survive_every_split() {
+        assert_chunking::<WebSocketFrameBuilder>(&[
+            &WebSocketFrame::new(b"hello", FrameType::Data, OpType::Text),
+            &WebSocketFrame::new_masked(&[0xAB; 130], FrameType::Data, OpType::Binary),
+        ]);
+
+        // A ping interleaved with the fragments of a message is returned ahead of it
+        let first = WebSocketFrame::fragment(b"hel", FrameType::Data, OpType::Text, false);
+        let ping = WebSocketFrame::new(b"ping", FrameType::Control, OpType::Ping);
+        let last = WebSocketFrame::fragment(b"lo", FrameType::Data, OpType::Continuation, true);
+        let encoded = encode(&[&first, &ping, &last]);
+        verify_chunking::<WebSocketMessageBuilder>(&encoded, 3).unwrap();
+    }
+
+    #[test]
+    fn wrapping_builders_survive_every_split() {
+        let hello = SimpleFrame::new(b"hello");
+        let world = SimpleFrame::new(b"world");
+
+        assert_chunking::<PaddedFrameBuilder<SimpleFrameBuilder>>(&[
+            &PaddedFrame::new(&hello, PaddingPolicy::BlockSize(16)),
+            &PaddedFrame::new(&world, PaddingPolicy::None),
+        ]);
+        assert_chunking::<Compressed<SimpleFrameBuilder>>(&[
+            &CompressedFrame::new(&hello, Compression::None).unwrap(),
+            &CompressedFrame::new(&world, Compression::None).unwrap(),
+        ]);
+        assert_chunking::<Signed<SimpleFrameBuilder, SumMac>>(&[
+            &SignedFrame::new(&hello, &SumMac).unwrap(),
+            &SignedFrame::new(&world, &SumMac).unwrap(),
+        ]);
+    }
+
+    #[cfg(feature = "echo")]
+    #[test]
+    fn echo_builder_survives_every_split() {
+        let echo = EchoFrame::echo();
+        assert_chunking::<EchoFrameBuilder>(&[&echo, &echo.reply()]);
+    }
+}
@@ -0,0 +1,191 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+#[cfg(unix)]
+use std::os::unix::io::RawFd;
+#[cfg(unix)]
+use std::time::{Duration, Instant};
+
+use crate::deadline::StalledFrame;
+
+/// Why a stream stopped carrying frames.
+///
+/// Once a stream has terminated, every subsequent send or receive on it fails with the same
+/// `Error`: `Error::Eof` or `Error::ProtocolViolation` where one fits, and otherwise an
+/// `Error::Io` carrying the `CloseReason`, retrievable with `get_ref()` and
+/// `downcast_ref::<CloseReason>()`. The stream's `close_reason()` returns it directly.
+#[derive(Debug)]
+pub enum CloseReason {
+    /// The peer closed the connection, as seen by a read of zero bytes.
+    PeerClosed,
+    /// The peer violated the wire protocol.
+    ProtocolError,
+    /// No traffic was received within the configured idle timeout.
+    IdleTimeout,
+    /// A frame was not completely received within the configured frame deadline.
+    StalledFrame(StalledFrame),
+    /// The connection was shut down locally.
+    LocalShutdown,
+    /// The peer ended the TLS session with a close_notify alert.
+    TlsShutdown,
+    /// The underlying transport failed, e.g. because the connection was reset or aborted, or
+    /// the peer stopped reading.
+    TransportError(io::Error),
+}
+
+impl CloseReason {
+    /// Returns the `std::io::ErrorKind` streams report this reason with.
+    pub fn kind(&self) -> io::ErrorKind {
+        match *self {
+            CloseReason::PeerClosed => io::ErrorKind::UnexpectedEof,
+            CloseReason::ProtocolError => io::ErrorKind::InvalidData,
+            CloseReason::IdleTimeout => io::ErrorKind::TimedOut,
+            CloseReason::StalledFrame(_) => io::ErrorKind::TimedOut,
+            CloseReason::LocalShutdown => io::ErrorKind::NotConnected,
+            CloseReason::TlsShutdown => io::ErrorKind::UnexpectedEof,
+            CloseReason::TransportError(ref e) => e.kind(),
+        }
+    }
+
+    /// Builds the `std::io::Error` streams report this reason with.
+    pub(crate) fn to_io_error(&self) -> io::Error {
+        io::Error::new(self.kind(), self.clone())
+    }
+}
+
+impl Clone for CloseReason {
+    fn clone(&self) -> CloseReason {
+        match *self {
+            CloseReason::PeerClosed => CloseReason::PeerClosed,
+            CloseReason::ProtocolError => CloseReason::ProtocolError,
+            CloseReason::IdleTimeout => CloseReason::IdleTimeout,
+            CloseReason::StalledFrame(stalled) => CloseReason::StalledFrame(stalled),
+            CloseReason::LocalShutdown => CloseReason::LocalShutdown,
+            CloseReason::TlsShutdown => CloseReason::TlsShutdown,
+            CloseReason::TransportError(ref e) => {
+                CloseReason::TransportError(io::Error::new(e.kind(), e.to_string()))
+            }
+        }
+    }
+}
+
+impl fmt::Display for CloseReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CloseReason::PeerClosed => write!(f, "Connection closed by peer"),
+            CloseReason::ProtocolError => write!(f, "Protocol error"),
+            CloseReason::IdleTimeout => write!(f, "Idle timeout"),
+            CloseReason::StalledFrame(ref stalled) => write!(f, "{}", stalled),
+            CloseReason::LocalShutdown => write!(f, "Connection shut down locally"),
+            CloseReason::TlsShutdown => write!(f, "TLS session closed by peer"),
+            CloseReason::TransportError(ref e) => write!(f, "Transport error: {}", e),
+        }
+    }
+}
+
+impl Error for CloseReason {}
+
+/// Calls `flush_tx` until it reports nothing left pending or `timeout` elapses, waiting for
+/// `fd` to become writable in between. `fd` is non-blocking meanwhile, so a peer that stopped
+/// reading can not hold the caller past `timeout`. Returns whether everything was written.
+#[cfg(unix)]
+pub(crate) fn drain_tx<F>(fd: RawFd, timeout: Duration, mut flush_tx: F) -> io::Result<bool>
+where
+    F: FnMut() -> io::Result<usize>,
+{
+    let deadline = Instant::now() + timeout;
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    if flags & libc::O_NONBLOCK == 0
+        && unsafe { libc::fcntl(fd, libc::F_SETFL, flags | libc::O_NONBLOCK) } < 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+
+    let result = loop {
+        let pending = match flush_tx() {
+            Ok(0) => break Ok(true),
+            Ok(pending) => pending,
+            Err(e) => break Err(e),
+        };
+
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            debug!("Closing with {} byte(s) not written", pending);
+            break Ok(false);
+        }
+
+        let mut pollfd = libc::pollfd {
+            fd,
+            events: libc::POLLOUT,
+            revents: 0,
+        };
+        let timeout_ms = remaining.as_millis().clamp(1, libc::c_int::MAX as u128);
+        if unsafe { libc::poll(&mut pollfd, 1, timeout_ms as libc::c_int) } < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() != io::ErrorKind::Interrupted {
+                break Err(e);
+            }
+        }
+    };
+
+    if flags & libc::O_NONBLOCK == 0 {
+        unsafe { libc::fcntl(fd, libc::F_SETFL, flags) };
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_errors_carry_the_reason() {
+        let reset = io::Error::from(io::ErrorKind::ConnectionReset);
+        let e = CloseReason::TransportError(reset).to_io_error();
+        assert_eq!(e.kind(), io::ErrorKind::ConnectionReset);
+
+        let reason = e.get_ref().unwrap().downcast_ref::<CloseReason>().unwrap();
+        assert!(
+            matches!(reason, CloseReason::TransportError(e) if e.kind() == io::ErrorKind::ConnectionReset)
+        );
+        assert_eq!(
+            CloseReason::LocalShutdown.to_io_error().kind(),
+            io::ErrorKind::NotConnected
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn draining_gives_up_at_the_timeout_and_restores_blocking_mode() {
+        use std::os::unix::io::AsRawFd;
+        use std::os::unix::net::UnixStream;
+
+        let (local, _remote) = UnixStream::pair().unwrap();
+        let fd = local.as_raw_fd();
+
+        let mut pending = 3;
+        let drained = drain_tx(fd, Duration::from_secs(5), || {
+            pending -= 1;
+            Ok(pending)
+        });
+        assert!(drained.unwrap());
+
+        let drained = drain_tx(fd, Duration::from_millis(20), || Ok(1));
+        assert!(!drained.unwrap());
+        assert_eq!(
+            unsafe { libc::fcntl(fd, libc::F_GETFL) } & libc::O_NONBLOCK,
+            0
+        );
+    }
+}
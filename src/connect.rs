@@ -0,0 +1,452 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::mem;
+#[cfg(feature = "openssl")]
+use std::net::ToSocketAddrs;
+use std::net::{SocketAddr, TcpStream};
+#[cfg(feature = "openssl")]
+use std::os::unix::io::RawFd;
+use std::os::unix::io::{AsRawFd, FromRawFd};
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+#[cfg(feature = "openssl")]
+use std::time::Instant;
+
+#[cfg(feature = "openssl")]
+use openssl::ssl::{ErrorCode, HandshakeError, SslConnector};
+
+use crate::cancel::{CancellationToken, Interest};
+use crate::frame::FrameBuilder;
+use crate::Plain;
+#[cfg(feature = "openssl")]
+use crate::Secure;
+
+/// How `connect_any` works through its list of addresses.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectPolicy {
+    /// Tries each address in order, giving each attempt up to the passed timeout.
+    Sequential(Duration),
+    /// Tries every address at once, each attempt timing out after the passed duration, and
+    /// keeps whichever connects first.
+    Parallel(Duration),
+}
+
+/// A connection attempt that failed.
+#[derive(Debug)]
+pub struct ConnectAttempt {
+    pub addr: SocketAddr,
+    pub error: io::Error,
+}
+
+/// A successful `connect_any`.
+pub struct Connected<FB: FrameBuilder> {
+    /// The framed stream, in blocking mode.
+    pub stream: Plain<TcpStream, FB>,
+    /// The address that was connected to.
+    pub addr: SocketAddr,
+    /// Attempts that failed before the connection was made.
+    pub failed: Vec<ConnectAttempt>,
+}
+
+/// Error carried by the `std::io::Error` returned from `connect_any` when every address
+/// failed. Retrieve it with `get_ref()` and `downcast_ref::<ConnectAnyError>()`.
+#[derive(Debug)]
+pub struct ConnectAnyError {
+    pub attempts: Vec<ConnectAttempt>,
+}
+
+/// The stage of `connect_secure_with_deadline` that ran out of time.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectPhase {
+    /// Resolving the host name.
+    Dns,
+    /// Establishing the TCP connection.
+    Tcp,
+    /// Performing the TLS handshake.
+    Tls,
+}
+
+/// Error carried by the `std::io::Error`, of `ErrorKind::TimedOut`, returned from
+/// `connect_secure_with_deadline` when its deadline passes. Retrieve it with `get_ref()` and
+/// `downcast_ref::<ConnectTimeout>()`.
+#[derive(Debug)]
+pub struct ConnectTimeout {
+    /// The stage that was in progress.
+    pub phase: ConnectPhase,
+    /// Time spent in total before giving up.
+    pub elapsed: Duration,
+}
+
+/// Connects to the first reachable address in `addrs` according to `policy` and wraps the
+/// connection in a `Plain` stream.
+pub fn connect_any<FB: FrameBuilder>(
+    addrs: &[SocketAddr],
+    policy: ConnectPolicy,
+) -> io::Result<Connected<FB>> {
+    if addrs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "No addresses to connect to",
+        ));
+    }
+
+    let mut failed = Vec::<ConnectAttempt>::new();
+    match policy {
+        ConnectPolicy::Sequential(timeout) => {
+            for &addr in addrs {
+                match TcpStream::connect_timeout(&addr, timeout) {
+                    Ok(stream) => return Ok(connected(stream, addr, failed)),
+                    Err(error) => {
+                        debug!("Connect to {} failed: {}", addr, error);
+                        failed.push(ConnectAttempt { addr, error });
+                    }
+                }
+            }
+        }
+        ConnectPolicy::Parallel(timeout) => {
+            let (tx, rx) = mpsc::channel();
+            for &addr in addrs {
+                let tx = tx.clone();
+                thread::spawn(move || {
+                    let result = TcpStream::connect_timeout(&addr, timeout);
+                    let _ = tx.send((addr, result));
+                });
+            }
+            drop(tx);
+
+            for (addr, result) in rx {
+                match result {
+                    Ok(stream) => return Ok(connected(stream, addr, failed)),
+                    Err(error) => {
+                        debug!("Connect to {} failed: {}", addr, error);
+                        failed.push(ConnectAttempt { addr, error });
+                    }
+                }
+            }
+        }
+    }
+
+    let kind = failed
+        .last()
+        .map(|attempt| attempt.error.kind())
+        .unwrap_or(io::ErrorKind::Other);
+    Err(io::Error::new(kind, ConnectAnyError { attempts: failed }))
+}
+
+/// Connects to `addr` and wraps the connection in a blocking `Plain` stream observing
+/// `token`. Fails with `ErrorKind::Interrupted` if `token` is cancelled before the connection
+/// is made.
+pub fn connect_cancellable<FB: FrameBuilder>(
+    addr: SocketAddr,
+    token: &CancellationToken,
+) -> io::Result<Plain<TcpStream, FB>> {
+    let (storage, len) = to_sockaddr(&addr);
+    let fd = unsafe { libc::socket(storage.ss_family as libc::c_int, libc::SOCK_STREAM, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    // Owned from here on, so the descriptor is closed on every early return
+    let stream = unsafe { TcpStream::from_raw_fd(fd) };
+    if unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) } < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    stream.set_nonblocking(true)?;
+
+    let result = unsafe {
+        libc::connect(
+            fd,
+            &storage as *const libc::sockaddr_storage as *const libc::sockaddr,
+            len,
+        )
+    };
+    if result < 0 {
+        let e = io::Error::last_os_error();
+        if e.raw_os_error() != Some(libc::EINPROGRESS) {
+            return Err(e);
+        }
+
+        token.wait(stream.as_raw_fd(), Interest::Writable)?;
+        if let Some(e) = stream.take_error()? {
+            return Err(e);
+        }
+    }
+
+    stream.set_nonblocking(false)?;
+    trace!("Connected to {}", addr);
+
+    let mut stream = Plain::new(stream);
+    stream.set_cancellation_token(token.clone());
+    Ok(stream)
+}
+
+/// Resolves `addr`, a `host:port` pair, connects to it and completes the TLS handshake for
+/// `host` with `connector`, all within `deadline` in total. Resolved addresses are tried in
+/// order until one connects. Fails with a `ConnectTimeout` naming the phase in progress if
+/// `deadline` passes. The stream is returned in blocking mode.
+#[cfg(feature = "openssl")]
+pub fn connect_secure_with_deadline<FB: FrameBuilder>(
+    addr: &str,
+    connector: &SslConnector,
+    deadline: Duration,
+) -> io::Result<Secure<TcpStream, FB>> {
+    let start = Instant::now();
+    let remaining = |phase| {
+        deadline
+            .checked_sub(start.elapsed())
+            .filter(|remaining| !remaining.is_zero())
+            .ok_or_else(|| timed_out(phase, start))
+    };
+
+    let host = match addr.rsplit_once(':') {
+        Some((host, _)) => host.trim_start_matches('[').trim_end_matches(']'),
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "Address is not a host:port pair",
+            ))
+        }
+    };
+
+    // Resolution has no timeout of its own, so it runs on a thread that is abandoned if slow
+    let (tx, rx) = mpsc::channel();
+    let query = addr.to_string();
+    thread::spawn(move || {
+        let _ = tx.send(
+            query
+                .to_socket_addrs()
+                .map(|addrs| addrs.collect::<Vec<_>>()),
+        );
+    });
+    let addrs = match rx.recv_timeout(remaining(ConnectPhase::Dns)?) {
+        Ok(result) => result?,
+        Err(_) => return Err(timed_out(ConnectPhase::Dns, start)),
+    };
+    trace!("Resolved {} to {:?}", addr, addrs);
+
+    let mut last_error = None;
+    let mut stream = None;
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, remaining(ConnectPhase::Tcp)?) {
+            Ok(connected) => {
+                stream = Some(connected);
+                break;
+            }
+            Err(ref e) if e.kind() == io::ErrorKind::TimedOut => {
+                return Err(timed_out(ConnectPhase::Tcp, start));
+            }
+            Err(e) => {
+                debug!("Connect to {} failed: {}", addr, e);
+                last_error = Some(e);
+            }
+        }
+    }
+    let stream = match stream {
+        Some(stream) => stream,
+        None => {
+            return Err(last_error.unwrap_or_else(|| {
+                io::Error::new(io::ErrorKind::NotFound, "Host resolved to no addresses")
+            }))
+        }
+    };
+
+    // Non-blocking, so every wait on the handshake is bounded by what is left of the deadline
+    stream.set_nonblocking(true)?;
+    let mut handshake = connector.connect(host, stream);
+    loop {
+        match handshake {
+            Ok(stream) => {
+                stream.get_ref().set_nonblocking(false)?;
+                trace!("Connected to {} in {:?}", addr, start.elapsed());
+                return Ok(Secure::new(stream));
+            }
+            Err(HandshakeError::WouldBlock(mid)) => {
+                let events = match mid.error().code() {
+                    ErrorCode::WANT_WRITE => libc::POLLOUT,
+                    _ => libc::POLLIN,
+                };
+                let timeout = remaining(ConnectPhase::Tls)?;
+                if !poll_fd(mid.get_ref().as_raw_fd(), events, timeout)? {
+                    return Err(timed_out(ConnectPhase::Tls, start));
+                }
+                handshake = mid.handshake();
+            }
+            Err(HandshakeError::SetupFailure(e)) => {
+                return Err(crate::Error::Tls(e.to_string()).into());
+            }
+            Err(HandshakeError::Failure(mid)) => {
+                error!("TLS handshake with {} failed: {}", addr, mid.error());
+                return Err(crate::Error::Tls(mid.error().to_string()).into());
+            }
+        }
+    }
+}
+
+#[cfg(feature = "openssl")]
+fn timed_out(phase: ConnectPhase, start: Instant) -> io::Error {
+    let elapsed = start.elapsed();
+    debug!("Connect timed out during {:?} after {:?}", phase, elapsed);
+    io::Error::new(io::ErrorKind::TimedOut, ConnectTimeout { phase, elapsed })
+}
+
+/// Waits up to `timeout` for `events` on `fd`, returning whether any occurred.
+#[cfg(feature = "openssl")]
+fn poll_fd(fd: RawFd, events: libc::c_short, timeout: Duration) -> io::Result<bool> {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events,
+        revents: 0,
+    };
+    let timeout_ms = timeout.as_millis().clamp(1, libc::c_int::MAX as u128) as libc::c_int;
+    loop {
+        let result = unsafe { libc::poll(&mut pollfd, 1, timeout_ms) };
+        if result < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+
+        return Ok(result > 0);
+    }
+}
+
+fn to_sockaddr(addr: &SocketAddr) -> (libc::sockaddr_storage, libc::socklen_t) {
+    let mut storage: libc::sockaddr_storage = unsafe { mem::zeroed() };
+    let len = match *addr {
+        SocketAddr::V4(ref addr) => {
+            let sin = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in) };
+            sin.sin_family = libc::AF_INET as libc::sa_family_t;
+            sin.sin_port = addr.port().to_be();
+            sin.sin_addr.s_addr = u32::from_ne_bytes(addr.ip().octets());
+            mem::size_of::<libc::sockaddr_in>()
+        }
+        SocketAddr::V6(ref addr) => {
+            let sin6 = unsafe { &mut *(&mut storage as *mut _ as *mut libc::sockaddr_in6) };
+            sin6.sin6_family = libc::AF_INET6 as libc::sa_family_t;
+            sin6.sin6_port = addr.port().to_be();
+            sin6.sin6_addr.s6_addr = addr.ip().octets();
+            sin6.sin6_flowinfo = addr.flowinfo();
+            sin6.sin6_scope_id = addr.scope_id();
+            mem::size_of::<libc::sockaddr_in6>()
+        }
+    };
+
+    (storage, len as libc::socklen_t)
+}
+
+fn connected<FB: FrameBuilder>(
+    stream: TcpStream,
+    addr: SocketAddr,
+    failed: Vec<ConnectAttempt>,
+) -> Connected<FB> {
+    trace!(
+        "Connected to {} after {} failed attempt(s)",
+        addr,
+        failed.len()
+    );
+    Connected {
+        stream: Plain::new(stream),
+        addr,
+        failed,
+    }
+}
+
+impl fmt::Display for ConnectAnyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "All {} connection attempt(s) failed",
+            self.attempts.len()
+        )?;
+        for attempt in self.attempts.iter() {
+            write!(f, "; {}: {}", attempt.addr, attempt.error)?;
+        }
+        Ok(())
+    }
+}
+
+impl Error for ConnectAnyError {}
+
+impl fmt::Display for ConnectTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let phase = match self.phase {
+            ConnectPhase::Dns => "DNS resolution",
+            ConnectPhase::Tcp => "TCP connect",
+            ConnectPhase::Tls => "TLS handshake",
+        };
+        write!(
+            f,
+            "Connect timed out during {} after {:?}",
+            phase, self.elapsed
+        )
+    }
+}
+
+impl Error for ConnectTimeout {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    use crate::frame::SimpleFrameBuilder;
+
+    /// Returns an address nothing listens on.
+    fn closed_addr() -> SocketAddr {
+        TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+    }
+
+    #[test]
+    fn the_first_reachable_address_is_connected() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let open = listener.local_addr().unwrap();
+        let closed = closed_addr();
+
+        for policy in [
+            ConnectPolicy::Sequential(Duration::from_secs(5)),
+            ConnectPolicy::Parallel(Duration::from_secs(5)),
+        ] {
+            let connected = connect_any::<SimpleFrameBuilder>(&[closed, open], policy).unwrap();
+            assert_eq!(connected.addr, open);
+            assert!(connected
+                .failed
+                .iter()
+                .all(|attempt| attempt.addr == closed));
+        }
+    }
+
+    #[test]
+    fn every_failed_attempt_is_reported() {
+        let addrs = [closed_addr(), closed_addr()];
+        let policy = ConnectPolicy::Sequential(Duration::from_secs(5));
+        let e = connect_any::<SimpleFrameBuilder>(&addrs, policy)
+            .err()
+            .unwrap();
+
+        let error = e.get_ref().unwrap().downcast_ref::<ConnectAnyError>();
+        assert_eq!(error.unwrap().attempts.len(), 2);
+    }
+
+    #[test]
+    fn cancellable_connects_complete() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let token = CancellationToken::new().unwrap();
+        let stream =
+            connect_cancellable::<SimpleFrameBuilder>(listener.local_addr().unwrap(), &token);
+        assert!(stream.is_ok());
+    }
+}
@@ -0,0 +1,187 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Frames over UDP, one frame per datagram.
+//!
+//! There is no receive buffer carrying bytes over from one datagram to the next, so a
+//! datagram that does not hold exactly one frame `FB` accepts is dropped on its own, without
+//! affecting the ones after it.
+//!
+//! ```ignore
+//! let mut telemetry = Datagram::<SimpleFrameBuilder>::bind("0.0.0.0:9000")?;
+//! loop {
+//!     let (frame, peer) = telemetry.recv_from()?;
+//!     println!("{} byte(s) from {}", frame.payload().len(), peer);
+//! }
+//! ```
+
+use std::io;
+use std::marker::PhantomData;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+
+use crate::frame::{Frame, FrameBuilder};
+
+/// Largest datagram received. Anything over it would be truncated by the socket.
+const MAX_DATAGRAM_LEN: usize = 64 * 1024;
+
+/// A UDP socket sending and receiving frames `FB` builds, one per datagram.
+pub struct Datagram<FB: FrameBuilder> {
+    socket: UdpSocket,
+    rx_buf: Vec<u8>,
+    rx_dropped: u64,
+    phantom: PhantomData<FB>,
+}
+
+impl<FB: FrameBuilder> Datagram<FB> {
+    /// Creates a framed socket over `socket`, in whatever blocking mode it is in.
+    pub fn new(socket: UdpSocket) -> Datagram<FB> {
+        Datagram {
+            socket,
+            rx_buf: vec![0u8; MAX_DATAGRAM_LEN],
+            rx_dropped: 0,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Binds a new socket to `addr`, in blocking mode.
+    pub fn bind<A: ToSocketAddrs>(addr: A) -> io::Result<Datagram<FB>> {
+        Ok(Datagram::new(UdpSocket::bind(addr)?))
+    }
+
+    /// Sends `frame` in a single datagram to `addr`.
+    pub fn send_to<A: ToSocketAddrs>(&self, frame: &dyn Frame, addr: A) -> io::Result<()> {
+        let bytes = frame.to_bytes();
+        let num_sent = self.socket.send_to(&bytes[..], addr)?;
+        ensure_sent(num_sent, bytes.len())
+    }
+
+    /// Sends `frame` in a single datagram to the address the socket is connected to.
+    pub fn send(&self, frame: &dyn Frame) -> io::Result<()> {
+        let bytes = frame.to_bytes();
+        let num_sent = self.socket.send(&bytes[..])?;
+        ensure_sent(num_sent, bytes.len())
+    }
+
+    /// Receives the next valid frame, along with the address it came from. Datagrams that
+    /// are not exactly one frame are dropped, and counted by `rx_frames_dropped`.
+    ///
+    /// Returns `ErrorKind::WouldBlock` on a non-blocking socket once nothing more is pending.
+    pub fn recv_from(&mut self) -> io::Result<(Box<dyn Frame>, SocketAddr)> {
+        loop {
+            let (num_read, addr) = self.socket.recv_from(&mut self.rx_buf[..])?;
+            if let Some(frame) = self.decode(num_read, &addr) {
+                return Ok((frame, addr));
+            }
+        }
+    }
+
+    /// Same as `recv_from`, on a socket connected to its peer.
+    pub fn recv(&mut self) -> io::Result<Box<dyn Frame>> {
+        let addr = self.socket.peer_addr()?;
+        loop {
+            let num_read = self.socket.recv(&mut self.rx_buf[..])?;
+            if let Some(frame) = self.decode(num_read, &addr) {
+                return Ok(frame);
+            }
+        }
+    }
+
+    /// Returns how many datagrams were dropped for not holding exactly one valid frame.
+    pub fn rx_frames_dropped(&self) -> u64 {
+        self.rx_dropped
+    }
+
+    /// Returns a reference to the underlying socket, e.g. to connect it or change its mode.
+    pub fn get_ref(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    /// Returns the underlying socket.
+    pub fn into_inner(self) -> UdpSocket {
+        self.socket
+    }
+
+    /// Decodes the first `len` bytes of `rx_buf`, received from `addr`, as a single frame.
+    fn decode(&mut self, len: usize, addr: &SocketAddr) -> Option<Box<dyn Frame>> {
+        let mut buf = self.rx_buf[..len].to_vec();
+        match FB::from_bytes(&mut buf) {
+            Some(frame) if buf.is_empty() => {
+                trace!("Datagram from {}: {}", addr, frame.fmt_summary());
+                Some(frame)
+            }
+            Some(_) => {
+                self.dropped(addr, len, "bytes after the frame");
+                None
+            }
+            None => {
+                self.dropped(addr, len, "no valid frame");
+                None
+            }
+        }
+    }
+
+    fn dropped(&mut self, addr: &SocketAddr, len: usize, problem: &str) {
+        self.rx_dropped += 1;
+        error!(
+            "Datagram from {} of {} byte(s) holds {}. Dropping it",
+            addr, len, problem
+        );
+    }
+}
+
+/// Fails if a datagram went out with fewer than `len` bytes.
+fn ensure_sent(num_sent: usize, len: usize) -> io::Result<()> {
+    if num_sent < len {
+        return Err(io::Error::new(
+            io::ErrorKind::WriteZero,
+            format!("Sent {} of the frame's {} byte(s)", num_sent, len),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{SimpleFrame, SimpleFrameBuilder};
+
+    #[test]
+    fn datagrams_that_are_not_one_frame_are_dropped() {
+        let mut server = Datagram::<SimpleFrameBuilder>::bind("127.0.0.1:0").unwrap();
+        let addr = server.get_ref().local_addr().unwrap();
+        let client = Datagram::<SimpleFrameBuilder>::bind("127.0.0.1:0").unwrap();
+        let raw = client.get_ref();
+
+        let mut two = SimpleFrame::new(b"a").to_bytes();
+        two.extend_from_slice(&SimpleFrame::new(b"b").to_bytes());
+        raw.send_to(&two[..], addr).unwrap();
+        raw.send_to(&[0xff; 6], addr).unwrap();
+        client.send_to(&SimpleFrame::new(b"ok"), addr).unwrap();
+
+        // Loopback keeps the datagrams in order
+        let (frame, peer) = server.recv_from().unwrap();
+        assert_eq!(frame.payload(), b"ok");
+        assert_eq!(peer, raw.local_addr().unwrap());
+        assert_eq!(server.rx_frames_dropped(), 2);
+    }
+
+    #[test]
+    fn connected_sockets_exchange_frames() {
+        let mut a = Datagram::<SimpleFrameBuilder>::bind("127.0.0.1:0").unwrap();
+        let mut b = Datagram::<SimpleFrameBuilder>::bind("127.0.0.1:0").unwrap();
+        let a_addr = a.get_ref().local_addr().unwrap();
+        let b_addr = b.get_ref().local_addr().unwrap();
+        a.get_ref().connect(b_addr).unwrap();
+        b.get_ref().connect(a_addr).unwrap();
+
+        a.send(&SimpleFrame::new(b"ping")).unwrap();
+        assert_eq!(b.recv().unwrap().payload(), b"ping");
+        b.send(&SimpleFrame::new(b"pong")).unwrap();
+        assert_eq!(a.recv().unwrap().payload(), b"pong");
+    }
+}
@@ -0,0 +1,99 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::error::Error;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// A frame started arriving but was not complete within the stream's frame deadline, as a
+/// peer trickling bytes to hold on to buffers would cause. Streams close with
+/// `CloseReason::StalledFrame` carrying this.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StalledFrame {
+    /// Bytes of the incomplete frame received.
+    pub buffered: usize,
+    /// How long the frame had been arriving for.
+    pub waited: Duration,
+}
+
+/// Tracks how long the frame at the front of the receive buffer has been incomplete.
+#[derive(Clone, Debug)]
+pub(crate) struct FrameDeadline {
+    limit: Duration,
+    /// When the first byte of the incomplete frame was seen.
+    since: Option<Instant>,
+}
+
+impl FrameDeadline {
+    pub(crate) fn new(limit: Duration) -> FrameDeadline {
+        FrameDeadline { limit, since: None }
+    }
+
+    /// Updates the tracked frame after a decode attempt, which left `buffered` bytes in the
+    /// receive buffer, and returns the frame if it is overdue. Any bytes left after a frame
+    /// was `decoded` are the start of a new frame.
+    pub(crate) fn update(&mut self, decoded: bool, buffered: usize) -> Option<StalledFrame> {
+        if decoded || buffered == 0 {
+            self.since = None;
+        }
+        if buffered == 0 {
+            return None;
+        }
+
+        let since = *self.since.get_or_insert_with(Instant::now);
+        let waited = since.elapsed();
+        if waited <= self.limit {
+            return None;
+        }
+
+        debug!(
+            "Frame incomplete after {:?} with {} byte(s) received",
+            waited, buffered
+        );
+        Some(StalledFrame { buffered, waited })
+    }
+}
+
+impl fmt::Display for StalledFrame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Frame incomplete after {:?} with {} byte(s) received",
+            self.waited, self.buffered
+        )
+    }
+}
+
+impl Error for StalledFrame {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn incomplete_frames_stall_after_the_limit() {
+        let mut deadline = FrameDeadline::new(Duration::from_millis(20));
+        assert_eq!(deadline.update(false, 3), None);
+        thread::sleep(Duration::from_millis(30));
+
+        let stalled = deadline.update(false, 5).unwrap();
+        assert_eq!(stalled.buffered, 5);
+        assert!(stalled.waited > Duration::from_millis(20));
+    }
+
+    #[test]
+    fn decoding_a_frame_restarts_the_clock() {
+        let mut deadline = FrameDeadline::new(Duration::from_millis(20));
+        assert_eq!(deadline.update(false, 3), None);
+        thread::sleep(Duration::from_millis(30));
+
+        assert_eq!(deadline.update(true, 2), None);
+        assert_eq!(deadline.update(false, 0), None);
+        assert_eq!(deadline.update(false, 1), None);
+    }
+}
@@ -0,0 +1,497 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Serves browsers over websockets and native clients over `SimpleFrame`s on the same port.
+//!
+//! ```ignore
+//! +--------------+     +-------------------------+     +--------------------+
+//! | "GET / ..."  |---->| 101 Switching Protocols |---->| WebSocket messages |
+//! +--------------+     +-------------------------+     +--------------------+
+//! | START (0x01) |------------------------------------>| SimpleFrames       |
+//! +--------------+                                     +--------------------+
+//! ```
+//!
+//! ```ignore
+//! let (socket, _) = listener.accept()?;
+//! let mut stream = DualStream::accept(socket)?;
+//! let payload = stream.b_recv()?.payload();
+//! stream.b_send_payload(&payload[..])?;
+//! ```
+//!
+//! With the `flate2` feature, websocket clients offering permessage-deflate get it.
+
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+#[cfg(windows)]
+use std::os::windows::io::AsRawSocket;
+use std::time::Instant;
+
+use crate::frame::{
+    Frame, FrameType, OpType, SimpleFrame, SimpleFrameBuilder, WebSocketFrame,
+    WebSocketMessageBuilder,
+};
+#[cfg(feature = "flate2")]
+use crate::permessage_deflate::PerMessageDeflate;
+#[cfg(unix)]
+use crate::socket::peek_fd;
+#[cfg(windows)]
+use crate::socket::peek_raw_socket;
+use crate::{Blocking, Error, NonBlocking, Plain, WebSocketSession};
+
+/// First byte of every `SimpleFrame`.
+const SIMPLE_START: u8 = 0x01;
+/// Largest HTTP upgrade request accepted, headers included.
+const MAX_REQUEST_LEN: usize = 8 * 1024;
+/// Appended to the client's key before hashing, as defined by RFC 6455.
+const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The framing a client connected with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClientProtocol {
+    /// The client upgraded from HTTP to a websocket.
+    WebSocket,
+    /// The client sent `SimpleFrame`s.
+    Simple,
+}
+
+/// A connection accepted by `DualStream::accept`, receiving and sending payloads in whichever
+/// framing the client used. Websocket clients get a `WebSocketSession` answering pings and
+/// closing, and their fragmented messages reassembled.
+pub enum DualStream<S: Read + Write> {
+    WebSocket(WebSocketSession<Plain<S, WebSocketMessageBuilder>>),
+    Simple(Plain<S, SimpleFrameBuilder>),
+}
+
+#[cfg(unix)]
+impl<S> DualStream<S>
+where
+    S: Read + Write + AsRawFd,
+{
+    /// Inspects the first bytes `stream` receives to tell the client's framing apart,
+    /// completing the websocket handshake for HTTP upgrade requests. `stream` should be in
+    /// blocking mode until this returns.
+    pub fn accept(stream: S) -> io::Result<DualStream<S>> {
+        let peeked = peek_fd(stream.as_raw_fd(), 1)?;
+        DualStream::from_first_byte(stream, peeked.first().copied())
+    }
+}
+
+#[cfg(windows)]
+impl<S> DualStream<S>
+where
+    S: Read + Write + AsRawSocket,
+{
+    /// Inspects the first bytes `stream` receives to tell the client's framing apart,
+    /// completing the websocket handshake for HTTP upgrade requests. `stream` should be in
+    /// blocking mode until this returns.
+    pub fn accept(stream: S) -> io::Result<DualStream<S>> {
+        let peeked = peek_raw_socket(stream.as_raw_socket(), 1)?;
+        DualStream::from_first_byte(stream, peeked.first().copied())
+    }
+}
+
+impl<S: Read + Write> DualStream<S> {
+    /// Picks the framing the client opened with `first`.
+    fn from_first_byte(mut stream: S, first: Option<u8>) -> io::Result<DualStream<S>> {
+        let first = match first {
+            Some(b) => b,
+            None => return Err(io::ErrorKind::UnexpectedEof.into()),
+        };
+
+        match first {
+            SIMPLE_START => {
+                debug!("Client connected with SimpleFrames");
+                Ok(DualStream::Simple(Plain::new(stream)))
+            }
+            b'G' => {
+                let extensions = upgrade(&mut stream)?;
+                debug!("Client connected with websockets");
+                let mut session = WebSocketSession::new(Plain::new(stream));
+                extensions.apply(&mut session);
+                Ok(DualStream::WebSocket(session))
+            }
+            b => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Unrecognized first byte: {:#x}", b),
+            )),
+        }
+    }
+}
+
+impl<S: Read + Write> DualStream<S> {
+    /// Returns the framing the client connected with.
+    pub fn protocol(&self) -> ClientProtocol {
+        match *self {
+            DualStream::WebSocket(_) => ClientProtocol::WebSocket,
+            DualStream::Simple(_) => ClientProtocol::Simple,
+        }
+    }
+
+    /// Sends `payload` in a frame of the client's framing, blocking until it is written.
+    /// Websocket clients receive it as a binary message.
+    pub fn b_send_payload(&mut self, payload: &[u8]) -> Result<(), Error> {
+        let frame = self.frame(payload);
+        self.b_send(&*frame)
+    }
+
+    /// Sends `payload` in a frame of the client's framing without blocking.
+    pub fn nb_send_payload(&mut self, payload: &[u8]) -> Result<(), Error> {
+        let frame = self.frame(payload);
+        self.nb_send(&*frame)
+    }
+
+    fn frame(&self, payload: &[u8]) -> Box<dyn Frame> {
+        match *self {
+            DualStream::WebSocket(_) => Box::new(WebSocketFrame::new(
+                payload,
+                FrameType::Data,
+                OpType::Binary,
+            )),
+            DualStream::Simple(_) => Box::new(SimpleFrame::new(payload)),
+        }
+    }
+}
+
+impl<S: Read + Write> Blocking for DualStream<S> {
+    fn b_recv(&mut self) -> Result<Box<dyn Frame>, Error> {
+        match *self {
+            DualStream::WebSocket(ref mut session) => session.b_recv(),
+            DualStream::Simple(ref mut stream) => stream.b_recv(),
+        }
+    }
+
+    fn b_send(&mut self, frame: &dyn Frame) -> Result<(), Error> {
+        match *self {
+            DualStream::WebSocket(ref mut session) => session.b_send(frame),
+            DualStream::Simple(ref mut stream) => stream.b_send(frame),
+        }
+    }
+}
+
+impl<S: Read + Write> NonBlocking for DualStream<S> {
+    fn nb_recv(&mut self) -> Result<Vec<Box<dyn Frame>>, Error> {
+        match *self {
+            DualStream::WebSocket(ref mut session) => session.nb_recv(),
+            DualStream::Simple(ref mut stream) => stream.nb_recv(),
+        }
+    }
+
+    fn nb_send(&mut self, frame: &dyn Frame) -> Result<(), Error> {
+        match *self {
+            DualStream::WebSocket(ref mut session) => session.nb_send(frame),
+            DualStream::Simple(ref mut stream) => stream.nb_send(frame),
+        }
+    }
+
+    fn nb_recv_retry_at(&self) -> Option<Instant> {
+        match *self {
+            DualStream::WebSocket(ref session) => session.nb_recv_retry_at(),
+            DualStream::Simple(ref stream) => stream.nb_recv_retry_at(),
+        }
+    }
+
+    fn nb_flush(&mut self) -> Result<bool, Error> {
+        match *self {
+            DualStream::WebSocket(ref mut session) => session.nb_flush(),
+            DualStream::Simple(ref mut stream) => stream.nb_flush(),
+        }
+    }
+}
+
+/// What a client asked for in its websocket upgrade request.
+struct UpgradeRequest {
+    key: String,
+    /// Every `Sec-WebSocket-Extensions` value, joined with commas.
+    extensions: String,
+}
+
+/// Websocket extensions negotiated in the opening handshake.
+struct Extensions {
+    #[cfg(feature = "flate2")]
+    deflate: Option<PerMessageDeflate>,
+}
+
+impl Extensions {
+    /// Accepts what this end supports of `offers`, a `Sec-WebSocket-Extensions` value.
+    #[cfg_attr(not(feature = "flate2"), allow(unused_variables))]
+    fn negotiate(offers: &str) -> Extensions {
+        Extensions {
+            #[cfg(feature = "flate2")]
+            deflate: PerMessageDeflate::default().accept(offers),
+        }
+    }
+
+    /// Returns the `Sec-WebSocket-Extensions` header line answering the client, if anything
+    /// was negotiated.
+    fn response_header(&self) -> String {
+        #[cfg(feature = "flate2")]
+        if let Some(ref deflate) = self.deflate {
+            return format!("Sec-WebSocket-Extensions: {}\r\n", deflate.header_value());
+        }
+        String::new()
+    }
+
+    /// Turns on what was negotiated for `session`.
+    #[cfg_attr(not(feature = "flate2"), allow(unused_variables))]
+    fn apply<S: Read + Write>(
+        self,
+        session: &mut WebSocketSession<Plain<S, WebSocketMessageBuilder>>,
+    ) {
+        #[cfg(feature = "flate2")]
+        session.set_permessage_deflate(self.deflate);
+    }
+}
+
+/// Reads an HTTP upgrade request from `stream` and answers it, leaving any bytes after the
+/// request unread. Returns the extensions negotiated.
+fn upgrade<S: Read + Write>(stream: &mut S) -> io::Result<Extensions> {
+    let request = read_request(stream)?;
+    let request = match upgrade_request(&request[..]) {
+        Some(request) => request,
+        None => {
+            stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+            stream.flush()?;
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Not a websocket upgrade request",
+            ));
+        }
+    };
+
+    let extensions = Extensions::negotiate(&request.extensions[..]);
+    let mut accept = request.key.into_bytes();
+    accept.extend_from_slice(WEBSOCKET_GUID.as_bytes());
+    let response = format!(
+        "HTTP/1.1 101 Switching Protocols\r\n\
+         Upgrade: websocket\r\n\
+         Connection: Upgrade\r\n\
+         Sec-WebSocket-Accept: {}\r\n\
+         {}\r\n",
+        base64(&sha1(&accept[..])),
+        extensions.response_header()
+    );
+    stream.write_all(response.as_bytes())?;
+    stream.flush()?;
+
+    Ok(extensions)
+}
+
+/// Reads up to and including the blank line ending the request headers, one byte at a time so
+/// frames sent right after the request stay in the socket.
+fn read_request<S: Read>(stream: &mut S) -> io::Result<String> {
+    let mut request = Vec::<u8>::with_capacity(512);
+    let mut byte = [0u8; 1];
+    while !request.ends_with(b"\r\n\r\n") {
+        if request.len() == MAX_REQUEST_LEN {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Upgrade request too long",
+            ));
+        }
+        stream.read_exact(&mut byte)?;
+        request.push(byte[0]);
+    }
+
+    String::from_utf8(request).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// Returns the key and extensions of `request` if it asks for a websocket upgrade.
+fn upgrade_request(request: &str) -> Option<UpgradeRequest> {
+    let mut lines = request.split("\r\n");
+    if !lines.next()?.starts_with("GET ") {
+        return None;
+    }
+
+    let mut upgrade = false;
+    let mut key = None;
+    let mut extensions = Vec::<&str>::new();
+    for line in lines {
+        let (name, value) = match line.split_once(':') {
+            Some(header) => header,
+            None => continue,
+        };
+        let value = value.trim();
+        if name.eq_ignore_ascii_case("Upgrade") {
+            upgrade = value.eq_ignore_ascii_case("websocket");
+        } else if name.eq_ignore_ascii_case("Sec-WebSocket-Key") {
+            key = Some(value.to_string());
+        } else if name.eq_ignore_ascii_case("Sec-WebSocket-Extensions") {
+            extensions.push(value);
+        }
+    }
+
+    if !upgrade {
+        return None;
+    }
+    Some(UpgradeRequest {
+        key: key?,
+        extensions: extensions.join(", "),
+    })
+}
+
+/// SHA-1 digest of `data`, as needed for `Sec-WebSocket-Accept`.
+#[cfg(feature = "openssl")]
+fn sha1(data: &[u8]) -> [u8; 20] {
+    openssl::sha::sha1(data)
+}
+
+/// SHA-1 digest of `data`, as needed for `Sec-WebSocket-Accept`.
+#[cfg(not(feature = "openssl"))]
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xEFCD_AB89,
+        0x98BA_DCFE,
+        0x1032_5476,
+        0xC3D2_E1F0,
+    ];
+
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..=39 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = temp;
+        }
+
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut digest = [0u8; 20];
+    for (i, v) in h.iter().enumerate() {
+        digest[i * 4..i * 4 + 4].copy_from_slice(&v.to_be_bytes());
+    }
+    digest
+}
+
+/// Standard, padded base64 encoding of `data`.
+#[cfg(feature = "openssl")]
+fn base64(data: &[u8]) -> String {
+    openssl::base64::encode_block(data)
+}
+
+/// Standard, padded base64 encoding of `data`.
+#[cfg(not(feature = "openssl"))]
+fn base64(data: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+    let mut encoded = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let n = (chunk[0] as u32) << 16
+            | (*chunk.get(1).unwrap_or(&0) as u32) << 8
+            | *chunk.get(2).unwrap_or(&0) as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(n >> (18 - i * 6)) as usize & 0x3F] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::MockStream;
+
+    /// Sends `request` to a `DualStream` and returns it along with the response.
+    fn handshake(request: &str) -> (DualStream<MockStream>, String, MockStream) {
+        let (local, mut remote) = MockStream::pair();
+        remote.write_all(request.as_bytes()).unwrap();
+        let stream = DualStream::from_first_byte(local, Some(b'G')).unwrap();
+
+        let mut response = vec![0u8; MAX_REQUEST_LEN];
+        let len = remote.read(&mut response[..]).unwrap();
+        response.truncate(len);
+        (stream, String::from_utf8(response).unwrap(), remote)
+    }
+
+    #[cfg(feature = "flate2")]
+    #[test]
+    fn permessage_deflate_is_negotiated() {
+        let (mut stream, response, mut remote) = handshake(
+            "GET /chat HTTP/1.1\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+             Sec-WebSocket-Extensions: permessage-deflate; client_max_window_bits\r\n\r\n",
+        );
+        assert!(response.ends_with("Sec-WebSocket-Extensions: permessage-deflate\r\n\r\n"));
+
+        stream.b_send_payload(&[b'a'; 64]).unwrap();
+        let mut client = Plain::<_, WebSocketMessageBuilder>::new(&mut remote);
+        let frame = client.nb_recv().unwrap().remove(0);
+        let frame = frame.downcast_ref::<WebSocketFrame>().unwrap();
+        assert!(frame.is_compressed());
+    }
+
+    #[test]
+    fn accept_matches_the_rfc_6455_sample() {
+        let (_, response, _) = handshake(
+            "GET /chat HTTP/1.1\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+        );
+        assert!(response.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(response.contains("\r\nSec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+    }
+
+    #[test]
+    fn sha1_and_base64_match_known_values() {
+        assert_eq!(base64(&sha1(b"")), "2jmj7l5rSw0yVb/vlWAYkK/YBwk=");
+        assert_eq!(base64(&sha1(&[b'a'; 1000])), "KR6abGaZSUm1e6XmUDYemPw2sbo=");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foo"), "Zm9v");
+    }
+
+    #[test]
+    fn extensions_are_not_answered_unless_offered() {
+        let (_, response, _) = handshake(
+            "GET /chat HTTP/1.1\r\n\
+             Upgrade: websocket\r\n\
+             Connection: Upgrade\r\n\
+             Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\r\n",
+        );
+        assert!(!response.contains("Sec-WebSocket-Extensions"));
+    }
+}
@@ -0,0 +1,139 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+
+/// One end of an in-memory, bidirectional byte pipe.
+///
+/// A `Duplex` behaves like a non-blocking socket: reading with nothing pending returns
+/// `ErrorKind::WouldBlock`, reading after the other end has been dropped returns `Ok(0)`, and
+/// writing after the other end has been dropped returns `ErrorKind::BrokenPipe`.
+///
+/// Reads and writes can be capped to a maximum chunk size in order to simulate a network
+/// fragmenting frames across multiple calls.
+#[derive(Debug)]
+pub struct Duplex {
+    rx: Arc<Mutex<VecDeque<u8>>>,
+    tx: Arc<Mutex<VecDeque<u8>>>,
+    max_read: usize,
+    max_write: usize,
+}
+
+impl Duplex {
+    /// Creates two connected ends. Bytes written to one end are read from the other.
+    pub fn pair() -> (Duplex, Duplex) {
+        let a_to_b = Arc::new(Mutex::new(VecDeque::new()));
+        let b_to_a = Arc::new(Mutex::new(VecDeque::new()));
+
+        let a = Duplex {
+            rx: b_to_a.clone(),
+            tx: a_to_b.clone(),
+            max_read: usize::MAX,
+            max_write: usize::MAX,
+        };
+        let b = Duplex {
+            rx: a_to_b,
+            tx: b_to_a,
+            max_read: usize::MAX,
+            max_write: usize::MAX,
+        };
+
+        (a, b)
+    }
+
+    /// Caps the number of bytes returned by a single `read` call.
+    pub fn set_max_read(&mut self, max: usize) {
+        self.max_read = max.max(1);
+    }
+
+    /// Caps the number of bytes accepted by a single `write` call.
+    pub fn set_max_write(&mut self, max: usize) {
+        self.max_write = max.max(1);
+    }
+
+    fn peer_connected(&self) -> bool {
+        Arc::strong_count(&self.tx) > 1
+    }
+}
+
+impl Read for Duplex {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut rx = self.rx.lock().unwrap_or_else(|e| e.into_inner());
+        if rx.is_empty() {
+            if self.peer_connected() {
+                return Err(io::ErrorKind::WouldBlock.into());
+            }
+            return Ok(0);
+        }
+
+        let len = buf.len().min(rx.len()).min(self.max_read);
+        for (dst, src) in buf.iter_mut().zip(rx.drain(..len)) {
+            *dst = src;
+        }
+
+        Ok(len)
+    }
+}
+
+impl Write for Duplex {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if !self.peer_connected() {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+
+        let len = buf.len().min(self.max_write);
+        let mut tx = self.tx.lock().unwrap_or_else(|e| e.into_inner());
+        tx.extend(&buf[..len]);
+
+        Ok(len)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_block_until_written_and_end_once_the_peer_is_dropped() {
+        let (mut a, mut b) = Duplex::pair();
+        let mut buf = [0u8; 8];
+        assert_eq!(
+            a.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::WouldBlock
+        );
+
+        b.write_all(b"hi").unwrap();
+        assert_eq!(a.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"hi");
+
+        drop(b);
+        assert_eq!(a.read(&mut buf).unwrap(), 0);
+        assert_eq!(
+            a.write(b"hi").unwrap_err().kind(),
+            io::ErrorKind::BrokenPipe
+        );
+    }
+
+    #[test]
+    fn reads_and_writes_are_capped() {
+        let (mut a, mut b) = Duplex::pair();
+        a.set_max_write(3);
+        b.set_max_read(2);
+
+        assert_eq!(a.write(b"hello").unwrap(), 3);
+        let mut buf = [0u8; 8];
+        assert_eq!(b.read(&mut buf).unwrap(), 2);
+        assert_eq!(b.read(&mut buf).unwrap(), 1);
+        assert_eq!(&buf[..1], b"l");
+    }
+}
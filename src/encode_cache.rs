@@ -0,0 +1,132 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Encodes frames sent to many streams once, keeping the bytes for every later send.
+//!
+//! ```ignore
+//! let cache = Arc::new(EncodeCache::new());
+//! for stream in subscribers.iter_mut() {
+//!     // Only the first send for "ticker" encodes the frame
+//!     stream.nb_send_queued(cache.queued("ticker", &frame))?;
+//! }
+//! cache.invalidate(&"ticker");
+//! ```
+
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::RwLock;
+
+use crate::frame::Frame;
+use crate::QueuedFrame;
+
+/// Encoded frames keyed by an application chosen `K`, shared between threads. Entries stay
+/// until invalidated, so a key must be invalidated or replaced whenever the frame it names
+/// changes.
+#[derive(Debug, Default)]
+pub struct EncodeCache<K> {
+    entries: RwLock<HashMap<K, Vec<u8>>>,
+}
+
+impl<K: Hash + Eq> EncodeCache<K> {
+    /// Creates an empty cache.
+    pub fn new() -> EncodeCache<K> {
+        EncodeCache {
+            entries: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Returns the frame cached under `key` ready to queue on a stream, encoding `frame` and
+    /// caching it first if `key` is not cached yet.
+    pub fn queued(&self, key: K, frame: &dyn Frame) -> QueuedFrame {
+        if let Some(queued) = self.get(&key) {
+            return queued;
+        }
+
+        let bytes = frame.to_bytes();
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        // Another thread may have encoded it in the meantime
+        let bytes = entries.entry(key).or_insert(bytes);
+        trace!("Cached {}", frame.fmt_summary());
+
+        QueuedFrame::from_bytes(bytes.clone())
+    }
+
+    /// Returns the frame cached under `key` ready to queue on a stream, if any.
+    pub fn get(&self, key: &K) -> Option<QueuedFrame> {
+        let entries = self.entries.read().unwrap_or_else(|e| e.into_inner());
+        entries
+            .get(key)
+            .map(|bytes| QueuedFrame::from_bytes(bytes.clone()))
+    }
+
+    /// Caches `frame` under `key`, replacing whatever was cached there.
+    pub fn insert(&self, key: K, frame: &dyn Frame) {
+        let bytes = frame.to_bytes();
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.insert(key, bytes);
+    }
+
+    /// Removes the frame cached under `key`, returning whether there was one.
+    pub fn invalidate(&self, key: &K) -> bool {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.remove(key).is_some()
+    }
+
+    /// Removes every cached frame.
+    pub fn clear(&self) {
+        let mut entries = self.entries.write().unwrap_or_else(|e| e.into_inner());
+        entries.clear();
+    }
+
+    /// Returns how many frames are cached.
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::SimpleFrame;
+
+    #[test]
+    fn frames_are_encoded_once_per_key() {
+        let cache = EncodeCache::new();
+        let first = cache.queued("ticker", &SimpleFrame::new(b"one"));
+        assert_eq!(first.bytes(), &SimpleFrame::new(b"one").to_bytes()[..]);
+
+        // The cached bytes win until the key is invalidated
+        let again = cache.queued("ticker", &SimpleFrame::new(b"two"));
+        assert_eq!(again.bytes(), first.bytes());
+        assert_eq!(cache.len(), 1);
+
+        assert!(cache.invalidate(&"ticker"));
+        assert!(!cache.invalidate(&"ticker"));
+        let fresh = cache.queued("ticker", &SimpleFrame::new(b"two"));
+        assert_eq!(fresh.bytes(), &SimpleFrame::new(b"two").to_bytes()[..]);
+    }
+
+    #[test]
+    fn inserts_replace_and_clear_empties() {
+        let cache = EncodeCache::new();
+        assert!(cache.get(&1).is_none());
+        cache.insert(1, &SimpleFrame::new(b"one"));
+        cache.insert(1, &SimpleFrame::new(b"uno"));
+        cache.insert(2, &SimpleFrame::new(b"two"));
+
+        let queued = cache.get(&1).unwrap();
+        assert_eq!(queued.bytes(), &SimpleFrame::new(b"uno").to_bytes()[..]);
+        assert_eq!(cache.len(), 2);
+
+        cache.clear();
+        assert!(cache.is_empty());
+    }
+}
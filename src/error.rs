@@ -0,0 +1,200 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Typed errors returned by the `Blocking` and `NonBlocking` streams.
+//!
+//! ```ignore
+//! match stream.b_recv() {
+//!     Ok(frame) => handle(frame),
+//!     Err(simple_stream::Error::Eof) => return,
+//!     Err(simple_stream::Error::Tls(msg)) => warn!("TLS failure: {}", msg),
+//!     Err(e) => return Err(e.into()),
+//! }
+//! ```
+
+use std::error;
+use std::fmt;
+use std::io;
+
+use crate::close::CloseReason;
+use crate::frame::Corruption;
+use crate::resync::Desync;
+use crate::tls::TlsError;
+
+/// What went wrong in a stream, as a type instead of an `ErrorKind` and a message.
+///
+/// `Blocking` and `NonBlocking` return it from every call. Lower level code working in
+/// `std::io::Error`s, such as `Read` and `Write` implementations, carries an `Error` inside them
+/// wherever the failure is more specific than the transport's, and `Error::from` recovers it.
+/// Each `Error` converts back into an `std::io::Error` with the same `ErrorKind`.
+#[derive(Debug)]
+pub enum Error {
+    /// The underlying transport failed, or the operation would block or was interrupted.
+    Io(io::Error),
+    /// The TLS layer failed, with OpenSSL's description of why.
+    Tls(String),
+    /// A frame announced a payload of `len` bytes, more than the `max` accepted.
+    FrameTooLarge { len: usize, max: usize },
+    /// A frame's checksum did not match its payload.
+    BadChecksum,
+    /// The peer violated the wire protocol, or sent bytes that can not be a valid frame.
+    ProtocolViolation,
+    /// The connection ended.
+    Eof,
+}
+
+impl Error {
+    /// Returns the `std::io::ErrorKind` this error is reported with.
+    pub fn kind(&self) -> io::ErrorKind {
+        match *self {
+            Error::Io(ref e) => e.kind(),
+            Error::Tls(_) => io::ErrorKind::Other,
+            Error::FrameTooLarge { .. } | Error::BadChecksum | Error::ProtocolViolation => {
+                io::ErrorKind::InvalidData
+            }
+            Error::Eof => io::ErrorKind::UnexpectedEof,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Error {
+        let kind = e.kind();
+        let close = e
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<CloseReason>());
+        if let Some(reason) = close {
+            return match *reason {
+                CloseReason::PeerClosed | CloseReason::TlsShutdown => Error::Eof,
+                CloseReason::ProtocolError => Error::ProtocolViolation,
+                _ => Error::Io(e),
+            };
+        }
+
+        let desync = e.get_ref().and_then(|inner| inner.downcast_ref::<Desync>());
+        if let Some(desync) = desync {
+            return match desync.corruption {
+                Corruption::BadChecksum => Error::BadChecksum,
+                Corruption::BadGuard | Corruption::Malformed => Error::ProtocolViolation,
+            };
+        }
+
+        let tls = e
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<TlsError>());
+        match tls {
+            Some(TlsError::Protocol(msg)) => return Error::Tls(msg.clone()),
+            Some(TlsError::Closed) | Some(TlsError::Transport(None)) => return Error::Eof,
+            _ => {}
+        }
+
+        if e.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            let inner = e.into_inner().expect("inner error checked above");
+            return *inner
+                .downcast::<Error>()
+                .expect("inner error checked above");
+        }
+
+        match kind {
+            io::ErrorKind::UnexpectedEof => Error::Eof,
+            _ => Error::Io(e),
+        }
+    }
+}
+
+impl From<io::ErrorKind> for Error {
+    fn from(kind: io::ErrorKind) -> Error {
+        Error::from(io::Error::from(kind))
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(e: Error) -> io::Error {
+        match e {
+            Error::Io(e) => e,
+            e => io::Error::new(e.kind(), e),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::Tls(ref msg) => write!(f, "TLS error: {}", msg),
+            Error::FrameTooLarge { len, max } => {
+                write!(f, "Frame of {} bytes exceeds the maximum of {}", len, max)
+            }
+            Error::BadChecksum => write!(f, "Frame checksum mismatch"),
+            Error::ProtocolViolation => write!(f, "Protocol violation"),
+            Error::Eof => write!(f, "Connection closed"),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    use crate::frame::{
+        Checksum32Frame, Checksum32FrameBuilder, Frame, FrameBuilder, SimpleFrame,
+        SimpleFrameBuilder,
+    };
+    use crate::resync::DecodePolicy;
+    use crate::testing::MockStream;
+    use crate::{Blocking, NonBlocking, Plain};
+
+    /// Receives `bytes` on a stream of `FB` that resyncs by scanning for the next frame.
+    fn recv_corrupt<FB: FrameBuilder>(bytes: &[u8]) -> Result<Vec<Box<dyn Frame>>, Error> {
+        let (local, mut remote) = MockStream::pair();
+        let mut stream = Plain::<_, FB>::new(local);
+        stream.set_decode_policy(Some(DecodePolicy::ScanForStart));
+        remote.write_all(bytes).unwrap();
+        stream.nb_recv()
+    }
+
+    #[test]
+    fn streams_return_typed_errors() {
+        let (mut stream, remote) = Plain::<_, SimpleFrameBuilder>::pair();
+        match stream.nb_recv() {
+            Err(Error::Io(ref e)) if e.kind() == io::ErrorKind::WouldBlock => {}
+            other => panic!("expected WouldBlock, got {:?}", other.map(|f| f.len())),
+        }
+
+        drop(remote);
+        assert!(matches!(stream.b_recv(), Err(Error::Eof)));
+        assert!(matches!(stream.nb_recv(), Err(Error::Eof)));
+    }
+
+    #[test]
+    fn desyncs_map_to_their_corruption() {
+        let mut bytes = Checksum32Frame::new(b"payload").to_bytes();
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert!(matches!(
+            recv_corrupt::<Checksum32FrameBuilder>(&bytes),
+            Err(Error::BadChecksum)
+        ));
+
+        let mut bytes = SimpleFrame::new(b"payload").to_bytes();
+        bytes[0] ^= 0xff;
+        assert!(matches!(
+            recv_corrupt::<SimpleFrameBuilder>(&bytes),
+            Err(Error::ProtocolViolation)
+        ));
+    }
+}
@@ -0,0 +1,281 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! ICMP errors reported through the Linux socket error queue. With `IP_RECVERR` set, the
+//! kernel reports errors caused by ICMP messages as soon as they arrive, and queues the
+//! message's details, such as its type and sender, on the socket's error queue.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::net::IpAddr;
+use std::os::unix::io::{AsRawFd, RawFd};
+
+/// What an ICMP or ICMPv6 error message reported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IcmpKind {
+    NetUnreachable,
+    HostUnreachable,
+    PortUnreachable,
+    /// The packet was too big for a link on the path, which has the passed MTU.
+    FragmentationNeeded {
+        mtu: u32,
+    },
+    AdminProhibited,
+    TimeExceeded,
+    /// Any other message, by ICMP or ICMPv6 type and code.
+    Other {
+        icmp_type: u8,
+        code: u8,
+    },
+}
+
+/// An ICMP error message received for a socket.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct IcmpError {
+    pub kind: IcmpKind,
+    /// The `errno` value the kernel mapped the message to.
+    pub errno: i32,
+    /// The host that sent the message, if known.
+    pub offender: Option<IpAddr>,
+}
+
+/// Sets or clears `IP_RECVERR`, or `IPV6_RECVERR` for IPv6 sockets, on `socket`.
+pub fn set_recv_err<F: AsRawFd>(socket: &F, enable: bool) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        use crate::sockopt::{getsockopt, setsockopt};
+
+        let fd = socket.as_raw_fd();
+        let (level, name) = match getsockopt::<libc::c_int>(fd, libc::SOL_SOCKET, libc::SO_DOMAIN)?
+        {
+            libc::AF_INET6 => (libc::IPPROTO_IPV6, libc::IPV6_RECVERR),
+            _ => (libc::IPPROTO_IP, libc::IP_RECVERR),
+        };
+        setsockopt(fd, level, name, enable as libc::c_int)
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = (socket, enable);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Removes every ICMP error queued on `socket`'s error queue, oldest first. Errors from other
+/// origins, such as local ones, are discarded. Always empty on platforms without an error
+/// queue.
+pub fn drain_error_queue<F: AsRawFd>(socket: &F) -> io::Result<Vec<IcmpError>> {
+    drain_fd(socket.as_raw_fd())
+}
+
+/// Drains the error queue of `fd` and returns the latest ICMP error on it, if any.
+pub(crate) fn take_icmp_error(fd: RawFd) -> Option<IcmpError> {
+    match drain_fd(fd) {
+        Ok(mut errors) => errors.pop(),
+        Err(e) => {
+            debug!("Unable to drain error queue: {}", e);
+            None
+        }
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn drain_fd(fd: RawFd) -> io::Result<Vec<IcmpError>> {
+    use std::mem;
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    let mut errors = Vec::<IcmpError>::new();
+    loop {
+        let mut data = [0u8; 512];
+        let mut iov = libc::iovec {
+            iov_base: data.as_mut_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+        // u64 aligns the buffer for cmsghdr
+        let mut control = [0u64; 64];
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = mem::size_of_val(&control) as _;
+
+        let result =
+            unsafe { libc::recvmsg(fd, &mut msg, libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT) };
+        if result < 0 {
+            let e = io::Error::last_os_error();
+            match e.kind() {
+                io::ErrorKind::WouldBlock => return Ok(errors),
+                io::ErrorKind::Interrupted => continue,
+                _ => return Err(e),
+            }
+        }
+
+        let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+        while !cmsg.is_null() {
+            let header = unsafe { &*cmsg };
+            let is_recverr = (header.cmsg_level == libc::IPPROTO_IP
+                && header.cmsg_type == libc::IP_RECVERR)
+                || (header.cmsg_level == libc::IPPROTO_IPV6
+                    && header.cmsg_type == libc::IPV6_RECVERR);
+            if is_recverr {
+                let ee = unsafe { libc::CMSG_DATA(cmsg) as *const libc::sock_extended_err };
+                let err = unsafe { ee.read_unaligned() };
+                let offender = unsafe { libc::SO_EE_OFFENDER(ee) };
+                let offender = unsafe {
+                    match (*offender).sa_family as libc::c_int {
+                        libc::AF_INET => {
+                            let sin = (offender as *const libc::sockaddr_in).read_unaligned();
+                            Some(IpAddr::V4(Ipv4Addr::from(u32::from_be(
+                                sin.sin_addr.s_addr,
+                            ))))
+                        }
+                        libc::AF_INET6 => {
+                            let sin6 = (offender as *const libc::sockaddr_in6).read_unaligned();
+                            Some(IpAddr::V6(Ipv6Addr::from(sin6.sin6_addr.s6_addr)))
+                        }
+                        _ => None,
+                    }
+                };
+
+                match err.ee_origin {
+                    libc::SO_EE_ORIGIN_ICMP => errors.push(IcmpError {
+                        kind: icmp_kind(err.ee_type, err.ee_code, err.ee_info),
+                        errno: err.ee_errno as i32,
+                        offender,
+                    }),
+                    libc::SO_EE_ORIGIN_ICMP6 => errors.push(IcmpError {
+                        kind: icmp6_kind(err.ee_type, err.ee_code, err.ee_info),
+                        errno: err.ee_errno as i32,
+                        offender,
+                    }),
+                    origin => trace!("Discarding queued error from origin {}", origin),
+                }
+            }
+            cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+fn drain_fd(_fd: RawFd) -> io::Result<Vec<IcmpError>> {
+    Ok(Vec::new())
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn icmp_kind(icmp_type: u8, code: u8, info: u32) -> IcmpKind {
+    const DEST_UNREACH: u8 = 3;
+    const TIME_EXCEEDED: u8 = 11;
+
+    match (icmp_type, code) {
+        (DEST_UNREACH, 0) => IcmpKind::NetUnreachable,
+        (DEST_UNREACH, 1) => IcmpKind::HostUnreachable,
+        (DEST_UNREACH, 3) => IcmpKind::PortUnreachable,
+        (DEST_UNREACH, 4) => IcmpKind::FragmentationNeeded { mtu: info },
+        (DEST_UNREACH, 9) | (DEST_UNREACH, 10) | (DEST_UNREACH, 13) => IcmpKind::AdminProhibited,
+        (TIME_EXCEEDED, _) => IcmpKind::TimeExceeded,
+        _ => IcmpKind::Other { icmp_type, code },
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn icmp6_kind(icmp_type: u8, code: u8, info: u32) -> IcmpKind {
+    const DEST_UNREACH: u8 = 1;
+    const PACKET_TOO_BIG: u8 = 2;
+    const TIME_EXCEEDED: u8 = 3;
+
+    match (icmp_type, code) {
+        (DEST_UNREACH, 0) => IcmpKind::NetUnreachable,
+        (DEST_UNREACH, 1) => IcmpKind::AdminProhibited,
+        (DEST_UNREACH, 3) => IcmpKind::HostUnreachable,
+        (DEST_UNREACH, 4) => IcmpKind::PortUnreachable,
+        (PACKET_TOO_BIG, _) => IcmpKind::FragmentationNeeded { mtu: info },
+        (TIME_EXCEEDED, _) => IcmpKind::TimeExceeded,
+        _ => IcmpKind::Other { icmp_type, code },
+    }
+}
+
+impl IcmpError {
+    /// Returns the `std::io::ErrorKind` of the error the kernel reported for this message.
+    pub fn io_kind(&self) -> io::ErrorKind {
+        io::Error::from_raw_os_error(self.errno).kind()
+    }
+}
+
+impl fmt::Display for IcmpError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.offender {
+            Some(ref addr) => write!(f, "ICMP {:?} from {}", self.kind, addr),
+            None => write!(f, "ICMP {:?}", self.kind),
+        }
+    }
+}
+
+impl Error for IcmpError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::UdpSocket;
+
+    #[test]
+    fn fresh_sockets_have_an_empty_queue() {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        assert!(drain_error_queue(&socket).unwrap().is_empty());
+        assert_eq!(take_icmp_error(socket.as_raw_fd()), None);
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn port_unreachable_is_queued() {
+        let closed = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = closed.local_addr().unwrap();
+        drop(closed);
+
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        set_recv_err(&socket, true).unwrap();
+        socket.send_to(b"ping", addr).unwrap();
+
+        // Loopback delivers the ICMP reply before send_to returns, but allow for a slow kernel
+        let mut errors = Vec::new();
+        for _ in 0..100 {
+            errors = drain_error_queue(&socket).unwrap();
+            if !errors.is_empty() {
+                break;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(10));
+        }
+
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].kind, IcmpKind::PortUnreachable);
+        assert_eq!(errors[0].io_kind(), io::ErrorKind::ConnectionRefused);
+        assert_eq!(errors[0].offender, Some(IpAddr::from([127, 0, 0, 1])));
+        assert!(drain_error_queue(&socket).unwrap().is_empty());
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    #[test]
+    fn icmp_types_map_to_kinds() {
+        assert_eq!(icmp_kind(3, 3, 0), IcmpKind::PortUnreachable);
+        assert_eq!(
+            icmp_kind(3, 4, 1400),
+            IcmpKind::FragmentationNeeded { mtu: 1400 }
+        );
+        assert_eq!(icmp_kind(11, 1, 0), IcmpKind::TimeExceeded);
+        assert_eq!(
+            icmp6_kind(2, 0, 1280),
+            IcmpKind::FragmentationNeeded { mtu: 1280 }
+        );
+        assert_eq!(icmp6_kind(1, 1, 0), IcmpKind::AdminProhibited);
+        assert_eq!(
+            icmp6_kind(4, 0, 0),
+            IcmpKind::Other {
+                icmp_type: 4,
+                code: 0
+            }
+        );
+    }
+}
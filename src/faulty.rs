@@ -0,0 +1,258 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Transport wrapper injecting pathological I/O, for soak testing streams.
+//!
+//! ```ignore
+//! let (a, b) = Duplex::pair();
+//! let faults = Faults {
+//!     short_read: 0.3,
+//!     would_block: 0.1,
+//!     interrupted: 0.05,
+//!     ..Faults::default()
+//! };
+//! // The same seed injects the same faults, so a failing run can be replayed
+//! let mut stream = Plain::<_, SimpleFrameBuilder>::new(FaultyTransport::new(a, faults, seed));
+//! ```
+
+use std::io::{self, Read, Write};
+#[cfg(unix)]
+use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(windows)]
+use std::os::windows::io::{AsRawSocket, RawSocket};
+use std::thread;
+use std::time::Duration;
+
+/// How often a `FaultyTransport` injects each fault, as probabilities from `0.0` to `1.0`
+/// checked on every `read` and `write`. All are zero by default.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Faults {
+    /// Reads are given only part of the buffer, so return fewer bytes than available.
+    pub short_read: f64,
+    /// Writes are given only part of the buffer, so accept fewer bytes than offered.
+    pub short_write: f64,
+    /// Calls fail with `ErrorKind::WouldBlock` without touching the transport.
+    pub would_block: f64,
+    /// Calls fail with `ErrorKind::Interrupted` without touching the transport.
+    pub interrupted: f64,
+    /// Calls sleep for up to `max_delay` first.
+    pub delay: f64,
+    pub max_delay: Duration,
+}
+
+/// Wraps a transport `S`, making its reads and writes short, fail with `WouldBlock` or
+/// `Interrupted`, or stall, at random as configured by `Faults`.
+///
+/// Faults are drawn from a generator seeded on creation, so the same seed injects the same
+/// faults into the same sequence of calls.
+#[derive(Debug)]
+pub struct FaultyTransport<S> {
+    inner: S,
+    faults: Faults,
+    state: u64,
+    injected: u64,
+}
+
+impl Default for Faults {
+    fn default() -> Faults {
+        Faults {
+            short_read: 0.0,
+            short_write: 0.0,
+            would_block: 0.0,
+            interrupted: 0.0,
+            delay: 0.0,
+            max_delay: Duration::from_millis(10),
+        }
+    }
+}
+
+impl<S> FaultyTransport<S> {
+    /// Wraps `inner`, injecting `faults` drawn from a generator seeded with `seed`.
+    pub fn new(inner: S, faults: Faults, seed: u64) -> FaultyTransport<S> {
+        FaultyTransport {
+            inner,
+            faults,
+            state: seed,
+            injected: 0,
+        }
+    }
+
+    /// Replaces the faults injected from now on, e.g. to let a test finish cleanly.
+    pub fn set_faults(&mut self, faults: Faults) {
+        self.faults = faults;
+    }
+
+    /// Returns how many faults have been injected so far.
+    pub fn faults_injected(&self) -> u64 {
+        self.injected
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// Sleeps, or returns the error to fail the call with, if either fault is drawn.
+    fn before_call(&mut self) -> io::Result<()> {
+        if self.roll(self.faults.delay) {
+            let max = self.faults.max_delay.as_nanos().min(u64::MAX as u128) as u64;
+            let delay = Duration::from_nanos(self.next_u64() % max.saturating_add(1));
+            trace!("Injecting a {:?} delay", delay);
+            thread::sleep(delay);
+        }
+        if self.roll(self.faults.interrupted) {
+            trace!("Injecting ErrorKind::Interrupted");
+            return Err(io::ErrorKind::Interrupted.into());
+        }
+        if self.roll(self.faults.would_block) {
+            trace!("Injecting ErrorKind::WouldBlock");
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+
+        Ok(())
+    }
+
+    /// Returns how much of a `len` byte buffer to pass on, shortening it with probability
+    /// `probability`.
+    fn call_len(&mut self, len: usize, probability: f64) -> usize {
+        if len > 1 && self.roll(probability) {
+            let short = 1 + (self.next_u64() % (len as u64 - 1)) as usize;
+            trace!("Injecting a short call of {} of {} byte(s)", short, len);
+            return short;
+        }
+
+        len
+    }
+
+    fn roll(&mut self, probability: f64) -> bool {
+        if probability <= 0.0 {
+            return false;
+        }
+
+        // 53 random bits, uniform in [0, 1)
+        let sample = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        let hit = sample < probability;
+        if hit {
+            self.injected += 1;
+        }
+        hit
+    }
+
+    /// splitmix64, which is plenty for picking faults and keeps runs reproducible.
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+}
+
+impl<S: Read> Read for FaultyTransport<S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.before_call()?;
+        let len = self.call_len(buf.len(), self.faults.short_read);
+        self.inner.read(&mut buf[..len])
+    }
+}
+
+impl<S: Write> Write for FaultyTransport<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.before_call()?;
+        let len = self.call_len(buf.len(), self.faults.short_write);
+        self.inner.write(&buf[..len])
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(unix)]
+impl<S: AsRawFd> AsRawFd for FaultyTransport<S> {
+    fn as_raw_fd(&self) -> RawFd {
+        self.inner.as_raw_fd()
+    }
+}
+
+#[cfg(windows)]
+impl<S: AsRawSocket> AsRawSocket for FaultyTransport<S> {
+    fn as_raw_socket(&self) -> RawSocket {
+        self.inner.as_raw_socket()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn faults() -> Faults {
+        Faults {
+            short_read: 0.3,
+            short_write: 0.3,
+            would_block: 0.2,
+            interrupted: 0.2,
+            ..Faults::default()
+        }
+    }
+
+    /// Returns the outcome of each of `num_writes` eight byte writes.
+    fn outcomes(seed: u64, num_writes: usize) -> Vec<Result<usize, io::ErrorKind>> {
+        let mut transport = FaultyTransport::new(Vec::<u8>::new(), faults(), seed);
+        (0..num_writes)
+            .map(|_| transport.write(&[0; 8]).map_err(|e| e.kind()))
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_injects_the_same_faults() {
+        assert_eq!(outcomes(7, 100), outcomes(7, 100));
+        assert_ne!(outcomes(7, 100), outcomes(8, 100));
+        assert!(outcomes(7, 100).contains(&Err(io::ErrorKind::WouldBlock)));
+        assert!(outcomes(7, 100).contains(&Err(io::ErrorKind::Interrupted)));
+    }
+
+    #[test]
+    fn retried_calls_move_every_byte() {
+        let data: Vec<u8> = (0..=255).collect();
+        let mut writer = FaultyTransport::new(Vec::<u8>::new(), faults(), 1);
+        let mut written = 0;
+        while written < data.len() {
+            let end = data.len().min(written + 16);
+            match writer.write(&data[written..end]) {
+                Ok(n) => written += n,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => panic!("unexpected error: {}", e),
+            }
+        }
+        assert!(writer.faults_injected() > 0);
+        assert_eq!(writer.get_ref(), &data);
+
+        let mut reader = FaultyTransport::new(Cursor::new(writer.into_inner()), faults(), 2);
+        let mut read = Vec::new();
+        let mut buf = [0u8; 16];
+        loop {
+            match reader.read(&mut buf) {
+                Ok(0) => break,
+                Ok(n) => read.extend_from_slice(&buf[..n]),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => panic!("unexpected error: {}", e),
+            }
+        }
+        assert_eq!(read, data);
+    }
+}
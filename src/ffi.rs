@@ -0,0 +1,443 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! C ABI around the framing core, so components written in other languages decode and encode
+//! frames exactly as the Rust side does. Declarations are in `include/simple_stream.h`.
+//!
+//! Build the shared library with:
+//!
+//! ```ignore
+//! cargo rustc --release --features ffi --crate-type cdylib
+//! ```
+//!
+//! Framings are selected by name:
+//!
+//! ```ignore
+//! "simple"              SimpleFrame
+//! "checksum32"          Checksum32Frame
+//! "headered"            HeaderedFrame
+//! "length_prefixed32"   LengthPrefixed32Frame, payloads of up to 16MiB
+//! "varint"              VarintFrame
+//! "newline"             DelimitedFrame ending with "\n"
+//! "crlf"                DelimitedFrame ending with "\r\n"
+//! "json"                JsonFrame, values of up to 1MiB
+//! "cobs"                CobsFrame, frames of up to 64KiB
+//! "websocket"           WebSocketFrame, encoded as unmasked binary data frames
+//! ```
+//!
+//! Every pointer returned by this module is owned by the caller until passed to the matching
+//! `ss_*_free` function. Passing a null pointer where an object is expected is reported as a
+//! failure rather than dereferenced.
+//!
+//! A decoder fed bytes that can never become a frame, e.g. because a checksum is wrong, drops
+//! them and fails from then on, as a stream closes on them. `ss_decoder_error` tells why.
+
+use std::ffi::{c_char, c_int, CStr};
+use std::ptr;
+use std::slice;
+
+use crate::frame::{
+    BuilderDecoder, Checksum32Frame, Checksum32FrameBuilder, CobsFrame, CobsFrameBuilder,
+    Corruption, CrLf, DelimitedFrame, DelimitedFrameBuilder, Delimiter, Frame, FrameBuilderInfo,
+    FrameDecoder, FrameType, HeaderedFrame, HeaderedFrameBuilder, JsonFrame, JsonFrameBuilder,
+    LengthPrefixed32Frame, LengthPrefixed32FrameBuilder, Newline, OpType, SimpleFrame,
+    SimpleFrameBuilder, VarintFrame, VarintFrameBuilder, WebSocketFrame, WebSocketFrameBuilder,
+};
+
+/// Version of the C ABI, bumped whenever a function's signature or behaviour changes.
+pub const SS_ABI_VERSION: u32 = 2;
+
+/// Returned by `ss_decoder_error` while every byte fed can still become a frame.
+pub const SS_OK: c_int = 0;
+/// Returned by `ss_decoder_error` once a byte marking the start or end of a frame was wrong.
+pub const SS_BAD_GUARD: c_int = 1;
+/// Returned by `ss_decoder_error` once a frame's checksum did not match its payload.
+pub const SS_BAD_CHECKSUM: c_int = 2;
+/// Returned by `ss_decoder_error` once a frame's header was not valid for any other reason.
+pub const SS_MALFORMED: c_int = 3;
+
+/// Decoder fed bytes by the caller, buffering them until they complete a frame.
+pub struct SsDecoder {
+    decoder: Box<dyn FrameDecoder>,
+    buf: Vec<u8>,
+    corruption: Option<Corruption>,
+}
+
+/// A decoded frame.
+pub struct SsFrame {
+    payload: Vec<u8>,
+    bytes: Vec<u8>,
+}
+
+/// Returns `SS_ABI_VERSION`.
+#[no_mangle]
+pub extern "C" fn ss_abi_version() -> u32 {
+    SS_ABI_VERSION
+}
+
+/// Creates a decoder for the framing named `framing`. Returns null if the name is not known.
+///
+/// # Safety
+///
+/// `framing` must be null or point to a NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ss_decoder_new(framing: *const c_char) -> *mut SsDecoder {
+    let decoder = match framing_name(framing).and_then(decoder_for) {
+        Some(decoder) => decoder,
+        None => return ptr::null_mut(),
+    };
+
+    Box::into_raw(Box::new(SsDecoder {
+        decoder,
+        buf: Vec::new(),
+        corruption: None,
+    }))
+}
+
+/// Frees a decoder, along with any bytes it still buffers.
+///
+/// # Safety
+///
+/// `decoder` must be null or have been returned by `ss_decoder_new` and not freed since.
+#[no_mangle]
+pub unsafe extern "C" fn ss_decoder_free(decoder: *mut SsDecoder) {
+    if !decoder.is_null() {
+        drop(Box::from_raw(decoder));
+    }
+}
+
+/// Appends `len` bytes at `data` to the decoder's buffer. Returns 0 on success, or -1 if
+/// `decoder` is null or failed, or `data` is null with a non-zero `len`.
+///
+/// # Safety
+///
+/// `decoder` must be null or a live decoder, and `data` must be valid for reads of `len`
+/// bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ss_decoder_feed(
+    decoder: *mut SsDecoder,
+    data: *const u8,
+    len: usize,
+) -> c_int {
+    let decoder = match decoder.as_mut() {
+        Some(decoder) if decoder.corruption.is_none() => decoder,
+        _ => return -1,
+    };
+
+    match bytes(data, len) {
+        Some(data) => {
+            decoder.buf.extend_from_slice(data);
+            0
+        }
+        None => -1,
+    }
+}
+
+/// Removes the next complete frame from the decoder's buffer. Returns null if no complete
+/// frame has been fed yet, or if the decoder failed, which `ss_decoder_error` then reports.
+///
+/// # Safety
+///
+/// `decoder` must be null or a live decoder.
+#[no_mangle]
+pub unsafe extern "C" fn ss_decoder_next(decoder: *mut SsDecoder) -> *mut SsFrame {
+    let decoder = match decoder.as_mut() {
+        Some(decoder) if decoder.corruption.is_none() => decoder,
+        _ => return ptr::null_mut(),
+    };
+
+    match decoder.decoder.decode(&mut decoder.buf) {
+        Some(frame) => {
+            let bytes = frame.to_bytes();
+            Box::into_raw(Box::new(SsFrame {
+                payload: frame.into_payload(),
+                bytes,
+            }))
+        }
+        None => {
+            if let Err(corruption) = decoder.decoder.validate(&decoder.buf[..]) {
+                debug!("{}. Dropping {} byte(s)", corruption, decoder.buf.len());
+                decoder.corruption = Some(corruption);
+                decoder.buf.clear();
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Returns `SS_OK` while the bytes fed can still become frames, or why they could not once
+/// the decoder failed: `SS_BAD_GUARD`, `SS_BAD_CHECKSUM` or `SS_MALFORMED`. Returns -1 if
+/// `decoder` is null.
+///
+/// # Safety
+///
+/// `decoder` must be null or a live decoder.
+#[no_mangle]
+pub unsafe extern "C" fn ss_decoder_error(decoder: *const SsDecoder) -> c_int {
+    match decoder.as_ref().map(|decoder| decoder.corruption) {
+        Some(None) => SS_OK,
+        Some(Some(Corruption::BadGuard)) => SS_BAD_GUARD,
+        Some(Some(Corruption::BadChecksum)) => SS_BAD_CHECKSUM,
+        Some(Some(Corruption::Malformed)) => SS_MALFORMED,
+        None => -1,
+    }
+}
+
+/// Returns how many bytes the decoder holds that are not part of a decoded frame yet.
+///
+/// # Safety
+///
+/// `decoder` must be null or a live decoder.
+#[no_mangle]
+pub unsafe extern "C" fn ss_decoder_buffered(decoder: *const SsDecoder) -> usize {
+    decoder.as_ref().map_or(0, |decoder| decoder.buf.len())
+}
+
+/// Returns the frame's payload, storing its length in `len`. The bytes live as long as the
+/// frame.
+///
+/// # Safety
+///
+/// `frame` must be null or a live frame, and `len` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ss_frame_payload(frame: *const SsFrame, len: *mut usize) -> *const u8 {
+    match frame.as_ref() {
+        Some(frame) => slice_out(&frame.payload[..], len),
+        None => slice_out(&[], len),
+    }
+}
+
+/// Returns the frame as it was on the wire, storing its length in `len`. The bytes live as
+/// long as the frame.
+///
+/// # Safety
+///
+/// `frame` must be null or a live frame, and `len` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ss_frame_bytes(frame: *const SsFrame, len: *mut usize) -> *const u8 {
+    match frame.as_ref() {
+        Some(frame) => slice_out(&frame.bytes[..], len),
+        None => slice_out(&[], len),
+    }
+}
+
+/// Frees a frame returned by `ss_decoder_next`.
+///
+/// # Safety
+///
+/// `frame` must be null or have been returned by `ss_decoder_next` and not freed since.
+#[no_mangle]
+pub unsafe extern "C" fn ss_frame_free(frame: *mut SsFrame) {
+    if !frame.is_null() {
+        drop(Box::from_raw(frame));
+    }
+}
+
+/// Encodes `len` bytes at `payload` as a frame of the framing named `framing`, storing the
+/// encoded length in `out_len`. Returns null if the name is not known, a pointer is invalid,
+/// or the payload can not be sent in that framing: it is longer than the framing allows, or
+/// contains the delimiter of `"newline"` or `"crlf"`. The result must be freed with
+/// `ss_buffer_free`.
+///
+/// # Safety
+///
+/// `framing` must be null or point to a NUL terminated string, `payload` must be valid for
+/// reads of `len` bytes, and `out_len` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn ss_encode(
+    framing: *const c_char,
+    payload: *const u8,
+    len: usize,
+    out_len: *mut usize,
+) -> *mut u8 {
+    let encoded = match (framing_name(framing), bytes(payload, len)) {
+        (Some(name), Some(payload)) => encode(name, payload),
+        _ => None,
+    };
+
+    match encoded {
+        Some(encoded) => {
+            let encoded = encoded.into_boxed_slice();
+            if !out_len.is_null() {
+                *out_len = encoded.len();
+            }
+            Box::into_raw(encoded) as *mut u8
+        }
+        None => {
+            if !out_len.is_null() {
+                *out_len = 0;
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees a buffer returned by `ss_encode`.
+///
+/// # Safety
+///
+/// `buf` must be null or have been returned by `ss_encode` along with `len`, and not freed
+/// since.
+#[no_mangle]
+pub unsafe extern "C" fn ss_buffer_free(buf: *mut u8, len: usize) {
+    if !buf.is_null() {
+        drop(Box::from_raw(ptr::slice_from_raw_parts_mut(buf, len)));
+    }
+}
+
+fn decoder_for(name: &str) -> Option<Box<dyn FrameDecoder>> {
+    let decoder = match name {
+        "simple" => BuilderDecoder::of::<SimpleFrameBuilder>(),
+        "checksum32" => BuilderDecoder::of::<Checksum32FrameBuilder>(),
+        "headered" => BuilderDecoder::of::<HeaderedFrameBuilder>(),
+        "length_prefixed32" => BuilderDecoder::of::<LengthPrefixed32FrameBuilder>(),
+        "varint" => BuilderDecoder::of::<VarintFrameBuilder>(),
+        "newline" => BuilderDecoder::of::<DelimitedFrameBuilder>(),
+        "crlf" => BuilderDecoder::of::<DelimitedFrameBuilder<CrLf>>(),
+        "json" => BuilderDecoder::of::<JsonFrameBuilder>(),
+        "cobs" => BuilderDecoder::of::<CobsFrameBuilder>(),
+        "websocket" => BuilderDecoder::of::<WebSocketFrameBuilder>(),
+        _ => {
+            debug!("Unknown framing: {}", name);
+            return None;
+        }
+    };
+
+    Some(Box::new(decoder))
+}
+
+fn encode(name: &str, payload: &[u8]) -> Option<Vec<u8>> {
+    let encoded = match name {
+        "simple" => SimpleFrame::new(within::<SimpleFrameBuilder>(payload)?).to_bytes(),
+        "checksum32" => Checksum32Frame::new(within::<Checksum32FrameBuilder>(payload)?).to_bytes(),
+        "headered" => HeaderedFrame::new(within::<HeaderedFrameBuilder>(payload)?).to_bytes(),
+        "length_prefixed32" => {
+            LengthPrefixed32Frame::new(within::<LengthPrefixed32FrameBuilder>(payload)?).to_bytes()
+        }
+        "varint" => VarintFrame::new(within::<VarintFrameBuilder>(payload)?).to_bytes(),
+        "newline" => {
+            let payload = undelimited::<Newline>(within::<DelimitedFrameBuilder>(payload)?)?;
+            DelimitedFrame::new(payload).to_bytes()
+        }
+        "crlf" => {
+            let payload = undelimited::<CrLf>(within::<DelimitedFrameBuilder<CrLf>>(payload)?)?;
+            DelimitedFrame::with_delimiter::<CrLf>(payload).to_bytes()
+        }
+        "json" => JsonFrame::new(within::<JsonFrameBuilder>(payload)?).to_bytes(),
+        "cobs" => CobsFrame::new(within::<CobsFrameBuilder>(payload)?).to_bytes(),
+        "websocket" => WebSocketFrame::new(payload, FrameType::Data, OpType::Binary).to_bytes(),
+        _ => {
+            debug!("Unknown framing: {}", name);
+            return None;
+        }
+    };
+
+    Some(encoded)
+}
+
+/// Returns `payload` if it is no longer than `FB` decodes.
+fn within<FB: FrameBuilderInfo>(payload: &[u8]) -> Option<&[u8]> {
+    match FB::MAX_PAYLOAD_LEN {
+        Some(max) if payload.len() > max => {
+            debug!(
+                "Payload of {} bytes exceeds {}'s maximum of {}",
+                payload.len(),
+                FB::NAME,
+                max
+            );
+            None
+        }
+        _ => Some(payload),
+    }
+}
+
+/// Returns `payload` if it does not contain `D`'s delimiter, which would split it on decode.
+fn undelimited<D: Delimiter>(payload: &[u8]) -> Option<&[u8]> {
+    if D::ESCAPE.is_none() && payload.windows(D::BYTES.len()).any(|w| w == D::BYTES) {
+        debug!("Payload contains its framing's delimiter");
+        return None;
+    }
+    Some(payload)
+}
+
+unsafe fn framing_name<'a>(framing: *const c_char) -> Option<&'a str> {
+    if framing.is_null() {
+        return None;
+    }
+    CStr::from_ptr(framing).to_str().ok()
+}
+
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> Option<&'a [u8]> {
+    match (data.is_null(), len) {
+        (true, 0) => Some(&[]),
+        (true, _) => None,
+        (false, _) => Some(slice::from_raw_parts(data, len)),
+    }
+}
+
+unsafe fn slice_out(buf: &[u8], len: *mut usize) -> *const u8 {
+    if !len.is_null() {
+        *len = buf.len();
+    }
+    buf.as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payloads_the_framing_can_not_carry_are_rejected() {
+        assert!(encode("simple", &[0u8; u16::MAX as usize]).is_some());
+        assert!(encode("simple", &[0u8; u16::MAX as usize + 1]).is_none());
+        assert!(encode("length_prefixed32", &vec![0u8; 16 * 1024 * 1024 + 1]).is_none());
+        assert!(encode("newline", b"one\ntwo").is_none());
+        assert!(encode("newline", b"one\rtwo").is_some());
+        assert!(encode("crlf", b"one\r\ntwo").is_none());
+        assert!(encode("crlf", b"one\ntwo").is_some());
+    }
+
+    #[test]
+    fn encoded_payloads_decode() {
+        let framings = [
+            "simple",
+            "checksum32",
+            "headered",
+            "length_prefixed32",
+            "varint",
+        ];
+        for name in framings
+            .into_iter()
+            .chain(["newline", "crlf", "cobs", "websocket"])
+        {
+            let mut bytes = encode(name, b"hello").unwrap();
+            let frame = decoder_for(name).unwrap().decode(&mut bytes).unwrap();
+            assert_eq!(frame.payload(), b"hello", "{}", name);
+            assert!(bytes.is_empty(), "{}", name);
+        }
+    }
+
+    #[test]
+    fn corrupt_bytes_fail_the_decoder() {
+        // A length longer than any checksum32 payload
+        let bytes = [0xff; 4];
+
+        unsafe {
+            let decoder = ss_decoder_new(c"checksum32".as_ptr());
+            assert_eq!(ss_decoder_feed(decoder, bytes.as_ptr(), bytes.len()), 0);
+            assert!(ss_decoder_next(decoder).is_null());
+            assert_eq!(ss_decoder_error(decoder), SS_MALFORMED);
+            assert_eq!(ss_decoder_buffered(decoder), 0);
+
+            // Nothing is decoded after that, not even a valid frame
+            let valid = encode("checksum32", b"hello").unwrap();
+            assert_eq!(ss_decoder_feed(decoder, valid.as_ptr(), valid.len()), -1);
+            assert!(ss_decoder_next(decoder).is_null());
+            ss_decoder_free(decoder);
+        }
+    }
+}
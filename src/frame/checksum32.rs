@@ -28,28 +28,142 @@
 //! [rfc-6455]: https://tools.ietf.org/html/rfc6455
 
 
+use std::borrow::Cow;
 use std::mem;
-use std::default::Default;
 
+use crate::buffer::RecvBuffer;
+
+use super::recycle::take_buffer;
+use super::Corruption;
 use super::Frame;
 use super::FrameBuilder;
+use super::FrameBuilderInfo;
+
 
+/// Largest payload the checksum is defined over.
+const MAX_LEN: usize = 16_843_009;
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Checksum32Frame {
     payload_len: usize,
     payload: Vec<u8>,
     checksum: u32
 }
 
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct Checksum32FrameBuilder;
+
+/// Outcome of decoding a frame from the start of a buffer.
+enum Decoded {
+    /// The frame, and its length on the wire.
+    Complete(Checksum32Frame, usize),
+    Incomplete,
+    /// The frame's checksum did not match its payload.
+    Corrupt
+}
+
 impl FrameBuilder for Checksum32FrameBuilder {
     fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
-        if buf.len() < 9 {
+        match Checksum32Frame::decode(&buf[..]) {
+            Decoded::Complete(frame, frame_len) => {
+                let mut remainder = Vec::<u8>::with_capacity(buf.len() - frame_len);
+                remainder.extend_from_slice(&buf[frame_len..buf.len()]);
+                mem::swap(buf, &mut remainder);
+
+                Some(Box::new(frame))
+            }
+            Decoded::Incomplete => None,
+            Decoded::Corrupt => {
+                error!("Checksum incorrect. Emptying passed buffer");
+                *buf = Vec::new();
+                None
+            }
+        }
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        match Checksum32Frame::decode(buf.as_slice()) {
+            Decoded::Complete(frame, frame_len) => {
+                buf.consume(frame_len);
+                Some(Box::new(frame))
+            }
+            Decoded::Incomplete => None,
+            Decoded::Corrupt => {
+                error!("Checksum incorrect. Emptying passed buffer");
+                buf.clear();
+                None
+            }
+        }
+    }
+
+    fn size_hint(buf: &[u8]) -> Option<usize> {
+        if buf.len() < 4 {
+            return None;
+        }
+
+        let payload_len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+        if payload_len > MAX_LEN {
             return None;
         }
 
+        Some(payload_len + 8)
+    }
+
+    fn validate(buf: &[u8]) -> Result<(), Corruption> {
+        if buf.len() < 4 {
+            return Ok(());
+        }
+
+        // A longer payload is never sent, so the length itself is corrupt
+        let payload_len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as usize;
+        if payload_len > MAX_LEN {
+            return Err(Corruption::Malformed);
+        }
+
+        let frame_len = payload_len + 8;
+        if buf.len() < frame_len {
+            return Ok(());
+        }
+
+        let payload = &buf[4..(frame_len - 4)];
+        let checksum = payload.iter().fold(0u32, |sum, &byte| sum.wrapping_add(byte as u32));
+        let expected = &buf[(frame_len - 4)..frame_len];
+        match checksum.to_be_bytes()[..] == expected[..] {
+            true => Ok(()),
+            false => Err(Corruption::BadChecksum)
+        }
+    }
+}
+
+impl FrameBuilderInfo for Checksum32FrameBuilder {
+    const NAME: &'static str = "checksum32";
+    const MAX_PAYLOAD_LEN: Option<usize> = Some(MAX_LEN);
+}
+
+impl Checksum32Frame {
+    pub fn new(buf: &[u8]) -> Self {
+        let len = buf.len();
+        let mut checksum: u32 = 0;
+        let mut v = Vec::<u8>::with_capacity(len);
+
+        for &byte in buf {
+            v.push(byte);
+            checksum = checksum.wrapping_add(byte as u32);
+        }
+
+        Checksum32Frame {
+            payload_len: len,
+            payload: v,
+            checksum
+        }
+    }
+
+    /// Decodes the frame at the start of `buf`.
+    fn decode(buf: &[u8]) -> Decoded {
+        if buf.len() < 8 {
+            return Decoded::Incomplete;
+        }
+
         let mut frame: Checksum32Frame = Default::default();
 
         // Payload length
@@ -63,63 +177,51 @@ impl FrameBuilder for Checksum32FrameBuilder {
         let payload_len = payload_len as usize;
         frame.payload_len = payload_len;
 
-        if buf.len() - 8 < payload_len {
-            return None;
+        match payload_len.checked_add(8) {
+            Some(frame_len) if buf.len() >= frame_len => {}
+            _ => return Decoded::Incomplete,
         }
 
         trace!("Payload length: {}", payload_len);
 
+        frame.payload = take_buffer(payload_len);
         let mut checksum: u32 = 0;
-        for x in 4..(payload_len + 4) {
-            let byte = buf[x];
+        for &byte in &buf[4..(payload_len + 4)] {
             frame.payload.push(byte);
-            checksum += byte as u32;
+            checksum = checksum.wrapping_add(byte as u32);
         }
 
         let mut maybe_checksum: u32 = 0;
-        maybe_checksum |= ((buf[payload_len + 4 + 0] as u32) << 24) & mask;
+        maybe_checksum |= ((buf[payload_len + 4] as u32) << 24) & mask;
         maybe_checksum |= ((buf[payload_len + 4 + 1] as u32) << 16) & mask;
         maybe_checksum |= ((buf[payload_len + 4 + 2] as u32) << 8) & mask;
         maybe_checksum |= buf[payload_len + 4 + 3] as u32;
 
         if maybe_checksum != checksum {
-            error!("Checksum incorrect. Emptying passed buffer");
-            *buf = Vec::new();
-            return None;
+            return Decoded::Corrupt;
         }
 
         frame.checksum = checksum;
-        let mut remainder = Vec::<u8>::with_capacity(buf.len() - frame.len_as_vec());
-        remainder.extend_from_slice(&buf[frame.len_as_vec()..buf.len()]);
-        mem::swap(buf, &mut remainder);
-
-        Some(Box::new(frame))
+        let frame_len = frame.len_as_vec();
+        Decoded::Complete(frame, frame_len)
     }
 }
 
-impl Checksum32Frame {
-    pub fn new(buf: &[u8]) -> Self {
-        let len = buf.len();
-        let mut checksum: u32 = 0;
-        let mut v = Vec::<u8>::with_capacity(len);
+impl Frame for Checksum32Frame {
+    fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
 
-        for x in 0..len {
-            let byte = buf[x];
-            v.push(byte);
-            checksum += byte as u32;
-        }
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.payload[..])
+    }
 
-        Checksum32Frame {
-            payload_len: len,
-            payload: v,
-            checksum: checksum
-        }
+    fn into_payload(self: Box<Self>) -> Vec<u8> {
+        self.payload
     }
-}
 
-impl Frame for Checksum32Frame {
-    fn payload(&self) -> Vec<u8> {
-        self.payload.clone()
+    fn with_payload(&self, payload: &[u8]) -> Option<Box<dyn Frame>> {
+        Some(Box::new(Checksum32Frame::new(payload)))
     }
 
     fn to_bytes(&self) -> Vec<u8> {
@@ -143,16 +245,10 @@ impl Frame for Checksum32Frame {
 
     fn as_mut_raw_erased(&self) -> *mut () {
         let dup = Box::new(self.clone());
-        return Box::into_raw(dup) as *mut _ as *mut ();
+        Box::into_raw(dup) as *mut _ as *mut ()
     }
-}
 
-impl Default for Checksum32Frame {
-    fn default() -> Checksum32Frame {
-        Checksum32Frame {
-            payload_len: 0,
-            payload: Vec::new(),
-            checksum: 0
-        }
+    fn kind(&self) -> &'static str {
+        "Checksum32Frame"
     }
 }
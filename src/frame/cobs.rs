@@ -0,0 +1,259 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Provides a frame encoded with Consistent Overhead Byte Stuffing, for serial ports and
+//! other byte oriented links.
+//!
+//! ```ignore
+//! +- - - - - - - - - - - - - - - - - - - - - - - - - -+- - - - -+
+//! |                COBS Encoded Payload               |  0x00   |
+//! +- - - - - - - - - - - - - - - - - - - - - - - - - -+- - - - -+
+//!
+//! COBS Encoded Payload:   The payload split at every zero byte, and into runs of at most
+//!                         254 bytes, each run preceded by a code byte of its length plus
+//!                         one. A code byte below 0xFF stands for the zero byte that ended
+//!                         its run. The encoding never contains a zero byte.
+//! Delimiter:              A single zero byte.
+//! ```
+//!
+//! As zero bytes only ever end frames, a receiver that lost bytes or joined mid-stream is back
+//! in sync as of the next zero byte. Frames whose code bytes do not add up are dropped, and
+//! zero bytes between frames are skipped, so a sender may lead with one to flush out a
+//! partial frame on the other end.
+
+use std::borrow::Cow;
+use std::mem;
+
+use crate::buffer::RecvBuffer;
+
+use super::recycle::take_buffer;
+use super::{Frame, FrameBuilder, FrameBuilderInfo};
+
+/// Largest encoded frame a `CobsFrameBuilder` accepts unless configured otherwise.
+pub const DEFAULT_MAX_COBS_LEN: usize = 64 * 1024;
+
+/// Longest run of non-zero bytes a code byte can describe.
+const MAX_RUN: usize = 254;
+
+#[derive(Clone, Debug, Default)]
+pub struct CobsFrame {
+    payload: Vec<u8>,
+}
+
+/// Builds `CobsFrame`s of up to `MAX` bytes, excluding the delimiter. Once `MAX` bytes have
+/// arrived without a delimiter, nothing more is decoded.
+#[derive(Clone, Copy, Debug)]
+pub struct CobsFrameBuilder<const MAX: usize = DEFAULT_MAX_COBS_LEN>;
+
+impl<const MAX: usize> FrameBuilder for CobsFrameBuilder<MAX> {
+    fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        let (frame, consumed) = CobsFrame::decode(&buf[..], MAX);
+        if consumed > 0 {
+            // Remove frame, and any dropped before it, from buffer
+            let mut remainder = Vec::<u8>::with_capacity(buf.len() - consumed);
+            remainder.extend_from_slice(&buf[consumed..buf.len()]);
+            mem::swap(buf, &mut remainder);
+        }
+
+        frame.map(|frame| Box::new(frame) as Box<dyn Frame>)
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        let (frame, consumed) = CobsFrame::decode(buf.as_slice(), MAX);
+        buf.consume(consumed);
+
+        frame.map(|frame| Box::new(frame) as Box<dyn Frame>)
+    }
+}
+
+impl<const MAX: usize> FrameBuilderInfo for CobsFrameBuilder<MAX> {
+    const NAME: &'static str = "cobs";
+    const MAX_PAYLOAD_LEN: Option<usize> = Some(MAX);
+    const ZERO_LENGTH_FRAMES: bool = false;
+}
+
+impl CobsFrame {
+    /// Creates a new `CobsFrame` holding `buf`, which may contain any bytes.
+    pub fn new(buf: &[u8]) -> Self {
+        CobsFrame {
+            payload: buf.to_vec(),
+        }
+    }
+
+    /// Decodes the first valid frame in `buf`, returning it along with the number of bytes
+    /// to remove from `buf`, which include any empty or malformed frames before it.
+    fn decode(buf: &[u8], max_len: usize) -> (Option<CobsFrame>, usize) {
+        let mut consumed = 0;
+        loop {
+            let rest = &buf[consumed..];
+            let encoded_len = match rest.iter().position(|&b| b == 0) {
+                Some(encoded_len) => encoded_len,
+                None => {
+                    if rest.len() > max_len {
+                        error!("No delimiter within {} bytes", max_len);
+                    }
+                    return (None, consumed);
+                }
+            };
+
+            let encoded = &rest[..encoded_len];
+            consumed += encoded_len + 1;
+            if encoded.is_empty() {
+                continue;
+            }
+            if encoded_len > max_len {
+                error!(
+                    "Frame of {} bytes exceeds {}. Dropping it",
+                    encoded_len, max_len
+                );
+                continue;
+            }
+
+            let mut frame = CobsFrame {
+                payload: take_buffer(encoded_len),
+            };
+            if !unstuff(encoded, &mut frame.payload) {
+                error!(
+                    "Malformed COBS encoding. Dropping {} bytes",
+                    encoded_len + 1
+                );
+                continue;
+            }
+
+            trace!("Payload length: {}", frame.payload.len());
+            return (Some(frame), consumed);
+        }
+    }
+}
+
+impl Frame for CobsFrame {
+    fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.payload[..])
+    }
+
+    fn into_payload(self: Box<Self>) -> Vec<u8> {
+        self.payload
+    }
+
+    fn with_payload(&self, payload: &[u8]) -> Option<Box<dyn Frame>> {
+        Some(Box::new(CobsFrame::new(payload)))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(self.len_as_vec());
+
+        // Code byte of the run being written, filled in once the run ends
+        let mut code_at = 0;
+        buf.push(0);
+        for &byte in &self.payload[..] {
+            if byte != 0 {
+                buf.push(byte);
+            }
+            if byte == 0 || buf.len() - code_at > MAX_RUN {
+                buf[code_at] = (buf.len() - code_at) as u8;
+                code_at = buf.len();
+                buf.push(0);
+            }
+        }
+        buf[code_at] = (buf.len() - code_at) as u8;
+
+        // Delimiter
+        buf.push(0);
+
+        buf
+    }
+
+    fn len_as_vec(&self) -> usize {
+        // Every payload byte is either copied or becomes a code byte. On top of that come the
+        // first code byte, one for every full run and the delimiter.
+        let mut full_runs = 0;
+        let mut run = 0;
+        for &byte in &self.payload[..] {
+            run = if byte == 0 { 0 } else { run + 1 };
+            if run == MAX_RUN {
+                full_runs += 1;
+                run = 0;
+            }
+        }
+
+        self.payload.len() + full_runs + 2
+    }
+
+    fn as_mut_raw_erased(&self) -> *mut () {
+        let dup = Box::new(self.clone());
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "CobsFrame"
+    }
+}
+
+/// Appends the payload `encoded` holds to `payload`. Returns `false` if a code byte runs past
+/// the end of `encoded`.
+fn unstuff(encoded: &[u8], payload: &mut Vec<u8>) -> bool {
+    let mut i = 0;
+    while i < encoded.len() {
+        let code = encoded[i] as usize;
+        if i + code > encoded.len() {
+            return false;
+        }
+
+        payload.extend_from_slice(&encoded[(i + 1)..(i + code)]);
+        i += code;
+
+        // The run ended at a zero byte, unless it was a full run or the last one
+        if code <= MAX_RUN && i < encoded.len() {
+            payload.push(0);
+        }
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_bytes_become_code_bytes() {
+        let frame = CobsFrame::new(&[0x11, 0x22, 0x00, 0x33]);
+        assert_eq!(frame.to_bytes(), [0x03, 0x11, 0x22, 0x02, 0x33, 0x00]);
+        assert_eq!(CobsFrame::new(&[0x00]).to_bytes(), [0x01, 0x01, 0x00]);
+    }
+
+    #[test]
+    fn payloads_round_trip_across_full_runs() {
+        for len in [1, 253, 254, 255, 600] {
+            let mut payload: Vec<u8> = (0..len).map(|i| (i % 255 + 1) as u8).collect();
+            payload[len / 2] = 0;
+            let frame = CobsFrame::new(&payload);
+            let mut buf = frame.to_bytes();
+            assert_eq!(buf.len(), frame.len_as_vec(), "length {}", len);
+            assert!(!buf[..buf.len() - 1].contains(&0));
+
+            let decoded = CobsFrameBuilder::<1024>::from_bytes(&mut buf).unwrap();
+            assert_eq!(decoded.payload(), payload, "length {}", len);
+            assert!(buf.is_empty());
+        }
+    }
+
+    #[test]
+    fn malformed_frames_are_dropped_up_to_the_next_delimiter() {
+        // A code byte running past the delimiter, then empty frames
+        let mut buf = vec![0x09, 0x01, 0x00, 0x00, 0x00];
+        buf.extend_from_slice(&CobsFrame::new(b"ok").to_bytes());
+
+        let frame = CobsFrameBuilder::<64>::from_bytes(&mut buf).unwrap();
+        assert_eq!(frame.payload(), b"ok");
+        assert!(buf.is_empty());
+    }
+}
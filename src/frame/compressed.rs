@@ -0,0 +1,340 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Compresses the payloads of frames of any format, with the schemes enabled by the `flate2`
+//! and `zstd` features.
+//!
+//! ```ignore
+//! +- - - - - - - -+- - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -+
+//! |  Compression  |                       Compressed Payload                         |
+//! +- - - - - - - -+- - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - - -+
+//!
+//! Compression:          Unsigned 8-bit integer, the preamble identifier of the scheme.
+//! Compressed Payload:   The payload, compressed with that scheme.
+//! ```
+//!
+//! Both make up the payload of a frame of the wrapped format. As every frame names its
+//! scheme, receivers decode whatever scheme a sender picked, and payloads that compression
+//! would not shrink are sent stored, as `Compression::None`.
+//!
+//! Streams compress the frames they send and decompress the frames they receive once a
+//! scheme is set with `set_compression`, e.g. the one negotiated by a `Preamble`. Otherwise:
+//!
+//! ```ignore
+//! let mut stream = Plain::<_, Compressed<SimpleFrameBuilder>>::new(socket);
+//! let json = serde_json::to_vec(&report)?;
+//! stream.b_send(&CompressedFrame::new(&SimpleFrame::new(&json), Compression::Zstd)?)?;
+//! ```
+
+use std::borrow::Cow;
+use std::io;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use crate::buffer::RecvBuffer;
+use crate::preamble::Compression;
+
+use super::{BuilderDecoder, Frame, FrameBuilder, FrameBuilderInfo, FrameDecoder};
+
+/// Largest payload a `Compressed` builder decompresses a frame into unless configured
+/// otherwise, so a small frame can not expand into an arbitrary amount of memory.
+pub const DEFAULT_MAX_DECOMPRESSED_LEN: usize = 16 * 1024 * 1024;
+
+/// A frame whose payload was compressed with `compression`.
+#[derive(Clone)]
+pub struct CompressedFrame {
+    frame: Arc<dyn Frame>,
+    payload: Vec<u8>,
+    compression: Compression,
+}
+
+/// Builds `CompressedFrame`s from frames `FB` builds, decompressing payloads of up to `MAX`
+/// bytes. Frames that fail to decompress are dropped.
+#[derive(Clone, Copy, Debug)]
+pub struct Compressed<FB, const MAX: usize = DEFAULT_MAX_DECOMPRESSED_LEN> {
+    phantom: PhantomData<FB>,
+}
+
+/// Decodes `CompressedFrame`s from frames `inner` decodes, for framing configured at runtime.
+#[derive(Clone)]
+pub struct CompressedDecoder {
+    inner: Box<dyn FrameDecoder>,
+    max_len: usize,
+}
+
+impl<FB: FrameBuilder, const MAX: usize> FrameBuilder for Compressed<FB, MAX> {
+    fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        decompress_frames(MAX, || FB::from_bytes(buf))
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        decompress_frames(MAX, || FB::from_buffer(buf))
+    }
+
+    fn size_hint(buf: &[u8]) -> Option<usize> {
+        FB::size_hint(buf)
+    }
+}
+
+impl<FB: FrameBuilderInfo, const MAX: usize> FrameBuilderInfo for Compressed<FB, MAX> {
+    const NAME: &'static str = "compressed";
+    const MAX_PAYLOAD_LEN: Option<usize> = Some(MAX);
+    const ZERO_LENGTH_FRAMES: bool = FB::ZERO_LENGTH_FRAMES;
+    const STREAMING: bool = FB::STREAMING;
+}
+
+impl CompressedDecoder {
+    /// Creates a decoder for compressed payloads of frames `inner` decodes.
+    pub fn new<D>(inner: D) -> CompressedDecoder
+    where
+        D: FrameDecoder + 'static,
+    {
+        CompressedDecoder {
+            inner: Box::new(inner),
+            max_len: DEFAULT_MAX_DECOMPRESSED_LEN,
+        }
+    }
+
+    /// Same as `new`, for frames `FB` builds.
+    pub fn of<FB: FrameBuilder>() -> CompressedDecoder {
+        CompressedDecoder::new(BuilderDecoder::of::<FB>())
+    }
+
+    /// Drops frames whose payload decompresses to more than `max_len` bytes, instead of
+    /// more than `DEFAULT_MAX_DECOMPRESSED_LEN`.
+    pub fn max_len(mut self, max_len: usize) -> CompressedDecoder {
+        self.max_len = max_len;
+        self
+    }
+}
+
+impl FrameDecoder for CompressedDecoder {
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        let inner = &mut self.inner;
+        decompress_frames(self.max_len, || inner.decode(buf))
+    }
+
+    fn decode_buffer(&mut self, buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        let inner = &mut self.inner;
+        decompress_frames(self.max_len, || inner.decode_buffer(buf))
+    }
+
+    fn size_hint(&self, buf: &[u8]) -> Option<usize> {
+        self.inner.size_hint(buf)
+    }
+
+    fn box_clone(&self) -> Box<dyn FrameDecoder> {
+        Box::new(self.clone())
+    }
+}
+
+impl CompressedFrame {
+    /// Compresses the payload of `frame` with `compression`, falling back to storing it if
+    /// that would not make it smaller.
+    pub fn new(frame: &dyn Frame, compression: Compression) -> io::Result<CompressedFrame> {
+        let payload = frame.payload();
+        let compressed = compress_payload(compression, &payload[..])?;
+        let compression = Compression::from_id(compressed[0]).expect("Compressed with a known id");
+        let frame = frame.with_payload(&compressed[..]).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} can not carry a compressed payload", frame.kind()),
+            )
+        })?;
+
+        Ok(CompressedFrame {
+            frame: Arc::from(frame),
+            payload,
+            compression,
+        })
+    }
+
+    /// Returns the scheme the payload was compressed with.
+    pub fn compression(&self) -> Compression {
+        self.compression
+    }
+
+    /// Returns the frame carrying the compressed payload.
+    pub fn inner(&self) -> &dyn Frame {
+        &*self.frame
+    }
+}
+
+impl Frame for CompressedFrame {
+    fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.payload[..])
+    }
+
+    fn into_payload(self: Box<Self>) -> Vec<u8> {
+        self.payload
+    }
+
+    /// Compresses `payload` with the scheme this frame was compressed with.
+    fn with_payload(&self, payload: &[u8]) -> Option<Box<dyn Frame>> {
+        let frame = self.frame.with_payload(payload)?;
+        match CompressedFrame::new(&*frame, self.compression) {
+            Ok(frame) => Some(Box::new(frame)),
+            Err(e) => {
+                error!("Unable to compress payload: {}", e);
+                None
+            }
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.frame.to_bytes()
+    }
+
+    fn len_as_vec(&self) -> usize {
+        self.frame.len_as_vec()
+    }
+
+    fn as_mut_raw_erased(&self) -> *mut () {
+        let dup = Box::new(self.clone());
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "CompressedFrame"
+    }
+}
+
+/// Returns `payload` compressed with `compression`, led by the identifier of the scheme, or
+/// stored if compressing would not make it smaller.
+pub(crate) fn compress_payload(compression: Compression, payload: &[u8]) -> io::Result<Vec<u8>> {
+    let compressed: Option<Vec<u8>> = match compression {
+        Compression::None => None,
+        #[cfg(feature = "flate2")]
+        Compression::Deflate => {
+            use std::io::Write;
+
+            let mut encoder = flate2::write::DeflateEncoder::new(
+                vec![compression.id()],
+                flate2::Compression::default(),
+            );
+            encoder.write_all(payload)?;
+            Some(encoder.finish()?)
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            let mut buf = vec![compression.id()];
+            buf.extend_from_slice(&zstd::bulk::compress(payload, 0)?[..]);
+            Some(buf)
+        }
+    };
+
+    match compressed {
+        Some(compressed) if compressed.len() <= payload.len() => Ok(compressed),
+        _ => {
+            let mut stored = Vec::<u8>::with_capacity(payload.len() + 1);
+            stored.push(Compression::None.id());
+            stored.extend_from_slice(payload);
+            Ok(stored)
+        }
+    }
+}
+
+/// Returns the payload `buf`, as built by `compress_payload`, holds, along with the scheme it
+/// was compressed with. Fails if it would be longer than `max_len` bytes.
+pub(crate) fn decompress_payload(buf: &[u8], max_len: usize) -> io::Result<(Compression, Vec<u8>)> {
+    let (&id, data) = buf
+        .split_first()
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Missing compression id"))?;
+    let compression = Compression::from_id(id).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Unsupported compression id {}", id),
+        )
+    })?;
+
+    // Stop one byte past the limit, which is enough to tell it was exceeded
+    let mut payload = Vec::<u8>::new();
+    match compression {
+        Compression::None => payload.extend_from_slice(data),
+        #[cfg(feature = "flate2")]
+        Compression::Deflate => {
+            use std::io::Read;
+
+            flate2::read::DeflateDecoder::new(data)
+                .take(max_len as u64 + 1)
+                .read_to_end(&mut payload)?;
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            use std::io::Read;
+
+            zstd::stream::read::Decoder::new(data)?
+                .take(max_len as u64 + 1)
+                .read_to_end(&mut payload)?;
+        }
+    }
+
+    if payload.len() > max_len {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("Payload decompresses to over {} bytes", max_len),
+        ));
+    }
+
+    Ok((compression, payload))
+}
+
+/// Decompresses the next frame `decode_inner` returns, skipping frames that fail to.
+fn decompress_frames<F>(max_len: usize, mut decode_inner: F) -> Option<Box<dyn Frame>>
+where
+    F: FnMut() -> Option<Box<dyn Frame>>,
+{
+    loop {
+        let frame = decode_inner()?;
+        match decompress_payload(&frame.payload_ref(), max_len) {
+            Ok((compression, payload)) => {
+                trace!(
+                    "Decompressed {:?} payload: {} byte(s)",
+                    compression,
+                    payload.len()
+                );
+                return Some(Box::new(CompressedFrame {
+                    frame: Arc::from(frame),
+                    payload,
+                    compression,
+                }));
+            }
+            Err(e) => error!("{}. Dropping {}", e, frame.fmt_summary()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{Decoder, SimpleFrame, SimpleFrameBuilder};
+
+    #[test]
+    fn stored_payloads_decode() {
+        let frame = CompressedFrame::new(&SimpleFrame::new(b"hello"), Compression::None).unwrap();
+        assert_eq!(frame.compression(), Compression::None);
+
+        let mut decoder = Decoder::<Compressed<SimpleFrameBuilder>>::new();
+        let decoded = decoder.push_bytes(&frame.to_bytes());
+        assert_eq!(decoded.len(), 1);
+        assert_eq!(decoded[0].payload(), b"hello");
+    }
+
+    #[test]
+    fn raw_pointers_are_to_a_copy_of_the_compressed_frame() {
+        let frame = CompressedFrame::new(&SimpleFrame::new(b"hello"), Compression::None).unwrap();
+
+        let copy = unsafe { Box::from_raw(frame.as_mut_raw_erased() as *mut CompressedFrame) };
+        assert_eq!(copy.compression(), frame.compression());
+        assert_eq!(copy.payload(), frame.payload());
+        assert_eq!(copy.to_bytes(), frame.to_bytes());
+    }
+}
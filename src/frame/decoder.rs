@@ -0,0 +1,296 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Decodes frames from bytes pushed in by hand, the same way streams do from bytes they read,
+//! so framing can be driven by fuzzers and property tests without any I/O.
+//!
+//! ```ignore
+//! fuzz_target!(|chunks: Vec<Vec<u8>>| {
+//!     let mut decoder = Decoder::<WebSocketFrameBuilder>::new();
+//!     for chunk in chunks {
+//!         for frame in decoder.push_bytes(&chunk) {
+//!             let _ = frame.to_bytes();
+//!         }
+//!     }
+//! });
+//! ```
+
+use std::marker::PhantomData;
+
+use crate::buffer::RecvBuffer;
+
+use super::{
+    reserve_frame, BuilderDecoder, Corruption, DynamicBuilder, Frame, FrameBuilder, FrameDecoder,
+};
+
+/// Buffers bytes until they hold complete frames `FB` builds, without a stream around it.
+pub struct Decoder<FB: FrameBuilder> {
+    buf: RecvBuffer,
+    decoder: Box<dyn FrameDecoder>,
+    corruption: Option<Corruption>,
+    phantom: PhantomData<FB>,
+}
+
+impl<FB: FrameBuilder> Decoder<FB> {
+    /// Creates a decoder with nothing buffered.
+    pub fn new() -> Decoder<FB> {
+        Decoder {
+            buf: RecvBuffer::with_capacity(0),
+            decoder: Box::new(BuilderDecoder::of::<FB>()),
+            corruption: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Appends `bytes` to what is buffered, and returns every frame now complete, in order.
+    /// Bytes after the last complete frame stay buffered for the next call, unless they can
+    /// never become a frame. Those are dropped, as a stream closes on them, and `corruption`
+    /// reports why. Nothing is decoded after that.
+    pub fn push_bytes(&mut self, bytes: &[u8]) -> Vec<Box<dyn Frame>> {
+        let mut frames = Vec::<Box<dyn Frame>>::new();
+        if self.corruption.is_some() {
+            return frames;
+        }
+
+        self.buf.extend_from_slice(bytes);
+        reserve_frame(&*self.decoder, &mut self.buf);
+
+        while let Some(frame) = self.decoder.decode_buffer(&mut self.buf) {
+            frames.push(frame);
+        }
+        if let Err(corruption) = self.decoder.validate(self.buf.as_slice()) {
+            self.corruption = Some(corruption);
+            self.buf.clear();
+        }
+        frames
+    }
+
+    /// Returns why the bytes pushed can never become a frame, once they could not.
+    pub fn corruption(&self) -> Option<Corruption> {
+        self.corruption
+    }
+
+    /// Returns the bytes buffered that are not part of a complete frame yet.
+    pub fn buffered(&self) -> &[u8] {
+        self.buf.as_slice()
+    }
+
+    /// Drops everything buffered, and forgets any corruption, so decoding starts over.
+    pub fn clear(&mut self) {
+        self.buf.clear();
+        self.corruption = None;
+    }
+}
+
+impl Decoder<DynamicBuilder> {
+    /// Creates a decoder using `decoder`, as `Plain::with_decoder` does.
+    pub fn with_decoder<D: FrameDecoder + 'static>(decoder: D) -> Decoder<DynamicBuilder> {
+        Decoder {
+            buf: RecvBuffer::with_capacity(0),
+            decoder: Box::new(decoder),
+            corruption: None,
+            phantom: PhantomData,
+        }
+    }
+}
+
+impl<FB: FrameBuilder> Default for Decoder<FB> {
+    fn default() -> Decoder<FB> {
+        Decoder::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::chunking::verify_chunking;
+    use crate::frame::{
+        Checksum32Frame, Checksum32FrameBuilder, CobsFrame, CobsFrameBuilder, DelimitedDecoder,
+        DelimitedFrame, DelimitedFrameBuilder, FrameType, HeaderedFrame, HeaderedFrameBuilder,
+        LengthPrefixed32Frame, LengthPrefixed32FrameBuilder, Lenient, OpType, PaddedFrame,
+        PaddedFrameBuilder, PaddingPolicy, SimpleFrame, SimpleFrameBuilder, TlvFrame,
+        TlvFrameBuilder, VarintFrame, VarintFrameBuilder, WebSocketFrame, WebSocketFrameBuilder,
+        WebSocketMessageBuilder,
+    };
+
+    /// Checks that `header`, announcing a frame far longer than will ever arrive, stays
+    /// buffered as is, as does every prefix of it, without a frame being decoded.
+    fn assert_incomplete<FB: FrameBuilder>(header: &[u8]) {
+        for len in 0..=header.len() {
+            let mut decoder = Decoder::<FB>::new();
+            assert!(decoder.push_bytes(&header[..len]).is_empty());
+            assert_eq!(decoder.buffered(), &header[..len]);
+            assert_eq!(decoder.corruption(), None);
+        }
+        verify_chunking::<FB>(header, 3).unwrap();
+    }
+
+    /// Checks that `header`, announcing a length `FB` never accepts, is reported as malformed
+    /// and dropped, rather than waited on.
+    fn assert_malformed<FB: FrameBuilder>(header: &[u8]) {
+        assert_eq!(FB::validate(header), Err(Corruption::Malformed));
+
+        let mut decoder = Decoder::<FB>::new();
+        assert!(decoder.push_bytes(header).is_empty());
+        assert_eq!(decoder.corruption(), Some(Corruption::Malformed));
+        assert!(decoder.buffered().is_empty());
+        assert!(decoder.push_bytes(header).is_empty());
+        assert!(decoder.buffered().is_empty());
+    }
+
+    /// Checks that `frame`, with an empty payload, decodes back to an empty payload.
+    fn assert_empty_payload<FB: FrameBuilder>(frame: &dyn Frame) {
+        let mut decoder = Decoder::<FB>::new();
+        let frames = decoder.push_bytes(&frame.to_bytes());
+        assert_eq!(frames.len(), 1);
+        assert!(frames[0].payload().is_empty());
+        assert!(decoder.buffered().is_empty());
+    }
+
+    #[test]
+    fn lengths_past_the_maximum_are_malformed() {
+        let mut checksum32 = u32::MAX.to_be_bytes().to_vec();
+        checksum32.extend_from_slice(&[0u8; 4]);
+        assert_malformed::<Checksum32FrameBuilder>(&checksum32);
+        assert_malformed::<LengthPrefixed32FrameBuilder>(&[0xff; 4]);
+
+        // A varint of u64::MAX
+        let mut varint = vec![0xff; 9];
+        varint.push(0x01);
+        assert_malformed::<VarintFrameBuilder>(&varint);
+
+        // u16::MAX headers, followed by a payload of u32::MAX bytes
+        assert_malformed::<HeaderedFrameBuilder>(&[1, 0, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff]);
+
+        let mut websocket = vec![0x82, 127];
+        websocket.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert_malformed::<WebSocketFrameBuilder>(&websocket);
+        assert_malformed::<WebSocketMessageBuilder>(&websocket);
+
+        let mut websocket = vec![0x82, 127];
+        websocket.extend_from_slice(&(i64::MAX as u64).to_be_bytes());
+        assert_malformed::<WebSocketMessageBuilder>(&websocket);
+    }
+
+    #[test]
+    fn lengths_up_to_the_maximum_wait_for_more_bytes() {
+        assert_incomplete::<LengthPrefixed32FrameBuilder<{ u32::MAX }>>(&[0xff; 4]);
+        assert_incomplete::<HeaderedFrameBuilder<Lenient, { u32::MAX }>>(&[
+            1, 0, 0, 0, 0xff, 0xff, 0xff, 0xff,
+        ]);
+
+        let mut websocket = vec![0x82, 127];
+        websocket.extend_from_slice(&(i64::MAX as u64).to_be_bytes());
+        assert_incomplete::<WebSocketFrameBuilder>(&websocket);
+    }
+
+    #[test]
+    fn empty_payloads_decode() {
+        assert_empty_payload::<SimpleFrameBuilder>(&SimpleFrame::new(&[]));
+        assert_empty_payload::<Checksum32FrameBuilder>(&Checksum32Frame::new(&[]));
+        assert_empty_payload::<LengthPrefixed32FrameBuilder>(&LengthPrefixed32Frame::new(&[]));
+        assert_empty_payload::<VarintFrameBuilder>(&VarintFrame::new(&[]));
+        assert_empty_payload::<HeaderedFrameBuilder>(&HeaderedFrame::new(&[]));
+        assert_empty_payload::<TlvFrameBuilder>(&TlvFrame::new(1, &[]));
+        assert_empty_payload::<DelimitedFrameBuilder>(&DelimitedFrame::new(&[]));
+        assert_empty_payload::<CobsFrameBuilder>(&CobsFrame::new(&[]));
+
+        let websocket = WebSocketFrame::new(&[], FrameType::Data, OpType::Binary);
+        assert_empty_payload::<WebSocketFrameBuilder>(&websocket);
+        assert_empty_payload::<WebSocketMessageBuilder>(&websocket);
+    }
+
+    /// Checks that `encoded`, one piece per frame, pushed at once or in chunks, decodes to
+    /// the payloads `expected` with nothing left buffered.
+    fn assert_drains<FB: FrameBuilder>(
+        new: impl Fn() -> Decoder<FB>,
+        encoded: &[Vec<u8>],
+        expected: &[Vec<u8>],
+    ) {
+        let bytes = encoded.concat();
+        for chunk_len in [bytes.len(), 7, 1] {
+            let mut decoder = new();
+            let mut payloads = Vec::<Vec<u8>>::new();
+            for chunk in bytes.chunks(chunk_len) {
+                payloads.extend(
+                    decoder
+                        .push_bytes(chunk)
+                        .iter()
+                        .map(|frame| frame.payload()),
+                );
+            }
+            assert_eq!(payloads, expected);
+            assert!(decoder.buffered().is_empty());
+        }
+    }
+
+    #[test]
+    fn many_frames_drain_in_order() {
+        let payloads: Vec<Vec<u8>> = (0..500u32).map(|i| i.to_string().into_bytes()).collect();
+
+        let delimited: Vec<Vec<u8>> = payloads
+            .iter()
+            .map(|payload| DelimitedFrame::new(payload).to_bytes())
+            .collect();
+        assert_drains(Decoder::<DelimitedFrameBuilder>::new, &delimited, &payloads);
+        assert_drains(
+            || Decoder::with_decoder(DelimitedDecoder::new(b"\n", 64)),
+            &delimited,
+            &payloads,
+        );
+
+        let padded: Vec<Vec<u8>> = payloads
+            .iter()
+            .map(|payload| PaddedFrame::new(&SimpleFrame::new(payload), PaddingPolicy::Random(16)))
+            .map(|frame| frame.to_bytes())
+            .collect();
+        assert_drains(
+            Decoder::<PaddedFrameBuilder<SimpleFrameBuilder>>::new,
+            &padded,
+            &payloads,
+        );
+
+        #[cfg(feature = "echo")]
+        {
+            use crate::frame::{EchoFrame, EchoFrameBuilder};
+
+            let echoes: Vec<EchoFrame> = (0..500).map(|_| EchoFrame::echo()).collect();
+            assert_drains(
+                Decoder::<EchoFrameBuilder>::new,
+                &echoes
+                    .iter()
+                    .map(|echo| echo.to_bytes())
+                    .collect::<Vec<_>>(),
+                &echoes.iter().map(|echo| echo.payload()).collect::<Vec<_>>(),
+            );
+        }
+
+        // Every message split in two, with a ping between its fragments
+        let mut messages = Vec::<Vec<u8>>::new();
+        let mut expected = Vec::<Vec<u8>>::new();
+        for payload in payloads.iter() {
+            let (head, tail) = payload.split_at(1);
+            messages.push(
+                [
+                    WebSocketFrame::fragment(head, FrameType::Data, OpType::Text, false),
+                    WebSocketFrame::new(b"ping", FrameType::Control, OpType::Ping),
+                    WebSocketFrame::fragment(tail, FrameType::Data, OpType::Continuation, true),
+                ]
+                .iter()
+                .flat_map(|frame| frame.to_bytes())
+                .collect(),
+            );
+            expected.push(b"ping".to_vec());
+            expected.push(payload.clone());
+        }
+        assert_drains(
+            Decoder::<WebSocketMessageBuilder>::new,
+            &messages,
+            &expected,
+        );
+    }
+}
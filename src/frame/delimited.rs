@@ -0,0 +1,344 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Provides a frame terminated by a delimiter, for line oriented protocols such as Redis
+//! inline commands, STOMP, or log shipping.
+//!
+//! ```ignore
+//! +- - - - - - - - - - - - - - - - - -+- - - - - - - - - - -+
+//! |           Payload Data            |      Delimiter      |
+//! +- - - - - - - - - - - - - - - - - -+- - - - - - - - - - -+
+//!
+//! Payload Data:   Any bytes other than the delimiter. If the delimiter defines an escape
+//!                 byte, occurrences of the escape byte and of the first byte of the
+//!                 delimiter are preceded by the escape byte.
+//! Delimiter:      One or more bytes, e.g. "\n" or "\r\n".
+//! ```
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::mem;
+
+use crate::buffer::RecvBuffer;
+
+use super::{Frame, FrameBuilder, FrameBuilderInfo, FrameDecoder};
+
+/// Largest payload a `DelimitedFrameBuilder` accepts unless configured otherwise.
+pub const DEFAULT_MAX_LINE_LEN: usize = 64 * 1024;
+
+/// The bytes ending a `DelimitedFrame`, and how occurrences of them in the payload are
+/// escaped. Implement this on a marker type to use a custom delimiter.
+pub trait Delimiter {
+    /// The delimiter. Must not be empty.
+    const BYTES: &'static [u8];
+    /// Escapes delimiters within payloads if set. Without one, payloads containing the
+    /// delimiter are split into several frames.
+    const ESCAPE: Option<u8> = None;
+}
+
+/// Frames end with `\n`.
+#[derive(Clone, Copy, Debug)]
+pub struct Newline;
+
+/// Frames end with `\r\n`.
+#[derive(Clone, Copy, Debug)]
+pub struct CrLf;
+
+/// Frames end with a zero byte, as STOMP frames do.
+#[derive(Clone, Copy, Debug)]
+pub struct Nul;
+
+/// Frames end with `\n`, and newlines and backslashes in payloads are escaped with `\`.
+#[derive(Clone, Copy, Debug)]
+pub struct EscapedNewline;
+
+impl Delimiter for Newline {
+    const BYTES: &'static [u8] = b"\n";
+}
+
+impl Delimiter for CrLf {
+    const BYTES: &'static [u8] = b"\r\n";
+}
+
+impl Delimiter for Nul {
+    const BYTES: &'static [u8] = b"\0";
+}
+
+impl Delimiter for EscapedNewline {
+    const BYTES: &'static [u8] = b"\n";
+    const ESCAPE: Option<u8> = Some(b'\\');
+}
+
+#[derive(Clone, Debug)]
+pub struct DelimitedFrame {
+    delimiter: Cow<'static, [u8]>,
+    escape: Option<u8>,
+    payload: Vec<u8>,
+}
+
+/// Builds `DelimitedFrame`s ending with `D`, with payloads of up to `MAX` bytes before
+/// escaping. Once `MAX` bytes have arrived without a delimiter, nothing more is decoded.
+#[derive(Clone, Copy, Debug)]
+pub struct DelimitedFrameBuilder<D: Delimiter = Newline, const MAX: usize = DEFAULT_MAX_LINE_LEN> {
+    phantom: PhantomData<D>,
+}
+
+/// Decodes `DelimitedFrame`s with a delimiter, escape byte and maximum payload length chosen
+/// at runtime, the same as `DelimitedFrameBuilder` does for ones chosen at compile time.
+#[derive(Clone, Debug)]
+pub struct DelimitedDecoder {
+    delimiter: Vec<u8>,
+    escape: Option<u8>,
+    max_line_len: usize,
+}
+
+impl<D: Delimiter, const MAX: usize> FrameBuilder for DelimitedFrameBuilder<D, MAX> {
+    fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        let (frame_len, payload) = find_frame(buf, D::BYTES, D::ESCAPE, MAX)?;
+        remove_frame(buf, frame_len);
+
+        Some(Box::new(DelimitedFrame {
+            delimiter: Cow::Borrowed(D::BYTES),
+            escape: D::ESCAPE,
+            payload,
+        }))
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        let (frame_len, payload) = find_frame(buf.as_slice(), D::BYTES, D::ESCAPE, MAX)?;
+        buf.consume(frame_len);
+
+        Some(Box::new(DelimitedFrame {
+            delimiter: Cow::Borrowed(D::BYTES),
+            escape: D::ESCAPE,
+            payload,
+        }))
+    }
+}
+
+impl<D: Delimiter, const MAX: usize> FrameBuilderInfo for DelimitedFrameBuilder<D, MAX> {
+    const NAME: &'static str = "delimited";
+    const MAX_PAYLOAD_LEN: Option<usize> = Some(MAX);
+}
+
+impl DelimitedDecoder {
+    /// Creates a decoder for frames ending with `delimiter`, with payloads of up to
+    /// `max_line_len` bytes before escaping.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `delimiter` is empty.
+    pub fn new(delimiter: &[u8], max_line_len: usize) -> DelimitedDecoder {
+        assert!(!delimiter.is_empty(), "Delimiter must not be empty");
+        DelimitedDecoder {
+            delimiter: delimiter.to_vec(),
+            escape: None,
+            max_line_len,
+        }
+    }
+
+    /// Escapes delimiters within payloads with `escape`.
+    pub fn escape(mut self, escape: u8) -> DelimitedDecoder {
+        self.escape = Some(escape);
+        self
+    }
+}
+
+impl FrameDecoder for DelimitedDecoder {
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        let (frame_len, payload) =
+            find_frame(buf, &self.delimiter[..], self.escape, self.max_line_len)?;
+        remove_frame(buf, frame_len);
+
+        Some(Box::new(DelimitedFrame {
+            delimiter: Cow::Owned(self.delimiter.clone()),
+            escape: self.escape,
+            payload,
+        }))
+    }
+
+    fn decode_buffer(&mut self, buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        let (frame_len, payload) = find_frame(
+            buf.as_slice(),
+            &self.delimiter[..],
+            self.escape,
+            self.max_line_len,
+        )?;
+        buf.consume(frame_len);
+
+        Some(Box::new(DelimitedFrame {
+            delimiter: Cow::Owned(self.delimiter.clone()),
+            escape: self.escape,
+            payload,
+        }))
+    }
+
+    fn box_clone(&self) -> Box<dyn FrameDecoder> {
+        Box::new(self.clone())
+    }
+}
+
+/// Looks for a complete frame at the start of `buf`, returning its length and unescaped
+/// payload.
+fn find_frame(
+    buf: &[u8],
+    delimiter: &[u8],
+    escape: Option<u8>,
+    max_line_len: usize,
+) -> Option<(usize, Vec<u8>)> {
+    let mut payload = Vec::<u8>::new();
+    let mut i = 0;
+    while i < buf.len() {
+        if buf[i..].starts_with(delimiter) {
+            trace!("Payload length: {}", payload.len());
+            return Some((i + delimiter.len(), payload));
+        }
+
+        if Some(buf[i]) == escape {
+            // The escaped byte has not arrived yet
+            if i + 1 == buf.len() {
+                return None;
+            }
+            i += 1;
+        }
+
+        if payload.len() == max_line_len {
+            error!(
+                "No delimiter within {} bytes. Buffer corrupted?",
+                max_line_len
+            );
+            return None;
+        }
+        payload.push(buf[i]);
+        i += 1;
+    }
+
+    None
+}
+
+fn remove_frame(buf: &mut Vec<u8>, frame_len: usize) {
+    let mut remainder = Vec::<u8>::with_capacity(buf.len() - frame_len);
+    remainder.extend_from_slice(&buf[frame_len..buf.len()]);
+    mem::swap(buf, &mut remainder);
+}
+
+impl DelimitedFrame {
+    /// Creates a new `DelimitedFrame` ending with a newline.
+    pub fn new(buf: &[u8]) -> Self {
+        DelimitedFrame::with_delimiter::<Newline>(buf)
+    }
+
+    /// Creates a new `DelimitedFrame` ending with `D`.
+    pub fn with_delimiter<D: Delimiter>(buf: &[u8]) -> Self {
+        DelimitedFrame {
+            delimiter: Cow::Borrowed(D::BYTES),
+            escape: D::ESCAPE,
+            payload: buf.to_vec(),
+        }
+    }
+
+    fn needs_escape(&self, byte: u8) -> bool {
+        match self.escape {
+            Some(escape) => byte == escape || byte == self.delimiter[0],
+            None => false,
+        }
+    }
+}
+
+impl Frame for DelimitedFrame {
+    fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.payload[..])
+    }
+
+    fn into_payload(self: Box<Self>) -> Vec<u8> {
+        self.payload
+    }
+
+    fn with_payload(&self, payload: &[u8]) -> Option<Box<dyn Frame>> {
+        let mut frame = self.clone();
+        frame.payload = payload.to_vec();
+        Some(Box::new(frame))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(self.len_as_vec());
+        for &byte in self.payload.iter() {
+            if self.needs_escape(byte) {
+                buf.push(self.escape.unwrap_or_default());
+            }
+            buf.push(byte);
+        }
+        buf.extend_from_slice(&self.delimiter[..]);
+
+        buf
+    }
+
+    fn len_as_vec(&self) -> usize {
+        let num_escaped = self
+            .payload
+            .iter()
+            .filter(|&&byte| self.needs_escape(byte))
+            .count();
+        self.payload.len() + num_escaped + self.delimiter.len()
+    }
+
+    fn as_mut_raw_erased(&self) -> *mut () {
+        let dup = Box::new(self.clone());
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "DelimitedFrame"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escaped_delimiters_stay_in_the_payload() {
+        let frame = DelimitedFrame::with_delimiter::<EscapedNewline>(b"a\nb\\c");
+        let bytes = frame.to_bytes();
+        assert_eq!(bytes, b"a\\\nb\\\\c\n");
+        assert_eq!(bytes.len(), frame.len_as_vec());
+
+        let mut buf = bytes.clone();
+        let decoded = DelimitedFrameBuilder::<EscapedNewline>::from_bytes(&mut buf).unwrap();
+        assert_eq!(decoded.payload(), b"a\nb\\c");
+        assert!(buf.is_empty());
+
+        let mut decoder = DelimitedDecoder::new(b"\n", 64).escape(b'\\');
+        let mut buf = bytes;
+        assert_eq!(decoder.decode(&mut buf).unwrap().payload(), b"a\nb\\c");
+    }
+
+    #[test]
+    fn unescaped_delimiters_split_the_payload() {
+        let mut buf = DelimitedFrame::with_delimiter::<CrLf>(b"one\r\ntwo").to_bytes();
+        let first = DelimitedFrameBuilder::<CrLf>::from_bytes(&mut buf).unwrap();
+        let second = DelimitedFrameBuilder::<CrLf>::from_bytes(&mut buf).unwrap();
+        assert_eq!(first.payload(), b"one");
+        assert_eq!(second.payload(), b"two");
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn lines_over_the_maximum_are_never_decoded() {
+        let mut buf = b"12345\n".to_vec();
+        assert!(DelimitedFrameBuilder::<Newline, 4>::from_bytes(&mut buf).is_none());
+        assert_eq!(buf, b"12345\n");
+
+        let mut buf = b"1234\n".to_vec();
+        let frame = DelimitedFrameBuilder::<Newline, 4>::from_bytes(&mut buf).unwrap();
+        assert_eq!(frame.payload(), b"1234");
+    }
+}
@@ -0,0 +1,212 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Provides a tiny echo protocol used to probe a connection and measure its round trip time.
+//! An `Echo` carries a nonce that the peer sends back, unchanged, in an `EchoReply`.
+//!
+//! ```ignore
+//! 0                   1                   2                   3
+//! 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |     Type      |                    Nonce                      |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                       Nonce Continued                         |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! | Nonce Cont.   |
+//! +-+-+-+-+-+-+-+-+
+//!
+//! Type:     8 bits (0x01 Echo, 0x02 EchoReply)
+//! Nonce:    Unsigned 64-bit integer Network Byte Order.
+//! ```
+
+use std::mem;
+
+use crate::buffer::RecvBuffer;
+
+use super::{random_u64, Corruption, Frame, FrameBuilder, FrameBuilderInfo};
+
+const ECHO: u8 = 0x01;
+const ECHO_REPLY: u8 = 0x02;
+const FRAME_LEN: usize = 9;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EchoType {
+    Echo,
+    EchoReply,
+}
+
+#[derive(Clone, Debug)]
+pub struct EchoFrame {
+    echo_type: EchoType,
+    nonce: u64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct EchoFrameBuilder;
+
+impl FrameBuilder for EchoFrameBuilder {
+    fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        // Frames of an unknown type are dropped, so the ones after them still decode
+        loop {
+            if buf.len() < FRAME_LEN {
+                return None;
+            }
+            let frame = EchoFrame::decode(buf);
+
+            // Remove frame from buffer
+            let mut remainder = Vec::<u8>::with_capacity(buf.len() - FRAME_LEN);
+            remainder.extend_from_slice(&buf[FRAME_LEN..buf.len()]);
+            mem::swap(buf, &mut remainder);
+
+            if let Some(frame) = frame {
+                return Some(Box::new(frame));
+            }
+        }
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        loop {
+            if buf.len() < FRAME_LEN {
+                return None;
+            }
+            let frame = EchoFrame::decode(buf.as_slice());
+            buf.consume(FRAME_LEN);
+
+            if let Some(frame) = frame {
+                return Some(Box::new(frame));
+            }
+        }
+    }
+
+    fn size_hint(_buf: &[u8]) -> Option<usize> {
+        Some(FRAME_LEN)
+    }
+
+    fn validate(buf: &[u8]) -> Result<(), Corruption> {
+        match buf.first() {
+            Some(&ECHO) | Some(&ECHO_REPLY) | None => Ok(()),
+            Some(_) => Err(Corruption::Malformed),
+        }
+    }
+}
+
+impl FrameBuilderInfo for EchoFrameBuilder {
+    const NAME: &'static str = "echo";
+    const MAX_PAYLOAD_LEN: Option<usize> = Some(8);
+    const ZERO_LENGTH_FRAMES: bool = false;
+}
+
+impl EchoFrame {
+    /// Creates a new `Echo` with a random nonce.
+    pub fn echo() -> Self {
+        EchoFrame {
+            echo_type: EchoType::Echo,
+            nonce: random_u64(),
+        }
+    }
+
+    /// Creates the `EchoReply` answering this frame.
+    pub fn reply(&self) -> Self {
+        EchoFrame {
+            echo_type: EchoType::EchoReply,
+            nonce: self.nonce,
+        }
+    }
+
+    /// Interprets a received frame as an `EchoFrame`, if it is one.
+    pub fn from_frame(frame: &dyn Frame) -> Option<Self> {
+        if frame.kind() != "EchoFrame" {
+            return None;
+        }
+
+        EchoFrame::decode(&frame.to_bytes())
+    }
+
+    pub fn echo_type(&self) -> EchoType {
+        self.echo_type
+    }
+
+    pub fn nonce(&self) -> u64 {
+        self.nonce
+    }
+
+    fn decode(buf: &[u8]) -> Option<Self> {
+        if buf.len() < FRAME_LEN {
+            return None;
+        }
+
+        let echo_type = match buf[0] {
+            ECHO => EchoType::Echo,
+            ECHO_REPLY => EchoType::EchoReply,
+            _ => {
+                error!("Invalid echo type: {:#b}. Dropping frame", buf[0]);
+                return None;
+            }
+        };
+
+        let mut nonce = [0u8; 8];
+        nonce.copy_from_slice(&buf[1..FRAME_LEN]);
+
+        Some(EchoFrame {
+            echo_type,
+            nonce: u64::from_be_bytes(nonce),
+        })
+    }
+}
+
+impl Frame for EchoFrame {
+    fn payload(&self) -> Vec<u8> {
+        self.nonce.to_be_bytes().to_vec()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(FRAME_LEN);
+        buf.push(match self.echo_type {
+            EchoType::Echo => ECHO,
+            EchoType::EchoReply => ECHO_REPLY,
+        });
+        buf.extend_from_slice(&self.nonce.to_be_bytes());
+
+        buf
+    }
+
+    fn len_as_vec(&self) -> usize {
+        FRAME_LEN
+    }
+
+    fn as_mut_raw_erased(&self) -> *mut () {
+        let dup = Box::new(self.clone());
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "EchoFrame"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::Decoder;
+
+    #[test]
+    fn unknown_types_are_dropped() {
+        let echo = EchoFrame::echo();
+        let mut bytes = vec![0x7f; FRAME_LEN];
+        bytes.extend(echo.to_bytes());
+
+        assert_eq!(
+            EchoFrameBuilder::validate(&bytes),
+            Err(Corruption::Malformed)
+        );
+        let mut decoder = Decoder::<EchoFrameBuilder>::new();
+        let frames = decoder.push_bytes(&bytes);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].payload(), echo.payload());
+        assert!(decoder.buffered().is_empty());
+    }
+}
@@ -0,0 +1,506 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Provides a frame carrying a versioned set of key/value headers in front of its payload.
+//!
+//! ```ignore
+//! 0                   1                   2                   3
+//! 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |    Version    |     Flags     |         Header Count          |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                        Payload Length                         |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |          Header Key           |      Header Value Length      |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                  Header Value ... (Header Count times)        |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                         Payload Data                          |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//!
+//! Version:              8 bits, currently 1
+//! Flags:                8 bits, all reserved and sent as zero
+//! Header Count:         Unsigned 16-bit integer Network Byte Order.
+//! Payload Length:       Unsigned 32-bit integer Network Byte Order.
+//! Header Key:           Unsigned 16-bit integer Network Byte Order.
+//! Header Value Length:  Unsigned 16-bit integer Network Byte Order.
+//! ```
+//!
+//! # Forward compatibility
+//!
+//! The layout of the fixed fields and of each header entry never changes between versions,
+//! so any version of the format can be framed by any peer. Newer versions only define new
+//! header keys and flag bits. Header keys below `APPLICATION_HEADER_KEYS` are reserved for
+//! this crate, keys at or above it belong to the application and are never validated.
+//!
+//! A `Lenient` builder accepts any version, ignores reserved flag bits, and keeps headers
+//! with unknown keys. A `Strict` builder only accepts frames this crate version fully
+//! understands, dropping any frame with a different version, reserved flag bits set, or an
+//! unknown reserved header key.
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::mem;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::buffer::RecvBuffer;
+
+use super::recycle::take_buffer;
+use super::{Corruption, Frame, FrameBuilder, FrameBuilderInfo};
+
+/// Version of the headered frame format produced by this crate.
+pub const HEADERED_FRAME_VERSION: u8 = 1;
+
+/// Largest headers and payload together a `HeaderedFrameBuilder` accepts unless configured
+/// otherwise.
+pub const DEFAULT_MAX_HEADERED_LEN: u32 = 16 * 1024 * 1024;
+
+/// First header key available for application use.
+pub const APPLICATION_HEADER_KEYS: u16 = 0x8000;
+
+/// Reserved header key carrying a `TraceContext`.
+pub const TRACE_CONTEXT_HEADER: u16 = 0x0001;
+
+/// Reserved header key carrying the time a frame expires at.
+pub const EXPIRY_HEADER: u16 = 0x0002;
+
+/// Reserved header keys understood by this crate version.
+const KNOWN_HEADER_KEYS: &[u16] = &[TRACE_CONTEXT_HEADER, EXPIRY_HEADER];
+
+const FIXED_LEN: usize = 8;
+const ENTRY_LEN: usize = 4;
+const TRACE_CONTEXT_LEN: usize = 25;
+const EXPIRY_LEN: usize = 8;
+
+/// Selects how a `HeaderedFrameBuilder` treats fields it does not understand.
+pub trait DecodeMode {
+    const STRICT: bool;
+}
+
+/// Rejects frames with a different version, reserved flags, or unknown reserved keys.
+#[derive(Clone, Copy, Debug)]
+pub struct Strict;
+
+/// Accepts frames from newer peers, ignoring fields it does not understand.
+#[derive(Clone, Copy, Debug)]
+pub struct Lenient;
+
+impl DecodeMode for Strict {
+    const STRICT: bool = true;
+}
+
+impl DecodeMode for Lenient {
+    const STRICT: bool = false;
+}
+
+/// Distributed tracing context propagated alongside a frame, laid out as in the W3C Trace
+/// Context `traceparent`: a 16 byte trace id, an 8 byte parent span id, and 8 bits of flags.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub span_id: u64,
+    pub flags: u8,
+}
+
+#[derive(Clone, Debug)]
+pub struct HeaderedFrame {
+    version: u8,
+    flags: u8,
+    headers: Vec<(u16, Vec<u8>)>,
+    payload: Vec<u8>,
+}
+
+/// Builds `HeaderedFrame`s with up to `MAX` bytes of headers and payload together. A frame
+/// announcing more is never decoded, and reported as `Corruption::Malformed`, so a peer can
+/// not make the stream buffer more than that.
+#[derive(Clone, Copy, Debug)]
+pub struct HeaderedFrameBuilder<M: DecodeMode = Lenient, const MAX: u32 = DEFAULT_MAX_HEADERED_LEN>
+{
+    phantom: PhantomData<M>,
+}
+
+impl<M: DecodeMode, const MAX: u32> FrameBuilder for HeaderedFrameBuilder<M, MAX> {
+    fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        loop {
+            let (frame, frame_len) = HeaderedFrame::decode(buf, MAX)?;
+
+            // Remove frame from buffer
+            let mut remainder = Vec::<u8>::with_capacity(buf.len() - frame_len);
+            remainder.extend_from_slice(&buf[frame_len..buf.len()]);
+            mem::swap(buf, &mut remainder);
+
+            if M::STRICT && !frame.is_understood() {
+                error!("Dropping headered frame this version does not understand");
+                continue;
+            }
+
+            return Some(Box::new(frame));
+        }
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        loop {
+            let (frame, frame_len) = HeaderedFrame::decode(buf.as_slice(), MAX)?;
+            buf.consume(frame_len);
+
+            if M::STRICT && !frame.is_understood() {
+                error!("Dropping headered frame this version does not understand");
+                continue;
+            }
+
+            return Some(Box::new(frame));
+        }
+    }
+
+    fn size_hint(buf: &[u8]) -> Option<usize> {
+        if buf.len() < FIXED_LEN {
+            return None;
+        }
+
+        // The payload follows a variable number of headers, so every header entry needs to
+        // have arrived before the total is known.
+        let header_count = u16::from_be_bytes([buf[2], buf[3]]) as usize;
+        let payload_len = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]) as usize;
+        let mut offset = FIXED_LEN;
+        for _ in 0..header_count {
+            if buf.len().saturating_sub(offset) < ENTRY_LEN {
+                return None;
+            }
+
+            let value_len = u16::from_be_bytes([buf[offset + 2], buf[offset + 3]]) as usize;
+            offset = offset.checked_add(ENTRY_LEN + value_len)?;
+        }
+
+        offset
+            .checked_add(payload_len)
+            .filter(|&frame_len| frame_len - FIXED_LEN <= MAX as usize)
+    }
+
+    fn validate(buf: &[u8]) -> Result<(), Corruption> {
+        if buf.len() < FIXED_LEN {
+            return Ok(());
+        }
+
+        // Checked as far as the header entries have arrived, computed in u64 so that it can
+        // not overflow
+        let header_count = u16::from_be_bytes([buf[2], buf[3]]);
+        let mut body_len = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]) as u64;
+        let mut offset = FIXED_LEN;
+        for _ in 0..header_count {
+            if body_len > MAX as u64 || buf.len().saturating_sub(offset) < ENTRY_LEN {
+                break;
+            }
+
+            let value_len = u16::from_be_bytes([buf[offset + 2], buf[offset + 3]]) as usize;
+            body_len += (ENTRY_LEN + value_len) as u64;
+            offset = offset.saturating_add(ENTRY_LEN + value_len);
+        }
+
+        if body_len > MAX as u64 {
+            return Err(Corruption::Malformed);
+        }
+
+        Ok(())
+    }
+}
+
+impl<M: DecodeMode, const MAX: u32> FrameBuilderInfo for HeaderedFrameBuilder<M, MAX> {
+    const NAME: &'static str = "headered";
+    const VERSION: u32 = HEADERED_FRAME_VERSION as u32;
+    const MAX_PAYLOAD_LEN: Option<usize> = Some(MAX as usize);
+}
+
+impl HeaderedFrame {
+    /// Creates a new `HeaderedFrame` with no headers.
+    pub fn new(buf: &[u8]) -> Self {
+        HeaderedFrame {
+            version: HEADERED_FRAME_VERSION,
+            flags: 0,
+            headers: Vec::new(),
+            payload: buf.to_vec(),
+        }
+    }
+
+    /// Interprets a received frame as a `HeaderedFrame`, if it is one.
+    pub fn from_frame(frame: &dyn Frame) -> Option<Self> {
+        if frame.kind() != "HeaderedFrame" {
+            return None;
+        }
+
+        HeaderedFrame::decode(&frame.to_bytes(), u32::MAX).map(|(frame, _)| frame)
+    }
+
+    /// Returns the format version the sender used.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Returns the value of the header `key`, if present.
+    pub fn header(&self, key: u16) -> Option<&[u8]> {
+        self.headers
+            .iter()
+            .find(|&&(k, _)| k == key)
+            .map(|(_, value)| &value[..])
+    }
+
+    /// Returns all headers, in the order they were sent.
+    pub fn headers(&self) -> &[(u16, Vec<u8>)] {
+        &self.headers[..]
+    }
+
+    /// Sets the header `key`, replacing any previous value. Values are truncated to
+    /// `u16::MAX` bytes.
+    pub fn set_header(&mut self, key: u16, value: &[u8]) {
+        let value = value[..value.len().min(u16::MAX as usize)].to_vec();
+        match self.headers.iter_mut().find(|&&mut (k, _)| k == key) {
+            Some(entry) => entry.1 = value,
+            None => self.headers.push((key, value)),
+        }
+    }
+
+    /// Removes the header `key`, returning its value if it was present.
+    pub fn remove_header(&mut self, key: u16) -> Option<Vec<u8>> {
+        let idx = self.headers.iter().position(|&(k, _)| k == key)?;
+        Some(self.headers.remove(idx).1)
+    }
+
+    /// Attaches a tracing context to this frame.
+    pub fn set_trace_context(&mut self, ctx: TraceContext) {
+        let mut value = Vec::<u8>::with_capacity(TRACE_CONTEXT_LEN);
+        value.extend_from_slice(&ctx.trace_id.to_be_bytes());
+        value.extend_from_slice(&ctx.span_id.to_be_bytes());
+        value.push(ctx.flags);
+        self.set_header(TRACE_CONTEXT_HEADER, &value[..]);
+    }
+
+    /// Returns the tracing context the sender attached, if any. A malformed context is
+    /// treated as absent.
+    pub fn trace_context(&self) -> Option<TraceContext> {
+        let value = self.header(TRACE_CONTEXT_HEADER)?;
+        if value.len() != TRACE_CONTEXT_LEN {
+            error!("Invalid trace context length: {}", value.len());
+            return None;
+        }
+
+        let mut trace_id = [0u8; 16];
+        trace_id.copy_from_slice(&value[0..16]);
+        let mut span_id = [0u8; 8];
+        span_id.copy_from_slice(&value[16..24]);
+
+        Some(TraceContext {
+            trace_id: u128::from_be_bytes(trace_id),
+            span_id: u64::from_be_bytes(span_id),
+            flags: value[24],
+        })
+    }
+
+    /// Marks this frame as stale after `expires_at`, so that relays forwarding it across
+    /// several hops can drop it instead. The time is sent in milliseconds since the Unix
+    /// epoch, so hops need reasonably synchronized clocks.
+    pub fn set_expires_at(&mut self, expires_at: SystemTime) {
+        let millis = expires_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or(Duration::ZERO)
+            .as_millis()
+            .min(u64::MAX as u128) as u64;
+        self.set_header(EXPIRY_HEADER, &millis.to_be_bytes());
+    }
+
+    /// Marks this frame as stale once `ttl` has passed from now.
+    pub fn set_ttl(&mut self, ttl: Duration) {
+        let now = SystemTime::now();
+        self.set_expires_at(now.checked_add(ttl).unwrap_or(now));
+    }
+
+    /// Returns the time the sender marked this frame stale after, if any. A malformed expiry
+    /// is treated as absent.
+    pub fn expires_at(&self) -> Option<SystemTime> {
+        let value = self.header(EXPIRY_HEADER)?;
+        let millis: [u8; EXPIRY_LEN] = match value.try_into() {
+            Ok(millis) => millis,
+            Err(_) => {
+                error!("Invalid expiry length: {}", value.len());
+                return None;
+            }
+        };
+
+        UNIX_EPOCH.checked_add(Duration::from_millis(u64::from_be_bytes(millis)))
+    }
+
+    /// Whether this frame is past the expiry its sender set. Relays and bridges should drop
+    /// expired frames instead of forwarding them.
+    pub fn is_expired(&self) -> bool {
+        self.expires_at()
+            .is_some_and(|expires_at| SystemTime::now() >= expires_at)
+    }
+
+    /// Whether every field of this frame is understood by this crate version.
+    fn is_understood(&self) -> bool {
+        self.version == HEADERED_FRAME_VERSION
+            && self.flags == 0
+            && self
+                .headers
+                .iter()
+                .all(|&(k, _)| k >= APPLICATION_HEADER_KEYS || KNOWN_HEADER_KEYS.contains(&k))
+    }
+
+    /// Decodes a complete frame from the front of `buf`, with up to `max` bytes of headers and
+    /// payload, returning it along with the number of bytes it occupied.
+    fn decode(buf: &[u8], max: u32) -> Option<(Self, usize)> {
+        if buf.len() < FIXED_LEN {
+            return None;
+        }
+
+        let version = buf[0];
+        let flags = buf[1];
+        let header_count = u16::from_be_bytes([buf[2], buf[3]]) as usize;
+        let payload_len = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]) as usize;
+
+        let mut offset = FIXED_LEN;
+        let mut headers = Vec::with_capacity(header_count);
+        for _ in 0..header_count {
+            if buf.len().saturating_sub(offset) < ENTRY_LEN {
+                return None;
+            }
+
+            let key = u16::from_be_bytes([buf[offset], buf[offset + 1]]);
+            let value_len = u16::from_be_bytes([buf[offset + 2], buf[offset + 3]]) as usize;
+            offset += ENTRY_LEN;
+            if buf.len() - offset < value_len {
+                return None;
+            }
+
+            headers.push((key, buf[offset..(offset + value_len)].to_vec()));
+            offset += value_len;
+        }
+
+        let frame_len = offset.checked_add(payload_len)?;
+        if frame_len - FIXED_LEN > max as usize {
+            error!(
+                "Headers and payload length {} exceed the maximum of {}",
+                frame_len - FIXED_LEN,
+                max
+            );
+            return None;
+        }
+        if buf.len() < frame_len {
+            return None;
+        }
+
+        trace!(
+            "Version: {} Headers: {} Payload length: {}",
+            version,
+            header_count,
+            payload_len
+        );
+
+        let mut payload = take_buffer(payload_len);
+        payload.extend_from_slice(&buf[offset..frame_len]);
+
+        let frame = HeaderedFrame {
+            version,
+            flags,
+            headers,
+            payload,
+        };
+
+        Some((frame, frame_len))
+    }
+}
+
+impl Frame for HeaderedFrame {
+    fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.payload[..])
+    }
+
+    fn into_payload(self: Box<Self>) -> Vec<u8> {
+        self.payload
+    }
+
+    fn with_payload(&self, payload: &[u8]) -> Option<Box<dyn Frame>> {
+        let mut frame = self.clone();
+        frame.payload = payload.to_vec();
+        Some(Box::new(frame))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(self.len_as_vec());
+        buf.push(self.version);
+        buf.push(self.flags);
+        buf.extend_from_slice(&(self.headers.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        for (key, value) in self.headers.iter() {
+            buf.extend_from_slice(&key.to_be_bytes());
+            buf.extend_from_slice(&(value.len() as u16).to_be_bytes());
+            buf.extend_from_slice(&value[..]);
+        }
+        buf.extend_from_slice(&self.payload[..]);
+
+        buf
+    }
+
+    fn len_as_vec(&self) -> usize {
+        let headers_len: usize = self
+            .headers
+            .iter()
+            .map(|(_, value)| ENTRY_LEN + value.len())
+            .sum();
+        FIXED_LEN + headers_len + self.payload.len()
+    }
+
+    fn as_mut_raw_erased(&self) -> *mut () {
+        let dup = Box::new(self.clone());
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "HeaderedFrame"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::Decoder;
+
+    #[test]
+    fn headers_survive_decoding() {
+        let ctx = TraceContext {
+            trace_id: 7,
+            span_id: 9,
+            flags: 1,
+        };
+        let mut frame = HeaderedFrame::new(b"hello");
+        frame.set_trace_context(ctx);
+        frame.set_header(APPLICATION_HEADER_KEYS, b"value");
+
+        let frames = Decoder::<HeaderedFrameBuilder>::new().push_bytes(&frame.to_bytes());
+        let decoded = HeaderedFrame::from_frame(&*frames[0]).unwrap();
+        assert_eq!(decoded.trace_context(), Some(ctx));
+        assert_eq!(decoded.header(APPLICATION_HEADER_KEYS), Some(&b"value"[..]));
+        assert_eq!(decoded.payload(), b"hello");
+    }
+
+    #[test]
+    fn only_lenient_builders_keep_unknown_reserved_headers() {
+        let mut unknown = HeaderedFrame::new(b"unknown");
+        unknown.set_header(APPLICATION_HEADER_KEYS - 1, b"value");
+        let mut bytes = unknown.to_bytes();
+        bytes.extend_from_slice(&HeaderedFrame::new(b"known").to_bytes());
+
+        let lenient = Decoder::<HeaderedFrameBuilder<Lenient>>::new().push_bytes(&bytes);
+        assert_eq!(lenient.len(), 2);
+
+        let strict = Decoder::<HeaderedFrameBuilder<Strict>>::new().push_bytes(&bytes);
+        assert_eq!(strict.len(), 1);
+        assert_eq!(strict[0].payload(), b"known");
+    }
+}
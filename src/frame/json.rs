@@ -0,0 +1,262 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Provides a frame holding one JSON value, for protocols sending JSON values back to back
+//! without a delimiter or length prefix.
+//!
+//! ```ignore
+//! {"id":1,"op":"subscribe"}{"id":2,"op":"ping"} [1,2,3]
+//! \_______________________/\_________________/ \_____/
+//!          JsonFrame            JsonFrame      JsonFrame
+//! ```
+//!
+//! A value ends where its closing brace, bracket or quote is found, skipping over any inside
+//! strings. Numbers, `true`, `false` and `null` only end once a byte that can not continue
+//! them arrives, so `JsonFrame::to_bytes` follows them with a newline. Whitespace between
+//! values is skipped. Values are framed, not validated, so a malformed value is still
+//! returned once its braces and brackets balance.
+
+use std::borrow::Cow;
+use std::mem;
+
+use crate::buffer::RecvBuffer;
+
+use super::recycle::take_buffer;
+use super::{Frame, FrameBuilder, FrameBuilderInfo};
+
+/// Largest value a `JsonFrameBuilder` accepts unless configured otherwise.
+pub const DEFAULT_MAX_JSON_LEN: usize = 1024 * 1024;
+
+#[derive(Clone, Debug, Default)]
+pub struct JsonFrame {
+    payload: Vec<u8>,
+}
+
+/// Builds `JsonFrame`s of up to `MAX` bytes. Once `MAX` bytes have arrived without a complete
+/// value, nothing more is decoded.
+#[derive(Clone, Copy, Debug)]
+pub struct JsonFrameBuilder<const MAX: usize = DEFAULT_MAX_JSON_LEN>;
+
+impl<const MAX: usize> FrameBuilder for JsonFrameBuilder<MAX> {
+    fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        let (frame, frame_len) = JsonFrame::decode(&buf[..], MAX)?;
+
+        // Remove frame and the whitespace before it from buffer
+        let mut remainder = Vec::<u8>::with_capacity(buf.len() - frame_len);
+        remainder.extend_from_slice(&buf[frame_len..buf.len()]);
+        mem::swap(buf, &mut remainder);
+
+        Some(Box::new(frame))
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        let (frame, frame_len) = JsonFrame::decode(buf.as_slice(), MAX)?;
+        buf.consume(frame_len);
+
+        Some(Box::new(frame))
+    }
+}
+
+impl<const MAX: usize> FrameBuilderInfo for JsonFrameBuilder<MAX> {
+    const NAME: &'static str = "json";
+    const MAX_PAYLOAD_LEN: Option<usize> = Some(MAX);
+    const ZERO_LENGTH_FRAMES: bool = false;
+}
+
+impl JsonFrame {
+    /// Creates a new `JsonFrame` holding `buf`, which should be a single JSON value.
+    pub fn new(buf: &[u8]) -> Self {
+        JsonFrame {
+            payload: buf.to_vec(),
+        }
+    }
+
+    /// Decodes the first value in `buf`, returning it along with the number of bytes it and
+    /// the whitespace before it occupied.
+    fn decode(buf: &[u8], max_len: usize) -> Option<(JsonFrame, usize)> {
+        let start = buf.iter().position(|&b| !is_whitespace(b))?;
+        let value = &buf[start..];
+        let value_len = match value[0] {
+            b'{' | b'[' => nested_len(value),
+            b'"' => string_len(&value[1..]).map(|len| len + 1),
+            b'-' | b'0'..=b'9' | b't' | b'f' | b'n' => {
+                value.iter().position(|&b| !is_scalar_byte(b))
+            }
+            b => {
+                error!(
+                    "Byte {:#x} can not start a JSON value. Buffer corrupted?",
+                    b
+                );
+                return None;
+            }
+        };
+
+        let value_len = match value_len {
+            Some(value_len) if value_len <= max_len => value_len,
+            Some(_) => {
+                error!("JSON value exceeds {} bytes", max_len);
+                return None;
+            }
+            None if value.len() > max_len => {
+                error!("No complete JSON value within {} bytes", max_len);
+                return None;
+            }
+            None => return None,
+        };
+
+        trace!("Value length: {}", value_len);
+
+        let mut frame = JsonFrame {
+            payload: take_buffer(value_len),
+        };
+        frame.payload.extend_from_slice(&value[..value_len]);
+
+        Some((frame, start + value_len))
+    }
+
+    /// Whether the payload is a number or literal, which needs a byte after it to end.
+    fn is_scalar(&self) -> bool {
+        self.payload.last().is_some_and(|&b| is_scalar_byte(b))
+    }
+}
+
+impl Frame for JsonFrame {
+    fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.payload[..])
+    }
+
+    fn into_payload(self: Box<Self>) -> Vec<u8> {
+        self.payload
+    }
+
+    fn with_payload(&self, payload: &[u8]) -> Option<Box<dyn Frame>> {
+        Some(Box::new(JsonFrame::new(payload)))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(self.len_as_vec());
+        buf.extend_from_slice(&self.payload[..]);
+        if self.is_scalar() {
+            buf.push(b'\n');
+        }
+
+        buf
+    }
+
+    fn len_as_vec(&self) -> usize {
+        self.payload.len() + self.is_scalar() as usize
+    }
+
+    fn as_mut_raw_erased(&self) -> *mut () {
+        let dup = Box::new(self.clone());
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "JsonFrame"
+    }
+}
+
+/// Returns the length of the object or array at the start of `buf`, once its closing brace
+/// or bracket has arrived.
+fn nested_len(buf: &[u8]) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut i = 0;
+    while i < buf.len() {
+        match buf[i] {
+            b'"' => i += string_len(&buf[(i + 1)..])?,
+            b'{' | b'[' => depth += 1,
+            b'}' | b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+
+    None
+}
+
+/// Returns the length of the string whose opening quote precedes `buf`, up to and including
+/// its closing quote, once that has arrived.
+fn string_len(buf: &[u8]) -> Option<usize> {
+    let mut escaped = false;
+    for (i, &b) in buf.iter().enumerate() {
+        match b {
+            _ if escaped => escaped = false,
+            b'\\' => escaped = true,
+            b'"' => return Some(i + 1),
+            _ => {}
+        }
+    }
+
+    None
+}
+
+fn is_whitespace(b: u8) -> bool {
+    matches!(b, b' ' | b'\t' | b'\n' | b'\r')
+}
+
+/// Whether `b` can be part of a number or of `true`, `false` or `null`.
+fn is_scalar_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'-' | b'+' | b'.')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode_all(buf: &mut Vec<u8>) -> Vec<Vec<u8>> {
+        let mut values = Vec::new();
+        while let Some(frame) = JsonFrameBuilder::<64>::from_bytes(buf) {
+            values.push(frame.payload());
+        }
+        values
+    }
+
+    #[test]
+    fn values_back_to_back_are_split() {
+        let mut buf = br#" {"a":"}{\""}[1,[2]]  "x" true"#.to_vec();
+        assert_eq!(
+            decode_all(&mut buf),
+            [
+                br#"{"a":"}{\""}"#.to_vec(),
+                b"[1,[2]]".to_vec(),
+                br#""x""#.to_vec()
+            ]
+        );
+
+        // The literal only ends once something follows it
+        assert_eq!(buf, b" true");
+        buf.push(b'\n');
+        assert_eq!(decode_all(&mut buf), [b"true".to_vec()]);
+        assert_eq!(buf, b"\n");
+    }
+
+    #[test]
+    fn scalars_are_sent_with_a_trailing_newline() {
+        assert_eq!(JsonFrame::new(b"-1.5").to_bytes(), b"-1.5\n");
+        assert_eq!(JsonFrame::new(b"[1]").to_bytes(), b"[1]");
+
+        let mut buf = JsonFrame::new(b"42").to_bytes();
+        assert_eq!(decode_all(&mut buf), [b"42".to_vec()]);
+    }
+
+    #[test]
+    fn values_over_the_maximum_are_never_decoded() {
+        let mut buf = br#"{"k":"0123456789"}"#.to_vec();
+        assert!(JsonFrameBuilder::<8>::from_bytes(&mut buf).is_none());
+        assert!(JsonFrameBuilder::<18>::from_bytes(&mut buf).is_some());
+    }
+}
@@ -0,0 +1,274 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Provides a frame with a 32-bit length prefix, for payloads larger than `SimpleFrame`
+//! allows.
+//!
+//! ```ignore
+//! 0                   1                   2                   3
+//! 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                        Payload Length                         |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                         Payload Data                          |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//!
+//! Payload Length:   Unsigned 32-bit integer Network Byte Order.
+//! Payload Data:     Payload Length bytes.
+//! ```
+
+use std::borrow::Cow;
+use std::mem;
+
+use crate::buffer::RecvBuffer;
+
+use super::recycle::take_buffer;
+use super::{Corruption, Frame, FrameBuilder, FrameBuilderInfo, FrameDecoder};
+
+const HEADER_LEN: usize = 4;
+
+/// Largest payload a `LengthPrefixed32FrameBuilder` accepts unless configured otherwise.
+pub const DEFAULT_MAX_PAYLOAD_LEN: u32 = 16 * 1024 * 1024;
+
+#[derive(Clone, Debug, Default)]
+pub struct LengthPrefixed32Frame {
+    payload: Vec<u8>,
+}
+
+/// Builds `LengthPrefixed32Frame`s with payloads of up to `MAX` bytes. A frame announcing a
+/// longer payload is never decoded, and reported as `Corruption::Malformed`, so a peer can not
+/// make the stream buffer more than that.
+#[derive(Clone, Copy, Debug)]
+pub struct LengthPrefixed32FrameBuilder<const MAX: u32 = DEFAULT_MAX_PAYLOAD_LEN>;
+
+/// Decodes `LengthPrefixed32Frame`s with payloads of up to a maximum chosen at runtime, the
+/// same as `LengthPrefixed32FrameBuilder` does for one chosen at compile time.
+#[derive(Clone, Copy, Debug)]
+pub struct LengthPrefixed32Decoder {
+    max_payload_len: u32,
+}
+
+impl<const MAX: u32> FrameBuilder for LengthPrefixed32FrameBuilder<MAX> {
+    fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        decode(buf, MAX)
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        decode_buffer(buf, MAX)
+    }
+
+    fn size_hint(buf: &[u8]) -> Option<usize> {
+        frame_len(buf, MAX)
+    }
+
+    fn validate(buf: &[u8]) -> Result<(), Corruption> {
+        validate(buf, MAX)
+    }
+}
+
+impl<const MAX: u32> FrameBuilderInfo for LengthPrefixed32FrameBuilder<MAX> {
+    const NAME: &'static str = "length_prefixed32";
+    const MAX_PAYLOAD_LEN: Option<usize> = Some(MAX as usize);
+}
+
+impl LengthPrefixed32Decoder {
+    /// Creates a decoder accepting payloads of up to `max_payload_len` bytes.
+    pub fn new(max_payload_len: u32) -> LengthPrefixed32Decoder {
+        LengthPrefixed32Decoder { max_payload_len }
+    }
+}
+
+impl Default for LengthPrefixed32Decoder {
+    fn default() -> LengthPrefixed32Decoder {
+        LengthPrefixed32Decoder::new(DEFAULT_MAX_PAYLOAD_LEN)
+    }
+}
+
+impl FrameDecoder for LengthPrefixed32Decoder {
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        decode(buf, self.max_payload_len)
+    }
+
+    fn decode_buffer(&mut self, buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        decode_buffer(buf, self.max_payload_len)
+    }
+
+    fn size_hint(&self, buf: &[u8]) -> Option<usize> {
+        frame_len(buf, self.max_payload_len)
+    }
+
+    fn validate(&self, buf: &[u8]) -> Result<(), Corruption> {
+        validate(buf, self.max_payload_len)
+    }
+
+    fn box_clone(&self) -> Box<dyn FrameDecoder> {
+        Box::new(*self)
+    }
+}
+
+fn decode(buf: &mut Vec<u8>, max_payload_len: u32) -> Option<Box<dyn Frame>> {
+    let (frame, frame_len) = parse(&buf[..], max_payload_len)?;
+
+    // Remove frame from buffer
+    let mut remainder = Vec::<u8>::with_capacity(buf.len() - frame_len);
+    remainder.extend_from_slice(&buf[frame_len..buf.len()]);
+    mem::swap(buf, &mut remainder);
+
+    Some(Box::new(frame))
+}
+
+fn decode_buffer(buf: &mut RecvBuffer, max_payload_len: u32) -> Option<Box<dyn Frame>> {
+    let (frame, frame_len) = parse(buf.as_slice(), max_payload_len)?;
+    buf.consume(frame_len);
+
+    Some(Box::new(frame))
+}
+
+/// Decodes the frame at the start of `buf`, returning it along with its length on the wire.
+fn parse(buf: &[u8], max_payload_len: u32) -> Option<(LengthPrefixed32Frame, usize)> {
+    if buf.len() < HEADER_LEN {
+        return None;
+    }
+
+    let payload_len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    if payload_len > max_payload_len {
+        error!(
+            "Payload length {} exceeds the maximum of {}",
+            payload_len, max_payload_len
+        );
+        return None;
+    }
+
+    let payload_len = payload_len as usize;
+    let frame_len = match HEADER_LEN.checked_add(payload_len) {
+        Some(frame_len) if buf.len() >= frame_len => frame_len,
+        _ => return None,
+    };
+
+    trace!("Payload length: {}", payload_len);
+
+    let mut frame = LengthPrefixed32Frame {
+        payload: take_buffer(payload_len),
+    };
+    frame.payload.extend_from_slice(&buf[HEADER_LEN..frame_len]);
+
+    Some((frame, frame_len))
+}
+
+fn frame_len(buf: &[u8], max_payload_len: u32) -> Option<usize> {
+    if buf.len() < HEADER_LEN {
+        return None;
+    }
+
+    let payload_len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    if payload_len > max_payload_len {
+        return None;
+    }
+
+    HEADER_LEN.checked_add(payload_len as usize)
+}
+
+fn validate(buf: &[u8], max_payload_len: u32) -> Result<(), Corruption> {
+    if buf.len() < HEADER_LEN {
+        return Ok(());
+    }
+
+    let payload_len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    if payload_len > max_payload_len {
+        return Err(Corruption::Malformed);
+    }
+
+    Ok(())
+}
+
+impl LengthPrefixed32Frame {
+    /// Creates a new `LengthPrefixed32Frame`. Payloads longer than `u32::MAX` bytes are
+    /// truncated.
+    pub fn new(buf: &[u8]) -> Self {
+        let len = buf.len().min(u32::MAX as usize);
+        LengthPrefixed32Frame {
+            payload: buf[..len].to_vec(),
+        }
+    }
+}
+
+impl Frame for LengthPrefixed32Frame {
+    fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.payload[..])
+    }
+
+    fn into_payload(self: Box<Self>) -> Vec<u8> {
+        self.payload
+    }
+
+    fn with_payload(&self, payload: &[u8]) -> Option<Box<dyn Frame>> {
+        Some(Box::new(LengthPrefixed32Frame::new(payload)))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(self.len_as_vec());
+        buf.extend_from_slice(&(self.payload.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&self.payload[..]);
+
+        buf
+    }
+
+    fn len_as_vec(&self) -> usize {
+        HEADER_LEN + self.payload.len()
+    }
+
+    fn as_mut_raw_erased(&self) -> *mut () {
+        let dup = Box::new(self.clone());
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "LengthPrefixed32Frame"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_round_trip_and_leave_the_remainder() {
+        let mut buf = LengthPrefixed32Frame::new(b"hello").to_bytes();
+        assert_eq!(&buf[..4], &[0, 0, 0, 5]);
+        buf.extend_from_slice(&[0, 0]);
+
+        let frame = LengthPrefixed32FrameBuilder::<16>::from_bytes(&mut buf).unwrap();
+        assert_eq!(frame.payload(), b"hello");
+        assert_eq!(buf, [0, 0]);
+        assert!(LengthPrefixed32FrameBuilder::<16>::from_bytes(&mut buf).is_none());
+    }
+
+    #[test]
+    fn payloads_over_the_maximum_are_malformed() {
+        let bytes = LengthPrefixed32Frame::new(&[7; 17]).to_bytes();
+        assert_eq!(
+            LengthPrefixed32FrameBuilder::<16>::validate(&bytes),
+            Err(Corruption::Malformed)
+        );
+        assert_eq!(LengthPrefixed32FrameBuilder::<16>::size_hint(&bytes), None);
+
+        let mut decoder = LengthPrefixed32Decoder::new(16);
+        assert_eq!(decoder.validate(&bytes), Err(Corruption::Malformed));
+        assert!(decoder.decode(&mut bytes.clone()).is_none());
+
+        let mut decoder = LengthPrefixed32Decoder::new(17);
+        assert_eq!(decoder.validate(&bytes), Ok(()));
+        assert_eq!(decoder.size_hint(&bytes), Some(21));
+        let mut buf = RecvBuffer::from(bytes);
+        assert_eq!(decoder.decode_buffer(&mut buf).unwrap().payload(), [7; 17]);
+        assert!(buf.is_empty());
+    }
+}
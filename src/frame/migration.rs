@@ -0,0 +1,351 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Moves a fleet from one frame format to another without a flag day. Every service is first
+//! deployed reading both formats, then switched to sending the new one, and finally the old
+//! one is dropped.
+//!
+//! ```ignore
+//! // Moving from text lines to SimpleFrames, which always start with 0x01
+//! let migration =
+//!     FormatMigration::new::<SimpleFrameBuilder, DelimitedFrameBuilder>(SimpleFrame::new(&[]))
+//!         .detector(|buf| match buf[0] {
+//!             0x01 => Some(WireFormat::Target),
+//!             _ => Some(WireFormat::Legacy),
+//!         });
+//!
+//! let mut stream = Plain::with_decoder(socket, migration.decoder());
+//! for frame in stream.nb_recv()? {
+//!     let frame = frame.downcast::<DualReadFrame>().unwrap();
+//!     if frame.format() == WireFormat::Legacy {
+//!         legacy_peers.inc();
+//!     }
+//!     stream.nb_send(&*migration.encode(&handle(frame.payload())))?;
+//! }
+//! ```
+
+use std::borrow::Cow;
+use std::fmt;
+use std::sync::Arc;
+
+use crate::buffer::RecvBuffer;
+
+use super::{BuilderDecoder, Frame, FrameBuilder, FrameDecoder};
+
+/// Which side of a migration a frame was received in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum WireFormat {
+    /// The format being migrated away from.
+    Legacy,
+    /// The format being migrated to.
+    Target,
+}
+
+/// The formats a protocol is being migrated between, shared by every stream taking part.
+#[derive(Clone)]
+pub struct FormatMigration {
+    target: Box<dyn FrameDecoder>,
+    legacy: Box<dyn FrameDecoder>,
+    template: Arc<dyn Frame>,
+    detector: Option<Detector>,
+}
+
+/// Tells the format of a connection from the bytes received first, or returns `None` if more
+/// bytes are needed.
+pub type Detector = fn(&[u8]) -> Option<WireFormat>;
+
+/// Decodes frames in either format of a `FormatMigration`, returned by
+/// `FormatMigration::decoder`.
+///
+/// As a peer does not switch formats mid-connection, the format is picked once, from the
+/// first bytes received, and used for the rest of the connection. It is picked by the
+/// migration's `Detector` if it has one. Otherwise the first frame is decoded with the target
+/// format's decoder if that succeeds, or else with the legacy one, which can only tell apart
+/// formats whose frames, complete or not, are never mistaken for one another.
+#[derive(Clone)]
+pub struct DualReadDecoder {
+    target: Box<dyn FrameDecoder>,
+    legacy: Box<dyn FrameDecoder>,
+    detector: Option<Detector>,
+    format: Option<WireFormat>,
+}
+
+/// A frame received by a `DualReadDecoder`, tagged with the format it was received in.
+#[derive(Clone)]
+pub struct DualReadFrame {
+    format: WireFormat,
+    frame: Arc<dyn Frame>,
+}
+
+impl FormatMigration {
+    /// Creates a migration from the `Legacy` frame format to the `Target` one. Frames are
+    /// sent in the target format by rebuilding `template` around their payload with
+    /// `Frame::with_payload`, which keeps any metadata it carries, e.g. headers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `template` can not be rebuilt around another payload.
+    pub fn new<Target, Legacy>(template: impl Frame) -> FormatMigration
+    where
+        Target: FrameBuilder,
+        Legacy: FrameBuilder,
+    {
+        FormatMigration::with_decoders(
+            BuilderDecoder::of::<Target>(),
+            BuilderDecoder::of::<Legacy>(),
+            template,
+        )
+    }
+
+    /// Same as `new`, for formats decoded with `FrameDecoder` instances.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `template` can not be rebuilt around another payload.
+    pub fn with_decoders<T, L>(target: T, legacy: L, template: impl Frame) -> FormatMigration
+    where
+        T: FrameDecoder + 'static,
+        L: FrameDecoder + 'static,
+    {
+        assert!(
+            template.with_payload(&[]).is_some(),
+            "{} can not be used as a template",
+            template.kind()
+        );
+
+        FormatMigration {
+            target: Box::new(target),
+            legacy: Box::new(legacy),
+            template: Arc::new(template),
+            detector: None,
+        }
+    }
+
+    /// Picks the format of each connection with `detector`, e.g. by a byte that differs
+    /// between the formats, instead of by decoding the first frame with either.
+    pub fn detector(mut self, detector: Detector) -> FormatMigration {
+        self.detector = Some(detector);
+        self
+    }
+
+    /// Returns a decoder for one stream, accepting frames in either format.
+    pub fn decoder(&self) -> DualReadDecoder {
+        DualReadDecoder {
+            target: self.target.clone(),
+            legacy: self.legacy.clone(),
+            detector: self.detector,
+            format: None,
+        }
+    }
+
+    /// Returns a frame in the target format carrying `payload`.
+    pub fn encode(&self, payload: &[u8]) -> Box<dyn Frame> {
+        self.template
+            .with_payload(payload)
+            .expect("Template checked on creation")
+    }
+}
+
+impl fmt::Debug for FormatMigration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("FormatMigration")
+            .field("template", &self.template.kind())
+            .finish()
+    }
+}
+
+impl DualReadDecoder {
+    /// Returns the format this connection's peer sends, once a frame has been received.
+    pub fn format(&self) -> Option<WireFormat> {
+        self.format
+    }
+
+    fn decoder_for(&mut self, format: WireFormat) -> &mut dyn FrameDecoder {
+        match format {
+            WireFormat::Target => &mut *self.target,
+            WireFormat::Legacy => &mut *self.legacy,
+        }
+    }
+
+    /// Picks the format with the detector, if there is one and the format is not known yet.
+    fn detect(&mut self, buf: &[u8]) {
+        let detector = match self.detector {
+            Some(detector) if self.format.is_none() && !buf.is_empty() => detector,
+            _ => return,
+        };
+
+        self.format = detector(buf);
+        if let Some(format) = self.format {
+            debug!("Peer sends {:?} frames", format);
+        }
+    }
+
+    /// Decodes the first frame in `buf` with whichever format can, returning it along with
+    /// the number of bytes it occupied. Decoders are handed a copy of `buf`, so one that
+    /// fails can not disturb the bytes for the other.
+    fn decode_first(&mut self, buf: &[u8]) -> Option<(DualReadFrame, usize)> {
+        for format in [WireFormat::Target, WireFormat::Legacy] {
+            let mut copy = buf.to_vec();
+            if let Some(frame) = self.decoder_for(format).decode(&mut copy) {
+                debug!("Peer sends {:?} frames", format);
+                self.format = Some(format);
+                let frame = DualReadFrame {
+                    format,
+                    frame: Arc::from(frame),
+                };
+                return Some((frame, buf.len() - copy.len()));
+            }
+        }
+
+        None
+    }
+}
+
+impl FrameDecoder for DualReadDecoder {
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        self.detect(&buf[..]);
+        let frame = match self.format {
+            Some(format) => DualReadFrame {
+                format,
+                frame: Arc::from(self.decoder_for(format).decode(buf)?),
+            },
+            None if self.detector.is_some() => return None,
+            None => {
+                let (frame, frame_len) = self.decode_first(&buf[..])?;
+                buf.drain(..frame_len);
+                frame
+            }
+        };
+
+        Some(Box::new(frame))
+    }
+
+    fn decode_buffer(&mut self, buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        self.detect(buf.as_slice());
+        let frame = match self.format {
+            Some(format) => DualReadFrame {
+                format,
+                frame: Arc::from(self.decoder_for(format).decode_buffer(buf)?),
+            },
+            None if self.detector.is_some() => return None,
+            None => {
+                let (frame, frame_len) = self.decode_first(buf.as_slice())?;
+                buf.consume(frame_len);
+                frame
+            }
+        };
+
+        Some(Box::new(frame))
+    }
+
+    fn size_hint(&self, buf: &[u8]) -> Option<usize> {
+        match self.format? {
+            WireFormat::Target => self.target.size_hint(buf),
+            WireFormat::Legacy => self.legacy.size_hint(buf),
+        }
+    }
+
+    fn box_clone(&self) -> Box<dyn FrameDecoder> {
+        Box::new(self.clone())
+    }
+}
+
+impl DualReadFrame {
+    /// Returns the format this frame was received in.
+    pub fn format(&self) -> WireFormat {
+        self.format
+    }
+
+    /// Returns the frame as decoded by its format's decoder.
+    pub fn inner(&self) -> &dyn Frame {
+        &*self.frame
+    }
+
+    /// Consumes this frame, returning it as decoded by its format's decoder.
+    pub fn into_inner(self) -> Arc<dyn Frame> {
+        self.frame
+    }
+}
+
+impl Frame for DualReadFrame {
+    fn payload(&self) -> Vec<u8> {
+        self.frame.payload()
+    }
+
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        self.frame.payload_ref()
+    }
+
+    fn with_payload(&self, payload: &[u8]) -> Option<Box<dyn Frame>> {
+        let frame = self.frame.with_payload(payload)?;
+        Some(Box::new(DualReadFrame {
+            format: self.format,
+            frame: Arc::from(frame),
+        }))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        self.frame.to_bytes()
+    }
+
+    fn len_as_vec(&self) -> usize {
+        self.frame.len_as_vec()
+    }
+
+    fn as_mut_raw_erased(&self) -> *mut () {
+        let dup = Box::new(self.clone());
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "DualReadFrame"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{
+        Decoder, DelimitedFrame, DelimitedFrameBuilder, SimpleFrame, SimpleFrameBuilder,
+    };
+
+    fn migration() -> FormatMigration {
+        FormatMigration::new::<SimpleFrameBuilder, DelimitedFrameBuilder>(SimpleFrame::new(&[]))
+            .detector(|buf| match buf[0] {
+                0x01 => Some(WireFormat::Target),
+                _ => Some(WireFormat::Legacy),
+            })
+    }
+
+    #[test]
+    fn frames_are_tagged_with_their_format() {
+        let migration = migration();
+        for (bytes, format) in [
+            (SimpleFrame::new(b"hello").to_bytes(), WireFormat::Target),
+            (DelimitedFrame::new(b"hello").to_bytes(), WireFormat::Legacy),
+        ] {
+            let mut decoder = Decoder::with_decoder(migration.decoder());
+            let frames = decoder.push_bytes(&bytes);
+            assert_eq!(frames.len(), 1);
+
+            let frame = frames[0].downcast_ref::<DualReadFrame>().unwrap();
+            assert_eq!(frame.format(), format);
+            assert_eq!(frame.payload(), b"hello");
+        }
+    }
+
+    #[test]
+    fn raw_pointers_are_to_a_copy_of_the_dual_read_frame() {
+        let mut decoder = Decoder::with_decoder(migration().decoder());
+        let frames = decoder.push_bytes(&SimpleFrame::new(b"hello").to_bytes());
+        let frame = frames[0].downcast_ref::<DualReadFrame>().unwrap();
+
+        let copy = unsafe { Box::from_raw(frame.as_mut_raw_erased() as *mut DualReadFrame) };
+        assert_eq!(copy.format(), frame.format());
+        assert_eq!(copy.to_bytes(), frame.to_bytes());
+    }
+}
@@ -14,23 +14,68 @@
 //! A `Frame` is the unit that streams operate on. Frames are used to ensure a complete piece of
 //! information has been received and that complete pieces of information are sent without
 //! fragmentation. A `FrameBuilder` is used by the stream types to construct a `Frame` from a
-//! chunk of bytes.
+//! chunk of bytes. Where the framing is only known at runtime, a configured `FrameDecoder`
+//! instance can be used in its place.
 
+use std::any::Any;
+use std::borrow::Cow;
+use std::fmt;
+use std::io;
+
+use crate::buffer::RecvBuffer;
 
 pub use self::simple::*;
 pub use self::websocket::*;
+pub use self::websocket_message::*;
 pub use self::checksum32::*;
+pub use self::padded::*;
+pub use self::headered::*;
+pub use self::tlv::*;
+pub use self::length_prefixed::*;
+pub use self::varint::*;
+pub use self::delimited::*;
+pub use self::json::*;
+pub use self::cobs::*;
+pub use self::compressed::*;
+pub use self::migration::*;
+pub use self::signed::*;
+pub use self::decoder::Decoder;
+pub use self::recycle::{recycle, set_recycle_limit};
+#[cfg(feature = "echo")]
+pub use self::echo::*;
 
 mod simple;
 mod websocket;
+mod websocket_message;
 mod checksum32;
+mod padded;
+mod headered;
+mod tlv;
+mod length_prefixed;
+mod varint;
+mod delimited;
+mod json;
+mod cobs;
+mod compressed;
+mod migration;
+mod signed;
+mod decoder;
+mod recycle;
+#[cfg(feature = "echo")]
+mod echo;
 
 /// The Frame trait allows for type construction/destruction to/from a chunk of bytes.
-pub trait Frame: Sync + Send {
+pub trait Frame: Any + Sync + Send {
     /// Transforms this type into a `Vec<u8>` in order to send through a stream.
     fn to_bytes(&self) -> Vec<u8>;
     /// Returns the paylaod data section of this `Frame`
     fn payload(&self) -> Vec<u8>;
+    /// Returns the payload data section of this `Frame`, borrowed from the frame where it is
+    /// stored as is, so large payloads can be read without copying them. The default
+    /// implementation copies it with `payload()`.
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.payload())
+    }
     /// Returns the total length of the frame as if `to_bytes().len()` was called.
     fn len_as_vec(&self) -> usize;
     /// Returns a `*mut ()` to the underlying frame in order to cast to/from a specific
@@ -38,7 +83,63 @@ pub trait Frame: Sync + Send {
     ///
     /// It is up to the caller of this method to take care of the cleanup required of the specific
     /// type the pointer was cast to (E.g. by calling `Box::from_raw(ptr)').
+    ///
+    /// Prefer the safe `downcast_ref` and `downcast` on `dyn Frame`.
     fn as_mut_raw_erased(&self) -> *mut ();
+    /// Consumes the frame, returning its payload. Frames owning their payload should move it
+    /// out rather than copy it.
+    fn into_payload(self: Box<Self>) -> Vec<u8> {
+        self.payload()
+    }
+    /// Returns a frame of the same type, and with the same metadata, as this one carrying
+    /// `payload` instead. Streams use this to apply payload transforms. Frames that can not be
+    /// rebuilt this way return `None`.
+    fn with_payload(&self, _payload: &[u8]) -> Option<Box<dyn Frame>> {
+        None
+    }
+    /// Returns a short, human readable name for the type of this `Frame`.
+    fn kind(&self) -> &'static str {
+        "Frame"
+    }
+    /// Returns a one line summary of this `Frame`, suitable for logs and error messages.
+    /// E.g. `SimpleFrame(42 bytes)`.
+    fn fmt_summary(&self) -> String {
+        format!("{}({} bytes)", self.kind(), self.len_as_vec())
+    }
+}
+
+impl dyn Frame {
+    /// Returns `true` if this frame is a `T`.
+    pub fn is<T: Frame>(&self) -> bool {
+        (self as &dyn Any).is::<T>()
+    }
+
+    /// Returns this frame as a `T`, or `None` if it is of another type.
+    pub fn downcast_ref<T: Frame>(&self) -> Option<&T> {
+        (self as &dyn Any).downcast_ref::<T>()
+    }
+
+    /// Returns this frame as a mutable `T`, or `None` if it is of another type.
+    pub fn downcast_mut<T: Frame>(&mut self) -> Option<&mut T> {
+        (self as &mut dyn Any).downcast_mut::<T>()
+    }
+
+    /// Converts a received frame back into its concrete type, or returns it unchanged if it is
+    /// of another type.
+    pub fn downcast<T: Frame>(self: Box<Self>) -> Result<Box<T>, Box<dyn Frame>> {
+        if !self.is::<T>() {
+            return Err(self);
+        }
+
+        let any: Box<dyn Any> = self;
+        Ok(any.downcast::<T>().expect("Frame type checked above"))
+    }
+}
+
+impl fmt::Debug for dyn Frame {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.fmt_summary())
+    }
 }
 
 pub trait FrameBuilder {
@@ -46,4 +147,255 @@ pub trait FrameBuilder {
     /// created from the bytes in `buf`. On success this method should remove all bytes that
     /// were used during the creation of the returned frame, from `buf`.
     fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>>;
+    /// Same as `from_bytes`, but consuming the frame's bytes from a stream's receive buffer
+    /// with `RecvBuffer::consume` instead of removing them, which costs nothing however many
+    /// bytes follow. Streams decode through this, which calls `from_bytes` unless overridden.
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        Self::from_bytes(buf.as_vec_mut())
+    }
+    /// Given the start of a frame still being received, returns the total length in bytes that
+    /// frame will have once complete, if enough of it has arrived to tell. Streams use this to
+    /// reserve receive buffer space up front instead of growing it on every read.
+    fn size_hint(_buf: &[u8]) -> Option<usize> {
+        None
+    }
+    /// Checks that `buf` could start with a valid frame, complete or not, failing if its
+    /// first bytes can not be one, e.g. because a guard byte or checksum is wrong, or a length
+    /// is past what the format accepts. Streams with a `DecodePolicy` call this before
+    /// decoding, and to find where to resync. Streams without one call it once nothing could be
+    /// decoded, and close rather than wait for bytes that can never complete a frame. Nothing
+    /// is ever corrupt unless overridden.
+    fn validate(_buf: &[u8]) -> Result<(), Corruption> {
+        Ok(())
+    }
+}
+
+/// Describes the frame format a `FrameBuilder` builds, so tooling and negotiation layers can
+/// tell what the framing of a stream supports at runtime, through `protocol_info`.
+pub trait FrameBuilderInfo: FrameBuilder {
+    /// Name of the frame format, e.g. `"simple"`.
+    const NAME: &'static str;
+    /// Version of the frame format, raised whenever its encoding changes incompatibly.
+    const VERSION: u32 = 1;
+    /// Largest payload a frame can carry, or is accepted with, in bytes. `None` if only
+    /// bounded by memory.
+    const MAX_PAYLOAD_LEN: Option<usize> = None;
+    /// Whether frames with an empty payload can be sent and received.
+    const ZERO_LENGTH_FRAMES: bool = true;
+    /// Whether one message can be streamed across several frames, e.g. as websocket
+    /// fragments, instead of being built complete before it is sent.
+    const STREAMING: bool = false;
+
+    /// Returns the above as a value.
+    fn info() -> ProtocolInfo {
+        ProtocolInfo {
+            name: Self::NAME,
+            version: Self::VERSION,
+            max_payload_len: Self::MAX_PAYLOAD_LEN,
+            zero_length_frames: Self::ZERO_LENGTH_FRAMES,
+            streaming: Self::STREAMING,
+        }
+    }
+}
+
+/// What a frame format supports, as described by `FrameBuilderInfo`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ProtocolInfo {
+    pub name: &'static str,
+    pub version: u32,
+    pub max_payload_len: Option<usize>,
+    pub zero_length_frames: bool,
+    pub streaming: bool,
+}
+
+/// Why the bytes at the start of a receive buffer can not be a valid frame, meaning the stream
+/// lost track of where frames start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Corruption {
+    /// A byte marking the start or end of a frame was wrong.
+    BadGuard,
+    /// A frame's checksum did not match its payload.
+    BadChecksum,
+    /// The frame's header was not valid for any other reason.
+    Malformed,
+}
+
+impl fmt::Display for Corruption {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Corruption::BadGuard => write!(f, "Bad frame guard byte"),
+            Corruption::BadChecksum => write!(f, "Frame checksum mismatch"),
+            Corruption::Malformed => write!(f, "Malformed frame header"),
+        }
+    }
+}
+
+/// Decodes frames like a `FrameBuilder`, but as an instance, so that it can carry runtime
+/// configuration such as a maximum payload length or the delimiter bytes. Streams are given
+/// one with `with_decoder` or `set_frame_decoder`.
+pub trait FrameDecoder: Send {
+    /// Same as `FrameBuilder::from_bytes`.
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Option<Box<dyn Frame>>;
+    /// Same as `FrameBuilder::from_buffer`.
+    fn decode_buffer(&mut self, buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        self.decode(buf.as_vec_mut())
+    }
+    /// Same as `FrameBuilder::size_hint`.
+    fn size_hint(&self, _buf: &[u8]) -> Option<usize> {
+        None
+    }
+    /// Same as `FrameBuilder::validate`.
+    fn validate(&self, _buf: &[u8]) -> Result<(), Corruption> {
+        Ok(())
+    }
+    /// Returns a boxed copy of this decoder, including any state it holds, for the stream
+    /// `try_clone` creates.
+    fn box_clone(&self) -> Box<dyn FrameDecoder>;
+}
+
+impl Clone for Box<dyn FrameDecoder> {
+    fn clone(&self) -> Box<dyn FrameDecoder> {
+        self.box_clone()
+    }
+}
+
+/// A `FrameDecoder` calling through to a `FrameBuilder`. This is what streams decode with
+/// unless given another decoder.
+#[derive(Clone, Copy)]
+pub struct BuilderDecoder {
+    from_bytes: fn(&mut Vec<u8>) -> Option<Box<dyn Frame>>,
+    from_buffer: fn(&mut RecvBuffer) -> Option<Box<dyn Frame>>,
+    size_hint: fn(&[u8]) -> Option<usize>,
+    validate: fn(&[u8]) -> Result<(), Corruption>,
+}
+
+impl BuilderDecoder {
+    /// Creates a decoder using `FB`.
+    pub fn of<FB: FrameBuilder>() -> BuilderDecoder {
+        BuilderDecoder {
+            from_bytes: FB::from_bytes,
+            from_buffer: FB::from_buffer,
+            size_hint: FB::size_hint,
+            validate: FB::validate,
+        }
+    }
+}
+
+impl FrameDecoder for BuilderDecoder {
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        (self.from_bytes)(buf)
+    }
+
+    fn decode_buffer(&mut self, buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        (self.from_buffer)(buf)
+    }
+
+    fn size_hint(&self, buf: &[u8]) -> Option<usize> {
+        (self.size_hint)(buf)
+    }
+
+    fn validate(&self, buf: &[u8]) -> Result<(), Corruption> {
+        (self.validate)(buf)
+    }
+
+    fn box_clone(&self) -> Box<dyn FrameDecoder> {
+        Box::new(*self)
+    }
+}
+
+/// Stands in for the `FrameBuilder` of streams created with `with_decoder`, which decode with
+/// the `FrameDecoder` they were given instead. It never builds a frame itself.
+#[derive(Clone, Copy, Debug)]
+pub struct DynamicBuilder;
+
+impl FrameBuilder for DynamicBuilder {
+    fn from_bytes(_buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        None
+    }
+}
+
+/// Largest amount of receive buffer space reserved ahead of a frame's bytes arriving, so that
+/// a bogus length can not make a stream allocate an arbitrary amount of memory.
+const MAX_RESERVE: usize = 1024 * 1024;
+
+/// Reserves space in `buf` for the rest of the frame it holds the start of, as reported by
+/// `decoder.size_hint`.
+pub(crate) fn reserve_frame(decoder: &dyn FrameDecoder, buf: &mut RecvBuffer) {
+    if let Some(frame_len) = decoder.size_hint(buf.as_slice()) {
+        let additional = frame_len.min(MAX_RESERVE).saturating_sub(buf.len());
+        buf.reserve(additional);
+    }
+}
+
+/// Returns a random value from a cryptographically secure source, OpenSSL's generator or
+/// without the `openssl` feature the operating system's. WebSocket masking keys must not be
+/// predictable (RFC 6455 section 5.3), and neither must padding meant to hide message sizes.
+///
+/// # Panics
+///
+/// If no random bytes can be read, which the sources used do not do once initialized.
+pub(crate) fn random_u64() -> u64 {
+    let mut bytes = [0u8; 8];
+    if let Err(e) = fill_random(&mut bytes) {
+        panic!("Unable to read random bytes: {}", e);
+    }
+
+    u64::from_ne_bytes(bytes)
+}
+
+#[cfg(feature = "openssl")]
+fn fill_random(buf: &mut [u8]) -> io::Result<()> {
+    openssl::rand::rand_bytes(buf).map_err(io::Error::other)
+}
+
+#[cfg(all(not(feature = "openssl"), any(target_os = "linux", target_os = "android")))]
+fn fill_random(mut buf: &mut [u8]) -> io::Result<()> {
+    while !buf.is_empty() {
+        let result = unsafe {
+            libc::getrandom(buf.as_mut_ptr() as *mut libc::c_void, buf.len(), 0)
+        };
+        if result < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(e);
+        }
+
+        buf = &mut buf[(result as usize)..];
+    }
+
+    Ok(())
+}
+
+#[cfg(all(not(feature = "openssl"), unix, not(any(target_os = "linux", target_os = "android"))))]
+fn fill_random(buf: &mut [u8]) -> io::Result<()> {
+    // getentropy reads up to 256 bytes at a time, more than is ever asked for here
+    let result = unsafe { libc::getentropy(buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(all(not(feature = "openssl"), windows))]
+fn fill_random(buf: &mut [u8]) -> io::Result<()> {
+    use windows_sys::Win32::Security::Cryptography::{
+        BCryptGenRandom, BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+    };
+
+    let status = unsafe {
+        BCryptGenRandom(
+            std::ptr::null_mut(),
+            buf.as_mut_ptr(),
+            buf.len() as u32,
+            BCRYPT_USE_SYSTEM_PREFERRED_RNG,
+        )
+    };
+    if status < 0 {
+        return Err(io::Error::other(format!("BCryptGenRandom failed: {:#x}", status)));
+    }
+
+    Ok(())
 }
@@ -0,0 +1,363 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Provides length padding around any other frame type in order to hide the true size of
+//! messages from anyone observing the (encrypted) traffic. Padding is added on send according
+//! to a `PaddingPolicy` and transparently removed on receive, so the application still deals
+//! with the wrapped frame type only.
+//!
+//! ```ignore
+//! 0                   1                   2                   3
+//! 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                        Frame Length                           |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                       Padding Length                          |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                         Frame Data                            |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                          Padding                              |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//!
+//! Frame Length      Unsigned 32-bit integer Network Byte Order.
+//! Padding Length    Unsigned 32-bit integer Network Byte Order.
+//! Frame Data        Frame Length bytes of the wrapped frame.
+//! Padding           Padding Length zero bytes.
+//! ```
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::mem;
+
+use crate::buffer::RecvBuffer;
+
+use super::{random_u64, Corruption, Frame, FrameBuilder, FrameBuilderInfo};
+
+const HEADER_LEN: usize = 8;
+
+/// Largest frame and padding together a `PaddedFrameBuilder` accepts unless configured
+/// otherwise.
+pub const DEFAULT_MAX_PADDED_LEN: usize = 16 * 1024 * 1024;
+
+/// Determines how much padding is appended to an outgoing frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PaddingPolicy {
+    /// No padding is added.
+    None,
+    /// Pads the encoded frame so that its total length is a multiple of the block size, e.g.
+    /// for transports that carry data in fixed size blocks.
+    BlockSize(u32),
+    /// Appends a random amount of padding between zero and the passed maximum, inclusive.
+    Random(u32),
+}
+
+/// Wraps another frame, adding padding according to a `PaddingPolicy`.
+#[derive(Clone)]
+pub struct PaddedFrame {
+    frame: Vec<u8>,
+    padding_len: u32,
+}
+
+/// Decodes a `PaddedFrame` and returns the wrapped frame, as built by `FB`, with the
+/// padding removed.
+///
+/// Frames of more than `MAX` bytes of frame and padding together are never decoded, and
+/// reported as `Corruption::Malformed`, which closes a stream without a `DecodePolicy` as soon
+/// as the header arrives. Padded frames not holding a frame `FB` decodes are dropped. Those
+/// whose frame `FB::validate` rejects, or that end before their frame does, are reported as
+/// `Corruption::Malformed` to a `DecodePolicy`.
+#[derive(Clone, Copy, Debug)]
+pub struct PaddedFrameBuilder<FB: FrameBuilder, const MAX: usize = DEFAULT_MAX_PADDED_LEN> {
+    phantom: PhantomData<FB>,
+}
+
+impl<FB: FrameBuilder, const MAX: usize> FrameBuilder for PaddedFrameBuilder<FB, MAX> {
+    fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        // Carry on past padded frames without a frame inside, rather than leave the ones
+        // after them stuck in the buffer
+        loop {
+            let (frame_len, total_len) = lengths(&buf[..])?;
+            if total_len - HEADER_LEN > MAX {
+                error!(
+                    "Padded frame length {} exceeds the maximum of {}",
+                    total_len - HEADER_LEN,
+                    MAX
+                );
+                return None;
+            }
+            if buf.len() < total_len {
+                return None;
+            }
+
+            trace!(
+                "Frame length: {} Padding length: {}",
+                frame_len,
+                total_len - HEADER_LEN - frame_len
+            );
+
+            // Remove frame and padding from buffer, decoding the frame where it is
+            let remainder = buf.split_off(total_len);
+            let mut inner_buf = mem::replace(buf, remainder);
+            inner_buf.truncate(HEADER_LEN + frame_len);
+            inner_buf.drain(..HEADER_LEN);
+
+            match FB::from_bytes(&mut inner_buf) {
+                Some(frame) => return Some(frame),
+                None => error!("Padded frame did not contain a complete inner frame. Dropping it"),
+            }
+        }
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        loop {
+            let (frame_len, total_len) = lengths(buf.as_slice())?;
+            if total_len - HEADER_LEN > MAX {
+                error!(
+                    "Padded frame length {} exceeds the maximum of {}",
+                    total_len - HEADER_LEN,
+                    MAX
+                );
+                return None;
+            }
+            if buf.len() < total_len {
+                return None;
+            }
+
+            trace!(
+                "Frame length: {} Padding length: {}",
+                frame_len,
+                total_len - HEADER_LEN - frame_len
+            );
+
+            // Only the frame is copied out, as FB decodes from a buffer of its own
+            let mut inner_buf =
+                RecvBuffer::from(buf.as_slice()[HEADER_LEN..(HEADER_LEN + frame_len)].to_vec());
+            buf.consume(total_len);
+
+            match FB::from_buffer(&mut inner_buf) {
+                Some(frame) => return Some(frame),
+                None => error!("Padded frame did not contain a complete inner frame. Dropping it"),
+            }
+        }
+    }
+
+    fn size_hint(buf: &[u8]) -> Option<usize> {
+        lengths(buf)
+            .filter(|&(_, total_len)| total_len - HEADER_LEN <= MAX)
+            .map(|(_, total_len)| total_len)
+    }
+
+    fn validate(buf: &[u8]) -> Result<(), Corruption> {
+        let (frame_len, total_len) = match lengths(buf) {
+            Some(lengths) => lengths,
+            None => return Ok(()),
+        };
+        if total_len - HEADER_LEN > MAX {
+            return Err(Corruption::Malformed);
+        }
+        if buf.len() < total_len {
+            return Ok(());
+        }
+
+        // Checked without decoding the frame, which is only done once, when it is taken
+        let inner = &buf[HEADER_LEN..(HEADER_LEN + frame_len)];
+        if FB::validate(inner).is_err() {
+            return Err(Corruption::Malformed);
+        }
+        match FB::size_hint(inner) {
+            Some(inner_len) if inner_len > frame_len => Err(Corruption::Malformed),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<FB: FrameBuilderInfo, const MAX: usize> FrameBuilderInfo for PaddedFrameBuilder<FB, MAX> {
+    const NAME: &'static str = "padded";
+    const MAX_PAYLOAD_LEN: Option<usize> = FB::MAX_PAYLOAD_LEN;
+    const ZERO_LENGTH_FRAMES: bool = FB::ZERO_LENGTH_FRAMES;
+    const STREAMING: bool = FB::STREAMING;
+}
+
+/// Returns the length of the frame inside the padded frame `buf` starts with, and of the whole
+/// padded frame, once its header has arrived.
+fn lengths(buf: &[u8]) -> Option<(usize, usize)> {
+    if buf.len() < HEADER_LEN {
+        return None;
+    }
+
+    let frame_len = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]) as u64;
+    let padding_len = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]) as u64;
+    // Computed in u64, which can not overflow, and saturated where usize is narrower
+    let total_len = HEADER_LEN as u64 + frame_len + padding_len;
+    Some((
+        frame_len as usize,
+        usize::try_from(total_len).unwrap_or(usize::MAX),
+    ))
+}
+
+impl PaddedFrame {
+    /// Creates a new `PaddedFrame` wrapping `frame`, padded according to `policy`.
+    pub fn new(frame: &dyn Frame, policy: PaddingPolicy) -> Self {
+        let frame = frame.to_bytes();
+        let padding_len = match policy {
+            PaddingPolicy::None => 0,
+            PaddingPolicy::BlockSize(0) => 0,
+            PaddingPolicy::BlockSize(block_size) => {
+                let block_size = block_size as usize;
+                let len = HEADER_LEN + frame.len();
+                ((block_size - (len % block_size)) % block_size) as u32
+            }
+            PaddingPolicy::Random(max) => (random_u64() % (max as u64 + 1)) as u32,
+        };
+
+        PaddedFrame { frame, padding_len }
+    }
+
+    /// Returns the number of padding bytes this frame will be sent with.
+    pub fn padding_len(&self) -> u32 {
+        self.padding_len
+    }
+}
+
+impl Frame for PaddedFrame {
+    fn payload(&self) -> Vec<u8> {
+        self.frame.clone()
+    }
+
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.frame[..])
+    }
+
+    fn into_payload(self: Box<Self>) -> Vec<u8> {
+        self.frame
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(self.len_as_vec());
+        buf.extend_from_slice(&(self.frame.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&self.padding_len.to_be_bytes());
+        buf.extend_from_slice(&self.frame[..]);
+        buf.resize(self.len_as_vec(), 0u8);
+
+        buf
+    }
+
+    fn len_as_vec(&self) -> usize {
+        HEADER_LEN + self.frame.len() + self.padding_len as usize
+    }
+
+    fn as_mut_raw_erased(&self) -> *mut () {
+        let dup = Box::new(self.clone());
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "PaddedFrame"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::frame::{Decoder, SimpleFrame, SimpleFrameBuilder};
+    use crate::testing::MockStream;
+    use crate::{Error, NonBlocking, Plain};
+
+    /// A padded frame holding `inner` as is, with `padding_len` bytes of padding.
+    fn padded(inner: &[u8], padding_len: u32) -> Vec<u8> {
+        let mut buf = Vec::<u8>::new();
+        buf.extend_from_slice(&(inner.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&padding_len.to_be_bytes());
+        buf.extend_from_slice(inner);
+        buf.resize(buf.len() + padding_len as usize, 0);
+        buf
+    }
+
+    #[test]
+    fn frames_after_a_bad_inner_frame_still_decode() {
+        let mut bytes = padded(b"bad", 2);
+        bytes.extend(
+            PaddedFrame::new(&SimpleFrame::new(b"ok"), PaddingPolicy::BlockSize(16)).to_bytes(),
+        );
+
+        type Builder = PaddedFrameBuilder<SimpleFrameBuilder>;
+        assert_eq!(Builder::validate(&bytes), Err(Corruption::Malformed));
+        let truncated = &SimpleFrame::new(b"long").to_bytes()[..4];
+        assert_eq!(
+            Builder::validate(&padded(truncated, 0)),
+            Err(Corruption::Malformed)
+        );
+        let mut decoder = Decoder::<Builder>::new();
+        let frames = decoder.push_bytes(&bytes);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].payload(), b"ok");
+        assert!(decoder.buffered().is_empty());
+    }
+
+    #[test]
+    fn lengths_over_the_maximum_are_rejected() {
+        type Builder = PaddedFrameBuilder<SimpleFrameBuilder, 16>;
+        let inner = SimpleFrame::new(b"0123456789").to_bytes();
+        let bytes = padded(&inner, 16 - inner.len() as u32 + 1);
+
+        assert_eq!(Builder::size_hint(&bytes), None);
+        assert_eq!(
+            Builder::validate(&bytes[..HEADER_LEN]),
+            Err(Corruption::Malformed)
+        );
+
+        // The header and everything after it are dropped, however much more arrives
+        let mut decoder = Decoder::<Builder>::new();
+        for chunk in bytes.chunks(HEADER_LEN) {
+            assert!(decoder.push_bytes(chunk).is_empty());
+            assert!(decoder.buffered().is_empty());
+        }
+        assert_eq!(decoder.corruption(), Some(Corruption::Malformed));
+
+        let (local, mut remote) = MockStream::pair();
+        let mut stream = Plain::<_, Builder>::new(local);
+        remote.write_all(&bytes).unwrap();
+        assert!(matches!(stream.nb_recv(), Err(Error::ProtocolViolation)));
+        assert!(stream.close_reason().is_some());
+
+        let mut header = [0xffu8; HEADER_LEN];
+        header[..4].copy_from_slice(&0u32.to_be_bytes());
+        assert_eq!(
+            PaddedFrameBuilder::<SimpleFrameBuilder>::size_hint(&header),
+            None
+        );
+    }
+
+    #[test]
+    fn padding_is_stripped() {
+        for policy in [
+            PaddingPolicy::None,
+            PaddingPolicy::BlockSize(64),
+            PaddingPolicy::Random(32),
+        ] {
+            let frame = PaddedFrame::new(&SimpleFrame::new(b"hello"), policy);
+            let frames = Decoder::<PaddedFrameBuilder<SimpleFrameBuilder>>::new()
+                .push_bytes(&frame.to_bytes());
+            assert_eq!(frames.len(), 1);
+            assert_eq!(frames[0].payload(), b"hello");
+        }
+    }
+
+    #[test]
+    fn block_size_aligns_the_encoded_length() {
+        for len in 0..40 {
+            let frame = PaddedFrame::new(
+                &SimpleFrame::new(&vec![0xAB; len]),
+                PaddingPolicy::BlockSize(16),
+            );
+            assert_eq!(frame.to_bytes().len() % 16, 0);
+            assert!(frame.padding_len() < 16);
+        }
+    }
+}
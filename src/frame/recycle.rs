@@ -0,0 +1,99 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Per-thread freelist of payload buffers for the built-in frame types.
+//!
+//! Passing frames that are no longer needed to `recycle` keeps their payload allocations
+//! around, and the built-in `FrameBuilder`s take from the freelist when decoding the next
+//! frame on the same thread. Frames that are never recycled simply fall back to allocating.
+
+use std::cell::RefCell;
+
+use super::Frame;
+
+const DEFAULT_LIMIT: usize = 256;
+const MAX_RETAINED_CAPACITY: usize = 64 * 1024;
+
+struct Freelist {
+    buffers: Vec<Vec<u8>>,
+    limit: usize,
+}
+
+thread_local! {
+    static FREELIST: RefCell<Freelist> = const {
+        RefCell::new(Freelist {
+            buffers: Vec::new(),
+            limit: DEFAULT_LIMIT,
+        })
+    };
+}
+
+/// Returns the payload allocation of `frame` to the current thread's freelist. Buffers larger
+/// than 64KiB are freed rather than kept.
+pub fn recycle(frame: Box<dyn Frame>) {
+    let mut buf = frame.into_payload();
+    if buf.capacity() == 0 || buf.capacity() > MAX_RETAINED_CAPACITY {
+        return;
+    }
+
+    buf.clear();
+    FREELIST.with(|freelist| {
+        let mut freelist = freelist.borrow_mut();
+        if freelist.buffers.len() < freelist.limit {
+            freelist.buffers.push(buf);
+        }
+    });
+}
+
+/// Sets the maximum number of buffers kept by the current thread's freelist. A limit of zero
+/// disables recycling.
+pub fn set_recycle_limit(limit: usize) {
+    FREELIST.with(|freelist| {
+        let mut freelist = freelist.borrow_mut();
+        freelist.limit = limit;
+        freelist.buffers.truncate(limit);
+    });
+}
+
+/// Takes an empty buffer from the current thread's freelist, or allocates one, with room for
+/// at least `capacity` bytes.
+pub(crate) fn take_buffer(capacity: usize) -> Vec<u8> {
+    let buf = FREELIST.with(|freelist| freelist.borrow_mut().buffers.pop());
+    match buf {
+        Some(mut buf) => {
+            buf.reserve(capacity);
+            buf
+        }
+        None => Vec::with_capacity(capacity),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::SimpleFrame;
+
+    #[test]
+    fn recycled_payloads_are_reused() {
+        set_recycle_limit(DEFAULT_LIMIT);
+        let frame = SimpleFrame::new(b"hello");
+        let payload = frame.payload_ref().as_ptr();
+        recycle(Box::new(frame));
+
+        let buf = take_buffer(3);
+        assert_eq!(buf.as_ptr(), payload);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn nothing_is_kept_past_the_limit() {
+        set_recycle_limit(0);
+        recycle(Box::new(SimpleFrame::new(b"hello")));
+
+        FREELIST.with(|freelist| assert!(freelist.borrow().buffers.is_empty()));
+    }
+}
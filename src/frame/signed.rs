@@ -0,0 +1,378 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Authenticates frames of any format with a message authentication code, for peers sharing a
+//! key over links without TLS.
+//!
+//! ```ignore
+//! +- - - - - - - - - - - - - - - - - - - - - - - - -+- - - - - - - - - - - - -+
+//! |                   Inner Frame                   |           Tag           |
+//! +- - - - - - - - - - - - - - - - - - - - - - - - -+- - - - - - - - - - - - -+
+//!
+//! Inner Frame:    A frame of the wrapped format, as encoded by its to_bytes.
+//! Tag:            Mac::TAG_LEN bytes, the MAC of the inner frame's bytes as sent.
+//! ```
+//!
+//! Frames whose tag does not match are dropped. Only authenticity is provided, payloads are
+//! still sent in the clear, and a recorded frame can be replayed.
+//!
+//! The tag is found by the length of the inner frame, so the wrapped format has to report it
+//! through `size_hint` once a frame's header has arrived. Formats that only find the end of a
+//! frame by scanning for it, such as `DelimitedFrameBuilder`, can not be signed.
+//!
+//! ```ignore
+//! let mac = HmacSha256::new(&key)?;
+//! let mut stream = Plain::with_decoder(socket, SignedDecoder::of::<SimpleFrameBuilder>(mac.clone()));
+//! stream.nb_send(&SignedFrame::new(&SimpleFrame::new(b"hello"), &mac)?)?;
+//! ```
+
+use std::borrow::Cow;
+use std::io;
+use std::marker::PhantomData;
+use std::mem;
+use std::sync::Arc;
+
+#[cfg(feature = "openssl")]
+use openssl::hash::MessageDigest;
+#[cfg(feature = "openssl")]
+use openssl::pkey::{PKey, Private};
+#[cfg(feature = "openssl")]
+use openssl::sign::Signer;
+
+use crate::buffer::RecvBuffer;
+
+use super::{BuilderDecoder, Frame, FrameBuilder, FrameBuilderInfo, FrameDecoder};
+
+/// A message authentication code, keyed by the implementing value.
+pub trait Mac {
+    /// Length of the tags `sign` returns.
+    const TAG_LEN: usize;
+
+    /// Returns the tag of `data`.
+    fn sign(&self, data: &[u8]) -> io::Result<Vec<u8>>;
+
+    /// Whether `tag` is the tag of `data`. The default implementation compares the tag `sign`
+    /// returns in constant time.
+    fn verify(&self, data: &[u8], tag: &[u8]) -> bool {
+        match self.sign(data) {
+            Ok(expected) => constant_time_eq(&expected[..], tag),
+            Err(e) => {
+                error!("Signing failed: {}", e);
+                false
+            }
+        }
+    }
+}
+
+/// HMAC-SHA256, with 32 byte tags.
+#[cfg(feature = "openssl")]
+#[derive(Clone)]
+pub struct HmacSha256 {
+    key: PKey<Private>,
+}
+
+/// A frame followed by the tag of its bytes.
+#[derive(Clone)]
+pub struct SignedFrame {
+    frame: Arc<dyn Frame>,
+    /// The inner frame as signed, which may differ from what `frame.to_bytes()` returns for
+    /// formats that encode the same frame differently each time, e.g. with random padding or
+    /// masking keys.
+    bytes: Vec<u8>,
+    tag: Vec<u8>,
+}
+
+/// Builds `SignedFrame`s around frames `FB` builds, keyed by `M::default()`.
+///
+/// As builders are not instances, the key has to be reachable from a `Default` `M`, e.g. one
+/// loaded into a static at startup. `SignedDecoder` takes the key as a value instead.
+#[derive(Clone, Copy, Debug)]
+pub struct Signed<FB, M> {
+    phantom: PhantomData<(FB, M)>,
+}
+
+/// Decodes `SignedFrame`s around frames `inner` decodes, verifying them with `mac`.
+#[derive(Clone)]
+pub struct SignedDecoder<M> {
+    inner: Box<dyn FrameDecoder>,
+    mac: M,
+}
+
+#[cfg(feature = "openssl")]
+impl HmacSha256 {
+    pub fn new(key: &[u8]) -> io::Result<HmacSha256> {
+        let key = PKey::hmac(key).map_err(io::Error::other)?;
+        Ok(HmacSha256 { key })
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl Mac for HmacSha256 {
+    const TAG_LEN: usize = 32;
+
+    fn sign(&self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.key)?;
+        signer.update(data)?;
+        Ok(signer.sign_to_vec()?)
+    }
+}
+
+impl<FB, M> FrameBuilder for Signed<FB, M>
+where
+    FB: FrameBuilder,
+    M: Mac + Default,
+{
+    fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        let mut recv_buf = RecvBuffer::from(mem::take(buf));
+        let frame = Self::from_buffer(&mut recv_buf);
+        *buf = recv_buf.take();
+
+        frame
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        decode(buf, &M::default(), &mut BuilderDecoder::of::<FB>())
+    }
+
+    fn size_hint(buf: &[u8]) -> Option<usize> {
+        FB::size_hint(buf).map(|len| len + M::TAG_LEN)
+    }
+}
+
+impl<FB, M> FrameBuilderInfo for Signed<FB, M>
+where
+    FB: FrameBuilderInfo,
+    M: Mac + Default,
+{
+    const NAME: &'static str = "signed";
+    const MAX_PAYLOAD_LEN: Option<usize> = FB::MAX_PAYLOAD_LEN;
+    const ZERO_LENGTH_FRAMES: bool = FB::ZERO_LENGTH_FRAMES;
+    const STREAMING: bool = FB::STREAMING;
+}
+
+impl<M> SignedDecoder<M>
+where
+    M: Mac + Clone + Send + 'static,
+{
+    /// Creates a decoder for frames `inner` decodes, each followed by a tag `mac` verifies.
+    pub fn new<D>(inner: D, mac: M) -> SignedDecoder<M>
+    where
+        D: FrameDecoder + 'static,
+    {
+        SignedDecoder {
+            inner: Box::new(inner),
+            mac,
+        }
+    }
+
+    /// Same as `new`, for frames `FB` builds.
+    pub fn of<FB: FrameBuilder>(mac: M) -> SignedDecoder<M> {
+        SignedDecoder::new(BuilderDecoder::of::<FB>(), mac)
+    }
+}
+
+impl<M> FrameDecoder for SignedDecoder<M>
+where
+    M: Mac + Clone + Send + 'static,
+{
+    fn decode(&mut self, buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        let mut recv_buf = RecvBuffer::from(mem::take(buf));
+        let frame = self.decode_buffer(&mut recv_buf);
+        *buf = recv_buf.take();
+
+        frame
+    }
+
+    fn decode_buffer(&mut self, buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        decode(buf, &self.mac, &mut *self.inner)
+    }
+
+    fn size_hint(&self, buf: &[u8]) -> Option<usize> {
+        self.inner.size_hint(buf).map(|len| len + M::TAG_LEN)
+    }
+
+    fn box_clone(&self) -> Box<dyn FrameDecoder> {
+        Box::new(self.clone())
+    }
+}
+
+impl SignedFrame {
+    /// Signs `frame` with `mac`.
+    pub fn new<M: Mac>(frame: &dyn Frame, mac: &M) -> io::Result<SignedFrame> {
+        let bytes = frame.to_bytes();
+        let tag = mac.sign(&bytes[..])?;
+        let frame = frame.with_payload(&frame.payload_ref()).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("{} can not be copied into a SignedFrame", frame.kind()),
+            )
+        })?;
+
+        Ok(SignedFrame {
+            frame: Arc::from(frame),
+            bytes,
+            tag,
+        })
+    }
+
+    /// Returns the frame that was signed.
+    pub fn inner(&self) -> &dyn Frame {
+        &*self.frame
+    }
+
+    /// Consumes this frame, returning the frame that was signed.
+    pub fn into_inner(self) -> Arc<dyn Frame> {
+        self.frame
+    }
+
+    pub fn tag(&self) -> &[u8] {
+        &self.tag[..]
+    }
+}
+
+impl Frame for SignedFrame {
+    fn payload(&self) -> Vec<u8> {
+        self.frame.payload()
+    }
+
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        self.frame.payload_ref()
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(self.bytes.len() + self.tag.len());
+        buf.extend_from_slice(&self.bytes[..]);
+        buf.extend_from_slice(&self.tag[..]);
+
+        buf
+    }
+
+    fn len_as_vec(&self) -> usize {
+        self.bytes.len() + self.tag.len()
+    }
+
+    fn as_mut_raw_erased(&self) -> *mut () {
+        let dup = Box::new(self.clone());
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "SignedFrame"
+    }
+}
+
+/// Decodes the next inner frame from `buf` with `inner`, and removes the tag after it,
+/// skipping frames whose tag `mac` does not verify. Leaves `buf` as it was if the frame or its
+/// tag has not arrived yet.
+fn decode<M: Mac>(
+    buf: &mut RecvBuffer,
+    mac: &M,
+    inner: &mut dyn FrameDecoder,
+) -> Option<Box<dyn Frame>> {
+    loop {
+        // Only the inner frame is copied, once its length and the tag after it are known
+        let len = inner.size_hint(buf.as_slice())?;
+        if buf.len() < len + M::TAG_LEN {
+            return None;
+        }
+
+        // The tag covers the bytes received, not the frame re-encoded
+        let (bytes, tag) = buf.as_slice()[..(len + M::TAG_LEN)].split_at(len);
+        let verified = mac.verify(bytes, tag);
+        let (bytes, tag) = (bytes.to_vec(), tag.to_vec());
+        buf.consume(len + M::TAG_LEN);
+        if !verified {
+            error!(
+                "Signed frame of {} byte(s) failed verification. Dropping it",
+                len
+            );
+            continue;
+        }
+
+        match inner.decode_buffer(&mut RecvBuffer::from(bytes.clone())) {
+            Some(frame) => {
+                return Some(Box::new(SignedFrame {
+                    frame: Arc::from(frame),
+                    bytes,
+                    tag,
+                }))
+            }
+            None => error!("Signed frame did not contain a complete inner frame. Dropping it"),
+        }
+    }
+}
+
+/// Compares `a` and `b` in time depending only on their lengths.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(all(test, feature = "openssl"))]
+mod tests {
+    use super::*;
+    use crate::frame::{Decoder, FrameType, OpType, WebSocketFrame, WebSocketFrameBuilder};
+
+    fn decoder(mac: &HmacSha256) -> Decoder<crate::frame::DynamicBuilder> {
+        Decoder::with_decoder(SignedDecoder::of::<WebSocketFrameBuilder>(mac.clone()))
+    }
+
+    #[test]
+    fn masked_frames_verify() {
+        let mac = HmacSha256::new(b"key").unwrap();
+        let frame = WebSocketFrame::new_masked(b"hello", FrameType::Data, OpType::Binary);
+        let signed = SignedFrame::new(&frame, &mac).unwrap();
+
+        let frames = decoder(&mac).push_bytes(&signed.to_bytes());
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].payload(), b"hello");
+    }
+
+    #[test]
+    fn bytes_received_are_kept_while_the_tag_arrives() {
+        let mac = HmacSha256::new(b"key").unwrap();
+        let frame = WebSocketFrame::new_masked(b"hello", FrameType::Data, OpType::Binary);
+        let bytes = SignedFrame::new(&frame, &mac).unwrap().to_bytes();
+
+        let mut decoder = decoder(&mac);
+        let split = bytes.len() - 1;
+        assert!(decoder.push_bytes(&bytes[..split]).is_empty());
+        assert_eq!(decoder.buffered(), &bytes[..split]);
+        let frames = decoder.push_bytes(&bytes[split..]);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].to_bytes(), bytes);
+    }
+
+    #[test]
+    fn bad_tags_are_dropped() {
+        let mac = HmacSha256::new(b"key").unwrap();
+        let other = HmacSha256::new(b"other key").unwrap();
+        let frame = WebSocketFrame::new(b"hello", FrameType::Data, OpType::Binary);
+        let mut bytes = SignedFrame::new(&frame, &other).unwrap().to_bytes();
+        bytes.extend(SignedFrame::new(&frame, &mac).unwrap().to_bytes());
+
+        let mut decoder = decoder(&mac);
+        let frames = decoder.push_bytes(&bytes);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].payload(), b"hello");
+        assert!(decoder.buffered().is_empty());
+    }
+
+    #[test]
+    fn raw_pointers_are_to_a_copy_of_the_signed_frame() {
+        let mac = HmacSha256::new(b"key").unwrap();
+        let frame = WebSocketFrame::new(b"hello", FrameType::Data, OpType::Binary);
+        let signed = SignedFrame::new(&frame, &mac).unwrap();
+
+        let copy = unsafe { Box::from_raw(signed.as_mut_raw_erased() as *mut SignedFrame) };
+        assert_eq!(copy.tag(), signed.tag());
+        assert_eq!(copy.to_bytes(), signed.to_bytes());
+    }
+}
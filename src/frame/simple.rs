@@ -22,9 +22,13 @@
 //! End Guard:      8 bits (0x17)
 //! ```
 
+use std::borrow::Cow;
 use std::mem;
 
-use super::{Frame, FrameBuilder};
+use crate::buffer::RecvBuffer;
+
+use super::recycle::take_buffer;
+use super::{Corruption, Frame, FrameBuilder, FrameBuilderInfo};
 
 bitflags! {
     #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -47,7 +51,66 @@ pub struct SimpleFrameBuilder;
 
 impl FrameBuilder for SimpleFrameBuilder {
     fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
-        if buf.len() < 5 {
+        let (frame, frame_len) = SimpleFrame::decode(&buf[..])?;
+
+        // Remove frame from buffer
+        let mut remainder = Vec::<u8>::with_capacity(buf.len() - frame_len);
+        remainder.extend_from_slice(&buf[frame_len..buf.len()]);
+        mem::swap(buf, &mut remainder);
+
+        Some(Box::new(frame))
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        let (frame, frame_len) = SimpleFrame::decode(buf.as_slice())?;
+        buf.consume(frame_len);
+
+        Some(Box::new(frame))
+    }
+
+    fn size_hint(buf: &[u8]) -> Option<usize> {
+        if buf.len() < 3 {
+            return None;
+        }
+
+        Some(u16::from_be_bytes([buf[1], buf[2]]) as usize + 4)
+    }
+
+    fn validate(buf: &[u8]) -> Result<(), Corruption> {
+        match buf.first() {
+            Some(&guard) if guard != FrameGuard::START.bits() => return Err(Corruption::BadGuard),
+            Some(_) if buf.len() >= 3 => {}
+            _ => return Ok(()),
+        }
+
+        let end_guard_at = u16::from_be_bytes([buf[1], buf[2]]) as usize + 3;
+        match buf.get(end_guard_at) {
+            Some(&guard) if guard != FrameGuard::END.bits() => Err(Corruption::BadGuard),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl FrameBuilderInfo for SimpleFrameBuilder {
+    const NAME: &'static str = "simple";
+    const MAX_PAYLOAD_LEN: Option<usize> = Some(u16::MAX as usize);
+}
+
+impl SimpleFrame {
+    /// Creates a new `SimpleFrame`
+    pub fn new(buf: &[u8]) -> Self {
+        SimpleFrame {
+            start_guard: FrameGuard::START,
+            payload_len: buf.len() as u16,
+            payload: buf.to_vec(),
+            end_guard: FrameGuard::END,
+        }
+    }
+
+    /// Decodes the frame at the start of `buf`, returning it along with its length on the
+    /// wire.
+    fn decode(buf: &[u8]) -> Option<(SimpleFrame, usize)> {
+        if buf.len() < 4 {
             return None;
         }
 
@@ -74,13 +137,14 @@ impl FrameBuilder for SimpleFrameBuilder {
         frame.payload_len = payload_len;
 
         let payload_len = payload_len as usize;
-        if buf.len() - 4 < payload_len {
+        if buf.len() < payload_len + 4 {
             return None;
         }
 
         trace!("Payload length: {}", payload_len);
 
         // Payload data
+        frame.payload = take_buffer(payload_len);
         frame.payload.extend_from_slice(&buf[3..(payload_len + 3)]);
 
         // Ending frame guard
@@ -98,24 +162,8 @@ impl FrameBuilder for SimpleFrameBuilder {
             }
         }
 
-        // Remove frame from buffer
-        let mut remainder = Vec::<u8>::with_capacity(buf.len() - frame.len_as_vec());
-        remainder.extend_from_slice(&buf[frame.len_as_vec()..buf.len()]);
-        mem::swap(buf, &mut remainder);
-
-        return Some(Box::new(frame));
-    }
-}
-
-impl SimpleFrame {
-    /// Creates a new `SimpleFrame`
-    pub fn new(buf: &[u8]) -> Self {
-        SimpleFrame {
-            start_guard: FrameGuard::START,
-            payload_len: buf.len() as u16,
-            payload: buf.to_vec(),
-            end_guard: FrameGuard::END,
-        }
+        let frame_len = frame.len_as_vec();
+        Some((frame, frame_len))
     }
 }
 
@@ -124,6 +172,18 @@ impl Frame for SimpleFrame {
         self.payload.clone()
     }
 
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.payload[..])
+    }
+
+    fn into_payload(self: Box<Self>) -> Vec<u8> {
+        self.payload
+    }
+
+    fn with_payload(&self, payload: &[u8]) -> Option<Box<dyn Frame>> {
+        Some(Box::new(SimpleFrame::new(payload)))
+    }
+
     fn to_bytes(&self) -> Vec<u8> {
         let mut buf = Vec::<u8>::with_capacity(self.len_as_vec());
         buf.push(self.start_guard.bits());
@@ -136,12 +196,16 @@ impl Frame for SimpleFrame {
     }
 
     fn len_as_vec(&self) -> usize {
-        (self.payload_len + 4) as usize
+        self.payload_len as usize + 4
     }
 
     fn as_mut_raw_erased(&self) -> *mut () {
         let dup = Box::new(self.clone());
-        return Box::into_raw(dup) as *mut _ as *mut ();
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "SimpleFrame"
     }
 }
 
@@ -0,0 +1,322 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Provides a Type-Length-Value frame, with either an 8 or 16-bit type tag.
+//!
+//! ```ignore
+//! 0                   1                   2                   3
+//! 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |  Tag (8 or 16 bits)   |              Value Length             |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |  Value Length Cont.   |                Value                  |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//!
+//! Tag:            Unsigned 8 or 16-bit integer Network Byte Order.
+//! Value Length:   Unsigned 32-bit integer Network Byte Order.
+//! Value:          Value Length bytes.
+//! ```
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::mem;
+use std::str;
+
+use crate::buffer::RecvBuffer;
+
+use super::recycle::take_buffer;
+use super::{Frame, FrameBuilder, FrameBuilderInfo};
+
+/// Width of the type tag used by a `TlvFrameBuilder`.
+pub trait TlvTag {
+    const WIDTH: usize;
+}
+
+/// 8-bit type tags.
+#[derive(Clone, Copy, Debug)]
+pub struct Tag8;
+
+/// 16-bit type tags.
+#[derive(Clone, Copy, Debug)]
+pub struct Tag16;
+
+impl TlvTag for Tag8 {
+    const WIDTH: usize = 1;
+}
+
+impl TlvTag for Tag16 {
+    const WIDTH: usize = 2;
+}
+
+#[derive(Clone, Debug)]
+pub struct TlvFrame {
+    tag_width: usize,
+    tag: u16,
+    value: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct TlvFrameBuilder<T: TlvTag = Tag8> {
+    phantom: PhantomData<T>,
+}
+
+/// Maps tags to human readable names for logging and diagnostics.
+#[derive(Clone, Debug, Default)]
+pub struct TlvRegistry {
+    names: HashMap<u16, &'static str>,
+}
+
+impl<T: TlvTag> FrameBuilder for TlvFrameBuilder<T> {
+    fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        let frame = TlvFrame::decode(buf, T::WIDTH)?;
+        let frame_len = frame.len_as_vec();
+
+        // Remove frame from buffer
+        let mut remainder = Vec::<u8>::with_capacity(buf.len() - frame_len);
+        remainder.extend_from_slice(&buf[frame_len..buf.len()]);
+        mem::swap(buf, &mut remainder);
+
+        Some(Box::new(frame))
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        let frame = TlvFrame::decode(buf.as_slice(), T::WIDTH)?;
+        buf.consume(frame.len_as_vec());
+
+        Some(Box::new(frame))
+    }
+
+    fn size_hint(buf: &[u8]) -> Option<usize> {
+        let header_len = T::WIDTH + 4;
+        if buf.len() < header_len {
+            return None;
+        }
+
+        let value_len = u32::from_be_bytes([
+            buf[T::WIDTH],
+            buf[T::WIDTH + 1],
+            buf[T::WIDTH + 2],
+            buf[T::WIDTH + 3],
+        ]) as usize;
+        header_len.checked_add(value_len)
+    }
+}
+
+impl<T: TlvTag> FrameBuilderInfo for TlvFrameBuilder<T> {
+    const NAME: &'static str = "tlv";
+    const MAX_PAYLOAD_LEN: Option<usize> = Some(u32::MAX as usize);
+}
+
+impl TlvFrame {
+    /// Creates a new `TlvFrame` with an 8-bit tag.
+    pub fn new(tag: u8, value: &[u8]) -> Self {
+        TlvFrame {
+            tag_width: Tag8::WIDTH,
+            tag: tag as u16,
+            value: value.to_vec(),
+        }
+    }
+
+    /// Creates a new `TlvFrame` with a 16-bit tag.
+    pub fn new_wide(tag: u16, value: &[u8]) -> Self {
+        TlvFrame {
+            tag_width: Tag16::WIDTH,
+            tag,
+            value: value.to_vec(),
+        }
+    }
+
+    /// Interprets a received frame as a `TlvFrame`, if it is one.
+    pub fn from_frame(frame: &dyn Frame) -> Option<Self> {
+        if frame.kind() != "TlvFrame" {
+            return None;
+        }
+
+        // The tag width is the only variable before the length, so recover it from the
+        // encoded length.
+        let buf = frame.to_bytes();
+        let tag_width = buf.len() - frame.payload().len() - 4;
+        TlvFrame::decode(&buf[..], tag_width)
+    }
+
+    pub fn tag(&self) -> u16 {
+        self.tag
+    }
+
+    pub fn value(&self) -> &[u8] {
+        &self.value[..]
+    }
+
+    /// Returns the value as a `u8`, if it is exactly one byte.
+    pub fn value_u8(&self) -> Option<u8> {
+        match self.value[..] {
+            [b] => Some(b),
+            _ => None,
+        }
+    }
+
+    /// Returns the value as a big endian `u16`, if it is exactly two bytes.
+    pub fn value_u16(&self) -> Option<u16> {
+        Some(u16::from_be_bytes(self.value[..].try_into().ok()?))
+    }
+
+    /// Returns the value as a big endian `u32`, if it is exactly four bytes.
+    pub fn value_u32(&self) -> Option<u32> {
+        Some(u32::from_be_bytes(self.value[..].try_into().ok()?))
+    }
+
+    /// Returns the value as a big endian `u64`, if it is exactly eight bytes.
+    pub fn value_u64(&self) -> Option<u64> {
+        Some(u64::from_be_bytes(self.value[..].try_into().ok()?))
+    }
+
+    /// Returns the value as a string slice, if it is valid UTF-8.
+    pub fn value_str(&self) -> Option<&str> {
+        str::from_utf8(&self.value[..]).ok()
+    }
+
+    fn decode(buf: &[u8], tag_width: usize) -> Option<Self> {
+        let header_len = tag_width + 4;
+        if buf.len() < header_len {
+            return None;
+        }
+
+        let tag = if tag_width == Tag16::WIDTH {
+            u16::from_be_bytes([buf[0], buf[1]])
+        } else {
+            buf[0] as u16
+        };
+        let value_len = u32::from_be_bytes([
+            buf[tag_width],
+            buf[tag_width + 1],
+            buf[tag_width + 2],
+            buf[tag_width + 3],
+        ]) as usize;
+
+        let frame_len = header_len.checked_add(value_len)?;
+        if buf.len() < frame_len {
+            return None;
+        }
+
+        trace!("Tag: {} Value length: {}", tag, value_len);
+
+        let mut value = take_buffer(value_len);
+        value.extend_from_slice(&buf[header_len..frame_len]);
+
+        Some(TlvFrame {
+            tag_width,
+            tag,
+            value,
+        })
+    }
+}
+
+impl Frame for TlvFrame {
+    fn payload(&self) -> Vec<u8> {
+        self.value.clone()
+    }
+
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.value[..])
+    }
+
+    fn into_payload(self: Box<Self>) -> Vec<u8> {
+        self.value
+    }
+
+    fn with_payload(&self, payload: &[u8]) -> Option<Box<dyn Frame>> {
+        let mut frame = self.clone();
+        frame.value = payload.to_vec();
+        Some(Box::new(frame))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(self.len_as_vec());
+        if self.tag_width == Tag16::WIDTH {
+            buf.extend_from_slice(&self.tag.to_be_bytes());
+        } else {
+            buf.push(self.tag as u8);
+        }
+        buf.extend_from_slice(&(self.value.len() as u32).to_be_bytes());
+        buf.extend_from_slice(&self.value[..]);
+
+        buf
+    }
+
+    fn len_as_vec(&self) -> usize {
+        self.tag_width + 4 + self.value.len()
+    }
+
+    fn as_mut_raw_erased(&self) -> *mut () {
+        let dup = Box::new(self.clone());
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "TlvFrame"
+    }
+}
+
+impl TlvRegistry {
+    /// Creates an empty registry.
+    pub fn new() -> Self {
+        TlvRegistry::default()
+    }
+
+    /// Associates `name` with `tag`, replacing any previous name.
+    pub fn register(&mut self, tag: u16, name: &'static str) {
+        self.names.insert(tag, name);
+    }
+
+    /// Returns the name registered for `tag`.
+    pub fn name(&self, tag: u16) -> Option<&'static str> {
+        self.names.get(&tag).copied()
+    }
+
+    /// Returns a one line description of `frame`, e.g. `Login(12 bytes)`, falling back to
+    /// the numeric tag when it has no registered name.
+    pub fn describe(&self, frame: &TlvFrame) -> String {
+        match self.name(frame.tag) {
+            Some(name) => format!("{}({} bytes)", name, frame.value.len()),
+            None => format!("Tag {:#x}({} bytes)", frame.tag, frame.value.len()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::Decoder;
+
+    #[test]
+    fn tags_and_values_survive_decoding() {
+        let frame = TlvFrame::new_wide(0x1234, &7u32.to_be_bytes());
+        let frames = Decoder::<TlvFrameBuilder<Tag16>>::new().push_bytes(&frame.to_bytes());
+
+        let decoded = TlvFrame::from_frame(&*frames[0]).unwrap();
+        assert_eq!(decoded.tag(), 0x1234);
+        assert_eq!(decoded.value_u32(), Some(7));
+        assert_eq!(decoded.value_u16(), None);
+        assert_eq!(decoded.to_bytes(), frame.to_bytes());
+    }
+
+    #[test]
+    fn registries_name_known_tags() {
+        let mut registry = TlvRegistry::new();
+        registry.register(1, "Login");
+
+        assert_eq!(
+            registry.describe(&TlvFrame::new(1, b"user")),
+            "Login(4 bytes)"
+        );
+        assert_eq!(
+            registry.describe(&TlvFrame::new(2, b"")),
+            "Tag 0x2(0 bytes)"
+        );
+    }
+}
@@ -0,0 +1,237 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Provides a frame with a variable length prefix, as written by protobuf's
+//! `writeDelimitedTo` and read by `parseDelimitedFrom`.
+//!
+//! ```ignore
+//! +-+-+-+-+-+-+-+-+- - - - - - - - - - - - - - - - - -+- - - - - - - - -+
+//! |  Payload Length (1 to 5 bytes, LEB128)           |  Payload Data   |
+//! +-+-+-+-+-+-+-+-+- - - - - - - - - - - - - - - - - -+- - - - - - - - -+
+//!
+//! Payload Length:   Unsigned 32-bit integer, 7 bits per byte, least significant group
+//!                   first. The high bit of each byte is set if another byte follows.
+//! Payload Data:     Payload Length bytes.
+//! ```
+
+use std::borrow::Cow;
+use std::mem;
+
+use crate::buffer::RecvBuffer;
+
+use super::recycle::take_buffer;
+use super::{Corruption, Frame, FrameBuilder, FrameBuilderInfo};
+
+/// Most bytes a 32-bit varint is encoded in.
+const MAX_VARINT_LEN: usize = 5;
+
+#[derive(Clone, Debug, Default)]
+pub struct VarintFrame {
+    payload: Vec<u8>,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct VarintFrameBuilder;
+
+/// Outcome of reading a varint from the start of a buffer.
+enum Varint {
+    /// The value, and the number of bytes it was encoded in.
+    Complete(u32, usize),
+    Incomplete,
+    Malformed,
+}
+
+impl FrameBuilder for VarintFrameBuilder {
+    fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        let (frame, frame_len) = VarintFrame::decode(&buf[..])?;
+
+        // Remove frame from buffer
+        let mut remainder = Vec::<u8>::with_capacity(buf.len() - frame_len);
+        remainder.extend_from_slice(&buf[frame_len..buf.len()]);
+        mem::swap(buf, &mut remainder);
+
+        Some(Box::new(frame))
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        let (frame, frame_len) = VarintFrame::decode(buf.as_slice())?;
+        buf.consume(frame_len);
+
+        Some(Box::new(frame))
+    }
+
+    fn size_hint(buf: &[u8]) -> Option<usize> {
+        match read_varint(buf) {
+            Varint::Complete(payload_len, header_len) => {
+                header_len.checked_add(payload_len as usize)
+            }
+            Varint::Incomplete | Varint::Malformed => None,
+        }
+    }
+
+    fn validate(buf: &[u8]) -> Result<(), Corruption> {
+        match read_varint(buf) {
+            Varint::Malformed => Err(Corruption::Malformed),
+            Varint::Complete(..) | Varint::Incomplete => Ok(()),
+        }
+    }
+}
+
+impl FrameBuilderInfo for VarintFrameBuilder {
+    const NAME: &'static str = "varint";
+    const MAX_PAYLOAD_LEN: Option<usize> = Some(u32::MAX as usize);
+}
+
+impl VarintFrame {
+    /// Creates a new `VarintFrame`. Payloads longer than `u32::MAX` bytes are truncated.
+    pub fn new(buf: &[u8]) -> Self {
+        let len = buf.len().min(u32::MAX as usize);
+        VarintFrame {
+            payload: buf[..len].to_vec(),
+        }
+    }
+
+    /// Decodes the frame at the start of `buf`, returning it along with its length on the
+    /// wire.
+    fn decode(buf: &[u8]) -> Option<(VarintFrame, usize)> {
+        let (payload_len, header_len) = match read_varint(buf) {
+            Varint::Complete(payload_len, header_len) => (payload_len as usize, header_len),
+            Varint::Incomplete => return None,
+            Varint::Malformed => {
+                error!("Payload length is not a valid 32-bit varint. Buffer corrupted?");
+                return None;
+            }
+        };
+
+        let frame_len = match header_len.checked_add(payload_len) {
+            Some(frame_len) if buf.len() >= frame_len => frame_len,
+            _ => return None,
+        };
+
+        trace!("Payload length: {}", payload_len);
+
+        let mut frame = VarintFrame {
+            payload: take_buffer(payload_len),
+        };
+        frame.payload.extend_from_slice(&buf[header_len..frame_len]);
+
+        Some((frame, frame_len))
+    }
+}
+
+impl Frame for VarintFrame {
+    fn payload(&self) -> Vec<u8> {
+        self.payload.clone()
+    }
+
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(&self.payload[..])
+    }
+
+    fn into_payload(self: Box<Self>) -> Vec<u8> {
+        self.payload
+    }
+
+    fn with_payload(&self, payload: &[u8]) -> Option<Box<dyn Frame>> {
+        Some(Box::new(VarintFrame::new(payload)))
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(self.len_as_vec());
+        let mut value = self.payload.len() as u32;
+        while value >= 0x80 {
+            buf.push((value as u8 & 0x7F) | 0x80);
+            value >>= 7;
+        }
+        buf.push(value as u8);
+        buf.extend_from_slice(&self.payload[..]);
+
+        buf
+    }
+
+    fn len_as_vec(&self) -> usize {
+        varint_len(self.payload.len() as u32) + self.payload.len()
+    }
+
+    fn as_mut_raw_erased(&self) -> *mut () {
+        let dup = Box::new(self.clone());
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "VarintFrame"
+    }
+}
+
+fn read_varint(buf: &[u8]) -> Varint {
+    let mut value = 0u64;
+    for (i, &byte) in buf.iter().take(MAX_VARINT_LEN).enumerate() {
+        value |= ((byte & 0x7F) as u64) << (7 * i);
+        if byte & 0x80 == 0 {
+            return match u32::try_from(value) {
+                Ok(value) => Varint::Complete(value, i + 1),
+                Err(_) => Varint::Malformed,
+            };
+        }
+    }
+
+    if buf.len() < MAX_VARINT_LEN {
+        Varint::Incomplete
+    } else {
+        Varint::Malformed
+    }
+}
+
+fn varint_len(value: u32) -> usize {
+    match value {
+        0..=0x7F => 1,
+        0x80..=0x3FFF => 2,
+        0x4000..=0x1F_FFFF => 3,
+        0x20_0000..=0xFFF_FFFF => 4,
+        _ => 5,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lengths_are_encoded_as_leb128() {
+        let frame = VarintFrame::new(&[9; 300]);
+        let bytes = frame.to_bytes();
+        assert_eq!(&bytes[..2], &[0xAC, 0x02]);
+        assert_eq!(bytes.len(), frame.len_as_vec());
+        assert_eq!(VarintFrameBuilder::size_hint(&bytes), Some(302));
+
+        let mut buf = bytes[..301].to_vec();
+        assert!(VarintFrameBuilder::from_bytes(&mut buf).is_none());
+        buf.push(9);
+        assert_eq!(
+            VarintFrameBuilder::from_bytes(&mut buf).unwrap().payload(),
+            [9; 300]
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn varints_longer_than_32_bits_are_malformed() {
+        assert_eq!(VarintFrameBuilder::validate(&[0x80; 4]), Ok(()));
+        assert_eq!(
+            VarintFrameBuilder::validate(&[0x80; 5]),
+            Err(Corruption::Malformed)
+        );
+        assert_eq!(
+            VarintFrameBuilder::validate(&[0xFF, 0xFF, 0xFF, 0xFF, 0x10]),
+            Err(Corruption::Malformed)
+        );
+        assert_eq!(
+            VarintFrameBuilder::validate(&[0xFF, 0xFF, 0xFF, 0xFF, 0x0F]),
+            Ok(())
+        );
+    }
+}
@@ -6,15 +6,25 @@
 // http://mozilla.org/MPL/2.0/.
 
 //! The `frame::websocket` module provides [RFC-6465][rfc-6455] support for websocket based
-//! streams. This module provides no support for the handshake part of the protocol, or any
-//! smarts about handling fragmentation messages. It simply encodes/decodes complete websocket
-//! frames.
+//! streams. This module provides no support for the handshake part of the protocol. It simply
+//! encodes/decodes complete websocket frames; fragmented messages are reassembled by
+//! `WebSocketMessageBuilder`.
 //!
 //! [rfc-6455]: https://tools.ietf.org/html/rfc6455
 
+use std::borrow::Cow;
 use std::{fmt, mem};
 
-use super::{Frame, FrameBuilder};
+use crate::buffer::RecvBuffer;
+
+use super::recycle::take_buffer;
+use super::{random_u64, Corruption, Frame, FrameBuilder, FrameBuilderInfo};
+
+/// Bit of the first header byte set on the last fragment of a message.
+const FIN: u8 = 0b1000_0000;
+/// Bit of the first header byte set on the first fragment of a message compressed with
+/// permessage-deflate.
+const RSV1: u8 = 0b0100_0000;
 
 bitflags! {
     #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
@@ -46,7 +56,9 @@ pub enum OpType {
 
 #[derive(Clone)]
 struct Header {
-    op_code: OpCode,
+    fin: bool,
+    rsv1: bool,
+    op_type: OpType,
     mask: bool,
     payload_len: u64,
     masking_key: [u8; 4],
@@ -69,34 +81,215 @@ pub struct WebSocketFrameBuilder;
 
 impl FrameBuilder for WebSocketFrameBuilder {
     fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
-        if buf.len() < 5 {
+        let (frame, frame_len) = WebSocketFrame::decode(&buf[..])?;
+
+        // Remove from buffer
+        let mut remainder = Vec::<u8>::with_capacity(buf.len() - frame_len);
+        remainder.extend_from_slice(&buf[frame_len..buf.len()]);
+        mem::swap(buf, &mut remainder);
+
+        Some(Box::new(frame))
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        let (frame, frame_len) = WebSocketFrame::decode(buf.as_slice())?;
+        buf.consume(frame_len);
+
+        Some(Box::new(frame))
+    }
+
+    fn size_hint(buf: &[u8]) -> Option<usize> {
+        if buf.len() < 2 {
+            return None;
+        }
+
+        let mask_len = if buf[1] & 0b1000_0000 > 0 { 4 } else { 0 };
+        let (header_len, payload_len) = match buf[1] & 0b0111_1111 {
+            126 => {
+                if buf.len() < 4 {
+                    return None;
+                }
+                (4, u16::from_be_bytes([buf[2], buf[3]]) as u64)
+            }
+            127 => {
+                if buf.len() < 10 {
+                    return None;
+                }
+                (10, u64::from_be_bytes(buf[2..10].try_into().ok()?))
+            }
+            len => (2, len as u64),
+        };
+
+        usize::try_from(payload_len)
+            .ok()?
+            .checked_add(header_len + mask_len)
+    }
+
+    fn validate(buf: &[u8]) -> Result<(), Corruption> {
+        match buf.first() {
+            Some(&byte) if OpType::from_bits(byte & 0b0000_1111).is_none() => {
+                return Err(Corruption::Malformed);
+            }
+            _ if buf.len() < 10 || buf[1] & 0b0111_1111 != 127 => return Ok(()),
+            _ => {}
+        }
+
+        // A 64-bit length must have its most significant bit clear (RFC 6455 section 5.2),
+        // and fit in memory along with the header
+        let payload_len = u64::from_be_bytes([
+            buf[2], buf[3], buf[4], buf[5], buf[6], buf[7], buf[8], buf[9],
+        ]);
+        if payload_len >> 63 != 0 || Self::size_hint(buf).is_none() {
+            return Err(Corruption::Malformed);
+        }
+
+        Ok(())
+    }
+}
+
+impl FrameBuilderInfo for WebSocketFrameBuilder {
+    const NAME: &'static str = "websocket";
+    const VERSION: u32 = 13;
+    const STREAMING: bool = true;
+}
+
+impl OpType {
+    /// Maps the opcode bits of a frame to an `OpType`, if they are a known opcode.
+    pub(super) fn from_bits(bits: u8) -> Option<OpType> {
+        match OpCode::from_bits(bits)? {
+            OpCode::CONTINUATION => Some(OpType::Continuation),
+            OpCode::TEXT => Some(OpType::Text),
+            OpCode::BINARY => Some(OpType::Binary),
+            OpCode::CLOSE => Some(OpType::Close),
+            OpCode::PING => Some(OpType::Ping),
+            OpCode::PONG => Some(OpType::Pong),
+            _ => None,
+        }
+    }
+
+    fn bits(&self) -> u8 {
+        match *self {
+            OpType::Continuation => OpCode::CONTINUATION.bits(),
+            OpType::Text => OpCode::TEXT.bits(),
+            OpType::Binary => OpCode::BINARY.bits(),
+            OpType::Close => OpCode::CLOSE.bits(),
+            OpType::Ping => OpCode::PING.bits(),
+            OpType::Pong => OpCode::PONG.bits(),
+        }
+    }
+}
+
+impl WebSocketFrame {
+    pub fn new(buf: &[u8], frame_type: FrameType, op_type: OpType) -> WebSocketFrame {
+        WebSocketFrame::fragment(buf, frame_type, op_type, true)
+    }
+
+    /// Creates one fragment of a message split across several frames. The first fragment
+    /// carries the message's `OpType`, the rest `OpType::Continuation`, and only the last is
+    /// `fin`.
+    pub fn fragment(
+        buf: &[u8],
+        frame_type: FrameType,
+        op_type: OpType,
+        fin: bool,
+    ) -> WebSocketFrame {
+        WebSocketFrame {
+            frame_type,
+            header: Header {
+                fin,
+                rsv1: false,
+                op_type,
+                mask: false,
+                payload_len: buf.len() as u64,
+                masking_key: [0u8; 4],
+            },
+            payload: Payload { data: buf.to_vec() },
+        }
+    }
+
+    /// Creates a frame masked with a random key, as RFC 6455 requires of every frame sent by
+    /// a client.
+    pub fn new_masked(buf: &[u8], frame_type: FrameType, op_type: OpType) -> WebSocketFrame {
+        WebSocketFrame::new(buf, frame_type, op_type).masked()
+    }
+
+    /// Returns this frame masked with a new random key, e.g. to mask a fragment. The payload
+    /// is unchanged, only its encoding.
+    pub fn masked(mut self) -> WebSocketFrame {
+        let mut data = self.payload_unmasked();
+        let masking_key = (random_u64() as u32).to_be_bytes();
+        for (x, byte) in data.iter_mut().enumerate() {
+            *byte ^= masking_key[x % 4];
+        }
+
+        self.header.mask = true;
+        self.header.masking_key = masking_key;
+        self.payload.data = data;
+        self
+    }
+
+    pub fn op_type(&self) -> OpType {
+        self.header.op_type
+    }
+
+    pub fn frame_type(&self) -> FrameType {
+        self.frame_type
+    }
+
+    /// Returns `true` if this frame is the last, or only, fragment of its message.
+    pub fn is_final(&self) -> bool {
+        self.header.fin
+    }
+
+    /// Returns `true` if RSV1 is set, which marks the first frame of a message compressed
+    /// with permessage-deflate.
+    pub fn is_compressed(&self) -> bool {
+        self.header.rsv1
+    }
+
+    /// Returns this frame with RSV1 set or cleared. The payload is left as it is.
+    pub fn with_compressed(mut self, compressed: bool) -> WebSocketFrame {
+        self.header.rsv1 = compressed;
+        self
+    }
+
+    pub fn is_masked(&self) -> bool {
+        self.header.mask
+    }
+
+    pub fn payload_unmasked(&self) -> Vec<u8> {
+        let len = self.payload.data.len();
+        let mut buf = Vec::<u8>::with_capacity(len);
+        for x in 0..len {
+            buf.push(self.payload.data[x] ^ self.header.masking_key[x % 4]);
+        }
+
+        buf
+    }
+
+    /// Decodes the frame at the start of `buf`, returning it along with its length on the
+    /// wire.
+    pub(super) fn decode(buf: &[u8]) -> Option<(WebSocketFrame, usize)> {
+        if buf.len() < 2 {
             return None;
         }
 
         let mut frame: WebSocketFrame = Default::default();
 
+        // Final fragment
+        frame.header.fin = buf[0] & FIN > 0;
+        frame.header.rsv1 = buf[0] & RSV1 > 0;
+
         // OpCode and FrameType
         const FIN_CLEAR_MASK: u8 = 0b0000_1111;
         let op_byte = buf[0] & FIN_CLEAR_MASK;
-        match OpCode::from_bits(op_byte) {
-            Some(op_code) => {
-                if op_code == OpCode::CONTINUATION {
-                    frame.frame_type = FrameType::Data;
-                } else if op_code == OpCode::TEXT {
-                    frame.frame_type = FrameType::Data;
-                } else if op_code == OpCode::BINARY {
-                    frame.frame_type = FrameType::Data;
-                } else if op_code == OpCode::CLOSE {
-                    frame.frame_type = FrameType::Control;
-                } else if op_code == OpCode::PING {
-                    frame.frame_type = FrameType::Control;
-                } else if op_code == OpCode::PONG {
-                    frame.frame_type = FrameType::Control;
-                } else {
-                    unreachable!();
-                }
-
-                frame.header.op_code = op_code;
+        match OpType::from_bits(op_byte) {
+            Some(op_type) => {
+                frame.frame_type = match op_type {
+                    OpType::Continuation | OpType::Text | OpType::Binary => FrameType::Data,
+                    OpType::Close | OpType::Ping | OpType::Pong => FrameType::Control,
+                };
+                frame.header.op_type = op_type;
             }
             None => {
                 error!("Invalid OpCode bits: {:#b}", buf[0]);
@@ -118,6 +311,10 @@ impl FrameBuilder for WebSocketFrameBuilder {
         if payload_len <= 125 {
             frame.header.payload_len = payload_len as u64;
         } else if payload_len == 126 {
+            if buf.len() < 4 {
+                return None;
+            }
+
             let mut len = (buf[2] as u16) << 8;
             len |= buf[3] as u16;
             frame.header.payload_len = len as u64;
@@ -144,7 +341,7 @@ impl FrameBuilder for WebSocketFrameBuilder {
 
         // Optional masking key
         if frame.header.mask {
-            if buf.len() <= next_offset + 4 {
+            if buf.len() < next_offset + 4 {
                 return None;
             }
             frame.header.masking_key[0] = buf[next_offset];
@@ -154,94 +351,78 @@ impl FrameBuilder for WebSocketFrameBuilder {
             next_offset += 4;
         }
 
-        if buf.len() < next_offset + frame.header.payload_len as usize {
+        // A malicious length must not overflow the offset math below
+        let frame_len = match usize::try_from(frame.header.payload_len)
+            .ok()
+            .and_then(|len| len.checked_add(next_offset))
+        {
+            Some(frame_len) => frame_len,
+            None => {
+                error!("Payload length too large: {}", frame.header.payload_len);
+                return None;
+            }
+        };
+        if buf.len() < frame_len {
             return None;
         }
 
         // Payload data
         let len = frame.header.payload_len as usize;
+        frame.payload.data = take_buffer(len);
         frame
             .payload
             .data
             .extend_from_slice(&buf[next_offset..(len + next_offset)]);
 
-        // Remove from buffer
-        let mut remainder = Vec::<u8>::with_capacity(buf.len() - frame.len_as_vec());
-        remainder.extend_from_slice(&buf[frame.len_as_vec()..buf.len()]);
-        mem::swap(buf, &mut remainder);
-
-        return Some(Box::new(frame));
+        Some((frame, frame_len))
     }
 }
 
-impl WebSocketFrame {
-    pub fn new(buf: &[u8], frame_type: FrameType, op_type: OpType) -> WebSocketFrame {
-        WebSocketFrame {
-            frame_type,
-            header: Header {
-                op_code: match op_type {
-                    OpType::Continuation => OpCode::CONTINUATION,
-                    OpType::Text => OpCode::TEXT,
-                    OpType::Binary => OpCode::BINARY,
-                    OpType::Close => OpCode::CLOSE,
-                    OpType::Ping => OpCode::PING,
-                    OpType::Pong => OpCode::PONG,
-                },
-                mask: false,
-                payload_len: buf.len() as u64,
-                masking_key: [0u8; 4],
-            },
-            payload: Payload { data: buf.to_vec() },
+impl Frame for WebSocketFrame {
+    fn payload(&self) -> Vec<u8> {
+        if self.header.mask {
+            self.payload_unmasked()
+        } else {
+            self.payload.data.clone()
         }
     }
 
-    pub fn op_type(&self) -> OpType {
-        match self.header.op_code {
-            OpCode::CONTINUATION => OpType::Continuation,
-            OpCode::TEXT => OpType::Text,
-            OpCode::BINARY => OpType::Binary,
-            OpCode::CLOSE => OpType::Close,
-            OpCode::PING => OpType::Ping,
-            OpCode::PONG => OpType::Pong,
-            _ => unreachable!(),
+    fn payload_ref(&self) -> Cow<'_, [u8]> {
+        if self.header.mask {
+            Cow::Owned(self.payload_unmasked())
+        } else {
+            Cow::Borrowed(&self.payload.data[..])
         }
     }
 
-    pub fn frame_type(&self) -> FrameType {
-        self.frame_type.clone()
-    }
-
-    pub fn is_masked(&self) -> bool {
-        self.header.mask
-    }
-
-    pub fn payload_unmasked(&self) -> Vec<u8> {
-        let len = self.payload.data.len();
-        let mut buf = Vec::<u8>::with_capacity(len);
-        for x in 0..len {
-            buf.push(self.payload.data[x] ^ self.header.masking_key[x % 4]);
+    fn into_payload(mut self: Box<Self>) -> Vec<u8> {
+        if self.header.mask {
+            let masking_key = self.header.masking_key;
+            for (x, byte) in self.payload.data.iter_mut().enumerate() {
+                *byte ^= masking_key[x % 4];
+            }
         }
 
-        buf
+        self.payload.data
     }
-}
 
-impl Frame for WebSocketFrame {
-    fn payload(&self) -> Vec<u8> {
-        if self.header.mask {
-            self.payload_unmasked()
-        } else {
-            self.payload.data.clone()
+    fn with_payload(&self, payload: &[u8]) -> Option<Box<dyn Frame>> {
+        let header = &self.header;
+        let frame = WebSocketFrame::fragment(payload, self.frame_type, header.op_type, header.fin)
+            .with_compressed(header.rsv1);
+        if header.mask {
+            return Some(Box::new(frame.masked()));
         }
+        Some(Box::new(frame))
     }
 
     fn to_bytes(&self) -> Vec<u8> {
         let mut buf = Vec::<u8>::with_capacity(self.len_as_vec());
 
         // OpCode
-        const FIN: u8 = 0b1000_0000;
-        let op_code_with_fin = FIN | self.header.op_code.bits();
-        buf.push(op_code_with_fin);
+        let fin = if self.header.fin { FIN } else { 0 };
+        let rsv1 = if self.header.rsv1 { RSV1 } else { 0 };
+        buf.push(fin | rsv1 | self.header.op_type.bits());
 
         // Mask and Payload len
         let mask_bit: u8 = if self.header.mask {
@@ -260,10 +441,10 @@ impl Frame for WebSocketFrame {
         buf.push(next_byte);
 
         // Optional payload len
-        if next_byte == 126 {
+        if next_7_bits == 126 {
             buf.push(((self.header.payload_len as u16) >> 8) as u8);
             buf.push(self.header.payload_len as u8);
-        } else if next_byte == 127 {
+        } else if next_7_bits == 127 {
             buf.push((self.header.payload_len >> 56) as u8);
             buf.push((self.header.payload_len >> 48) as u8);
             buf.push((self.header.payload_len >> 40) as u8);
@@ -298,7 +479,7 @@ impl Frame for WebSocketFrame {
         len += 1;
 
         // Extended Payload length
-        if self.header.payload_len > 125 && self.header.payload_len < u16::MAX as u64 {
+        if self.header.payload_len > 125 && self.header.payload_len <= u16::MAX as u64 {
             len += 2;
         } else if self.header.payload_len > u16::MAX as u64 {
             len += 8;
@@ -317,7 +498,20 @@ impl Frame for WebSocketFrame {
 
     fn as_mut_raw_erased(&self) -> *mut () {
         let dup = Box::new(self.clone());
-        return Box::into_raw(dup) as *mut _ as *mut ();
+        Box::into_raw(dup) as *mut _ as *mut ()
+    }
+
+    fn kind(&self) -> &'static str {
+        "WebSocketFrame"
+    }
+
+    fn fmt_summary(&self) -> String {
+        format!(
+            "{}({:?}, {} bytes)",
+            self.kind(),
+            self.header.op_type,
+            self.len_as_vec()
+        )
     }
 }
 
@@ -326,7 +520,9 @@ impl Default for WebSocketFrame {
         WebSocketFrame {
             frame_type: FrameType::Control,
             header: Header {
-                op_code: OpCode::CONTINUATION,
+                fin: true,
+                rsv1: false,
+                op_type: OpType::Continuation,
                 mask: false,
                 payload_len: 0u64,
                 masking_key: [0u8; 4],
@@ -359,3 +555,32 @@ impl fmt::Display for OpType {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+    use crate::frame::Decoder;
+    use crate::testing::MockStream;
+    use crate::{Error, NonBlocking, Plain};
+
+    #[test]
+    fn reserved_opcodes_are_malformed() {
+        let mut bytes = WebSocketFrame::new(b"ok", FrameType::Data, OpType::Binary).to_bytes();
+        bytes.extend_from_slice(&[FIN | 0x3, 2, b'n', b'o']);
+
+        let mut decoder = Decoder::<WebSocketFrameBuilder>::new();
+        let frames = decoder.push_bytes(&bytes);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].payload(), b"ok");
+        assert_eq!(decoder.corruption(), Some(Corruption::Malformed));
+
+        // Without a DecodePolicy the stream fails rather than wait for a valid frame
+        let (local, mut remote) = MockStream::pair();
+        let mut stream = Plain::<_, WebSocketFrameBuilder>::new(local);
+        remote.write_all(&[FIN | 0xB, 0]).unwrap();
+        assert!(matches!(stream.nb_recv(), Err(Error::ProtocolViolation)));
+        assert!(matches!(stream.nb_recv(), Err(Error::ProtocolViolation)));
+    }
+}
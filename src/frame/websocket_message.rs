@@ -0,0 +1,297 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Reassembles websocket messages split across several frames.
+//!
+//! ```ignore
+//! +------------------+   +-------------------------+   +------+   +-------------------------+
+//! | Text, !FIN, "He" |-->| Continuation, !FIN, "l" |-->| Ping |-->| Continuation, FIN, "lo" |
+//! +------------------+   +-------------------------+   +------+   +-------------------------+
+//!
+//! Yields: Ping, then Text "Hello".
+//! ```
+//!
+//! Control frames sent in the middle of a fragmented message are yielded as soon as they
+//! arrive, as RFC 6455 requires, ahead of the message they interrupted.
+
+use std::mem;
+
+use crate::buffer::RecvBuffer;
+
+use super::recycle::take_buffer;
+use super::{
+    Corruption, Frame, FrameBuilder, FrameBuilderInfo, FrameType, OpType, WebSocketFrame,
+    WebSocketFrameBuilder,
+};
+
+/// Largest message a `WebSocketMessageBuilder` reassembles unless configured otherwise.
+pub const DEFAULT_MAX_MESSAGE_LEN: usize = 16 * 1024 * 1024;
+
+/// Builds complete websocket messages, joining fragments until the one with FIN set. Messages
+/// are returned as a single final `WebSocketFrame` with the `OpType` and RSV1 bit of their
+/// first fragment and an unmasked payload of up to `MAX` bytes. A message announcing more than
+/// that is never decoded, and reported as `Corruption::Malformed`.
+#[derive(Clone, Copy, Debug)]
+pub struct WebSocketMessageBuilder<const MAX: usize = DEFAULT_MAX_MESSAGE_LEN>;
+
+/// The parts of a frame header needed to find message boundaries without decoding payloads.
+struct FrameHead {
+    fin: bool,
+    op_type: OpType,
+    payload_len: u64,
+    frame_len: usize,
+}
+
+impl<const MAX: usize> FrameBuilder for WebSocketMessageBuilder<MAX> {
+    fn from_bytes(buf: &mut Vec<u8>) -> Option<Box<dyn Frame>> {
+        let mut recv_buf = RecvBuffer::from(mem::take(buf));
+        let frame = Self::from_buffer(&mut recv_buf);
+        *buf = recv_buf.take();
+
+        frame
+    }
+
+    fn from_buffer(buf: &mut RecvBuffer) -> Option<Box<dyn Frame>> {
+        loop {
+            let first = FrameHead::read(buf.as_slice())?;
+            if first.is_control() {
+                return WebSocketFrameBuilder::from_buffer(buf);
+            }
+
+            if first.fin && first.op_type != OpType::Continuation {
+                if first.payload_len > MAX as u64 {
+                    error!("Message exceeds the maximum of {} bytes", MAX);
+                    return None;
+                }
+                return WebSocketFrameBuilder::from_buffer(buf);
+            }
+
+            if first.op_type == OpType::Continuation {
+                // Nothing to continue, so the fragment can only be discarded
+                error!("Continuation frame without a message to continue");
+                if buf.len() < first.frame_len {
+                    return None;
+                }
+                buf.consume(first.frame_len);
+                continue;
+            }
+
+            // Find the final fragment before copying any payload
+            let mut message_len = 0u64;
+            let mut offset = 0;
+            loop {
+                let head = FrameHead::read(&buf.as_slice()[offset..])?;
+                if head.is_control() {
+                    if buf.len() - offset < head.frame_len {
+                        return None;
+                    }
+                    return take_control_frame(buf, offset);
+                }
+
+                if offset > 0 && head.op_type != OpType::Continuation {
+                    error!("New message started before the previous one finished");
+                    buf.consume(offset);
+                    break;
+                }
+
+                message_len = message_len.saturating_add(head.payload_len);
+                if message_len > MAX as u64 {
+                    error!("Message exceeds the maximum of {} bytes", MAX);
+                    return None;
+                }
+
+                if buf.len() - offset < head.frame_len {
+                    return None;
+                }
+                offset += head.frame_len;
+
+                if head.fin {
+                    trace!("Message length: {}", message_len);
+                    let message = join_fragments(&buf.as_slice()[..offset], message_len as usize);
+                    buf.consume(offset);
+                    return Some(Box::new(message));
+                }
+            }
+        }
+    }
+
+    fn size_hint(buf: &[u8]) -> Option<usize> {
+        WebSocketFrameBuilder::size_hint(buf)
+    }
+
+    fn validate(buf: &[u8]) -> Result<(), Corruption> {
+        // Checked as far as the fragments of the first message have arrived
+        let mut message_len = 0u64;
+        let mut offset = 0;
+        loop {
+            WebSocketFrameBuilder::validate(&buf[offset..])?;
+            let head = match FrameHead::read(&buf[offset..]) {
+                Some(head) => head,
+                None => return Ok(()),
+            };
+            let complete = buf.len() - offset >= head.frame_len;
+
+            if head.is_control() {
+                if offset == 0 || !complete {
+                    return Ok(());
+                }
+                offset += head.frame_len;
+                continue;
+            }
+
+            if offset > 0 && head.op_type != OpType::Continuation {
+                return Ok(());
+            }
+
+            message_len = message_len.saturating_add(head.payload_len);
+            if message_len > MAX as u64 {
+                return Err(Corruption::Malformed);
+            }
+
+            if head.fin || !complete {
+                return Ok(());
+            }
+            offset += head.frame_len;
+        }
+    }
+}
+
+impl<const MAX: usize> FrameBuilderInfo for WebSocketMessageBuilder<MAX> {
+    const NAME: &'static str = "websocket_message";
+    const VERSION: u32 = 13;
+    const MAX_PAYLOAD_LEN: Option<usize> = Some(MAX);
+}
+
+impl FrameHead {
+    fn read(buf: &[u8]) -> Option<FrameHead> {
+        if buf.len() < 2 {
+            return None;
+        }
+
+        let op_type = match OpType::from_bits(buf[0] & 0b0000_1111) {
+            Some(op_type) => op_type,
+            None => {
+                error!("Invalid OpCode bits: {:#b}", buf[0]);
+                return None;
+            }
+        };
+
+        // Only succeeds once the extended payload length has arrived
+        let frame_len = WebSocketFrameBuilder::size_hint(buf)?;
+        let payload_len = match buf[1] & 0b0111_1111 {
+            126 => u16::from_be_bytes([buf[2], buf[3]]) as u64,
+            127 => u64::from_be_bytes(buf[2..10].try_into().ok()?),
+            len => len as u64,
+        };
+
+        Some(FrameHead {
+            fin: buf[0] & 0b1000_0000 > 0,
+            op_type,
+            payload_len,
+            frame_len,
+        })
+    }
+
+    fn is_control(&self) -> bool {
+        match self.op_type {
+            OpType::Close | OpType::Ping | OpType::Pong => true,
+            OpType::Continuation | OpType::Text | OpType::Binary => false,
+        }
+    }
+}
+
+/// Removes the complete control frame at `offset` from `buf`, leaving the fragments before
+/// it in place.
+fn take_control_frame(buf: &mut RecvBuffer, offset: usize) -> Option<Box<dyn Frame>> {
+    let (frame, frame_len) = WebSocketFrame::decode(&buf.as_slice()[offset..])?;
+    let fragments = buf.as_slice()[..offset].to_vec();
+    buf.consume(offset + frame_len);
+    buf.unconsume(&fragments[..]);
+
+    Some(Box::new(frame))
+}
+
+/// Joins the fragments `buf` holds into one frame.
+fn join_fragments(buf: &[u8], message_len: usize) -> WebSocketFrame {
+    let mut payload = take_buffer(message_len);
+    let mut op_type = OpType::Continuation;
+    let mut compressed = false;
+    let mut offset = 0;
+    while offset < buf.len() {
+        let (fragment, fragment_len) = match WebSocketFrame::decode(&buf[offset..]) {
+            Some(decoded) => decoded,
+            None => break,
+        };
+        if offset == 0 {
+            op_type = fragment.op_type();
+            compressed = fragment.is_compressed();
+        }
+        payload.extend_from_slice(&fragment.payload_ref());
+        offset += fragment_len;
+    }
+
+    WebSocketFrame::new(&payload[..], FrameType::Data, op_type).with_compressed(compressed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn message_bytes(fragments: &[WebSocketFrame]) -> Vec<u8> {
+        fragments
+            .iter()
+            .flat_map(|frame| frame.to_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn fragments_are_joined_after_interleaved_control_frames() {
+        let mut buf = message_bytes(&[
+            WebSocketFrame::fragment(b"He", FrameType::Data, OpType::Text, false),
+            WebSocketFrame::fragment(b"l", FrameType::Data, OpType::Continuation, false),
+            WebSocketFrame::new(b"", FrameType::Control, OpType::Ping),
+            WebSocketFrame::fragment(b"lo", FrameType::Data, OpType::Continuation, true),
+        ]);
+
+        let ping = WebSocketMessageBuilder::<64>::from_bytes(&mut buf).unwrap();
+        let ping = ping.downcast_ref::<WebSocketFrame>().unwrap();
+        assert_eq!(ping.op_type(), OpType::Ping);
+
+        let message = WebSocketMessageBuilder::<64>::from_bytes(&mut buf).unwrap();
+        let message = message.downcast_ref::<WebSocketFrame>().unwrap();
+        assert_eq!(message.op_type(), OpType::Text);
+        assert!(message.is_final());
+        assert_eq!(message.payload(), b"Hello");
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn continuations_without_a_message_are_discarded() {
+        let mut buf = message_bytes(&[
+            WebSocketFrame::fragment(b"stray", FrameType::Data, OpType::Continuation, true),
+            WebSocketFrame::new(b"next", FrameType::Data, OpType::Binary),
+        ]);
+
+        let frame = WebSocketMessageBuilder::<64>::from_bytes(&mut buf).unwrap();
+        assert_eq!(frame.payload(), b"next");
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn messages_over_the_maximum_are_malformed() {
+        let first = WebSocketFrame::fragment(&[0; 3], FrameType::Data, OpType::Binary, false);
+        let second = WebSocketFrame::fragment(&[0; 2], FrameType::Data, OpType::Continuation, true);
+        let mut buf = message_bytes(&[first, second]);
+
+        assert_eq!(WebSocketMessageBuilder::<5>::validate(&buf), Ok(()));
+        assert_eq!(
+            WebSocketMessageBuilder::<4>::validate(&buf),
+            Err(Corruption::Malformed)
+        );
+        assert!(WebSocketMessageBuilder::<4>::from_bytes(&mut buf).is_none());
+    }
+}
@@ -0,0 +1,109 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Receives frames one at a time instead of in batches.
+//!
+//! ```ignore
+//! // Socket is readable
+//! for frame in stream.frames() {
+//!     handle(frame?);
+//! }
+//! ```
+
+use std::io::{self, Read, Write};
+
+use crate::frame::{Frame, FrameBuilder};
+use crate::tls::TlsSession;
+use crate::{Plain, Secure};
+
+/// Iterator over the frames a stream receives without blocking, returned by `frames()`.
+///
+/// Frames are decoded as the iterator is advanced, instead of all at once as `nb_recv`
+/// does. It ends once no complete frame has arrived, which is not necessarily the end of the
+/// stream, so `frames()` can be called again when the stream is readable. An error is
+/// yielded at most once, ending the iterator.
+pub struct FrameIter<'a, T> {
+    stream: &'a mut T,
+    done: bool,
+}
+
+impl<'a, T> FrameIter<'a, T> {
+    pub(crate) fn new(stream: &'a mut T) -> FrameIter<'a, T> {
+        FrameIter {
+            stream,
+            done: false,
+        }
+    }
+
+    fn advance<F>(&mut self, try_next_frame: F) -> Option<io::Result<Box<dyn Frame>>>
+    where
+        F: FnOnce(&mut T) -> io::Result<Option<Box<dyn Frame>>>,
+    {
+        if self.done {
+            return None;
+        }
+
+        let next = try_next_frame(self.stream).transpose();
+        self.done = !matches!(next, Some(Ok(_)));
+        next
+    }
+}
+
+impl<'a, S, FB> Iterator for FrameIter<'a, Plain<S, FB>>
+where
+    S: Read + Write,
+    FB: FrameBuilder,
+{
+    type Item = io::Result<Box<dyn Frame>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.advance(Plain::try_next_frame)
+    }
+}
+
+impl<'a, S, FB, T> Iterator for FrameIter<'a, Secure<S, FB, T>>
+where
+    FB: FrameBuilder,
+    T: TlsSession<Stream = S>,
+{
+    type Item = io::Result<Box<dyn Frame>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.advance(Secure::try_next_frame)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{SimpleFrame, SimpleFrameBuilder};
+    use crate::NonBlocking;
+
+    #[test]
+    fn iteration_ends_once_no_frame_has_arrived() {
+        let (mut local, mut remote) = Plain::<_, SimpleFrameBuilder>::pair();
+        remote.nb_send(&SimpleFrame::new(b"one")).unwrap();
+        remote.nb_send(&SimpleFrame::new(b"two")).unwrap();
+
+        let payloads: Vec<Vec<u8>> = local.frames().map(|f| f.unwrap().payload()).collect();
+        assert_eq!(payloads, [b"one".to_vec(), b"two".to_vec()]);
+        assert!(local.frames().next().is_none());
+
+        remote.nb_send(&SimpleFrame::new(b"three")).unwrap();
+        assert_eq!(local.frames().next().unwrap().unwrap().payload(), b"three");
+    }
+
+    #[test]
+    fn errors_are_yielded_once() {
+        let (mut local, remote) = Plain::<_, SimpleFrameBuilder>::pair();
+        drop(remote);
+
+        let mut frames = local.frames();
+        assert!(frames.next().unwrap().is_err());
+        assert!(frames.next().is_none());
+    }
+}
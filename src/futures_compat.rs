@@ -0,0 +1,280 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Async stream for any executor built on the `futures-io` traits, such as async-std or smol,
+//! enabled by the `futures-io` feature. It does not depend on Tokio; see `AsyncPlain` for
+//! that.
+//!
+//! ```ignore
+//! use simple_stream::frame::{SimpleFrame, SimpleFrameBuilder};
+//! use simple_stream::FuturesPlain;
+//! use smol::net::TcpStream;
+//!
+//! let stream = TcpStream::connect("127.0.0.1:9000").await?;
+//! let mut stream = FuturesPlain::<_, SimpleFrameBuilder>::new(stream);
+//!
+//! stream.send(&SimpleFrame::new(&[1, 2, 3, 4])).await?;
+//! let frame = stream.recv().await?;
+//! ```
+
+use std::future::poll_fn;
+use std::io::{self, Error, ErrorKind};
+use std::marker::PhantomData;
+use std::pin::Pin;
+
+use futures_io::{AsyncRead, AsyncWrite};
+
+use crate::buffer::RecvBuffer;
+use crate::close::CloseReason;
+use crate::frame::{reserve_frame, BuilderDecoder, Frame, FrameBuilder};
+use crate::resync::decode_unsynced;
+
+const BUF_SIZE: usize = 1024;
+
+/// Plain text stream over a `futures_io::AsyncRead + AsyncWrite` transport.
+pub struct FuturesPlain<S, FB>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    FB: FrameBuilder,
+{
+    inner: S,
+    rx_buf: RecvBuffer,
+    close_reason: Option<CloseReason>,
+    phantom: PhantomData<FB>,
+}
+
+impl<S, FB> FuturesPlain<S, FB>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+    FB: FrameBuilder,
+{
+    /// Creates a new plain text stream.
+    pub fn new(stream: S) -> FuturesPlain<S, FB> {
+        FuturesPlain {
+            inner: stream,
+            rx_buf: RecvBuffer::with_capacity(BUF_SIZE),
+            close_reason: None,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Returns why the connection terminated, or `None` while it is still open.
+    pub fn close_reason(&self) -> Option<&CloseReason> {
+        self.close_reason.as_ref()
+    }
+
+    /// Returns the underlying stream.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns the underlying stream mutably. Reading from or writing to it directly corrupts
+    /// the framing.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// Waits until a complete frame has been read, or an `std::io::Error` has occurred.
+    ///
+    /// Cancel safe: if the future is dropped before completing, no bytes are lost and the
+    /// next call picks up where this one left off.
+    pub async fn recv(&mut self) -> io::Result<Box<dyn Frame>> {
+        // Empty anything that is in our buffer already from any previous reads
+        if let Some(boxed_frame) = self.decode()? {
+            debug!("Complete frame read: {}", boxed_frame.fmt_summary());
+            return Ok(boxed_frame);
+        }
+
+        self.ensure_open()?;
+
+        loop {
+            let mut buf = [0u8; BUF_SIZE];
+            let result = poll_fn(|cx| Pin::new(&mut self.inner).poll_read(cx, &mut buf)).await;
+            let num_read = match result {
+                Ok(0) => return Err(self.close(CloseReason::PeerClosed)),
+                Ok(num_read) => num_read,
+                Err(e) => return Err(self.fail(e)),
+            };
+            trace!("Read {} byte(s)", num_read);
+            self.rx_buf.extend_from_slice(&buf[0..num_read]);
+            reserve_frame(&BuilderDecoder::of::<FB>(), &mut self.rx_buf);
+
+            if let Some(boxed_frame) = self.decode()? {
+                debug!("Complete frame read: {}", boxed_frame.fmt_summary());
+                return Ok(boxed_frame);
+            }
+        }
+    }
+
+    /// Waits until `frame` has been written and the underlying stream flushed, or an
+    /// `std::io::Error` has occurred.
+    ///
+    /// Not cancel safe: if the future is dropped before completing, part of the frame may
+    /// have been written, and the stream should be closed.
+    pub async fn send(&mut self, frame: &dyn Frame) -> io::Result<()> {
+        self.ensure_open()?;
+
+        let buf = frame.to_bytes();
+        let mut written = 0;
+        while written < buf.len() {
+            let result =
+                poll_fn(|cx| Pin::new(&mut self.inner).poll_write(cx, &buf[written..])).await;
+            match result {
+                Ok(0) => {
+                    let e = Error::new(ErrorKind::WriteZero, "Write returned zero");
+                    return Err(self.fail(e));
+                }
+                Ok(num_written) => written += num_written,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => {}
+                Err(e) => return Err(self.fail(e)),
+            }
+        }
+
+        if let Err(e) = poll_fn(|cx| Pin::new(&mut self.inner).poll_flush(cx)).await {
+            return Err(self.fail(e));
+        }
+
+        trace!("Wrote {} byte(s)", buf.len());
+        Ok(())
+    }
+
+    /// Closes the write half of the underlying stream. Every later call fails with
+    /// `CloseReason::LocalShutdown`.
+    pub async fn shutdown(&mut self) -> io::Result<()> {
+        if self.close_reason.is_some() {
+            return Ok(());
+        }
+
+        self.close_reason = Some(CloseReason::LocalShutdown);
+        poll_fn(|cx| Pin::new(&mut self.inner).poll_close(cx)).await
+    }
+
+    /// Decodes the next frame from `rx_buf`, closing the stream if it holds bytes that can
+    /// never become a frame.
+    fn decode(&mut self) -> io::Result<Option<Box<dyn Frame>>> {
+        decode_unsynced(&mut BuilderDecoder::of::<FB>(), &mut self.rx_buf).inspect_err(|_| {
+            self.close(CloseReason::ProtocolError);
+        })
+    }
+
+    fn ensure_open(&self) -> Result<(), Error> {
+        match self.close_reason {
+            Some(ref reason) => Err(reason.to_io_error()),
+            None => Ok(()),
+        }
+    }
+
+    fn close(&mut self, reason: CloseReason) -> Error {
+        debug!("Stream closed: {}", reason);
+        let err = reason.to_io_error();
+        self.close_reason = Some(reason);
+        err
+    }
+
+    /// Closes the stream if `e` terminated the connection, returning the error to report.
+    fn fail(&mut self, e: Error) -> Error {
+        match e.kind() {
+            ErrorKind::Interrupted => e,
+            _ => self.close(CloseReason::TransportError(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use std::task::{Context, Poll, Waker};
+
+    use crate::frame::{SimpleFrame, SimpleFrameBuilder};
+
+    /// In-memory transport: reads drain `rx`, writes append to `tx`.
+    #[derive(Default)]
+    struct Pipe {
+        rx: Vec<u8>,
+        tx: Vec<u8>,
+        closed: bool,
+    }
+
+    impl AsyncRead for Pipe {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<io::Result<usize>> {
+            let n = buf.len().min(self.rx.len());
+            buf[..n].copy_from_slice(&self.rx[..n]);
+            self.rx.drain(..n);
+            Poll::Ready(Ok(n))
+        }
+    }
+
+    impl AsyncWrite for Pipe {
+        fn poll_write(
+            mut self: Pin<&mut Self>,
+            _: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<io::Result<usize>> {
+            self.tx.extend_from_slice(buf);
+            Poll::Ready(Ok(buf.len()))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+            self.closed = true;
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    /// Polls `future` until it completes. Only for futures over in-memory transports, which
+    /// make progress on every poll.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = std::pin::pin!(future);
+        let mut cx = Context::from_waker(Waker::noop());
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    #[test]
+    fn sent_frames_are_received_until_the_peer_closes() {
+        let mut stream = FuturesPlain::<_, SimpleFrameBuilder>::new(Pipe::default());
+        block_on(stream.send(&SimpleFrame::new(b"one"))).unwrap();
+        block_on(stream.send(&SimpleFrame::new(b"two"))).unwrap();
+
+        let pipe = stream.get_mut();
+        pipe.rx = std::mem::take(&mut pipe.tx);
+        assert_eq!(block_on(stream.recv()).unwrap().payload(), b"one");
+        assert_eq!(block_on(stream.recv()).unwrap().payload(), b"two");
+
+        let e = block_on(stream.recv()).unwrap_err();
+        assert_eq!(e.kind(), ErrorKind::UnexpectedEof);
+        assert!(matches!(
+            stream.close_reason(),
+            Some(CloseReason::PeerClosed)
+        ));
+    }
+
+    #[test]
+    fn shutdown_closes_the_transport_and_fails_later_sends() {
+        let mut stream = FuturesPlain::<_, SimpleFrameBuilder>::new(Pipe::default());
+        block_on(stream.shutdown()).unwrap();
+        assert!(stream.get_ref().closed);
+
+        assert!(block_on(stream.send(&SimpleFrame::new(b"late"))).is_err());
+        assert!(stream.get_ref().tx.is_empty());
+        assert!(matches!(
+            stream.close_reason(),
+            Some(CloseReason::LocalShutdown)
+        ));
+    }
+}
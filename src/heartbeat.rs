@@ -0,0 +1,335 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Sends keepalive frames on a quiet connection, and closes it once the peer goes silent.
+//!
+//! ```ignore
+//! let config = HeartbeatConfig {
+//!     interval: Duration::from_secs(15),
+//!     timeout: Duration::from_secs(45),
+//! };
+//! let mut stream = Heartbeat::new(stream, config, SimpleKeepalive::default());
+//!
+//! // Pings go out, and received ones are dropped, while receiving
+//! let frame = stream.b_recv()?;
+//! ```
+//!
+//! Over websockets, put the session on top so it still answers the peer's pings:
+//!
+//! ```ignore
+//! let stream = Heartbeat::new(stream, config, WebSocketKeepalive::client());
+//! let mut session = WebSocketSession::new_client(stream);
+//! ```
+
+use std::io;
+use std::time::Instant;
+
+use crate::close::CloseReason;
+use crate::error::Error;
+use crate::frame::{Frame, FrameType, OpType, SimpleFrame, WebSocketFrame};
+use crate::liveness::{HeartbeatConfig, Liveness};
+use crate::{Blocking, NonBlocking};
+
+/// The frames a `Heartbeat` sends to keep a connection alive, and how it recognizes the
+/// ones it should not pass on.
+pub trait Keepalive {
+    /// Returns the frame sent once nothing else has been sent for the heartbeat interval.
+    fn ping(&self) -> Box<dyn Frame>;
+
+    /// Whether `frame`, as received, only served to keep the connection alive, in which case
+    /// it is dropped instead of returned.
+    fn is_keepalive(&self, frame: &dyn Frame) -> bool;
+}
+
+/// Keepalives for streams of `SimpleFrame`s, sent and recognized by their payload.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SimpleKeepalive {
+    payload: Vec<u8>,
+}
+
+/// Keepalives for streams of websocket frames. Sends pings, and drops the pongs they get
+/// back. Pings from the peer are passed on, for a `WebSocketSession` to answer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WebSocketKeepalive {
+    /// Whether pings are masked, as they must be by clients.
+    mask: bool,
+}
+
+/// Wraps a stream, sending a keepalive frame whenever nothing has been sent for the
+/// heartbeat interval, and closing it with `CloseReason::IdleTimeout` once nothing has been
+/// received for the heartbeat timeout.
+///
+/// Both are checked on every receive, send and flush. A blocking receive only gets to send
+/// pings while it waits if the stream has a receive timeout shorter than the interval, such
+/// as one set with `set_recv_timeout`.
+pub struct Heartbeat<S> {
+    stream: S,
+    keepalive: Box<dyn Keepalive>,
+    liveness: Liveness,
+    /// Whether the peer went silent for too long.
+    dead: bool,
+}
+
+impl SimpleKeepalive {
+    /// Keepalives carrying `payload`. The default carries none, so no frame the application
+    /// sends should have an empty payload.
+    pub fn new(payload: &[u8]) -> SimpleKeepalive {
+        SimpleKeepalive {
+            payload: payload.to_vec(),
+        }
+    }
+}
+
+impl Keepalive for SimpleKeepalive {
+    fn ping(&self) -> Box<dyn Frame> {
+        Box::new(SimpleFrame::new(&self.payload[..]))
+    }
+
+    fn is_keepalive(&self, frame: &dyn Frame) -> bool {
+        frame.downcast_ref::<SimpleFrame>().is_some() && frame.payload_ref()[..] == self.payload[..]
+    }
+}
+
+impl WebSocketKeepalive {
+    /// Keepalives for the server side of a connection.
+    pub fn server() -> WebSocketKeepalive {
+        WebSocketKeepalive { mask: false }
+    }
+
+    /// Keepalives for the client side of a connection, whose pings are masked.
+    pub fn client() -> WebSocketKeepalive {
+        WebSocketKeepalive { mask: true }
+    }
+}
+
+impl Keepalive for WebSocketKeepalive {
+    fn ping(&self) -> Box<dyn Frame> {
+        let frame = WebSocketFrame::new(&[], FrameType::Control, OpType::Ping);
+        match self.mask {
+            true => Box::new(frame.masked()),
+            false => Box::new(frame),
+        }
+    }
+
+    fn is_keepalive(&self, frame: &dyn Frame) -> bool {
+        frame
+            .downcast_ref::<WebSocketFrame>()
+            .is_some_and(|frame| matches!(frame.op_type(), OpType::Pong))
+    }
+}
+
+impl<S> Heartbeat<S>
+where
+    S: Blocking + NonBlocking,
+{
+    /// Wraps `stream`, keeping it alive with the frames of `keepalive` as `config` says.
+    pub fn new<K>(stream: S, config: HeartbeatConfig, keepalive: K) -> Heartbeat<S>
+    where
+        K: Keepalive + 'static,
+    {
+        let mut liveness = Liveness::new();
+        liveness.set_heartbeat(Some(config));
+        Heartbeat {
+            stream,
+            keepalive: Box::new(keepalive),
+            liveness,
+            dead: false,
+        }
+    }
+
+    /// Sends a keepalive if one is due, and fails with `CloseReason::IdleTimeout` if the peer
+    /// has been silent for too long. Call it on a timer when the stream may go unused for
+    /// longer than the heartbeat interval.
+    pub fn tick(&mut self) -> Result<(), Error> {
+        if self.dead || self.liveness.is_idle() {
+            self.dead = true;
+            return Err(CloseReason::IdleTimeout.to_io_error().into());
+        }
+        if !self.liveness.heartbeat_due() {
+            return Ok(());
+        }
+
+        trace!("Sending keepalive");
+        let ping = self.keepalive.ping();
+        match self.stream.nb_send(&*ping) {
+            Ok(()) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+        self.liveness.sent();
+        Ok(())
+    }
+
+    /// Whether the peer was silent for longer than the heartbeat timeout.
+    pub fn is_dead(&self) -> bool {
+        self.dead
+    }
+
+    /// Returns a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns a mutable reference to the wrapped stream. Frames sent or received through it
+    /// are not seen by the heartbeat.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> Blocking for Heartbeat<S>
+where
+    S: Blocking + NonBlocking,
+{
+    fn b_recv(&mut self) -> Result<Box<dyn Frame>, Error> {
+        loop {
+            self.tick()?;
+            let frame = match self.stream.b_recv() {
+                Ok(frame) => frame,
+                Err(ref e) if is_recv_timeout(e) => continue,
+                Err(e) => return Err(e),
+            };
+
+            self.liveness.received();
+            if !self.keepalive.is_keepalive(&*frame) {
+                return Ok(frame);
+            }
+            trace!("Received keepalive");
+        }
+    }
+
+    fn b_send(&mut self, frame: &dyn Frame) -> Result<(), Error> {
+        self.tick()?;
+        self.stream.b_send(frame)?;
+        self.liveness.sent();
+        Ok(())
+    }
+}
+
+impl<S> NonBlocking for Heartbeat<S>
+where
+    S: Blocking + NonBlocking,
+{
+    fn nb_recv(&mut self) -> Result<Vec<Box<dyn Frame>>, Error> {
+        self.tick()?;
+        let mut frames = self.stream.nb_recv()?;
+        self.liveness.received();
+        frames.retain(|frame| !self.keepalive.is_keepalive(&**frame));
+
+        if frames.is_empty() {
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        Ok(frames)
+    }
+
+    fn nb_send(&mut self, frame: &dyn Frame) -> Result<(), Error> {
+        self.tick()?;
+        let result = self.stream.nb_send(frame);
+        match result {
+            Ok(()) => self.liveness.sent(),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => self.liveness.sent(),
+            Err(_) => {}
+        }
+        result
+    }
+
+    fn nb_recv_retry_at(&self) -> Option<Instant> {
+        self.stream.nb_recv_retry_at()
+    }
+
+    fn nb_flush(&mut self) -> Result<bool, Error> {
+        self.tick()?;
+        self.stream.nb_flush()
+    }
+}
+
+/// Whether `e` is a blocking receive giving up after the stream's receive timeout, after
+/// which it can be retried.
+#[cfg(unix)]
+fn is_recv_timeout(e: &Error) -> bool {
+    match *e {
+        Error::Io(ref e) => e
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<crate::IoTimeout>())
+            .is_some_and(|e| matches!(e, crate::IoTimeout::Recv(_))),
+        _ => false,
+    }
+}
+
+#[cfg(not(unix))]
+fn is_recv_timeout(_: &Error) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+
+    use crate::frame::SimpleFrameBuilder;
+    use crate::Plain;
+
+    fn config(interval_ms: u64, timeout_ms: u64) -> HeartbeatConfig {
+        HeartbeatConfig {
+            interval: Duration::from_millis(interval_ms),
+            timeout: Duration::from_millis(timeout_ms),
+        }
+    }
+
+    #[test]
+    fn quiet_streams_send_keepalives_the_peer_drops() {
+        let (local, remote) = Plain::<_, SimpleFrameBuilder>::pair();
+        let keepalive = SimpleKeepalive::new(b"ka");
+        let mut local = Heartbeat::new(local, config(20, 60_000), keepalive.clone());
+        let mut remote = Heartbeat::new(remote, config(60_000, 60_000), keepalive);
+
+        local.tick().unwrap();
+        thread::sleep(Duration::from_millis(30));
+        local.tick().unwrap();
+        let frames = remote.get_mut().nb_recv().unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].payload(), b"ka");
+
+        local.nb_send(&SimpleFrame::new(b"ka")).unwrap();
+        local.nb_send(&SimpleFrame::new(b"data")).unwrap();
+        let frames = remote.nb_recv().unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].payload(), b"data");
+    }
+
+    #[test]
+    fn silent_peers_fail_the_stream() {
+        let (local, _remote) = Plain::<_, SimpleFrameBuilder>::pair();
+        let mut local = Heartbeat::new(local, config(60_000, 20), SimpleKeepalive::default());
+        assert!(local.tick().is_ok());
+
+        thread::sleep(Duration::from_millis(30));
+        let e = local.nb_send(&SimpleFrame::new(b"late")).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+        assert!(local.is_dead());
+        assert!(local.tick().is_err());
+    }
+
+    #[test]
+    fn websocket_pongs_are_keepalives() {
+        let client = WebSocketKeepalive::client();
+        let ping = client.ping();
+        let ping = ping.downcast_ref::<WebSocketFrame>().unwrap();
+        assert_eq!(ping.op_type(), OpType::Ping);
+        assert!(ping.is_masked());
+        assert!(!client.is_keepalive(ping));
+
+        let pong = WebSocketFrame::new(&[], FrameType::Control, OpType::Pong);
+        assert!(client.is_keepalive(&pong));
+    }
+}
@@ -53,6 +53,30 @@
 //! }
 //! ```
 //!
+//! ## Mixing blocking and non-blocking calls
+//!
+//! `Plain` and `Secure` share one receive buffer and one send queue between their `Blocking`
+//! and `NonBlocking` methods, so a connection may switch between them, e.g. blocking during a
+//! handshake and non-blocking afterwards:
+//!
+//! * Frames left in the receive buffer by either kind of read are returned by the next read
+//!   of either kind, before more bytes are read.
+//! * Bytes `nb_send` could not write yet are written by the next `b_send` ahead of its own
+//!   frame, so frames are never interleaved on the wire.
+//! * If `b_send` is called on a non-blocking socket and the write would block, the unwritten
+//!   rest of the frame stays queued and goes out ahead of the next send.
+//!
+//! Switching the blocking mode of the underlying socket is up to the caller.
+//!
+//! ## Platforms
+//!
+//! `Plain`, `Secure`, `Socket` and `DualStream` work on unix and Windows. Socket options,
+//! cancellation tokens, blocking timeouts, `connect_any`, `close_graceful` and ICMP error
+//! reporting are built on unix APIs and are only available there.
+//!
+//! `Reactor` is backed by epoll on Linux and Android, and by kqueue on macOS, iOS and the
+//! BSDs.
+//!
 //!
 //! [rust-openssl-repo]: https://github.com/sfackler/rust-openssl
 
@@ -63,48 +87,239 @@ extern crate libc;
 extern crate log;
 #[cfg(feature = "openssl")]
 extern crate openssl;
+#[cfg(feature = "futures-io")]
+extern crate futures_io;
+#[cfg(feature = "mio")]
+extern crate mio;
+#[cfg(feature = "tokio")]
+extern crate tokio;
+#[cfg(feature = "tokio")]
+extern crate tokio_openssl;
+#[cfg(feature = "tracing")]
+extern crate tracing;
 
+#[cfg(feature = "tokio")]
+mod async_io;
+pub mod buffer;
+#[cfg(unix)]
+mod cancel;
+mod capture;
+mod chunking;
+mod close;
+#[cfg(unix)]
+mod connect;
+mod datagram;
+mod deadline;
+mod dual;
+mod duplex;
+mod encode_cache;
+#[cfg(unix)]
+mod errqueue;
+mod error;
+mod faulty;
+mod frame_iter;
+mod heartbeat;
+#[cfg(feature = "ffi")]
+pub mod ffi;
 pub mod frame;
+#[cfg(feature = "futures-io")]
+mod futures_compat;
+mod listener;
+mod liveness;
+#[cfg(all(unix, feature = "mio"))]
+mod mio_compat;
+#[cfg(feature = "flate2")]
+mod permessage_deflate;
 mod plain;
-#[cfg(feature = "openssl")]
+mod preamble;
+mod protocol;
+mod ratelimit;
+mod resync;
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+mod reactor;
+#[cfg(feature = "registry")]
+mod registry;
+#[cfg(feature = "echo")]
+mod rtt;
+mod scheduler;
 mod secure;
+mod socket;
+#[cfg(unix)]
+mod sockopt;
+mod spans;
+mod stats;
+pub mod testing;
+#[cfg(unix)]
+mod timeout;
+mod tls;
+mod transform;
+mod watermark;
+mod websocket_session;
+mod wirelog;
 
 use std::io;
+use std::time::Instant;
 
 use frame::Frame;
 
+#[cfg(feature = "tokio")]
+pub use async_io::*;
+#[cfg(unix)]
+pub use cancel::CancellationToken;
+pub use capture::*;
+pub use chunking::*;
+pub use close::*;
+#[cfg(unix)]
+pub use connect::*;
+pub use datagram::Datagram;
+pub use deadline::StalledFrame;
+pub use dual::*;
+pub use duplex::*;
+pub use encode_cache::EncodeCache;
+#[cfg(unix)]
+pub use errqueue::*;
+pub use error::Error;
+pub use faulty::*;
+pub use frame_iter::FrameIter;
+pub use heartbeat::*;
+#[cfg(feature = "futures-io")]
+pub use futures_compat::*;
+pub use listener::*;
+pub use liveness::HeartbeatConfig;
+#[cfg(all(unix, feature = "mio"))]
+pub use mio_compat::Readiness;
+#[cfg(feature = "flate2")]
+pub use permessage_deflate::*;
 pub use plain::*;
-#[cfg(feature = "openssl")]
+pub use preamble::*;
+pub use protocol::Protocol;
+pub use ratelimit::{FrameRateLimit, RateExceeded, RateLimitAction};
+pub use resync::{DecodePolicy, Desync};
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "netbsd",
+    target_os = "openbsd",
+    target_os = "dragonfly"
+))]
+pub use reactor::*;
+#[cfg(feature = "registry")]
+pub use registry::{active_streams, StreamId, StreamSnapshot};
+#[cfg(feature = "echo")]
+pub use rtt::*;
+pub use scheduler::*;
 pub use secure::*;
+pub use socket::*;
+#[cfg(unix)]
+pub use sockopt::*;
+pub use stats::{LatencyHistogram, SendProgress};
+#[cfg(unix)]
+pub use timeout::IoTimeout;
+pub use tls::*;
+pub use transform::PayloadTransform;
+pub use watermark::{TxQueueFull, Watermark, Watermarks};
+pub use websocket_session::*;
+pub use wirelog::*;
 
 /// The `Blocking` trait provides method definitions for use with blocking streams.
+///
+/// Once the connection terminates, every call fails with an `Error` describing how: `Error::Eof`
+/// for an orderly close, `Error::ProtocolViolation` for a protocol error, and otherwise
+/// `Error::Io` wrapping the `CloseReason` the stream was closed with.
 pub trait Blocking {
     /// Performs a blocking read on the underlying stream until a complete Frame has been read
-    /// or an `std::io::Error` has occurred.
-    fn b_recv(&mut self) -> io::Result<Box<dyn Frame>>;
+    /// or an `Error` has occurred.
+    fn b_recv(&mut self) -> Result<Box<dyn Frame>, Error>;
     /// Performs a blocking send on the underlying stream until a complete frame has been sent
-    /// or an `std::io::Error` has occurred.
-    fn b_send(&mut self, frame: &dyn Frame) -> io::Result<()>;
+    /// or an `Error` has occurred. Partial writes are retried until every byte of the frame has
+    /// been written, and the underlying stream is flushed afterwards.
+    fn b_send(&mut self, frame: &dyn Frame) -> Result<(), Error>;
+    /// Same as `b_send`, for every frame in `frames` in order. `Plain` and `Secure` write the
+    /// whole batch with as few writes as they can, instead of at least one per frame.
+    ///
+    /// The default sends the frames one at a time, stopping at the first error.
+    fn b_send_all(&mut self, frames: &[&dyn Frame]) -> Result<(), Error> {
+        for frame in frames {
+            self.b_send(*frame)?;
+        }
+        Ok(())
+    }
 }
 
 /// The `NonBlocking` trait provides method definitions for use with non-blocking streams.
+///
+/// Once the connection terminates, every call fails with an `Error` describing how, the same as
+/// `Blocking`. Frames completed by the final read are still returned first.
 pub trait NonBlocking {
     /// Performs a non-blocking read on the underlying stream until `ErrorKind::WouldBlock` or an
-    /// `std::io::Error` has occurred.
+    /// `Error` has occurred.
     ///
     /// # `simple_stream::Secure` notes
     ///
-    /// Unlike its blocking counterpart, errors received on the OpenSSL level will be returned as
-    /// `ErrorKind::Other` with various OpenSSL error information as strings in the description
-    /// field of the `std::io::Error`.
-    fn nb_recv(&mut self) -> io::Result<Vec<Box<dyn Frame>>>;
+    /// OpenSSL errors that do not terminate the session, such as `WantWrite` during a read, are
+    /// returned as `Error::Io` of `ErrorKind::Other` wrapping the `TlsError`.
+    fn nb_recv(&mut self) -> Result<Vec<Box<dyn Frame>>, Error>;
+    /// Same as `nb_recv`, except decoded frames are appended to `frames`, so a caller polling
+    /// in a loop can reuse one `Vec` instead of receiving a new one on every call. Returns the
+    /// number of frames appended.
+    fn nb_recv_into(&mut self, frames: &mut Vec<Box<dyn Frame>>) -> Result<usize, Error> {
+        let mut received = self.nb_recv()?;
+        let num_frames = received.len();
+        frames.append(&mut received);
+        Ok(num_frames)
+    }
+    /// Returns when `nb_recv` should be called again even if the underlying stream reports no
+    /// readiness, because receives are being held back by a `RateLimitAction::Delay` limit
+    /// with bytes possibly left unread. Event loops using edge-triggered readiness are not
+    /// told about those bytes again. Returns `None` if nothing is being held back.
+    ///
+    /// The default returns `None`, for implementations that never hold receives back.
+    fn nb_recv_retry_at(&self) -> Option<Instant> {
+        None
+    }
     /// Performs a non-blocking send on the underlying stream until `ErrorKind::WouldBlock` or an
-    /// `std::io::Error` has occurred.
+    /// `Error` has occurred.
     ///
     /// # `simple_stream::Secure` notes
     ///
-    /// Unlike its blocking counterpart, errors received on the OpenSSL level will be returned as
-    /// `ErrorKind::Other` with various OpenSSL error information as strings in the description
-    /// field of the `std::io::Error`.
-    fn nb_send(&mut self, frame: &dyn Frame) -> io::Result<()>;
+    /// OpenSSL errors that do not terminate the session, such as `WantWrite` during a read, are
+    /// returned as `Error::Io` of `ErrorKind::Other` wrapping the `TlsError`.
+    fn nb_send(&mut self, frame: &dyn Frame) -> Result<(), Error>;
+    /// Same as `nb_send`, for every frame in `frames` in order. `Plain` and `Secure` queue
+    /// the whole batch before writing it with as few writes as they can, instead of at least
+    /// one per frame. Returns `ErrorKind::WouldBlock` if anything is left queued.
+    ///
+    /// The default sends the frames one at a time, stopping at the first error other than
+    /// `ErrorKind::WouldBlock`.
+    fn nb_send_all(&mut self, frames: &[&dyn Frame]) -> Result<(), Error> {
+        let mut result = Ok(());
+        for frame in frames {
+            result = self.nb_send(*frame);
+            match result {
+                Err(ref e) if e.kind() != io::ErrorKind::WouldBlock => return result,
+                _ => {}
+            }
+        }
+        result
+    }
+    /// Writes as much of what `nb_send` left queued as possible without blocking, for event
+    /// loops to call once the underlying stream is writable again. Returns `true` once nothing
+    /// is left queued.
+    ///
+    /// The default returns `true`, for implementations that never leave bytes queued.
+    fn nb_flush(&mut self) -> Result<bool, Error> {
+        Ok(true)
+    }
 }
@@ -0,0 +1,295 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::io;
+use std::marker::PhantomData;
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+#[cfg(feature = "openssl")]
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[cfg(feature = "openssl")]
+use openssl::ssl::{SslAcceptor, SslAcceptorBuilder, SslFiletype, SslVerifyMode};
+#[cfg(feature = "openssl")]
+use openssl::x509::store::{X509Lookup, X509StoreBuilder};
+#[cfg(feature = "openssl")]
+use openssl::x509::verify::X509VerifyFlags;
+#[cfg(feature = "openssl")]
+use openssl::x509::{X509Ref, X509};
+
+use crate::frame::FrameBuilder;
+#[cfg(unix)]
+use crate::sockopt::TcpOptions;
+use crate::Plain;
+#[cfg(feature = "openssl")]
+use crate::{Error, Secure};
+
+/// Settings applied to every connection a listener accepts, before it is returned.
+#[derive(Clone, Debug, Default)]
+pub struct ConnectionTemplate {
+    /// Sets `TCP_NODELAY`.
+    pub nodelay: bool,
+    /// Puts the socket in non-blocking mode, for streams driven through `NonBlocking`. TLS
+    /// handshakes still complete in blocking mode first.
+    pub nonblocking: bool,
+    /// Read timeout of the socket.
+    pub read_timeout: Option<Duration>,
+    /// Write timeout of the socket.
+    pub write_timeout: Option<Duration>,
+    /// Passed to `set_tcp_options`.
+    #[cfg(unix)]
+    pub tcp: Option<TcpOptions>,
+    /// Passed to `set_max_frame_len`.
+    pub max_frame_len: Option<usize>,
+    /// Passed to `set_idle_timeout`.
+    pub idle_timeout: Option<Duration>,
+}
+
+/// TCP listener that yields `Plain` streams, set up from a `ConnectionTemplate`.
+pub struct FramedListener<FB: FrameBuilder> {
+    listener: TcpListener,
+    template: ConnectionTemplate,
+    phantom: PhantomData<FB>,
+}
+
+/// Certificate authorities client certificates are verified against.
+#[cfg(feature = "openssl")]
+#[derive(Clone)]
+pub struct ClientCa {
+    /// Trusted CA certificates.
+    pub certs: Vec<X509>,
+    /// Optional PEM file of certificate revocation lists. When set, client certificates are
+    /// checked for revocation.
+    pub crl_file: Option<PathBuf>,
+}
+
+/// Client certificate requirements applied to every connection accepted by a listener.
+#[cfg(feature = "openssl")]
+#[derive(Clone)]
+pub enum ClientAuth {
+    /// Clients are not asked for a certificate.
+    NoClientAuth,
+    /// Clients are asked for a certificate, which is verified if presented.
+    OptionalClientAuth(ClientCa),
+    /// Clients must present a certificate that verifies against the CA set.
+    RequiredClientAuth(ClientCa),
+}
+
+/// Callback invoked with the verified client certificate, if any, and the peer address after
+/// the handshake completes. Returning an error rejects the connection.
+#[cfg(feature = "openssl")]
+pub type IdentityCallback = dyn Fn(Option<&X509Ref>, &SocketAddr) -> io::Result<()> + Send + Sync;
+
+/// TCP listener that yields `Secure` streams, enforcing a `ClientAuth` policy.
+#[cfg(feature = "openssl")]
+pub struct SecureListener<FB: FrameBuilder> {
+    listener: TcpListener,
+    acceptor: SslAcceptor,
+    on_identity: Option<Box<IdentityCallback>>,
+    template: ConnectionTemplate,
+    phantom: PhantomData<FB>,
+}
+
+impl ConnectionTemplate {
+    /// Applies the socket options to `socket`, leaving its blocking mode alone.
+    fn configure(&self, socket: &TcpStream) -> io::Result<()> {
+        socket.set_nodelay(self.nodelay)?;
+        socket.set_read_timeout(self.read_timeout)?;
+        socket.set_write_timeout(self.write_timeout)?;
+        Ok(())
+    }
+}
+
+impl<FB: FrameBuilder> FramedListener<FB> {
+    /// Creates a `FramedListener` accepting connections from `listener`, each set up from
+    /// `template`.
+    pub fn new(listener: TcpListener, template: ConnectionTemplate) -> FramedListener<FB> {
+        FramedListener {
+            listener,
+            template,
+            phantom: PhantomData,
+        }
+    }
+
+    /// Binds a new listener to `addr`.
+    pub fn bind<A: ToSocketAddrs>(
+        addr: A,
+        template: ConnectionTemplate,
+    ) -> io::Result<FramedListener<FB>> {
+        Ok(FramedListener::new(TcpListener::bind(addr)?, template))
+    }
+
+    /// Accepts a connection, blocking until one arrives unless the listener is non-blocking.
+    pub fn accept(&self) -> io::Result<(Plain<TcpStream, FB>, SocketAddr)> {
+        let (socket, addr) = self.listener.accept()?;
+        self.template.configure(&socket)?;
+        socket.set_nonblocking(self.template.nonblocking)?;
+
+        let mut stream = Plain::new(socket);
+        #[cfg(unix)]
+        if let Some(options) = self.template.tcp {
+            stream.set_tcp_options(options);
+        }
+        stream.set_max_frame_len(self.template.max_frame_len);
+        stream.set_idle_timeout(self.template.idle_timeout);
+
+        trace!("Accepted {}", addr);
+        Ok((stream, addr))
+    }
+
+    /// Returns the local address this listener is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Returns a reference to the underlying listener, e.g. to make it non-blocking.
+    pub fn get_ref(&self) -> &TcpListener {
+        &self.listener
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl ClientAuth {
+    /// Configures `builder` to request and verify client certificates per this policy.
+    pub fn apply(&self, builder: &mut SslAcceptorBuilder) -> io::Result<()> {
+        let (ca, mode) = match *self {
+            ClientAuth::NoClientAuth => {
+                builder.set_verify(SslVerifyMode::NONE);
+                return Ok(());
+            }
+            ClientAuth::OptionalClientAuth(ref ca) => (ca, SslVerifyMode::PEER),
+            ClientAuth::RequiredClientAuth(ref ca) => (
+                ca,
+                SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT,
+            ),
+        };
+
+        let mut store = X509StoreBuilder::new()?;
+        for cert in ca.certs.iter() {
+            builder.add_client_ca(cert)?;
+            store.add_cert(cert.clone())?;
+        }
+
+        if let Some(ref crl_file) = ca.crl_file {
+            let lookup = store.add_lookup(X509Lookup::file())?;
+            lookup.load_crl_file(crl_file, SslFiletype::PEM)?;
+            store.set_flags(X509VerifyFlags::CRL_CHECK | X509VerifyFlags::CRL_CHECK_ALL)?;
+        }
+
+        builder.set_cert_store(store.build());
+        builder.set_verify(mode);
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl<FB: FrameBuilder> SecureListener<FB> {
+    /// Creates a new `SecureListener` accepting connections from `listener`, using the TLS
+    /// configuration in `builder` with the client certificate policy `auth` applied.
+    pub fn new(
+        listener: TcpListener,
+        mut builder: SslAcceptorBuilder,
+        auth: ClientAuth,
+    ) -> io::Result<SecureListener<FB>> {
+        auth.apply(&mut builder)?;
+
+        Ok(SecureListener::with_acceptor(listener, builder.build()))
+    }
+
+    /// Creates a new `SecureListener` accepting connections from `listener` with `acceptor`,
+    /// which is used as configured.
+    pub fn with_acceptor(listener: TcpListener, acceptor: SslAcceptor) -> SecureListener<FB> {
+        SecureListener {
+            listener,
+            acceptor,
+            on_identity: None,
+            template: ConnectionTemplate::default(),
+            phantom: PhantomData,
+        }
+    }
+
+    /// Sets up every connection accepted from now on from `template`.
+    pub fn set_template(&mut self, template: ConnectionTemplate) {
+        self.template = template;
+    }
+
+    /// Sets a callback that receives the verified client identity of each connection before
+    /// it is returned from `accept`.
+    pub fn set_identity_callback<F>(&mut self, callback: F)
+    where
+        F: Fn(Option<&X509Ref>, &SocketAddr) -> io::Result<()> + Send + Sync + 'static,
+    {
+        self.on_identity = Some(Box::new(callback));
+    }
+
+    /// Accepts a connection and performs the TLS handshake, blocking until both complete.
+    pub fn accept(&self) -> io::Result<(Secure<TcpStream, FB>, SocketAddr)> {
+        let (stream, addr) = self.listener.accept()?;
+        self.template.configure(&stream)?;
+        stream.set_nonblocking(false)?;
+        let stream = self.acceptor.accept(stream).map_err(|e| {
+            error!("TLS handshake with {} failed: {}", addr, e);
+            io::Error::from(Error::Tls(e.to_string()))
+        })?;
+
+        if let Some(ref on_identity) = self.on_identity {
+            let peer_cert = stream.ssl().peer_certificate();
+            on_identity(peer_cert.as_deref(), &addr)?;
+        }
+
+        stream
+            .get_ref()
+            .set_nonblocking(self.template.nonblocking)?;
+        let mut stream = Secure::new(stream);
+        #[cfg(unix)]
+        if let Some(options) = self.template.tcp {
+            stream.set_tcp_options(options);
+        }
+        stream.set_max_frame_len(self.template.max_frame_len);
+        stream.set_idle_timeout(self.template.idle_timeout);
+
+        Ok((stream, addr))
+    }
+
+    /// Returns the local address this listener is bound to.
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{SimpleFrame, SimpleFrameBuilder};
+    use crate::{Blocking, NonBlocking};
+
+    #[test]
+    fn accepted_streams_are_set_up_from_the_template() {
+        let template = ConnectionTemplate {
+            nodelay: true,
+            nonblocking: true,
+            read_timeout: Some(Duration::from_secs(5)),
+            ..ConnectionTemplate::default()
+        };
+        let listener = FramedListener::<SimpleFrameBuilder>::bind("127.0.0.1:0", template).unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (mut stream, addr) = listener.accept().unwrap();
+        assert_eq!(addr, client.local_addr().unwrap());
+
+        let socket = stream.get_ref();
+        assert!(socket.nodelay().unwrap());
+        assert_eq!(socket.read_timeout().unwrap(), Some(Duration::from_secs(5)));
+        let e = stream.nb_recv().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+
+        let mut client = Plain::<_, SimpleFrameBuilder>::new(client);
+        client.b_send(&SimpleFrame::new(b"hello")).unwrap();
+        stream.get_ref().set_nonblocking(false).unwrap();
+        assert_eq!(stream.b_recv().unwrap().payload(), b"hello");
+    }
+}
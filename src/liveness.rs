@@ -0,0 +1,134 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Idle timeouts and heartbeats, which tell a live but quiet connection from a dead one.
+//!
+//! ```ignore
+//! stream.set_heartbeat(Some(HeartbeatConfig {
+//!     interval: Duration::from_secs(15),
+//!     timeout: Duration::from_secs(45),
+//! }));
+//!
+//! // On a timer
+//! if stream.heartbeat_due() {
+//!     stream.nb_send(&ping)?;
+//! }
+//! ```
+
+use std::time::{Duration, Instant};
+
+/// How often a connection should carry traffic, and how long the peer may stay silent before
+/// the connection is considered dead.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct HeartbeatConfig {
+    /// A heartbeat is due once nothing has been sent for this long.
+    pub interval: Duration,
+    /// Streams close with `CloseReason::IdleTimeout` once nothing has been received for
+    /// this long.
+    pub timeout: Duration,
+}
+
+/// Tracks when a stream last received and sent bytes.
+#[derive(Clone, Debug)]
+pub(crate) struct Liveness {
+    idle_timeout: Option<Duration>,
+    heartbeat: Option<HeartbeatConfig>,
+    last_rx: Instant,
+    last_tx: Instant,
+}
+
+impl Liveness {
+    pub(crate) fn new() -> Liveness {
+        let now = Instant::now();
+        Liveness {
+            idle_timeout: None,
+            heartbeat: None,
+            last_rx: now,
+            last_tx: now,
+        }
+    }
+
+    pub(crate) fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.idle_timeout = timeout;
+    }
+
+    pub(crate) fn set_heartbeat(&mut self, heartbeat: Option<HeartbeatConfig>) {
+        self.heartbeat = heartbeat;
+    }
+
+    pub(crate) fn received(&mut self) {
+        self.last_rx = Instant::now();
+    }
+
+    pub(crate) fn sent(&mut self) {
+        self.last_tx = Instant::now();
+    }
+
+    /// Whether nothing has been received for longer than the idle timeout, or the heartbeat
+    /// timeout if that is shorter.
+    pub(crate) fn is_idle(&self) -> bool {
+        let limit = match (self.idle_timeout, self.heartbeat.map(|h| h.timeout)) {
+            (Some(a), Some(b)) => a.min(b),
+            (Some(limit), None) | (None, Some(limit)) => limit,
+            (None, None) => return false,
+        };
+
+        let silent = self.last_rx.elapsed();
+        if silent <= limit {
+            return false;
+        }
+
+        debug!("Nothing received for {:?}", silent);
+        true
+    }
+
+    /// Whether nothing has been sent for the heartbeat interval.
+    pub(crate) fn heartbeat_due(&self) -> bool {
+        self.heartbeat
+            .is_some_and(|h| self.last_tx.elapsed() >= h.interval)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    fn the_shorter_timeout_makes_a_stream_idle() {
+        let mut liveness = Liveness::new();
+        thread::sleep(Duration::from_millis(30));
+        assert!(!liveness.is_idle());
+
+        liveness.set_idle_timeout(Some(Duration::from_secs(60)));
+        liveness.set_heartbeat(Some(HeartbeatConfig {
+            interval: Duration::from_secs(60),
+            timeout: Duration::from_millis(20),
+        }));
+        assert!(liveness.is_idle());
+
+        liveness.received();
+        assert!(!liveness.is_idle());
+    }
+
+    #[test]
+    fn heartbeats_are_due_after_a_quiet_interval() {
+        let mut liveness = Liveness::new();
+        assert!(!liveness.heartbeat_due());
+
+        liveness.set_heartbeat(Some(HeartbeatConfig {
+            interval: Duration::from_millis(20),
+            timeout: Duration::from_secs(60),
+        }));
+        assert!(!liveness.heartbeat_due());
+        thread::sleep(Duration::from_millis(30));
+        assert!(liveness.heartbeat_due());
+
+        liveness.sent();
+        assert!(!liveness.heartbeat_due());
+    }
+}
@@ -0,0 +1,167 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! mio integration, enabled by the `mio` feature. `Plain` and `Secure` over sockets are mio
+//! `Source`s, registered through the descriptor of the underlying socket, and `Readiness`
+//! turns the events mio reports into frames received and writes flushed.
+//!
+//! ```ignore
+//! let mut stream = Plain::<TcpStream, SimpleFrameBuilder>::new(socket);
+//! poll.registry().register(&mut stream, CLIENT, Interest::READABLE)?;
+//!
+//! for event in events.iter() {
+//!     if event.is_readable() {
+//!         for frame in stream.handle_readable()? {
+//!             stream.nb_send(&*frame).or_else(ignore_would_block)?;
+//!         }
+//!     }
+//!     if event.is_writable() || !stream.pending_frames().is_empty() {
+//!         let interest = stream.handle_writable()?;
+//!         poll.registry().reregister(&mut stream, CLIENT, interest)?;
+//!     }
+//! }
+//! ```
+//!
+//! Sockets have to be in non-blocking mode. As mio notifies edge triggered, every event is
+//! handled until the socket would block, which both methods do.
+
+use std::io::{self, Read, Write};
+use std::os::unix::io::AsRawFd;
+
+use mio::event::Source;
+use mio::unix::SourceFd;
+use mio::{Interest, Registry, Token};
+
+use crate::frame::{Frame, FrameBuilder};
+use crate::tls::TlsSession;
+use crate::{Error, NonBlocking, Plain, Secure};
+
+/// Handles mio readiness events for a non-blocking stream.
+pub trait Readiness: NonBlocking {
+    /// Receives every complete frame the socket holds, reading until it would block. Call on
+    /// readable events. Returns no frames if none completed.
+    ///
+    /// Frames completed before the connection terminated are returned first, and the next
+    /// call fails with the reason.
+    fn handle_readable(&mut self) -> Result<Vec<Box<dyn Frame>>, Error> {
+        match self.nb_recv() {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(Vec::new()),
+            result => result,
+        }
+    }
+
+    /// Writes queued frames until everything is written or the socket would block. Call on
+    /// writable events, and after queueing frames. Returns the interest to register the
+    /// stream with: writable only while frames remain queued.
+    fn handle_writable(&mut self) -> Result<Interest, Error> {
+        match self.nb_flush()? {
+            true => Ok(Interest::READABLE),
+            false => Ok(Interest::READABLE | Interest::WRITABLE),
+        }
+    }
+}
+
+impl<T: NonBlocking + ?Sized> Readiness for T {}
+
+impl<S, FB> Source for Plain<S, FB>
+where
+    S: Read + Write + AsRawFd,
+    FB: FrameBuilder,
+{
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interest: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).register(registry, token, interest)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interest: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).reregister(registry, token, interest)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).deregister(registry)
+    }
+}
+
+impl<S, FB, T> Source for Secure<S, FB, T>
+where
+    S: AsRawFd,
+    FB: FrameBuilder,
+    T: TlsSession<Stream = S>,
+{
+    fn register(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interest: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).register(registry, token, interest)
+    }
+
+    fn reregister(
+        &mut self,
+        registry: &Registry,
+        token: Token,
+        interest: Interest,
+    ) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).reregister(registry, token, interest)
+    }
+
+    fn deregister(&mut self, registry: &Registry) -> io::Result<()> {
+        SourceFd(&self.as_raw_fd()).deregister(registry)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::net::UnixStream;
+    use std::time::Duration;
+
+    use mio::{Events, Poll};
+
+    use crate::frame::{SimpleFrame, SimpleFrameBuilder};
+
+    #[test]
+    fn readable_events_deliver_every_frame() {
+        let (a, b) = UnixStream::pair().unwrap();
+        a.set_nonblocking(true).unwrap();
+        b.set_nonblocking(true).unwrap();
+        let mut local = Plain::<_, SimpleFrameBuilder>::new(a);
+        let mut remote = Plain::<_, SimpleFrameBuilder>::new(b);
+
+        let mut poll = Poll::new().unwrap();
+        let mut events = Events::with_capacity(4);
+        poll.registry()
+            .register(&mut local, Token(1), Interest::READABLE)
+            .unwrap();
+        assert!(local.handle_readable().unwrap().is_empty());
+
+        remote.nb_send(&SimpleFrame::new(b"one")).unwrap();
+        remote.nb_send(&SimpleFrame::new(b"two")).unwrap();
+        poll.poll(&mut events, Some(Duration::from_secs(5)))
+            .unwrap();
+        let event = events.iter().next().unwrap();
+        assert_eq!(event.token(), Token(1));
+        assert!(event.is_readable());
+
+        let frames = local.handle_readable().unwrap();
+        let payloads: Vec<Vec<u8>> = frames.iter().map(|f| f.payload()).collect();
+        assert_eq!(payloads, [b"one".to_vec(), b"two".to_vec()]);
+        assert_eq!(local.handle_writable().unwrap(), Interest::READABLE);
+
+        poll.registry().deregister(&mut local).unwrap();
+    }
+}
@@ -0,0 +1,362 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! The permessage-deflate websocket extension, as defined by RFC 7692. Negotiated through the
+//! `Sec-WebSocket-Extensions` header of the opening handshake, which `DualStream::accept`
+//! does itself and is otherwise left to the application, and applied by
+//! `WebSocketSession::set_permessage_deflate`.
+//!
+//! ```ignore
+//! // Client
+//! let ours = PerMessageDeflate::default();
+//! request.insert("Sec-WebSocket-Extensions", ours.header_value());
+//! // ... send the request, read the response
+//! let negotiated = ours.confirm(response.get("Sec-WebSocket-Extensions").unwrap_or(""))?;
+//! session.set_permessage_deflate(negotiated);
+//!
+//! // Server
+//! let negotiated = PerMessageDeflate::default().accept(&offers);
+//! if let Some(ref negotiated) = negotiated {
+//!     response.insert("Sec-WebSocket-Extensions", negotiated.header_value());
+//! }
+//! session.set_permessage_deflate(negotiated);
+//! ```
+//!
+//! Messages are always compressed with a 32KiB window. Offers asking for a smaller one for
+//! messages this end sends are declined, while messages received may use any window size.
+
+use std::io;
+
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+
+use crate::frame::DEFAULT_MAX_MESSAGE_LEN;
+
+/// Name of the extension in `Sec-WebSocket-Extensions`.
+pub const PERMESSAGE_DEFLATE: &str = "permessage-deflate";
+
+/// Bytes every compressed message ends with, which are left out on the wire.
+const TRAILER: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Window size, as a power of two, messages are compressed with.
+const WINDOW_BITS: u8 = 15;
+
+/// The parameters of the extension, as offered, accepted or negotiated.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct PerMessageDeflate {
+    /// The server compresses every message on its own, without referring to earlier ones.
+    pub server_no_context_takeover: bool,
+    /// The client compresses every message on its own, without referring to earlier ones.
+    pub client_no_context_takeover: bool,
+}
+
+/// Compresses the messages one end of a connection sends and decompresses the messages it
+/// receives, with the negotiated parameters.
+pub struct DeflateCodec {
+    tx: Compress,
+    rx: Decompress,
+    reset_tx: bool,
+    reset_rx: bool,
+    max_len: usize,
+}
+
+impl PerMessageDeflate {
+    /// Returns this as a `Sec-WebSocket-Extensions` value, e.g. to offer it as a client, or
+    /// to answer an offer with it as a server.
+    pub fn header_value(&self) -> String {
+        let mut value = String::from(PERMESSAGE_DEFLATE);
+        if self.server_no_context_takeover {
+            value.push_str("; server_no_context_takeover");
+        }
+        if self.client_no_context_takeover {
+            value.push_str("; client_no_context_takeover");
+        }
+
+        value
+    }
+
+    /// Picks the first offer in `offers`, the `Sec-WebSocket-Extensions` value of a client's
+    /// request, that a server with these parameters can accept. Returns what was negotiated,
+    /// which the server should answer with, or `None` to not use the extension.
+    pub fn accept(&self, offers: &str) -> Option<PerMessageDeflate> {
+        let negotiated = deflate_params(offers)
+            .filter_map(|params| from_params(&params[..], "server_max_window_bits"))
+            .next()?;
+
+        Some(PerMessageDeflate {
+            server_no_context_takeover: negotiated.server_no_context_takeover
+                || self.server_no_context_takeover,
+            client_no_context_takeover: negotiated.client_no_context_takeover
+                || self.client_no_context_takeover,
+        })
+    }
+
+    /// Checks `response`, the `Sec-WebSocket-Extensions` value of the server's response to a
+    /// client that offered these parameters. Returns what was negotiated, or `None` if the
+    /// server did not accept the extension.
+    ///
+    /// Fails if the server answered with parameters that were not offered or that this end
+    /// can not honor, in which case the client must fail the connection.
+    pub fn confirm(&self, response: &str) -> io::Result<Option<PerMessageDeflate>> {
+        let mut answers = deflate_params(response);
+        let params = match answers.next() {
+            Some(params) => params,
+            None => return Ok(None),
+        };
+        if answers.next().is_some() {
+            return Err(invalid_answer("accepted more than once"));
+        }
+
+        let negotiated = from_params(&params[..], "client_max_window_bits")
+            .ok_or_else(|| invalid_answer("with unsupported parameters"))?;
+        if self.server_no_context_takeover && !negotiated.server_no_context_takeover {
+            return Err(invalid_answer("without server_no_context_takeover"));
+        }
+
+        debug!("Negotiated {}", negotiated.header_value());
+        Ok(Some(negotiated))
+    }
+}
+
+impl DeflateCodec {
+    /// Creates the codec for the server end of a connection that negotiated `params`.
+    pub fn server(params: &PerMessageDeflate) -> DeflateCodec {
+        DeflateCodec::new(
+            params.server_no_context_takeover,
+            params.client_no_context_takeover,
+        )
+    }
+
+    /// Creates the codec for the client end of a connection that negotiated `params`.
+    pub fn client(params: &PerMessageDeflate) -> DeflateCodec {
+        DeflateCodec::new(
+            params.client_no_context_takeover,
+            params.server_no_context_takeover,
+        )
+    }
+
+    fn new(reset_tx: bool, reset_rx: bool) -> DeflateCodec {
+        DeflateCodec {
+            tx: Compress::new(Compression::default(), false),
+            rx: Decompress::new(false),
+            reset_tx,
+            reset_rx,
+            max_len: DEFAULT_MAX_MESSAGE_LEN,
+        }
+    }
+
+    /// Fails to decompress messages longer than `max_len` bytes, instead of longer than
+    /// `DEFAULT_MAX_MESSAGE_LEN`.
+    pub fn max_len(mut self, max_len: usize) -> DeflateCodec {
+        self.max_len = max_len;
+        self
+    }
+
+    /// Returns the payload of a message carrying `payload` compressed.
+    pub fn compress(&mut self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        let mut buf = Vec::<u8>::with_capacity(payload.len() / 2 + 64);
+        let start = self.tx.total_in();
+        loop {
+            if buf.len() == buf.capacity() {
+                buf.reserve(buf.capacity());
+            }
+
+            let consumed = (self.tx.total_in() - start) as usize;
+            self.tx
+                .compress_vec(&payload[consumed..], &mut buf, FlushCompress::Sync)
+                .map_err(io::Error::other)?;
+
+            // Flushed once everything is consumed without filling the output
+            let consumed = (self.tx.total_in() - start) as usize;
+            if consumed == payload.len() && buf.len() < buf.capacity() {
+                break;
+            }
+        }
+
+        if buf.ends_with(&TRAILER) {
+            buf.truncate(buf.len() - TRAILER.len());
+        }
+        if self.reset_tx {
+            self.tx.reset();
+        }
+
+        Ok(buf)
+    }
+
+    /// Returns the payload of a message whose compressed payload is `payload`.
+    pub fn decompress(&mut self, payload: &[u8]) -> io::Result<Vec<u8>> {
+        let mut input = Vec::<u8>::with_capacity(payload.len() + TRAILER.len());
+        input.extend_from_slice(payload);
+        input.extend_from_slice(&TRAILER);
+
+        let mut buf = Vec::<u8>::with_capacity((payload.len() * 2).clamp(64, self.max_len + 1));
+        let start = self.rx.total_in();
+        loop {
+            if buf.len() > self.max_len {
+                self.rx.reset(false);
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Message decompresses to over {} bytes", self.max_len),
+                ));
+            }
+            if buf.len() == buf.capacity() {
+                buf.reserve(buf.capacity());
+            }
+
+            let consumed = (self.rx.total_in() - start) as usize;
+            let status = self
+                .rx
+                .decompress_vec(&input[consumed..], &mut buf, FlushDecompress::Sync)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+            let consumed = (self.rx.total_in() - start) as usize;
+            let done = consumed == input.len() && buf.len() < buf.capacity();
+            if done || status == Status::StreamEnd {
+                break;
+            }
+        }
+
+        if self.reset_rx {
+            self.rx.reset(false);
+        }
+
+        Ok(buf)
+    }
+
+    /// Forgets the messages compressed so far, e.g. after one could not be sent, so the next
+    /// does not refer to data the peer never received.
+    pub fn reset_compressor(&mut self) {
+        self.tx.reset();
+    }
+}
+
+/// Returns the parameters of every permessage-deflate entry in a `Sec-WebSocket-Extensions`
+/// value, as name and optional value pairs.
+fn deflate_params(header: &str) -> impl Iterator<Item = Vec<(&str, Option<&str>)>> {
+    header.split(',').filter_map(|extension| {
+        let mut parts = extension.split(';').map(str::trim);
+        if !parts.next()?.eq_ignore_ascii_case(PERMESSAGE_DEFLATE) {
+            return None;
+        }
+
+        let params = parts
+            .filter(|param| !param.is_empty())
+            .map(|param| match param.split_once('=') {
+                Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+                None => (param, None),
+            })
+            .collect();
+        Some(params)
+    })
+}
+
+/// Interprets the parameters of one offer or answer. `ours` names the window size parameter
+/// limiting the messages this end sends, which can only be honored at `WINDOW_BITS`. Returns
+/// `None` if a parameter is unknown, repeated, malformed or can not be honored.
+fn from_params(params: &[(&str, Option<&str>)], ours: &str) -> Option<PerMessageDeflate> {
+    let mut deflate = PerMessageDeflate::default();
+    let mut seen = Vec::<&str>::with_capacity(params.len());
+    for &(name, value) in params {
+        if seen.contains(&name) {
+            return None;
+        }
+        seen.push(name);
+
+        match (name, value) {
+            ("server_no_context_takeover", None) => deflate.server_no_context_takeover = true,
+            ("client_no_context_takeover", None) => deflate.client_no_context_takeover = true,
+            ("server_max_window_bits", Some(_)) | ("client_max_window_bits", _) => {
+                let bits = match value {
+                    Some(value) => value.parse::<u8>().ok().filter(|b| (8..=15).contains(b))?,
+                    // Only a client offering to limit its own window leaves out the value
+                    None if ours == "server_max_window_bits" => continue,
+                    None => return None,
+                };
+                if name == ours && bits < WINDOW_BITS {
+                    debug!("Unable to honor {}={}", name, bits);
+                    return None;
+                }
+            }
+            _ => return None,
+        }
+    }
+
+    Some(deflate)
+}
+
+fn invalid_answer(problem: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Server answered {} {}", PERMESSAGE_DEFLATE, problem),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_round_trip_between_client_and_server() {
+        let params = PerMessageDeflate::default();
+        let mut client = DeflateCodec::client(&params);
+        let mut server = DeflateCodec::server(&params);
+
+        let message = b"Hello, Hello, Hello, Hello".repeat(8);
+        let first = client.compress(&message).unwrap();
+        assert!(!first.ends_with(&TRAILER));
+        assert_eq!(server.decompress(&first).unwrap(), message);
+
+        // The second message refers back to the first
+        let second = client.compress(&message).unwrap();
+        assert!(second.len() < first.len());
+        assert_eq!(server.decompress(&second).unwrap(), message);
+
+        // Example from RFC 7692, section 7.2.3.1
+        let hello = [0xf2, 0x48, 0xcd, 0xc9, 0xc9, 0x07, 0x00];
+        let mut codec = DeflateCodec::server(&params);
+        assert_eq!(codec.decompress(&hello).unwrap(), b"Hello");
+    }
+
+    #[test]
+    fn oversized_messages_fail_to_decompress() {
+        let params = PerMessageDeflate::default();
+        let compressed = DeflateCodec::client(&params).compress(&[0; 4096]).unwrap();
+
+        let mut server = DeflateCodec::server(&params).max_len(1024);
+        let e = server.decompress(&compressed).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn offers_that_can_be_honored_are_accepted() {
+        let server = PerMessageDeflate::default();
+        let offers = "permessage-deflate; server_max_window_bits=10, \
+                      permessage-deflate; client_max_window_bits; client_no_context_takeover";
+        let negotiated = server.accept(offers).unwrap();
+        assert!(negotiated.client_no_context_takeover);
+        assert_eq!(
+            negotiated.header_value(),
+            "permessage-deflate; client_no_context_takeover"
+        );
+        assert_eq!(server.accept("x-webkit-deflate-frame"), None);
+
+        let client = PerMessageDeflate {
+            server_no_context_takeover: true,
+            ..PerMessageDeflate::default()
+        };
+        assert_eq!(client.confirm("").unwrap(), None);
+        assert!(client.confirm("permessage-deflate").is_err());
+        assert!(client
+            .confirm("permessage-deflate; server_no_context_takeover; foo")
+            .is_err());
+        assert_eq!(
+            client
+                .confirm("permessage-deflate; server_no_context_takeover")
+                .unwrap(),
+            Some(client)
+        );
+    }
+}
@@ -5,30 +5,107 @@
 // distributed with this file, You can obtain one at
 // http://mozilla.org/MPL/2.0/.
 
-use std::io::{Error, ErrorKind, Read, Write};
+use std::io::{self, Error, ErrorKind, IoSlice, Read, Write};
 use std::marker::PhantomData;
-use std::mem;
+#[cfg(windows)]
+use std::net::Shutdown;
+#[cfg(feature = "registry")]
+use std::net::SocketAddr;
+#[cfg(unix)]
 use std::os::unix::io::{AsRawFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::{UnixListener, UnixStream};
+#[cfg(windows)]
+use std::os::windows::io::{AsRawSocket, RawSocket};
+#[cfg(unix)]
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 // use libc;
 // use errno::errno;
 
-use crate::frame::{Frame, FrameBuilder};
+use crate::buffer::RecvBuffer;
+#[cfg(unix)]
+use crate::cancel::{Cancellable, CancellationToken, Interest};
+use crate::capture::Capture;
+#[cfg(unix)]
+use crate::close::drain_tx;
+use crate::close::CloseReason;
+use crate::deadline::FrameDeadline;
+use crate::duplex::Duplex;
+#[cfg(unix)]
+use crate::errqueue::{set_recv_err, take_icmp_error};
+use crate::frame::{
+    compress_payload, decompress_payload, reserve_frame, BuilderDecoder, DynamicBuilder, Frame,
+    FrameBuilder, FrameBuilderInfo, FrameDecoder, ProtocolInfo, DEFAULT_MAX_DECOMPRESSED_LEN,
+};
+use crate::frame_iter::FrameIter;
+use crate::liveness::{HeartbeatConfig, Liveness};
+use crate::preamble::Compression;
+use crate::protocol::Protocol;
+use crate::ratelimit::{decode_limited, FrameRateLimit, FrameRateLimiter};
+#[cfg(feature = "registry")]
+use crate::registry::{Registration, StreamId};
+use crate::resync::{is_desync, DecodePolicy, Resync};
+use crate::scheduler::{FifoScheduler, FrameSummary, QueuedFrame, TxScheduler};
+#[cfg(unix)]
+use crate::socket::peek_fd;
+use crate::socket::TryClone;
+#[cfg(windows)]
+use crate::socket::{peek_raw_socket, shutdown_raw_socket};
+#[cfg(unix)]
+use crate::sockopt::{TcpOptions, TcpTuning};
+use crate::spans::Spans;
+use crate::stats::{LatencyHistogram, SendProgress, SendTimings};
+#[cfg(unix)]
+use crate::timeout::Timeouts;
+use crate::transform::{transform_frame, PayloadTransform};
+use crate::watermark::{TxQueueFull, WatermarkMonitor, Watermarks};
+use crate::wirelog::WireLog;
 
 use super::{Blocking, NonBlocking};
 
 const BUF_SIZE: usize = 1024;
+/// Most bytes of queued frames committed to `tx_buf` at once.
+const TX_BATCH: usize = 64 * 1024;
 
 /// Plain text stream.
-#[derive(Clone)]
 pub struct Plain<S, FB>
 where
     S: Read + Write,
     FB: FrameBuilder,
 {
     inner: S,
-    rx_buf: Vec<u8>,
+    rx_buf: RecvBuffer,
     tx_buf: Vec<u8>,
+    send_timings: SendTimings,
+    close_reason: Option<CloseReason>,
+    #[cfg(unix)]
+    tcp: Option<TcpTuning>,
+    #[cfg(unix)]
+    cancel: Option<Cancellable>,
+    #[cfg(unix)]
+    timeouts: Option<Timeouts>,
+    #[cfg(unix)]
+    icmp_fd: Option<RawFd>,
+    rx_limit: Option<FrameRateLimiter>,
+    resync: Option<Resync>,
+    rx_deadline: Option<FrameDeadline>,
+    max_frame_len: Option<usize>,
+    liveness: Liveness,
+    wire_log: WireLog,
+    capture: Option<Capture>,
+    spans: Spans,
+    tx_transform: Option<Box<dyn PayloadTransform>>,
+    tx_pending: usize,
+    tx_high_water: Option<usize>,
+    tx_marks: Option<WatermarkMonitor>,
+    rx_marks: Option<WatermarkMonitor>,
+    rx_transform: Option<Box<dyn PayloadTransform>>,
+    scheduler: Box<dyn TxScheduler>,
+    decoder: Box<dyn FrameDecoder>,
+    #[cfg(feature = "registry")]
+    registration: Option<Registration>,
     phantom: PhantomData<FB>,
 }
 
@@ -41,135 +118,1153 @@ where
     pub fn new(stream: S) -> Plain<S, FB> {
         Plain {
             inner: stream,
-            rx_buf: Vec::<u8>::with_capacity(BUF_SIZE),
+            rx_buf: RecvBuffer::with_capacity(BUF_SIZE),
             tx_buf: Vec::<u8>::with_capacity(BUF_SIZE),
+            send_timings: SendTimings::default(),
+            close_reason: None,
+            #[cfg(unix)]
+            tcp: None,
+            #[cfg(unix)]
+            cancel: None,
+            #[cfg(unix)]
+            timeouts: None,
+            #[cfg(unix)]
+            icmp_fd: None,
+            rx_limit: None,
+            resync: None,
+            rx_deadline: None,
+            max_frame_len: None,
+            liveness: Liveness::new(),
+            wire_log: WireLog::default(),
+            capture: None,
+            spans: Spans::default(),
+            tx_transform: None,
+            tx_pending: 0,
+            tx_high_water: None,
+            tx_marks: None,
+            rx_marks: None,
+            rx_transform: None,
+            scheduler: Box::new(FifoScheduler::default()),
+            decoder: Box::new(BuilderDecoder::of::<FB>()),
+            #[cfg(feature = "registry")]
+            registration: None,
             phantom: PhantomData,
         }
     }
-}
 
-impl<S, FB> Blocking for Plain<S, FB>
-where
-    S: Read + Write,
-    FB: FrameBuilder,
-{
-    fn b_recv(&mut self) -> Result<Box<dyn Frame>, Error> {
-        // Empty anything that is in our buffer already from any previous reads
-        match FB::from_bytes(&mut self.rx_buf) {
-            Some(boxed_frame) => {
-                debug!("Complete frame read");
-                return Ok(boxed_frame);
+    /// Returns the distribution of time between frames being queued by `nb_send` or `b_send`
+    /// and their last byte being written to the underlying stream.
+    pub fn send_latency(&self) -> &LatencyHistogram {
+        self.send_timings.histogram()
+    }
+
+    /// Reports the progress of sending frames of at least `progress.threshold()` bytes, as
+    /// their bytes are written to the underlying stream. `None` stops reporting.
+    pub fn set_send_progress(&mut self, progress: Option<SendProgress>) {
+        self.send_timings.set_progress(progress);
+    }
+
+    /// Returns why the connection terminated, or `None` while it is still open.
+    pub fn close_reason(&self) -> Option<&CloseReason> {
+        self.close_reason.as_ref()
+    }
+
+    /// Returns a reference to the underlying stream, e.g. to change its socket options.
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying stream. Bytes read from or written to
+    /// it directly bypass this stream's buffers, and would corrupt the framing.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    fn ensure_open(&self) -> Result<(), Error> {
+        match self.close_reason {
+            Some(ref reason) => Err(reason.to_io_error()),
+            None => Ok(()),
+        }
+    }
+
+    fn close(&mut self, reason: CloseReason) -> Error {
+        debug!("Stream closed: {}", reason);
+        let err = reason.to_io_error();
+        self.close_reason = Some(reason);
+        err
+    }
+
+    /// Closes the stream if `e` terminated the connection, returning the error to report.
+    fn fail(&mut self, e: Error) -> Error {
+        match e.kind() {
+            ErrorKind::WouldBlock | ErrorKind::Interrupted => e,
+            #[cfg(unix)]
+            _ => match self.icmp_fd.and_then(take_icmp_error) {
+                // Attribute the failure to the ICMP message that caused it
+                Some(icmp) => {
+                    let e = Error::new(e.kind(), icmp);
+                    self.close(CloseReason::TransportError(e))
+                }
+                None => self.close(CloseReason::TransportError(e)),
+            },
+            #[cfg(not(unix))]
+            _ => self.close(CloseReason::TransportError(e)),
+        }
+    }
+
+    /// Queues `frame` with the stream's `TxScheduler` and writes as much of the queue as
+    /// possible without blocking. Returns `ErrorKind::WouldBlock` if anything is left queued.
+    pub fn nb_send_queued(&mut self, frame: QueuedFrame) -> Result<(), Error> {
+        self.ensure_open()?;
+        self.check_tx_room()?;
+        self.enqueue(frame);
+        self.write_queued(false)
+    }
+
+    /// Same as `nb_send`, queueing `frame` with `priority` for schedulers that use it, such
+    /// as `PriorityScheduler` and `FairScheduler`. With the default `FifoScheduler` the
+    /// priority is ignored.
+    pub fn nb_send_with_priority(&mut self, frame: &dyn Frame, priority: u8) -> Result<(), Error> {
+        let span = self.spans.send("nb_send_with_priority", frame);
+        let result = self.transform_tx(frame).and_then(|transformed| {
+            let queued = QueuedFrame::new(transformed.as_deref().unwrap_or(frame));
+            self.nb_send_queued(queued.with_priority(priority))
+        });
+        span.done(&result);
+        result
+    }
+
+    /// Sends one frame already encoded across `bufs`, e.g. a header and a payload kept in
+    /// separate buffers, with a single vectored write and without joining them first. Streams
+    /// whose `write_vectored` only writes the first buffer still work, one buffer per write.
+    /// Bytes that could not be written are queued as with `nb_send`, and if other frames are
+    /// queued already the frame is joined and queued behind them. Transforms are not applied.
+    pub fn nb_send_vectored(&mut self, bufs: &[IoSlice]) -> Result<(), Error> {
+        self.ensure_open()?;
+        if self.tx_pending > 0 {
+            return self.nb_send_queued(QueuedFrame::from_slices(bufs));
+        }
+
+        let frame_len = bufs.iter().map(|buf| buf.len()).sum();
+        self.wire_log.sent_vectored(bufs);
+        if let Some(ref capture) = self.capture {
+            capture.sent_vectored(bufs);
+        }
+        self.send_timings.enqueued(FrameSummary {
+            len: frame_len,
+            remaining: frame_len,
+            priority: 0,
+            deadline: None,
+            enqueued_at: Instant::now(),
+        });
+        self.count_pending(frame_len);
+        #[cfg(unix)]
+        if let Some(ref mut tcp) = self.tcp {
+            tcp.before_write(frame_len);
+        }
+
+        let mut slices = bufs.to_vec();
+        let mut remaining = &mut slices[..];
+        IoSlice::advance_slices(&mut remaining, 0);
+        while !remaining.is_empty() {
+            let num_written = match self.inner.write_vectored(remaining) {
+                Ok(0) => {
+                    let e = Error::new(ErrorKind::WriteZero, "Write returned zero");
+                    return Err(self.fail(e));
+                }
+                Ok(num_written) => num_written,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(ref e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) => return Err(self.fail(e)),
+            };
+            trace!("Wrote {} byte(s) of a vectored frame", num_written);
+
+            self.send_timings.flushed(num_written);
+            self.liveness.sent();
+            self.tx_pending -= num_written;
+            self.tx_pending_changed();
+            IoSlice::advance_slices(&mut remaining, num_written);
+        }
+
+        if remaining.is_empty() {
+            #[cfg(unix)]
+            if let Some(ref mut tcp) = self.tcp {
+                tcp.after_write(0);
             }
-            None => {}
+            return Ok(());
+        }
+
+        // The rest goes out ahead of anything queued later
+        for slice in remaining.iter() {
+            self.tx_buf.extend_from_slice(slice);
+        }
+        Err(ErrorKind::WouldBlock.into())
+    }
+
+    /// Limits how many frames per second are accepted from the peer, or removes the limit if
+    /// `None`.
+    pub fn set_rx_frame_limit(&mut self, limit: Option<FrameRateLimit>) {
+        self.rx_limit = limit.map(FrameRateLimiter::new);
+    }
+
+    /// Checks received bytes for corruption and recovers from it as `policy` says, or stops
+    /// checking if `None`. The receive call that notices fails with `Error::BadChecksum` for
+    /// a checksum mismatch, and `Error::ProtocolViolation` for any other corruption. If frames
+    /// were received ahead of the corrupt bytes in the same call, they are returned instead
+    /// and the desync is only counted by `rx_desyncs`.
+    pub fn set_decode_policy(&mut self, policy: Option<DecodePolicy>) {
+        self.resync = policy.map(Resync::new);
+    }
+
+    /// Closes the stream with `CloseReason::StalledFrame` once a frame has been arriving for
+    /// longer than `deadline` without completing, or removes the deadline if `None`.
+    ///
+    /// The deadline is checked whenever bytes are read and on every `nb_recv`. A blocking read
+    /// from a peer that stopped sending altogether only returns once the transport's own read
+    /// timeout, if any, expires.
+    pub fn set_frame_deadline(&mut self, deadline: Option<Duration>) {
+        self.rx_deadline = deadline.map(FrameDeadline::new);
+    }
+
+    /// Closes the stream with `CloseReason::ProtocolError` once a frame longer than `max`
+    /// bytes starts arriving, or removes the limit if `None`. The receive call that notices
+    /// fails with `simple_stream::Error::FrameTooLarge`.
+    ///
+    /// Frames whose length their `FrameBuilder` can tell from their start are refused as soon
+    /// as that has arrived, others once more than `max` bytes of them have.
+    pub fn set_max_frame_len(&mut self, max: Option<usize>) {
+        self.max_frame_len = max;
+    }
+
+    /// Closes the stream with `CloseReason::IdleTimeout` once nothing has been received for
+    /// longer than `timeout`, or removes the timeout if `None`. Checked when the deadline set
+    /// with `set_frame_deadline` would be.
+    pub fn set_idle_timeout(&mut self, timeout: Option<Duration>) {
+        self.liveness.set_idle_timeout(timeout);
+    }
+
+    /// Sets how often this stream should carry traffic, and how long the peer may stay
+    /// silent, or removes both if `None`. The timeout is enforced like an idle timeout.
+    pub fn set_heartbeat(&mut self, heartbeat: Option<HeartbeatConfig>) {
+        self.liveness.set_heartbeat(heartbeat);
+    }
+
+    /// Whether nothing has been sent for the heartbeat interval, so a frame should be sent to
+    /// keep the connection alive.
+    pub fn heartbeat_due(&self) -> bool {
+        self.liveness.heartbeat_due()
+    }
+
+    /// Applies the limits and settings of `protocol`, other than its decoder.
+    fn apply_protocol(&mut self, protocol: &Protocol) {
+        debug!("Using protocol {}", protocol.name);
+        self.set_compression(protocol.compression);
+        self.max_frame_len = protocol.max_frame_len;
+        self.liveness.set_idle_timeout(protocol.idle_timeout);
+        self.liveness.set_heartbeat(protocol.heartbeat);
+    }
+
+    /// Rewrites the payload of every frame passed to `nb_send` or `b_send` with `transform`
+    /// before it is encoded, or stops doing so if `None`. Frames queued already encoded with
+    /// `nb_send_queued` are sent as they are.
+    pub fn set_tx_transform(&mut self, transform: Option<Box<dyn PayloadTransform>>) {
+        self.tx_transform = transform;
+    }
+
+    /// Rewrites the payload of every received frame with `transform` once it is decoded, or
+    /// stops doing so if `None`. The stream is closed with `CloseReason::ProtocolError` if
+    /// `transform` fails.
+    pub fn set_rx_transform(&mut self, transform: Option<Box<dyn PayloadTransform>>) {
+        self.rx_transform = transform;
+    }
+
+    /// Compresses the payload of every frame sent with `compression`, and decompresses the
+    /// payload of every frame received, as `CompressedFrame` and `Compressed` do. Replaces
+    /// the transforms set with `set_tx_transform` and `set_rx_transform`, or removes them if
+    /// `Compression::None`, in which case payloads are sent as they are. Frames that fail to
+    /// decompress close the stream with `CloseReason::ProtocolError`.
+    pub fn set_compression(&mut self, compression: Compression) {
+        if compression == Compression::None {
+            self.tx_transform = None;
+            self.rx_transform = None;
+            return;
+        }
+
+        debug!("Compressing payloads with {:?}", compression);
+        let compress = move |payload: Vec<u8>| compress_payload(compression, &payload[..]);
+        let decompress = |payload: Vec<u8>| {
+            decompress_payload(&payload[..], DEFAULT_MAX_DECOMPRESSED_LEN).map(|(_, p)| p)
         };
+        self.tx_transform = Some(Box::new(compress));
+        self.rx_transform = Some(Box::new(decompress));
+    }
+
+    /// Calls back when the bytes read but not yet decoded into frames cross `marks`, or stops
+    /// if `None`.
+    pub fn set_rx_watermarks(&mut self, marks: Option<Watermarks>) {
+        self.rx_marks = marks.map(WatermarkMonitor::new);
+        self.rx_buffered_changed();
+    }
+
+    /// Calls back when the bytes of queued frames not yet written cross `marks`, or stops if
+    /// `None`.
+    pub fn set_tx_watermarks(&mut self, marks: Option<Watermarks>) {
+        self.tx_marks = marks.map(WatermarkMonitor::new);
+        self.tx_pending_changed();
+    }
+
+    /// Returns a handle to this stream's wire log, which is off until configured.
+    pub fn wire_log(&self) -> WireLog {
+        self.wire_log.clone()
+    }
+
+    /// Records every frame sent or received from now on to `capture`, or stops recording if
+    /// `None`.
+    pub fn set_capture(&mut self, capture: Option<Capture>) {
+        self.capture = capture;
+    }
+
+    /// Returns how many received frames were discarded for exceeding the frame rate limit.
+    pub fn rx_frames_dropped(&self) -> u64 {
+        self.rx_limit.as_ref().map_or(0, |l| l.dropped())
+    }
+
+    /// Returns how often received bytes were found corrupt under the `DecodePolicy` set.
+    pub fn rx_desyncs(&self) -> u64 {
+        self.resync.as_ref().map_or(0, |r| r.desyncs())
+    }
+
+    /// Replaces the policy picking which queued frame is written next. Frames queued with the
+    /// previous scheduler are moved over to `scheduler`.
+    pub fn set_tx_scheduler(&mut self, mut scheduler: Box<dyn TxScheduler>) {
+        while let Some(frame) = self.scheduler.pop() {
+            scheduler.push(frame);
+        }
+        self.scheduler = scheduler;
+    }
+
+    /// Decodes received frames with `decoder` from now on, instead of the stream's
+    /// `FrameBuilder`. Bytes already received and not yet decoded are left for `decoder`.
+    pub fn set_frame_decoder(&mut self, decoder: Box<dyn FrameDecoder>) {
+        self.decoder = decoder;
+    }
+
+    /// Adds this stream to the list returned by `active_streams`, with `peer_addr` as the
+    /// address it is connected to, and returns its id there. The stream is removed once
+    /// dropped. Registering again replaces the previous entry.
+    #[cfg(feature = "registry")]
+    pub fn register(&mut self, peer_addr: Option<SocketAddr>) -> StreamId {
+        let registration = Registration::new("plain", peer_addr);
+        registration.set_rx_buffered(self.rx_buf.len());
+        registration.set_tx_pending(self.tx_pending);
+        let id = registration.id();
+        self.registration = Some(registration);
+        id
+    }
+
+    /// Same as `b_send`, returning how many bytes were written: the encoded `frame` plus
+    /// anything `nb_send` had left queued ahead of it.
+    pub fn b_send_written(&mut self, frame: &dyn Frame) -> Result<usize, Error> {
+        let span = self.spans.send("b_send", frame);
+        let result = self.send_blocking(frame);
+        span.done(&result);
+        result
+    }
+
+    fn send_blocking(&mut self, frame: &dyn Frame) -> Result<usize, Error> {
+        self.ensure_open()?;
+        let transformed = self.transform_tx(frame)?;
+        self.enqueue(QueuedFrame::new(transformed.as_deref().unwrap_or(frame)));
+        let num_written = self.tx_pending;
+
+        // Anything nb_send left queued goes out along with this frame
+        self.flush()?;
+        Ok(num_written)
+    }
+
+    /// Queues every frame in `frames` and writes them out together. If `blocking`, waits
+    /// until everything queued has been written.
+    fn send_all(&mut self, frames: &[&dyn Frame], blocking: bool) -> Result<(), Error> {
+        self.ensure_open()?;
+        if !blocking {
+            self.check_tx_room()?;
+        }
 
+        // Transformed up front, so that none are queued if one fails
+        let mut queued = Vec::<QueuedFrame>::with_capacity(frames.len());
+        for &frame in frames {
+            let transformed = self.transform_tx(frame)?;
+            queued.push(QueuedFrame::new(transformed.as_deref().unwrap_or(frame)));
+        }
+        for frame in queued {
+            self.enqueue(frame);
+        }
+
+        match blocking {
+            true => self.flush(),
+            false => self.write_queued(false),
+        }
+    }
+
+    /// Caps how many bytes of frames `nb_send` may leave queued, or removes the cap if `None`.
+    /// Once `high_water` bytes are pending, non-blocking sends write what they can and fail
+    /// with a `TxQueueFull` error without queueing the frame if that is still too many.
+    /// Blocking sends are not limited.
+    pub fn set_tx_high_water(&mut self, high_water: Option<usize>) {
+        self.tx_high_water = high_water;
+    }
+
+    /// Returns how many bytes of queued frames have not been written yet.
+    pub fn pending_tx_bytes(&self) -> usize {
+        self.tx_pending
+    }
+
+    /// Describes every frame queued and not completely written yet, in the order they are
+    /// written in. Frames the stream has started writing come first, followed by the frames
+    /// still held by the `TxScheduler`.
+    pub fn pending_frames(&self) -> Vec<FrameSummary> {
+        let mut frames: Vec<FrameSummary> = self.send_timings.pending().copied().collect();
+        frames.extend(self.scheduler.queued().iter().map(|frame| frame.summary()));
+        frames
+    }
+
+    /// Writes as much of what `nb_send` left queued as possible without blocking, returning
+    /// how many bytes are still pending.
+    pub fn flush_tx(&mut self) -> Result<usize, Error> {
+        self.ensure_open()?;
+        match self.write_queued(false) {
+            Ok(()) => {}
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+        Ok(self.tx_pending)
+    }
+
+    /// Returns the next frame received, reading from the underlying stream without blocking
+    /// only if the frames already read have all been returned. Returns `None` if no complete
+    /// frame arrived yet. Unlike `nb_recv`, frames are decoded one at a time, so they can be
+    /// handled as they arrive without collecting them into a `Vec`.
+    pub fn try_next_frame(&mut self) -> Result<Option<Box<dyn Frame>>, Error> {
         loop {
-            let mut buf = [0u8; BUF_SIZE];
-            let read_result = self.inner.read(&mut buf);
-            if read_result.is_err() {
-                let err = read_result.unwrap_err();
-                return Err(err);
+            if let Some(boxed_frame) = self.decode_next(false)? {
+                self.rx_buffered_changed();
+                self.check_rx(true)?;
+                return Ok(Some(boxed_frame));
+            }
+            if !self.can_read() || !self.fill_rx_buf() {
+                break;
             }
+        }
 
-            let num_read = read_result.unwrap();
-            trace!("Read {} byte(s)", num_read);
-            self.rx_buf.extend_from_slice(&buf[0..num_read]);
+        self.rx_buffered_changed();
+        self.check_rx(false)?;
+        self.ensure_open()?;
+        Ok(None)
+    }
 
-            match FB::from_bytes(&mut self.rx_buf) {
-                Some(boxed_frame) => {
-                    debug!("Complete frame read");
-                    return Ok(boxed_frame);
+    /// Returns an iterator over the frames `try_next_frame` returns, ending once no complete
+    /// frame has arrived, or after the first error.
+    pub fn frames(&mut self) -> FrameIter<'_, Self> {
+        FrameIter::new(self)
+    }
+
+    /// Writes everything `nb_send` left queued, blocking until it is written, then flushes
+    /// the underlying stream.
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.ensure_open()?;
+        #[cfg(unix)]
+        self.write_queued(true)
+            .map_err(|e| self.classify(Interest::Writable, e))?;
+        #[cfg(not(unix))]
+        self.write_queued(true)?;
+        self.inner.flush().map_err(|e| self.fail(e))
+    }
+
+    /// Writes queued frames until everything has been written or the underlying stream
+    /// would block. When `blocking`, waits for the stream to become writable first if a
+    /// `CancellationToken` is set.
+    fn write_queued(&mut self, blocking: bool) -> Result<(), Error> {
+        loop {
+            if self.tx_buf.is_empty() {
+                self.fill_tx_buf();
+                if self.tx_buf.is_empty() {
+                    break;
+                }
+            }
+
+            if blocking {
+                #[cfg(unix)]
+                self.wait(Interest::Writable)?;
+            }
+            #[cfg(unix)]
+            if let Some(ref mut tcp) = self.tcp {
+                tcp.before_write(self.tx_buf.len());
+            }
+
+            let num_written = match self.inner.write(&self.tx_buf[..]) {
+                Ok(0) => {
+                    let e = Error::new(ErrorKind::WriteZero, "Write returned zero");
+                    return Err(self.fail(e));
                 }
-                None => {}
+                Ok(num_written) => num_written,
+                Err(ref e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(e) => return Err(self.fail(e)),
             };
+            trace!(
+                "Tried to write {} byte(s) wrote {} byte(s)",
+                self.tx_buf.len(),
+                num_written
+            );
+
+            self.send_timings.flushed(num_written);
+            self.liveness.sent();
+            self.tx_buf.drain(..num_written);
+            self.tx_pending -= num_written;
+            self.tx_pending_changed();
+        }
+
+        #[cfg(unix)]
+        if let Some(ref mut tcp) = self.tcp {
+            tcp.after_write(0);
         }
+
+        Ok(())
     }
 
-    fn b_send(&mut self, frame: &dyn Frame) -> Result<(), Error> {
-        let out_buf = frame.to_bytes();
-        let write_result = self.inner.write(&out_buf[..]);
-        if write_result.is_err() {
-            let err = write_result.unwrap_err();
-            return Err(err);
+    /// Returns `frame` as rewritten by the transmit transform, if one is set.
+    fn transform_tx(&mut self, frame: &dyn Frame) -> Result<Option<Box<dyn Frame>>, Error> {
+        match self.tx_transform {
+            Some(ref mut transform) => transform_frame(&mut **transform, frame).map(Some),
+            None => Ok(None),
         }
+    }
 
-        trace!("Wrote {} byte(s)", write_result.unwrap());
+    /// Returns `frame` as rewritten by the receive transform, closing the stream if that fails.
+    fn transform_rx(&mut self, frame: Box<dyn Frame>) -> Result<Box<dyn Frame>, Error> {
+        #[cfg(feature = "registry")]
+        if let Some(ref registration) = self.registration {
+            registration.received();
+        }
 
-        Ok(())
+        let transformed = match self.rx_transform {
+            Some(ref mut transform) => transform_frame(&mut **transform, &*frame),
+            None => return Ok(frame),
+        };
+
+        transformed.map_err(|e| {
+            error!("Receive transform failed: {}", e);
+            self.close(CloseReason::ProtocolError)
+        })
     }
-}
 
-impl<S, FB> NonBlocking for Plain<S, FB>
-where
-    S: Read + Write,
-    FB: FrameBuilder,
-{
-    fn nb_recv(&mut self) -> Result<Vec<Box<dyn Frame>>, Error> {
+    /// Fails with `TxQueueFull` if the send queue is still at its high-water mark after
+    /// writing as much of it as possible.
+    fn check_tx_room(&mut self) -> Result<(), Error> {
+        let high_water = match self.tx_high_water {
+            Some(high_water) if self.tx_pending >= high_water => high_water,
+            _ => return Ok(()),
+        };
+
+        let pending = self.flush_tx()?;
+        if pending < high_water {
+            return Ok(());
+        }
+
+        trace!("Send queue full with {} byte(s) pending", pending);
+        Err(Error::other(TxQueueFull {
+            pending,
+            high_water,
+        }))
+    }
+
+    /// Hands `frame` to the scheduler, counting it as pending until written.
+    fn enqueue(&mut self, frame: QueuedFrame) {
+        self.wire_log.sent(frame.bytes());
+        if let Some(ref capture) = self.capture {
+            capture.sent(frame.bytes());
+        }
+        self.count_pending(frame.len());
+        self.scheduler.push(frame);
+    }
+
+    /// Counts a frame of `len` bytes as pending until written.
+    fn count_pending(&mut self, len: usize) {
+        self.tx_pending += len;
+        #[cfg(feature = "registry")]
+        if let Some(ref registration) = self.registration {
+            registration.sent();
+        }
+        self.tx_pending_changed();
+    }
+
+    fn tx_pending_changed(&mut self) {
+        if let Some(ref mut marks) = self.tx_marks {
+            marks.update(self.tx_pending);
+        }
+        #[cfg(feature = "registry")]
+        if let Some(ref registration) = self.registration {
+            registration.set_tx_pending(self.tx_pending);
+        }
+    }
+
+    fn rx_buffered_changed(&mut self) {
+        if let Some(ref mut marks) = self.rx_marks {
+            marks.update(self.rx_buf.len());
+        }
+        #[cfg(feature = "registry")]
+        if let Some(ref registration) = self.registration {
+            registration.set_rx_buffered(self.rx_buf.len());
+        }
+    }
+
+    /// Checks on the frame being received after a decode attempt, closing the stream if it
+    /// is overdue or over the maximum frame length, or if nothing has been received within
+    /// the idle timeout. Frames held back by the rate limiter are not counted.
+    fn check_rx(&mut self, decoded: bool) -> Result<(), Error> {
+        if self.rx_limit.as_ref().is_some_and(|l| l.defers_reads()) {
+            return Ok(());
+        }
+        if !decoded {
+            if self.liveness.is_idle() {
+                return Err(self.close(CloseReason::IdleTimeout));
+            }
+            self.check_frame_len(None)?;
+        }
+
+        let buffered = self.rx_buf.len();
+        match self
+            .rx_deadline
+            .as_mut()
+            .and_then(|d| d.update(decoded, buffered))
+        {
+            Some(stalled) => Err(self.close(CloseReason::StalledFrame(stalled))),
+            None => Ok(()),
+        }
+    }
+
+    /// Fails with `FrameTooLarge`, closing the stream, if a frame of `len` bytes, or the
+    /// frame being received if `None`, is over the maximum frame length.
+    fn check_frame_len(&mut self, len: Option<usize>) -> Result<(), Error> {
+        let max = match self.max_frame_len {
+            Some(max) => max,
+            None => return Ok(()),
+        };
+
+        let len = len.unwrap_or_else(|| {
+            let buf = self.rx_buf.as_slice();
+            self.decoder.size_hint(buf).unwrap_or(buf.len())
+        });
+        if len <= max {
+            return Ok(());
+        }
+
+        error!("Frame of {} byte(s) exceeds the maximum of {}", len, max);
+        self.close(CloseReason::ProtocolError);
+        Err(crate::Error::FrameTooLarge { len, max }.into())
+    }
+
+    /// Whether a non-blocking receive should read from the underlying stream.
+    fn can_read(&mut self) -> bool {
+        self.close_reason.is_none() && !self.rx_limit.as_mut().is_some_and(|l| l.holds_back_reads())
+    }
+
+    /// Reads once from the underlying stream into `rx_buf` without blocking. Returns `false`
+    /// once nothing more can be read for now.
+    fn fill_rx_buf(&mut self) -> bool {
+        let mut buf = [0u8; BUF_SIZE];
+        let num_read = match self.inner.read(&mut buf) {
+            Ok(0) => {
+                self.close(CloseReason::PeerClosed);
+                return false;
+            }
+            Ok(num_read) => num_read,
+            Err(e) => match e.kind() {
+                ErrorKind::WouldBlock => return false,
+                ErrorKind::Interrupted => return true,
+                _ => {
+                    self.fail(e);
+                    return false;
+                }
+            },
+        };
+        trace!("Read {} byte(s)", num_read);
+        self.liveness.received();
+        self.rx_buf.extend_from_slice(&buf[0..num_read]);
+        reserve_frame(&*self.decoder, &mut self.rx_buf);
+        self.rx_buffered_changed();
+        #[cfg(unix)]
+        if let Some(ref tcp) = self.tcp {
+            tcp.after_read();
+        }
+
+        true
+    }
+
+    /// Receives the next frame, waiting for one to arrive.
+    fn recv_blocking(&mut self) -> Result<Box<dyn Frame>, Error> {
+        // Empty anything that is in our buffer already from any previous reads
+        if let Some(boxed_frame) = self.decode_next(true)? {
+            self.rx_buffered_changed();
+            self.check_rx(true)?;
+            return Ok(boxed_frame);
+        }
+
+        self.ensure_open()?;
+
         loop {
+            #[cfg(unix)]
+            if let Some(ref tcp) = self.tcp {
+                tcp.before_blocking_read();
+            }
+            #[cfg(unix)]
+            self.wait(Interest::Readable)?;
             let mut buf = [0u8; BUF_SIZE];
-            let read_result = self.inner.read(&mut buf);
-            if read_result.is_err() {
-                let err = read_result.unwrap_err();
-                if err.kind() == ErrorKind::WouldBlock {
-                    break;
+            let num_read = match self.inner.read(&mut buf) {
+                Ok(0) => return Err(self.close(CloseReason::PeerClosed)),
+                Ok(num_read) => num_read,
+                Err(e) => {
+                    // A read timeout is the only chance to catch a peer gone quiet mid-frame
+                    if e.kind() == ErrorKind::WouldBlock {
+                        self.check_rx(false)?;
+                    }
+                    let e = self.fail(e);
+                    #[cfg(unix)]
+                    let e = self.classify(Interest::Readable, e);
+                    return Err(e);
                 }
-                return Err(err);
-            }
-
-            let num_read = read_result.unwrap();
+            };
             trace!("Read {} byte(s)", num_read);
+            self.liveness.received();
             self.rx_buf.extend_from_slice(&buf[0..num_read]);
+            reserve_frame(&*self.decoder, &mut self.rx_buf);
+            self.rx_buffered_changed();
+            #[cfg(unix)]
+            if let Some(ref tcp) = self.tcp {
+                tcp.after_read();
+            }
+
+            if let Some(boxed_frame) = self.decode_next(true)? {
+                self.rx_buffered_changed();
+                self.check_rx(true)?;
+                return Ok(boxed_frame);
+            }
+            self.check_rx(false)?;
         }
+    }
+
+    /// Appends every frame that can be received without blocking to `frames`.
+    fn recv_nonblocking(&mut self, frames: &mut Vec<Box<dyn Frame>>) -> Result<usize, Error> {
+        while self.can_read() && self.fill_rx_buf() {}
 
-        let mut ret_buf = Vec::<Box<dyn Frame>>::with_capacity(5);
-        while let Some(boxed_frame) = FB::from_bytes(&mut self.rx_buf) {
-            debug!("Complete frame read");
-            ret_buf.push(boxed_frame);
+        let mut num_frames = 0;
+        loop {
+            let boxed_frame = match self.decode_next(false) {
+                Ok(Some(boxed_frame)) => boxed_frame,
+                Ok(None) => break,
+                // Frames received before the limit was exceeded are returned instead
+                Err(e) if num_frames == 0 => return Err(e),
+                Err(_) => break,
+            };
+            frames.push(boxed_frame);
+            num_frames += 1;
         }
 
-        if ret_buf.len() > 0 {
-            debug!("Read {} frame(s)", ret_buf.len());
-            return Ok(ret_buf);
+        self.rx_buffered_changed();
+        self.check_rx(num_frames > 0)?;
+
+        if num_frames > 0 {
+            debug!("Read {} frame(s)", num_frames);
+            return Ok(num_frames);
         }
 
-        Err(Error::new(ErrorKind::WouldBlock, "WouldBlock"))
+        self.ensure_open()?;
+        Err(ErrorKind::WouldBlock.into())
     }
 
-    fn nb_send(&mut self, frame: &dyn Frame) -> Result<(), Error> {
-        self.tx_buf.extend_from_slice(&frame.to_bytes()[..]);
+    /// Decodes the next frame from `rx_buf`, if it holds a complete one. `blocking` decides
+    /// whether a delaying rate limit sleeps or reports no frame.
+    fn decode_next(&mut self, blocking: bool) -> Result<Option<Box<dyn Frame>>, Error> {
+        let limiter = self.rx_limit.as_mut();
+        let resync = self.resync.as_mut();
+        let decoded = decode_limited(
+            &mut *self.decoder,
+            &mut self.rx_buf,
+            limiter,
+            resync,
+            blocking,
+        );
+        let boxed_frame = match decoded {
+            Ok(Some(boxed_frame)) => boxed_frame,
+            Ok(None) => return Ok(None),
+            Err(e) => {
+                // Without a policy, bytes that can never become a frame close the stream
+                let closes = match self.resync {
+                    Some(ref resync) => resync.closes_on(&e),
+                    None => is_desync(&e),
+                };
+                if closes {
+                    self.close(CloseReason::ProtocolError);
+                }
+                return Err(e);
+            }
+        };
+        debug!("Complete frame read: {}", boxed_frame.fmt_summary());
+        self.check_frame_len(Some(boxed_frame.len_as_vec()))?;
+        self.wire_log.received(&*boxed_frame);
+        if let Some(ref capture) = self.capture {
+            capture.received(&*boxed_frame);
+        }
+        self.transform_rx(boxed_frame).map(Some)
+    }
 
-        let mut out_buf = Vec::<u8>::with_capacity(BUF_SIZE);
-        mem::swap(&mut self.tx_buf, &mut out_buf);
+    /// Waits for the socket to become ready for `interest` if a `CancellationToken` or a
+    /// timeout for `interest` is set.
+    #[cfg(unix)]
+    fn wait(&mut self, interest: Interest) -> Result<(), Error> {
+        let result = match (self.timeouts, self.cancel.as_ref()) {
+            (Some(timeouts), cancel) => timeouts.wait(interest, cancel.map(Cancellable::token)),
+            (None, Some(cancel)) => cancel.wait(interest),
+            (None, None) => return Ok(()),
+        };
 
-        let write_result = self.inner.write(&out_buf[..]);
-        if write_result.is_err() {
-            let err = write_result.unwrap_err();
-            return Err(err);
+        match result {
+            // Timing out leaves the stream open
+            Err(e) if e.kind() == ErrorKind::TimedOut => Err(e),
+            result => result.map_err(|e| self.fail(e)),
         }
+    }
 
-        let num_written = write_result.unwrap();
-        if num_written == 0 {
-            return Err(Error::new(ErrorKind::Other, "Write returned zero"));
+    /// Returns `e` as an `IoTimeout` if a socket timeout for `interest` caused it.
+    #[cfg(unix)]
+    fn classify(&self, interest: Interest, e: Error) -> Error {
+        match self.timeouts {
+            Some(ref timeouts) => timeouts.classify(interest, e),
+            None => e,
         }
+    }
 
-        trace!(
-            "Tried to write {} byte(s) wrote {} byte(s)",
-            out_buf.len(),
-            num_written
-        );
+    /// Moves frames picked by the scheduler into `tx_buf`, up to `TX_BATCH` bytes, which
+    /// commits them to the order they are written in.
+    fn fill_tx_buf(&mut self) {
+        while self.tx_buf.len() < TX_BATCH {
+            let frame = match self.scheduler.pop() {
+                Some(frame) => frame,
+                None => return,
+            };
+
+            self.send_timings.enqueued(frame.summary());
+            self.tx_buf.extend_from_slice(&frame.into_bytes()[..]);
+        }
+    }
+}
+
+impl<S> Plain<S, DynamicBuilder>
+where
+    S: Read + Write,
+{
+    /// Creates a new plain text stream decoding received frames with `decoder`, for framing
+    /// configured at runtime.
+    pub fn with_decoder<D>(stream: S, decoder: D) -> Plain<S, DynamicBuilder>
+    where
+        D: FrameDecoder + 'static,
+    {
+        let mut plain = Plain::new(stream);
+        plain.decoder = Box::new(decoder);
+        plain
+    }
+
+    /// Creates a new plain text stream decoding received frames with the decoder of `protocol`, and
+    /// applying its limits and settings.
+    pub fn with_protocol(stream: S, protocol: &Protocol) -> Plain<S, DynamicBuilder> {
+        let mut plain = Plain::new(stream);
+        plain.decoder = protocol.decoder.clone();
+        plain.apply_protocol(protocol);
+        plain
+    }
+}
+
+impl<S, FB> Plain<S, FB>
+where
+    S: Read + Write,
+    FB: FrameBuilderInfo,
+{
+    /// Describes the frame format of `FB`, which this stream was created for. Decoders set
+    /// with `set_frame_decoder` are not taken into account.
+    pub fn protocol_info(&self) -> ProtocolInfo {
+        FB::info()
+    }
+}
+
+impl<FB> Plain<Duplex, FB>
+where
+    FB: FrameBuilder,
+{
+    /// Creates two plain text streams connected to each other through an in-memory `Duplex`,
+    /// for exercising client and server logic against each other without sockets.
+    pub fn pair() -> (Plain<Duplex, FB>, Plain<Duplex, FB>) {
+        let (a, b) = Duplex::pair();
+        (Plain::new(a), Plain::new(b))
+    }
+
+    /// Same as `pair`, except every read and write on the underlying transport is capped at
+    /// `chunk_size` bytes, in order to simulate frames being fragmented by the network.
+    pub fn pair_with_chunk_size(chunk_size: usize) -> (Plain<Duplex, FB>, Plain<Duplex, FB>) {
+        let (mut a, mut b) = Duplex::pair();
+        for end in [&mut a, &mut b] {
+            end.set_max_read(chunk_size);
+            end.set_max_write(chunk_size);
+        }
+        (Plain::new(a), Plain::new(b))
+    }
+}
+
+#[cfg(unix)]
+impl<FB> Plain<UnixStream, FB>
+where
+    FB: FrameBuilder,
+{
+    /// Connects to the unix domain socket at `path`. The stream is in blocking mode, for use
+    /// with `Blocking`.
+    pub fn connect_unix<P: AsRef<Path>>(path: P) -> io::Result<Plain<UnixStream, FB>> {
+        let stream = UnixStream::connect(path.as_ref())?;
+        trace!("Connected to {}", path.as_ref().display());
+        Ok(Plain::new(stream))
+    }
+
+    /// Same as `connect_unix`, with the stream in non-blocking mode, for use with
+    /// `NonBlocking`.
+    pub fn connect_unix_nonblocking<P: AsRef<Path>>(path: P) -> io::Result<Plain<UnixStream, FB>> {
+        let stream = UnixStream::connect(path.as_ref())?;
+        stream.set_nonblocking(true)?;
+        trace!("Connected to {}", path.as_ref().display());
+        Ok(Plain::new(stream))
+    }
+
+    /// Accepts the next connection on `listener`. The stream is in blocking mode, whatever
+    /// mode `listener` is in.
+    pub fn accept_unix(listener: &UnixListener) -> io::Result<Plain<UnixStream, FB>> {
+        let (stream, _) = listener.accept()?;
+        stream.set_nonblocking(false)?;
+        Ok(Plain::new(stream))
+    }
+
+    /// Same as `accept_unix`, with the stream in non-blocking mode. Fails with
+    /// `ErrorKind::WouldBlock` if `listener` is non-blocking and no connection is pending.
+    pub fn accept_unix_nonblocking(listener: &UnixListener) -> io::Result<Plain<UnixStream, FB>> {
+        let (stream, _) = listener.accept()?;
+        stream.set_nonblocking(true)?;
+        Ok(Plain::new(stream))
+    }
+
+    /// Creates two streams connected to each other through an unnamed unix socket pair, e.g.
+    /// to hand one end to a child process. Both are in blocking mode.
+    pub fn pair_unix() -> io::Result<(Plain<UnixStream, FB>, Plain<UnixStream, FB>)> {
+        let (a, b) = UnixStream::pair()?;
+        Ok((Plain::new(a), Plain::new(b)))
+    }
+}
+
+impl<S, FB> Blocking for Plain<S, FB>
+where
+    S: Read + Write,
+    FB: FrameBuilder,
+{
+    fn b_recv(&mut self) -> Result<Box<dyn Frame>, crate::Error> {
+        let span = self.spans.recv("b_recv");
+        let result = self.recv_blocking();
+        span.received(&result);
+        Ok(result?)
+    }
+
+    fn b_send(&mut self, frame: &dyn Frame) -> Result<(), crate::Error> {
+        self.b_send_written(frame)?;
+        Ok(())
+    }
+
+    fn b_send_all(&mut self, frames: &[&dyn Frame]) -> Result<(), crate::Error> {
+        let span = self.spans.send_all("b_send_all", frames);
+        let result = self.send_all(frames, true);
+        span.done(&result);
+        Ok(result?)
+    }
+}
+
+impl<S, FB> NonBlocking for Plain<S, FB>
+where
+    S: Read + Write,
+    FB: FrameBuilder,
+{
+    fn nb_recv(&mut self) -> Result<Vec<Box<dyn Frame>>, crate::Error> {
+        // Allocated by the first frame pushed, so polls that would block allocate nothing
+        let mut frames = Vec::<Box<dyn Frame>>::new();
+        self.nb_recv_into(&mut frames)?;
+        Ok(frames)
+    }
+
+    fn nb_recv_into(&mut self, frames: &mut Vec<Box<dyn Frame>>) -> Result<usize, crate::Error> {
+        let span = self.spans.recv("nb_recv");
+        let num_frames = frames.len();
+        let result = self.recv_nonblocking(frames);
+        span.received_many(&frames[num_frames..], &result);
+        Ok(result?)
+    }
+
+    fn nb_send(&mut self, frame: &dyn Frame) -> Result<(), crate::Error> {
+        let span = self.spans.send("nb_send", frame);
+        let result = self.transform_tx(frame).and_then(|transformed| {
+            self.nb_send_queued(QueuedFrame::new(transformed.as_deref().unwrap_or(frame)))
+        });
+        span.done(&result);
+        Ok(result?)
+    }
+
+    fn nb_send_all(&mut self, frames: &[&dyn Frame]) -> Result<(), crate::Error> {
+        let span = self.spans.send_all("nb_send_all", frames);
+        let result = self.send_all(frames, false);
+        span.done(&result);
+        Ok(result?)
+    }
 
-        if num_written < out_buf.len() {
-            let out_buf_len = out_buf.len();
-            self.tx_buf
-                .extend_from_slice(&out_buf[num_written..out_buf_len]);
+    fn nb_recv_retry_at(&self) -> Option<Instant> {
+        self.rx_limit.as_ref().and_then(|l| l.reads_resume_at())
+    }
+
+    fn nb_flush(&mut self) -> Result<bool, crate::Error> {
+        Ok(self.flush_tx()? == 0)
+    }
+}
+
+impl<S, FB> Plain<S, FB>
+where
+    S: Read + Write + TryClone,
+    FB: FrameBuilder,
+{
+    /// Creates a second stream over a duplicate handle to the same connection, e.g. to
+    /// receive on one thread while sending on another.
+    ///
+    /// The clone starts with empty buffers, so frames already received or still queued stay
+    /// with this stream, and bytes arriving afterwards go to whichever stream reads them
+    /// first. It decodes and transforms frames the same as this stream. Rate limits,
+    /// deadlines, watermarks, the send scheduler and queue cap, TCP options, cancellation,
+    /// timeouts and registration are not carried over. Fails if this stream is closed.
+    ///
+    /// Streams are not `Clone`, as copying buffered bytes into two streams reading the same
+    /// connection would deliver frames twice:
+    ///
+    /// ```compile_fail
+    /// use std::net::TcpStream;
+    /// use simple_stream::frame::SimpleFrameBuilder;
+    /// use simple_stream::Plain;
+    ///
+    /// fn split(stream: Plain<TcpStream, SimpleFrameBuilder>) {
+    ///     let reader = stream.clone();
+    ///     let writer = stream;
+    /// }
+    /// ```
+    pub fn try_clone(&self) -> Result<Plain<S, FB>, Error> {
+        self.ensure_open()?;
+        let mut clone = Plain::new(self.inner.try_clone()?);
+        clone.decoder = self.decoder.clone();
+        clone.tx_transform = self.tx_transform.clone();
+        clone.rx_transform = self.rx_transform.clone();
+        Ok(clone)
+    }
+}
+
+#[cfg(unix)]
+impl<S, FB> Plain<S, FB>
+where
+    S: Read + Write + AsRawFd,
+    FB: FrameBuilder,
+{
+    /// Returns up to `max` bytes pending on the underlying socket without reading them into
+    /// this stream's buffers. Bytes already buffered by previous reads are not included.
+    pub fn peek_socket(&self, max: usize) -> io::Result<Vec<u8>> {
+        peek_fd(self.inner.as_raw_fd(), max)
+    }
+
+    /// Applies `options` to every subsequent send and receive on this stream.
+    pub fn set_tcp_options(&mut self, options: TcpOptions) {
+        self.tcp = Some(TcpTuning::new(self.inner.as_raw_fd(), options));
+    }
+
+    /// Makes blocking sends and receives on this stream return `ErrorKind::Interrupted` once
+    /// `token` is cancelled, instead of waiting on the socket indefinitely.
+    pub fn set_cancellation_token(&mut self, token: CancellationToken) {
+        self.cancel = Some(Cancellable::new(self.inner.as_raw_fd(), token));
+    }
+
+    /// Makes blocking receives fail with an `IoTimeout` once nothing has been received for
+    /// `timeout`, instead of waiting indefinitely, or removes the timeout if `None`. The stream
+    /// stays open after a timeout.
+    pub fn set_recv_timeout(&mut self, timeout: Option<Duration>) {
+        let fd = self.inner.as_raw_fd();
+        let timeouts = self.timeouts.get_or_insert_with(|| Timeouts::new(fd));
+        timeouts.set(Interest::Readable, timeout);
+    }
 
-            return Err(Error::new(ErrorKind::WouldBlock, "WouldBlock"));
+    /// Makes blocking sends fail with an `IoTimeout` once nothing could be written for
+    /// `timeout`, instead of waiting indefinitely, or removes the timeout if `None`. What was
+    /// not written yet stays queued, and goes out with the next send or flush.
+    pub fn set_send_timeout(&mut self, timeout: Option<Duration>) {
+        let fd = self.inner.as_raw_fd();
+        let timeouts = self.timeouts.get_or_insert_with(|| Timeouts::new(fd));
+        timeouts.set(Interest::Writable, timeout);
+    }
+
+    /// Records the file descriptor of the underlying socket in the `fd` field of this
+    /// stream's tracing spans, which is otherwise left empty.
+    #[cfg(feature = "tracing")]
+    pub fn trace_fd(&mut self) {
+        let fd = self.as_raw_fd();
+        self.spans.set_fd(fd as i64);
+    }
+
+    /// Enables `IP_RECVERR` on the underlying socket, so ICMP errors such as port unreachable
+    /// fail the stream as soon as they arrive. The stream is then closed with a
+    /// `CloseReason::TransportError` carrying the `IcmpError`, which can be retrieved with
+    /// `get_ref()` and `downcast_ref::<IcmpError>()`. Linux only.
+    pub fn enable_icmp_errors(&mut self) -> io::Result<()> {
+        let fd = self.inner.as_raw_fd();
+        set_recv_err(&fd, true)?;
+        self.icmp_fd = Some(fd);
+        Ok(())
+    }
+
+    /// Shuts down both halves of the underlying socket. Subsequent sends and receives fail
+    /// with `CloseReason::LocalShutdown`. Does nothing if the connection already terminated.
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        if self.close_reason.is_some() {
+            return Ok(());
+        }
+
+        let result = unsafe { libc::shutdown(self.inner.as_raw_fd(), libc::SHUT_RDWR) };
+        self.close(CloseReason::LocalShutdown);
+        if result < 0 {
+            return Err(Error::last_os_error());
         }
 
         Ok(())
     }
+
+    /// Waits up to `timeout` for everything queued to be written, then shuts down the socket
+    /// like `shutdown`. Returns whether every queued frame was written, otherwise the rest is
+    /// dropped. Returns `false` right away if the connection already terminated with frames
+    /// queued.
+    pub fn close_graceful(&mut self, timeout: Duration) -> io::Result<bool> {
+        if self.close_reason.is_some() {
+            return Ok(self.tx_pending == 0);
+        }
+
+        let delivered = drain_tx(self.inner.as_raw_fd(), timeout, || self.flush_tx());
+        let shutdown = self.shutdown();
+        let delivered = delivered?;
+        shutdown?;
+        Ok(delivered)
+    }
 }
 
+#[cfg(unix)]
 impl<S, FB> AsRawFd for Plain<S, FB>
 where
     S: Read + Write + AsRawFd,
@@ -179,3 +1274,214 @@ where
         self.inner.as_raw_fd()
     }
 }
+
+#[cfg(windows)]
+impl<S, FB> Plain<S, FB>
+where
+    S: Read + Write + AsRawSocket,
+    FB: FrameBuilder,
+{
+    /// Returns up to `max` bytes pending on the underlying socket without reading them into
+    /// this stream's buffers. Bytes already buffered by previous reads are not included.
+    pub fn peek_socket(&self, max: usize) -> io::Result<Vec<u8>> {
+        peek_raw_socket(self.inner.as_raw_socket(), max)
+    }
+
+    /// Shuts down both halves of the underlying socket. Subsequent sends and receives fail
+    /// with `CloseReason::LocalShutdown`. Does nothing if the connection already terminated.
+    pub fn shutdown(&mut self) -> io::Result<()> {
+        if self.close_reason.is_some() {
+            return Ok(());
+        }
+
+        let result = shutdown_raw_socket(self.inner.as_raw_socket(), Shutdown::Both);
+        self.close(CloseReason::LocalShutdown);
+        result
+    }
+}
+
+#[cfg(windows)]
+impl<S, FB> AsRawSocket for Plain<S, FB>
+where
+    S: Read + Write + AsRawSocket,
+    FB: FrameBuilder,
+{
+    fn as_raw_socket(&self) -> RawSocket {
+        self.inner.as_raw_socket()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{
+        LengthPrefixed32Frame, LengthPrefixed32FrameBuilder, SimpleFrame, SimpleFrameBuilder,
+    };
+    use crate::testing::{MockStep, MockStream};
+
+    /// Returns everything sent to `remote` so far.
+    fn received(remote: &mut impl Read) -> Vec<u8> {
+        let mut bytes = Vec::<u8>::new();
+        let mut buf = [0u8; 256];
+        while let Ok(len @ 1..) = remote.read(&mut buf) {
+            bytes.extend_from_slice(&buf[..len]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn b_send_writes_every_byte_a_few_at_a_time() {
+        let (mut local, mut remote) = Duplex::pair();
+        local.set_max_write(3);
+        let mut stream = Plain::<_, SimpleFrameBuilder>::new(local);
+
+        let frame = SimpleFrame::new(&[7u8; 100][..]);
+        let bytes = frame.to_bytes();
+        assert_eq!(stream.b_send_written(&frame).unwrap(), bytes.len());
+        assert_eq!(received(&mut remote), bytes);
+    }
+
+    #[test]
+    fn b_send_retries_interrupted_writes() {
+        let (local, mut remote) = MockStream::pair();
+        let mut stream = Plain::<_, SimpleFrameBuilder>::new(local);
+        stream.get_mut().script_writes(&[
+            MockStep::Interrupted,
+            MockStep::Partial(2),
+            MockStep::Interrupted,
+            MockStep::Partial(1),
+        ]);
+
+        let frame = SimpleFrame::new(b"interrupted");
+        stream.b_send(&frame).unwrap();
+        assert_eq!(stream.get_ref().steps_remaining(), 0);
+        assert_eq!(received(&mut remote), frame.to_bytes());
+    }
+
+    #[test]
+    fn b_send_all_writes_frames_in_order() {
+        let (mut local, mut remote) = Duplex::pair();
+        local.set_max_write(5);
+        let mut stream = Plain::<_, SimpleFrameBuilder>::new(local);
+
+        let frames = [
+            SimpleFrame::new(b"one"),
+            SimpleFrame::new(b"two"),
+            SimpleFrame::new(b"three"),
+        ];
+        let refs: Vec<&dyn Frame> = frames.iter().map(|f| f as &dyn Frame).collect();
+        stream.b_send_all(&refs).unwrap();
+
+        let expected: Vec<u8> = frames.iter().flat_map(|f| f.to_bytes()).collect();
+        assert_eq!(received(&mut remote), expected);
+    }
+
+    #[test]
+    fn b_send_fails_rather_than_truncating() {
+        let (local, _remote) = MockStream::pair();
+        let mut stream = Plain::<_, SimpleFrameBuilder>::new(local);
+        stream.get_mut().script_writes(&[
+            MockStep::Partial(4),
+            MockStep::Fail(ErrorKind::ConnectionReset),
+        ]);
+
+        // A reset connection is a transport failure, not the peer closing cleanly
+        let result = stream.b_send(&SimpleFrame::new(b"reset"));
+        match result {
+            Err(crate::Error::Io(ref e)) if e.kind() == ErrorKind::ConnectionReset => {}
+            other => panic!("expected ConnectionReset, got {:?}", other.err()),
+        }
+        match stream.close_reason() {
+            Some(CloseReason::TransportError(e)) => {
+                assert_eq!(e.kind(), ErrorKind::ConnectionReset)
+            }
+            other => panic!("expected TransportError, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn b_send_writes_after_what_nb_send_left_queued() {
+        let (local, mut remote) = MockStream::pair();
+        let mut stream = Plain::<_, SimpleFrameBuilder>::new(local);
+        stream
+            .get_mut()
+            .script_writes(&[MockStep::Partial(3), MockStep::WouldBlock]);
+
+        let first = SimpleFrame::new(b"first");
+        let second = SimpleFrame::new(b"second");
+        match stream.nb_send(&first) {
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+            other => panic!("expected WouldBlock, got {:?}", other),
+        }
+        assert_eq!(received(&mut remote), &first.to_bytes()[..3]);
+
+        stream.b_send(&second).unwrap();
+        let mut expected = first.to_bytes()[3..].to_vec();
+        expected.extend(second.to_bytes());
+        assert_eq!(received(&mut remote), expected);
+        assert!(stream.nb_flush().unwrap());
+    }
+
+    #[test]
+    fn nb_recv_returns_frames_b_recv_left_buffered() {
+        let (local, mut remote) = MockStream::pair();
+        let mut stream = Plain::<_, SimpleFrameBuilder>::new(local);
+
+        let frames = [SimpleFrame::new(b"first"), SimpleFrame::new(b"second")];
+        for frame in frames.iter() {
+            remote.write_all(&frame.to_bytes()).unwrap();
+        }
+
+        assert_eq!(stream.b_recv().unwrap().payload(), b"first");
+        let received = stream.nb_recv().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].payload(), b"second");
+    }
+
+    #[test]
+    fn b_recv_completes_a_frame_nb_recv_started() {
+        let (local, mut remote) = MockStream::pair();
+        let mut stream = Plain::<_, SimpleFrameBuilder>::new(local);
+
+        let bytes = [
+            SimpleFrame::new(b"first").to_bytes(),
+            SimpleFrame::new(b"second").to_bytes(),
+        ]
+        .concat();
+        let split = bytes.len() - 4;
+        remote.write_all(&bytes[..split]).unwrap();
+        let received = stream.nb_recv().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].payload(), b"first");
+
+        remote.write_all(&bytes[split..]).unwrap();
+        assert_eq!(stream.b_recv().unwrap().payload(), b"second");
+        match stream.nb_recv() {
+            Err(ref e) if e.kind() == ErrorKind::WouldBlock => {}
+            other => panic!("expected WouldBlock, got {:?}", other.map(|f| f.len())),
+        }
+    }
+
+    #[test]
+    fn nb_recv_closes_on_a_length_past_the_maximum() {
+        let (local, mut remote) = MockStream::pair();
+        let mut stream = Plain::<_, LengthPrefixed32FrameBuilder>::new(local);
+
+        let mut bytes = LengthPrefixed32Frame::new(b"first").to_bytes();
+        bytes.extend_from_slice(&[0xff; 8]);
+        remote.write_all(&bytes).unwrap();
+
+        // The frame in front of the bad length is still returned
+        let received = stream.nb_recv().unwrap();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].payload(), b"first");
+        assert!(matches!(
+            stream.close_reason(),
+            Some(CloseReason::ProtocolError)
+        ));
+        assert!(matches!(
+            stream.nb_recv(),
+            Err(crate::Error::ProtocolViolation)
+        ));
+    }
+}
@@ -0,0 +1,389 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Negotiates the frame format and compression of a plain text connection before any frames
+//! are exchanged, for protocols where ALPN is not available. Each side sends a preamble
+//! listing what it supports, most preferred first, and the acceptor's most preferred option
+//! that the initiator also supports is used.
+//!
+//! ```ignore
+//! 0                   1                   2                   3
+//! 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |                         Magic ("SSPR")                        |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! |    Version    | Format Count  |     Formats ...               |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//! | Compr. Count  |     Compressions ...                          |
+//! +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//!
+//! Magic:          The ASCII bytes "SSPR".
+//! Version:        Unsigned 8-bit integer, currently 1.
+//! Format Count:   Unsigned 8-bit integer.
+//! Formats:        Format Count 8-bit frame format identifiers.
+//! Compr. Count:   Unsigned 8-bit integer.
+//! Compressions:   Compr. Count 8-bit compression identifiers.
+//! ```
+
+use std::io::{self, Read, Write};
+use std::time::Instant;
+
+use crate::frame::{
+    Checksum32FrameBuilder, Frame, FrameBuilderInfo, HeaderedFrameBuilder, ProtocolInfo,
+    SimpleFrameBuilder, Tag16, TlvFrameBuilder, WebSocketFrameBuilder,
+};
+use crate::{Blocking, Error, NonBlocking, Plain};
+
+const MAGIC: &[u8; 4] = b"SSPR";
+const PREAMBLE_VERSION: u8 = 1;
+
+/// A frame format that can be negotiated by a preamble.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FrameFormat {
+    Simple,
+    WebSocket,
+    Checksum32,
+    Headered,
+    Tlv8,
+    Tlv16,
+}
+
+/// A compression scheme that can be negotiated by a preamble. Schemes other than `None` are
+/// available with the feature of the same name in lowercase.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Compression {
+    None,
+    /// Raw DEFLATE, as in RFC 1951, by `flate2`.
+    #[cfg(feature = "flate2")]
+    Deflate,
+    /// Zstandard, at its default level, by `zstd`.
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+/// Which end of the connection a side is, deciding whose preference order wins.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PreambleRole {
+    Initiator,
+    Acceptor,
+}
+
+/// What one side of a connection supports, most preferred first.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Preamble {
+    pub formats: Vec<FrameFormat>,
+    pub compressions: Vec<Compression>,
+}
+
+/// The outcome of a preamble exchange.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Negotiated {
+    pub format: FrameFormat,
+    pub compression: Compression,
+}
+
+/// A `Plain` stream using whichever frame format was negotiated.
+pub enum NegotiatedStream<S: Read + Write> {
+    Simple(Plain<S, SimpleFrameBuilder>),
+    WebSocket(Plain<S, WebSocketFrameBuilder>),
+    Checksum32(Plain<S, Checksum32FrameBuilder>),
+    Headered(Plain<S, HeaderedFrameBuilder>),
+    Tlv8(Plain<S, TlvFrameBuilder>),
+    Tlv16(Plain<S, TlvFrameBuilder<Tag16>>),
+}
+
+impl FrameFormat {
+    /// Describes this frame format.
+    pub fn info(&self) -> ProtocolInfo {
+        match *self {
+            FrameFormat::Simple => SimpleFrameBuilder::info(),
+            FrameFormat::WebSocket => WebSocketFrameBuilder::info(),
+            FrameFormat::Checksum32 => Checksum32FrameBuilder::info(),
+            FrameFormat::Headered => <HeaderedFrameBuilder>::info(),
+            FrameFormat::Tlv8 => <TlvFrameBuilder>::info(),
+            FrameFormat::Tlv16 => TlvFrameBuilder::<Tag16>::info(),
+        }
+    }
+
+    fn id(&self) -> u8 {
+        match *self {
+            FrameFormat::Simple => 1,
+            FrameFormat::WebSocket => 2,
+            FrameFormat::Checksum32 => 3,
+            FrameFormat::Headered => 4,
+            FrameFormat::Tlv8 => 5,
+            FrameFormat::Tlv16 => 6,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<FrameFormat> {
+        match id {
+            1 => Some(FrameFormat::Simple),
+            2 => Some(FrameFormat::WebSocket),
+            3 => Some(FrameFormat::Checksum32),
+            4 => Some(FrameFormat::Headered),
+            5 => Some(FrameFormat::Tlv8),
+            6 => Some(FrameFormat::Tlv16),
+            _ => None,
+        }
+    }
+}
+
+impl Compression {
+    pub(crate) fn id(&self) -> u8 {
+        match *self {
+            Compression::None => 0,
+            #[cfg(feature = "flate2")]
+            Compression::Deflate => 1,
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => 2,
+        }
+    }
+
+    pub(crate) fn from_id(id: u8) -> Option<Compression> {
+        match id {
+            0 => Some(Compression::None),
+            #[cfg(feature = "flate2")]
+            1 => Some(Compression::Deflate),
+            #[cfg(feature = "zstd")]
+            2 => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+}
+
+impl Preamble {
+    /// Creates a preamble supporting `formats`, most preferred first, without compression.
+    pub fn new(formats: &[FrameFormat]) -> Self {
+        Preamble {
+            formats: formats.to_vec(),
+            compressions: vec![Compression::None],
+        }
+    }
+
+    /// Sends this preamble on `stream`, reads the peer's, and picks the best mutual frame
+    /// format and compression. Identifiers the peer sends that this version does not know are
+    /// ignored. `stream` should be in blocking mode.
+    pub fn negotiate<S: Read + Write>(
+        &self,
+        stream: &mut S,
+        role: PreambleRole,
+    ) -> io::Result<Negotiated> {
+        stream.write_all(&self.to_bytes()[..])?;
+        stream.flush()?;
+
+        let theirs = Preamble::read_from(stream)?;
+        let (preferred, other) = match role {
+            PreambleRole::Acceptor => (self, &theirs),
+            PreambleRole::Initiator => (&theirs, self),
+        };
+
+        let format = preferred
+            .formats
+            .iter()
+            .find(|f| other.formats.contains(f))
+            .copied();
+        let compression = preferred
+            .compressions
+            .iter()
+            .find(|c| other.compressions.contains(c))
+            .copied();
+
+        match (format, compression) {
+            (Some(format), Some(compression)) => {
+                debug!("Negotiated {:?} with {:?} compression", format, compression);
+                Ok(Negotiated {
+                    format,
+                    compression,
+                })
+            }
+            (None, _) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "No mutually supported frame format",
+            )),
+            (_, None) => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "No mutually supported compression",
+            )),
+        }
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(8 + self.formats.len() + self.compressions.len());
+        buf.extend_from_slice(MAGIC);
+        buf.push(PREAMBLE_VERSION);
+
+        // Each list is capped at what its count field can describe
+        let formats = &self.formats[..self.formats.len().min(u8::MAX as usize)];
+        buf.push(formats.len() as u8);
+        buf.extend(formats.iter().map(|f| f.id()));
+
+        let compressions = &self.compressions[..self.compressions.len().min(u8::MAX as usize)];
+        buf.push(compressions.len() as u8);
+        buf.extend(compressions.iter().map(|c| c.id()));
+
+        buf
+    }
+
+    /// Reads exactly one preamble from `stream`, leaving any bytes after it unread.
+    fn read_from<S: Read>(stream: &mut S) -> io::Result<Preamble> {
+        let mut header = [0u8; 6];
+        stream.read_exact(&mut header)?;
+        if &header[0..4] != MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Peer did not send a preamble",
+            ));
+        }
+
+        trace!("Preamble version: {}", header[4]);
+
+        let mut formats = vec![0u8; header[5] as usize];
+        stream.read_exact(&mut formats[..])?;
+
+        let mut count = [0u8; 1];
+        stream.read_exact(&mut count)?;
+        let mut compressions = vec![0u8; count[0] as usize];
+        stream.read_exact(&mut compressions[..])?;
+
+        Ok(Preamble {
+            formats: formats
+                .into_iter()
+                .filter_map(FrameFormat::from_id)
+                .collect(),
+            compressions: compressions
+                .into_iter()
+                .filter_map(Compression::from_id)
+                .collect(),
+        })
+    }
+}
+
+macro_rules! with_stream {
+    ($stream:expr, $inner:ident => $body:expr) => {
+        match $stream {
+            NegotiatedStream::Simple($inner) => $body,
+            NegotiatedStream::WebSocket($inner) => $body,
+            NegotiatedStream::Checksum32($inner) => $body,
+            NegotiatedStream::Headered($inner) => $body,
+            NegotiatedStream::Tlv8($inner) => $body,
+            NegotiatedStream::Tlv16($inner) => $body,
+        }
+    };
+}
+
+impl Negotiated {
+    /// Wraps `stream` in a `Plain` stream using the negotiated frame format and compression.
+    pub fn into_stream<S: Read + Write>(self, stream: S) -> NegotiatedStream<S> {
+        let mut stream = match self.format {
+            FrameFormat::Simple => NegotiatedStream::Simple(Plain::new(stream)),
+            FrameFormat::WebSocket => NegotiatedStream::WebSocket(Plain::new(stream)),
+            FrameFormat::Checksum32 => NegotiatedStream::Checksum32(Plain::new(stream)),
+            FrameFormat::Headered => NegotiatedStream::Headered(Plain::new(stream)),
+            FrameFormat::Tlv8 => NegotiatedStream::Tlv8(Plain::new(stream)),
+            FrameFormat::Tlv16 => NegotiatedStream::Tlv16(Plain::new(stream)),
+        };
+
+        with_stream!(&mut stream, plain => plain.set_compression(self.compression));
+        stream
+    }
+}
+
+impl<S: Read + Write> NegotiatedStream<S> {
+    /// Describes the negotiated frame format.
+    pub fn protocol_info(&self) -> ProtocolInfo {
+        with_stream!(self, stream => stream.protocol_info())
+    }
+}
+
+impl<S: Read + Write> Blocking for NegotiatedStream<S> {
+    fn b_recv(&mut self) -> Result<Box<dyn Frame>, Error> {
+        with_stream!(self, stream => stream.b_recv())
+    }
+
+    fn b_send(&mut self, frame: &dyn Frame) -> Result<(), Error> {
+        with_stream!(self, stream => stream.b_send(frame))
+    }
+}
+
+impl<S: Read + Write> NonBlocking for NegotiatedStream<S> {
+    fn nb_recv(&mut self) -> Result<Vec<Box<dyn Frame>>, Error> {
+        with_stream!(self, stream => stream.nb_recv())
+    }
+
+    fn nb_recv_into(&mut self, frames: &mut Vec<Box<dyn Frame>>) -> Result<usize, Error> {
+        with_stream!(self, stream => stream.nb_recv_into(frames))
+    }
+
+    fn nb_send(&mut self, frame: &dyn Frame) -> Result<(), Error> {
+        with_stream!(self, stream => stream.nb_send(frame))
+    }
+
+    fn nb_recv_retry_at(&self) -> Option<Instant> {
+        with_stream!(self, stream => stream.nb_recv_retry_at())
+    }
+
+    fn nb_flush(&mut self) -> Result<bool, Error> {
+        with_stream!(self, stream => stream.nb_flush())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unknown_identifiers_are_ignored() {
+        let mut bytes = Preamble::new(&[FrameFormat::Tlv16, FrameFormat::Simple]).to_bytes();
+        // Two formats and one compression, each list led by an identifier from the future
+        bytes[5] += 1;
+        bytes.insert(6, 0xff);
+        let last = bytes.len() - 2;
+        bytes[last] += 1;
+        bytes.insert(last + 1, 0xff);
+        bytes.extend_from_slice(b"rest");
+
+        let mut reader = &bytes[..];
+        let preamble = Preamble::read_from(&mut reader).unwrap();
+        assert_eq!(
+            preamble,
+            Preamble::new(&[FrameFormat::Tlv16, FrameFormat::Simple])
+        );
+        assert_eq!(reader, b"rest");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn the_acceptors_preference_wins() {
+        use std::os::unix::net::UnixStream;
+        use std::thread;
+
+        use crate::frame::Checksum32Frame;
+
+        let (mut local, mut remote) = UnixStream::pair().unwrap();
+        let acceptor = thread::spawn(move || {
+            let preamble = Preamble::new(&[FrameFormat::Checksum32, FrameFormat::Simple]);
+            let negotiated = preamble
+                .negotiate(&mut remote, PreambleRole::Acceptor)
+                .unwrap();
+            let mut stream = negotiated.into_stream(remote);
+            stream.b_send(&Checksum32Frame::new(b"hello")).unwrap();
+            negotiated
+        });
+
+        let preamble = Preamble::new(&[FrameFormat::Simple, FrameFormat::Checksum32]);
+        let negotiated = preamble
+            .negotiate(&mut local, PreambleRole::Initiator)
+            .unwrap();
+        assert_eq!(negotiated, acceptor.join().unwrap());
+        assert_eq!(negotiated.format, FrameFormat::Checksum32);
+
+        let mut stream = negotiated.into_stream(local);
+        assert_eq!(stream.protocol_info().name, "checksum32");
+        assert_eq!(stream.b_recv().unwrap().payload(), b"hello");
+    }
+}
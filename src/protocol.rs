@@ -0,0 +1,117 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Named protocol profiles, so every service speaking a protocol frames and polices its
+//! connections the same way.
+//!
+//! ```ignore
+//! pub fn telemetry() -> Protocol {
+//!     Protocol {
+//!         max_frame_len: Some(64 * 1024),
+//!         idle_timeout: Some(Duration::from_secs(60)),
+//!         heartbeat: Some(HeartbeatConfig {
+//!             interval: Duration::from_secs(15),
+//!             timeout: Duration::from_secs(45),
+//!         }),
+//!         ..Protocol::of::<LengthPrefixed32FrameBuilder>("telemetry/1")
+//!     }
+//! }
+//!
+//! let stream = Plain::with_protocol(socket, &telemetry());
+//! ```
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::frame::{BuilderDecoder, FrameBuilder, FrameDecoder};
+use crate::liveness::HeartbeatConfig;
+use crate::preamble::Compression;
+
+/// Everything a stream needs to know to speak a protocol: how frames are decoded, and the
+/// limits and settings applied to its connections. Streams are created from one with
+/// `Plain::with_protocol` or `Secure::with_protocol`.
+#[derive(Clone)]
+pub struct Protocol {
+    /// Identifies the protocol in logs.
+    pub name: &'static str,
+    pub decoder: Box<dyn FrameDecoder>,
+    /// See `Plain::set_max_frame_len`.
+    pub max_frame_len: Option<usize>,
+    /// See `Plain::set_idle_timeout`.
+    pub idle_timeout: Option<Duration>,
+    /// See `Plain::set_heartbeat`.
+    pub heartbeat: Option<HeartbeatConfig>,
+    pub compression: Compression,
+}
+
+impl Protocol {
+    /// Creates a profile named `name` decoding frames with `FB`, without limits.
+    pub fn of<FB: FrameBuilder>(name: &'static str) -> Protocol {
+        Protocol::with_decoder(name, BuilderDecoder::of::<FB>())
+    }
+
+    /// Creates a profile named `name` decoding frames with `decoder`, without limits.
+    pub fn with_decoder<D>(name: &'static str, decoder: D) -> Protocol
+    where
+        D: FrameDecoder + 'static,
+    {
+        Protocol {
+            name,
+            decoder: Box::new(decoder),
+            max_frame_len: None,
+            idle_timeout: None,
+            heartbeat: None,
+            compression: Compression::None,
+        }
+    }
+}
+
+impl fmt::Debug for Protocol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Protocol")
+            .field("name", &self.name)
+            .field("max_frame_len", &self.max_frame_len)
+            .field("idle_timeout", &self.idle_timeout)
+            .field("heartbeat", &self.heartbeat)
+            .field("compression", &self.compression)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duplex::Duplex;
+    use crate::frame::{LengthPrefixed32Frame, LengthPrefixed32FrameBuilder};
+    use crate::{Error, NonBlocking, Plain};
+
+    #[test]
+    fn streams_decode_and_police_frames_as_their_protocol_says() {
+        let protocol = Protocol {
+            max_frame_len: Some(16),
+            ..Protocol::of::<LengthPrefixed32FrameBuilder>("test/1")
+        };
+        let (a, b) = Duplex::pair();
+        let mut local = Plain::with_protocol(a, &protocol.clone());
+        let mut remote = Plain::<_, LengthPrefixed32FrameBuilder>::new(b);
+
+        remote
+            .nb_send(&LengthPrefixed32Frame::new(b"small"))
+            .unwrap();
+        let frames = local.nb_recv().unwrap();
+        assert_eq!(frames[0].kind(), "LengthPrefixed32Frame");
+        assert_eq!(frames[0].payload(), b"small");
+
+        remote
+            .nb_send(&LengthPrefixed32Frame::new(&[0; 32]))
+            .unwrap();
+        assert!(matches!(
+            local.nb_recv(),
+            Err(Error::FrameTooLarge { max: 16, .. })
+        ));
+    }
+}
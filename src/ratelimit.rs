@@ -0,0 +1,258 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::buffer::RecvBuffer;
+use crate::frame::{Frame, FrameDecoder};
+use crate::resync::{decode_unsynced, Resync};
+
+/// What a stream does with frames received faster than its `FrameRateLimit` allows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RateLimitAction {
+    /// Stops reading until the peer is back under the limit. Blocking receives sleep, and
+    /// non-blocking receives leave the socket unread and return `ErrorKind::WouldBlock`, so
+    /// callers using edge-triggered readiness should retry at `nb_recv_retry_at`, as
+    /// `Reactor` does.
+    Delay,
+    /// Discards the frame and counts it in `rx_frames_dropped`.
+    Drop,
+    /// Discards the frame, counts it in `rx_frames_dropped`, and fails the receive with a
+    /// `RateExceeded` error. The stream stays open.
+    Fail,
+}
+
+/// Maximum rate a peer may send frames at, independent of their size.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameRateLimit {
+    /// Frames per second allowed on average.
+    pub frames_per_sec: u32,
+    /// Frames allowed back to back before the average rate is enforced.
+    pub burst: u32,
+    pub action: RateLimitAction,
+}
+
+/// Error carried by the `Error::Io` a receive fails with under `RateLimitAction::Fail`.
+/// Retrieve it with `get_ref()` and `downcast_ref::<RateExceeded>()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct RateExceeded {
+    pub frames_per_sec: u32,
+}
+
+/// Enforces a `FrameRateLimit` as a generic cell rate algorithm, tracking the theoretical
+/// arrival time of the next frame.
+#[derive(Clone, Debug)]
+pub(crate) struct FrameRateLimiter {
+    limit: FrameRateLimit,
+    interval: Duration,
+    tolerance: Duration,
+    next_arrival: Instant,
+    dropped: u64,
+    /// Whether a non-blocking receive was held back since reads were last let through.
+    held_back: bool,
+}
+
+impl FrameRateLimit {
+    /// Allows `frames_per_sec` frames per second, with bursts of up to a second's worth.
+    pub fn new(frames_per_sec: u32, action: RateLimitAction) -> FrameRateLimit {
+        FrameRateLimit {
+            frames_per_sec,
+            burst: frames_per_sec,
+            action,
+        }
+    }
+}
+
+impl FrameRateLimiter {
+    pub(crate) fn new(limit: FrameRateLimit) -> FrameRateLimiter {
+        let interval = Duration::from_secs(1) / limit.frames_per_sec.max(1);
+        FrameRateLimiter {
+            limit,
+            interval,
+            tolerance: interval * limit.burst.saturating_sub(1),
+            next_arrival: Instant::now(),
+            dropped: 0,
+            held_back: false,
+        }
+    }
+
+    pub(crate) fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Returns `true` if reads should be held off because the peer is over the limit.
+    pub(crate) fn defers_reads(&self) -> bool {
+        self.limit.action == RateLimitAction::Delay && !self.wait_time().is_zero()
+    }
+
+    /// Same as `defers_reads`, for a non-blocking receive about to read, remembering whether
+    /// it was held back.
+    pub(crate) fn holds_back_reads(&mut self) -> bool {
+        self.held_back = self.defers_reads();
+        self.held_back
+    }
+
+    /// Returns when a non-blocking receive that was held back, possibly leaving bytes unread,
+    /// should be retried, or `None` if none was.
+    pub(crate) fn reads_resume_at(&self) -> Option<Instant> {
+        if !self.held_back {
+            return None;
+        }
+        Some(Instant::now() + self.wait_time())
+    }
+
+    /// Returns how long until another frame is allowed.
+    fn wait_time(&self) -> Duration {
+        let allowed_at = self
+            .next_arrival
+            .checked_sub(self.tolerance)
+            .unwrap_or(self.next_arrival);
+        allowed_at.saturating_duration_since(Instant::now())
+    }
+
+    fn admit(&mut self) {
+        self.next_arrival = self.next_arrival.max(Instant::now()) + self.interval;
+    }
+
+    fn exceeded(&mut self) -> io::Error {
+        self.dropped += 1;
+        trace!("Frame over the rate limit dropped");
+        io::Error::other(RateExceeded {
+            frames_per_sec: self.limit.frames_per_sec,
+        })
+    }
+}
+
+/// Removes the next complete frame from `buf` using `decoder`, subject to `limiter` if one is
+/// set, and recovering from corrupt bytes with `resync` if set, or failing on them if not.
+/// `blocking` decides whether `RateLimitAction::Delay` sleeps or reports no frame.
+pub(crate) fn decode_limited(
+    decoder: &mut dyn FrameDecoder,
+    buf: &mut RecvBuffer,
+    limiter: Option<&mut FrameRateLimiter>,
+    mut resync: Option<&mut Resync>,
+    blocking: bool,
+) -> io::Result<Option<Box<dyn Frame>>> {
+    let mut decode = |buf: &mut RecvBuffer| match resync {
+        Some(ref mut resync) => resync.decode(decoder, buf),
+        None => decode_unsynced(decoder, buf),
+    };
+    let limiter = match limiter {
+        Some(limiter) => limiter,
+        None => return decode(buf),
+    };
+
+    loop {
+        let wait = limiter.wait_time();
+        if wait.is_zero() {
+            let frame = decode(buf)?;
+            if frame.is_some() {
+                limiter.admit();
+            }
+            return Ok(frame);
+        }
+
+        match limiter.limit.action {
+            RateLimitAction::Delay if blocking => {
+                trace!("Over the frame rate limit, delaying reads for {:?}", wait);
+                thread::sleep(wait);
+            }
+            RateLimitAction::Delay => {
+                limiter.held_back = true;
+                return Ok(None);
+            }
+            RateLimitAction::Drop => match decode(buf)? {
+                Some(_) => {
+                    limiter.exceeded();
+                }
+                None => return Ok(None),
+            },
+            RateLimitAction::Fail => match decode(buf)? {
+                Some(_) => return Err(limiter.exceeded()),
+                None => return Ok(None),
+            },
+        }
+    }
+}
+
+impl fmt::Display for RateExceeded {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Peer exceeded {} frame(s) per second",
+            self.frames_per_sec
+        )
+    }
+}
+
+impl Error for RateExceeded {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{BuilderDecoder, SimpleFrame, SimpleFrameBuilder};
+
+    /// Returns a buffer holding `count` frames, and a limiter allowing two of them.
+    fn setup(count: usize, action: RateLimitAction) -> (RecvBuffer, FrameRateLimiter) {
+        let mut buf = RecvBuffer::with_capacity(0);
+        for _ in 0..count {
+            buf.extend_from_slice(&SimpleFrame::new(b"hi").to_bytes());
+        }
+        let limiter = FrameRateLimiter::new(FrameRateLimit {
+            frames_per_sec: 1,
+            burst: 2,
+            action,
+        });
+        (buf, limiter)
+    }
+
+    fn decode(
+        buf: &mut RecvBuffer,
+        limiter: &mut FrameRateLimiter,
+    ) -> io::Result<Option<Box<dyn Frame>>> {
+        let mut decoder = BuilderDecoder::of::<SimpleFrameBuilder>();
+        decode_limited(&mut decoder, buf, Some(limiter), None, false)
+    }
+
+    #[test]
+    fn frames_past_the_burst_are_dropped() {
+        let (mut buf, mut limiter) = setup(4, RateLimitAction::Drop);
+        assert!(decode(&mut buf, &mut limiter).unwrap().is_some());
+        assert!(decode(&mut buf, &mut limiter).unwrap().is_some());
+        assert!(decode(&mut buf, &mut limiter).unwrap().is_none());
+        assert_eq!(limiter.dropped(), 2);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn frames_past_the_burst_fail_one_receive_each() {
+        let (mut buf, mut limiter) = setup(3, RateLimitAction::Fail);
+        assert!(decode(&mut buf, &mut limiter).unwrap().is_some());
+        assert!(decode(&mut buf, &mut limiter).unwrap().is_some());
+
+        let e = decode(&mut buf, &mut limiter).err().unwrap();
+        let exceeded = e.get_ref().unwrap().downcast_ref::<RateExceeded>();
+        assert_eq!(exceeded, Some(&RateExceeded { frames_per_sec: 1 }));
+        assert!(decode(&mut buf, &mut limiter).unwrap().is_none());
+    }
+
+    #[test]
+    fn delayed_frames_stay_buffered() {
+        let (mut buf, mut limiter) = setup(3, RateLimitAction::Delay);
+        assert!(decode(&mut buf, &mut limiter).unwrap().is_some());
+        assert!(decode(&mut buf, &mut limiter).unwrap().is_some());
+        assert!(decode(&mut buf, &mut limiter).unwrap().is_none());
+
+        assert_eq!(buf.len(), SimpleFrame::new(b"hi").to_bytes().len());
+        assert!(limiter.defers_reads());
+        assert!(limiter.reads_resume_at().is_some());
+    }
+}
@@ -0,0 +1,160 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::io;
+use std::os::unix::io::RawFd;
+use std::ptr;
+use std::time::Duration;
+
+use super::{Event, MAX_EVENTS};
+
+/// Events after which the stream is read, which reports hang ups and errors as it does.
+const READ_EVENTS: u32 =
+    (libc::EPOLLIN | libc::EPOLLRDHUP | libc::EPOLLHUP | libc::EPOLLERR) as u32;
+
+/// An epoll instance, closed when dropped.
+pub(super) struct Poller {
+    fd: RawFd,
+    events: Vec<libc::epoll_event>,
+}
+
+impl Poller {
+    pub(super) fn new() -> io::Result<Poller> {
+        let fd = unsafe { libc::epoll_create1(libc::EPOLL_CLOEXEC) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(Poller {
+            fd,
+            events: Vec::with_capacity(MAX_EVENTS),
+        })
+    }
+
+    /// Watches `fd` edge triggered for reads, writes and hang ups, reporting them with `token`.
+    pub(super) fn add(&self, fd: RawFd, token: u64) -> io::Result<()> {
+        let flags = libc::EPOLLIN | libc::EPOLLOUT | libc::EPOLLRDHUP | libc::EPOLLET;
+        let mut event = libc::epoll_event {
+            events: flags as u32,
+            u64: token,
+        };
+        let result = unsafe { libc::epoll_ctl(self.fd, libc::EPOLL_CTL_ADD, fd, &mut event) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    pub(super) fn delete(&self, fd: RawFd) -> io::Result<()> {
+        let result = unsafe { libc::epoll_ctl(self.fd, libc::EPOLL_CTL_DEL, fd, ptr::null_mut()) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    /// Waits up to `timeout` for events, forever if `None`. Returns no events if interrupted
+    /// by a signal.
+    pub(super) fn wait(&mut self, timeout: Option<Duration>) -> io::Result<Vec<Event>> {
+        // Rounded up, so a short timeout does not turn into a busy loop
+        let timeout_ms = match timeout {
+            Some(timeout) => timeout
+                .as_nanos()
+                .div_ceil(1_000_000)
+                .min(libc::c_int::MAX as u128) as libc::c_int,
+            None => -1,
+        };
+
+        let num_events = unsafe {
+            libc::epoll_wait(
+                self.fd,
+                self.events.as_mut_ptr(),
+                MAX_EVENTS as libc::c_int,
+                timeout_ms,
+            )
+        };
+        if num_events < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                return Ok(Vec::new());
+            }
+            return Err(e);
+        }
+
+        unsafe { self.events.set_len(num_events as usize) };
+        let events = self
+            .events
+            .iter()
+            .map(|e| Event {
+                token: e.u64,
+                readable: e.events & READ_EVENTS != 0,
+                writable: e.events & libc::EPOLLOUT as u32 != 0,
+            })
+            .collect();
+
+        Ok(events)
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+
+    fn wait(poller: &mut Poller) -> Vec<(u64, bool, bool)> {
+        let events = poller.wait(Some(Duration::from_millis(100))).unwrap();
+        events
+            .iter()
+            .map(|e| (e.token, e.readable, e.writable))
+            .collect()
+    }
+
+    fn readable(events: &[(u64, bool, bool)], token: u64) -> bool {
+        events
+            .iter()
+            .any(|&(t, readable, _)| t == token && readable)
+    }
+
+    #[test]
+    fn readiness_is_reported_with_the_token() {
+        let (local, mut remote) = UnixStream::pair().unwrap();
+        let mut poller = Poller::new().unwrap();
+        poller.add(local.as_raw_fd(), 7).unwrap();
+
+        // Writable as soon as it is added, then nothing until something changes
+        assert_eq!(wait(&mut poller), [(7, false, true)]);
+        assert!(wait(&mut poller).is_empty());
+
+        remote.write_all(b"hi").unwrap();
+        assert!(readable(&wait(&mut poller), 7));
+
+        drop(remote);
+        assert!(readable(&wait(&mut poller), 7));
+    }
+
+    #[test]
+    fn deleted_descriptors_are_no_longer_reported() {
+        let (local, mut remote) = UnixStream::pair().unwrap();
+        let mut poller = Poller::new().unwrap();
+        poller.add(local.as_raw_fd(), 1).unwrap();
+        poller.delete(local.as_raw_fd()).unwrap();
+
+        remote.write_all(b"hi").unwrap();
+        assert!(wait(&mut poller).is_empty());
+        assert!(poller.delete(local.as_raw_fd()).is_err());
+    }
+}
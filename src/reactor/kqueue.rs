@@ -0,0 +1,191 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::io;
+use std::mem;
+use std::os::unix::io::RawFd;
+use std::ptr;
+use std::time::Duration;
+
+use super::{Event, MAX_EVENTS};
+
+/// A kqueue, closed when dropped.
+///
+/// The field types of `kevent` differ between the BSDs, hence the inferred casts.
+pub(super) struct Poller {
+    fd: RawFd,
+    events: Vec<libc::kevent>,
+}
+
+impl Poller {
+    pub(super) fn new() -> io::Result<Poller> {
+        let fd = unsafe { libc::kqueue() };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let poller = Poller {
+            fd,
+            events: Vec::with_capacity(MAX_EVENTS),
+        };
+        let result = unsafe { libc::fcntl(fd, libc::F_SETFD, libc::FD_CLOEXEC) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(poller)
+    }
+
+    /// Watches `fd` edge triggered for reads and writes, reporting them with `token`. Hang
+    /// ups are reported as reads.
+    pub(super) fn add(&self, fd: RawFd, token: u64) -> io::Result<()> {
+        let flags = libc::EV_ADD | libc::EV_CLEAR;
+        self.apply(&[
+            change(fd, libc::EVFILT_READ as _, flags as _, token),
+            change(fd, libc::EVFILT_WRITE as _, flags as _, token),
+        ])
+    }
+
+    pub(super) fn delete(&self, fd: RawFd) -> io::Result<()> {
+        self.apply(&[
+            change(fd, libc::EVFILT_READ as _, libc::EV_DELETE as _, 0),
+            change(fd, libc::EVFILT_WRITE as _, libc::EV_DELETE as _, 0),
+        ])
+    }
+
+    /// Waits up to `timeout` for events, forever if `None`. Returns no events if interrupted
+    /// by a signal.
+    #[allow(clippy::unnecessary_cast)]
+    pub(super) fn wait(&mut self, timeout: Option<Duration>) -> io::Result<Vec<Event>> {
+        let timeout = timeout.map(|timeout| libc::timespec {
+            tv_sec: timeout.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+            tv_nsec: timeout.subsec_nanos() as _,
+        });
+        let timeout_ptr = match timeout {
+            Some(ref timeout) => timeout as *const libc::timespec,
+            None => ptr::null(),
+        };
+
+        let num_events = unsafe {
+            libc::kevent(
+                self.fd,
+                ptr::null(),
+                0,
+                self.events.as_mut_ptr(),
+                MAX_EVENTS as _,
+                timeout_ptr,
+            )
+        };
+        if num_events < 0 {
+            let e = io::Error::last_os_error();
+            if e.kind() == io::ErrorKind::Interrupted {
+                return Ok(Vec::new());
+            }
+            return Err(e);
+        }
+
+        unsafe { self.events.set_len(num_events as usize) };
+        let events = self
+            .events
+            .iter()
+            .map(|e| {
+                let failed = e.flags as u32 & (libc::EV_EOF | libc::EV_ERROR) as u32 != 0;
+                Event {
+                    token: e.udata as u64,
+                    readable: e.filter as i64 == libc::EVFILT_READ as i64 || failed,
+                    writable: e.filter as i64 == libc::EVFILT_WRITE as i64,
+                }
+            })
+            .collect();
+
+        Ok(events)
+    }
+
+    fn apply(&self, changes: &[libc::kevent]) -> io::Result<()> {
+        let result = unsafe {
+            libc::kevent(
+                self.fd,
+                changes.as_ptr(),
+                changes.len() as _,
+                ptr::null_mut(),
+                0,
+                ptr::null(),
+            )
+        };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for Poller {
+    fn drop(&mut self) {
+        unsafe { libc::close(self.fd) };
+    }
+}
+
+fn change(fd: RawFd, filter: i64, flags: u64, token: u64) -> libc::kevent {
+    let mut event: libc::kevent = unsafe { mem::zeroed() };
+    event.ident = fd as _;
+    event.filter = filter as _;
+    event.flags = flags as _;
+    event.udata = token as _;
+    event
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+
+    fn wait(poller: &mut Poller) -> Vec<(u64, bool, bool)> {
+        let events = poller.wait(Some(Duration::from_millis(100))).unwrap();
+        events
+            .iter()
+            .map(|e| (e.token, e.readable, e.writable))
+            .collect()
+    }
+
+    fn readable(events: &[(u64, bool, bool)], token: u64) -> bool {
+        events
+            .iter()
+            .any(|&(t, readable, _)| t == token && readable)
+    }
+
+    #[test]
+    fn readiness_is_reported_with_the_token() {
+        let (local, mut remote) = UnixStream::pair().unwrap();
+        let mut poller = Poller::new().unwrap();
+        poller.add(local.as_raw_fd(), 7).unwrap();
+
+        // Writable as soon as it is added, then nothing until something changes
+        assert_eq!(wait(&mut poller), [(7, false, true)]);
+        assert!(wait(&mut poller).is_empty());
+
+        remote.write_all(b"hi").unwrap();
+        assert!(readable(&wait(&mut poller), 7));
+
+        drop(remote);
+        assert!(readable(&wait(&mut poller), 7));
+    }
+
+    #[test]
+    fn deleted_descriptors_are_no_longer_reported() {
+        let (local, mut remote) = UnixStream::pair().unwrap();
+        let mut poller = Poller::new().unwrap();
+        poller.add(local.as_raw_fd(), 1).unwrap();
+        poller.delete(local.as_raw_fd()).unwrap();
+
+        remote.write_all(b"hi").unwrap();
+        assert!(wait(&mut poller).is_empty());
+        assert!(poller.delete(local.as_raw_fd()).is_err());
+    }
+}
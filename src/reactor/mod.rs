@@ -0,0 +1,369 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! An edge triggered event loop driving many non-blocking streams from one thread, built on
+//! epoll on Linux and Android, and on kqueue on macOS, iOS and the BSDs.
+//!
+//! ```ignore
+//! struct Echo;
+//!
+//! impl Handler for Echo {
+//!     fn on_frame(&mut self, reactor: &mut Reactor, conn: ConnId, frame: Box<dyn Frame>) {
+//!         let _ = reactor.send(conn, &*frame);
+//!     }
+//!
+//!     fn on_close(&mut self, _reactor: &mut Reactor, conn: ConnId) {
+//!         println!("{} closed", conn);
+//!     }
+//! }
+//!
+//! let mut reactor = Reactor::new()?;
+//! for socket in listener.incoming() {
+//!     let socket = socket?;
+//!     socket.set_nonblocking(true)?;
+//!     reactor.add(Plain::<_, SimpleFrameBuilder>::new(socket))?;
+//!     reactor.poll(&mut Echo, Some(Duration::ZERO))?;
+//! }
+//! ```
+//!
+//! Streams have to be in non-blocking mode. Every readiness event is handled until the
+//! socket would block, and frames `send` could not write right away stay queued in the
+//! stream until the socket is writable again. Streams holding receives back under a
+//! `RateLimitAction::Delay` limit are received from again once `nb_recv_retry_at` passes,
+//! by whichever `poll` is running then.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
+
+use crate::frame::Frame;
+use crate::{Error, NonBlocking};
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+use self::epoll::Poller;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+use self::kqueue::Poller;
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod epoll;
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+mod kqueue;
+
+/// Most events returned by a single wait.
+const MAX_EVENTS: usize = 256;
+
+/// Identifies a stream for as long as it is part of a reactor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConnId(u64);
+
+/// Callbacks a `Reactor` invokes as its streams receive frames and close.
+pub trait Handler {
+    /// Called for every frame `conn` receives, in order. Replies can be sent through
+    /// `reactor`.
+    fn on_frame(&mut self, reactor: &mut Reactor, conn: ConnId, frame: Box<dyn Frame>);
+
+    /// Called once `conn` has failed or was closed by its peer, after it left the reactor.
+    fn on_close(&mut self, reactor: &mut Reactor, conn: ConnId);
+}
+
+/// Owns non-blocking streams and dispatches their readiness events to a `Handler`.
+pub struct Reactor {
+    poller: Poller,
+    conns: HashMap<ConnId, Connection>,
+    failed: Vec<ConnId>,
+    next_id: u64,
+}
+
+struct Connection {
+    stream: Box<dyn NonBlocking>,
+    fd: RawFd,
+    /// Frames `send` could not write yet are queued in the stream.
+    tx_pending: bool,
+    /// When to receive again without a readiness event, as receives are being held back.
+    retry_at: Option<Instant>,
+}
+
+/// The reactor on platforms backed by epoll.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+pub type EpollReactor = Reactor;
+
+/// The reactor on platforms backed by kqueue.
+#[cfg(not(any(target_os = "linux", target_os = "android")))]
+pub type KqueueReactor = Reactor;
+
+/// Readiness of one stream, as reported by the backend.
+struct Event {
+    token: u64,
+    /// Data, a hang up or an error is pending, all of which reading reports.
+    readable: bool,
+    writable: bool,
+}
+
+impl fmt::Display for ConnId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+impl Reactor {
+    /// Creates a reactor without streams.
+    pub fn new() -> io::Result<Reactor> {
+        Ok(Reactor {
+            poller: Poller::new()?,
+            conns: HashMap::new(),
+            failed: Vec::new(),
+            next_id: 1,
+        })
+    }
+
+    /// Hands `stream` to the reactor, watching it for as long as it stays open. Frames
+    /// already buffered by the stream are delivered on the next event its socket reports.
+    pub fn add<S>(&mut self, stream: S) -> io::Result<ConnId>
+    where
+        S: NonBlocking + AsRawFd + 'static,
+    {
+        let conn = ConnId(self.next_id);
+        let fd = stream.as_raw_fd();
+        self.poller.add(fd, conn.0)?;
+        self.next_id += 1;
+        self.conns.insert(
+            conn,
+            Connection {
+                stream: Box::new(stream),
+                fd,
+                tx_pending: false,
+                retry_at: None,
+            },
+        );
+        debug!("Reactor added {} on fd {}", conn, fd);
+
+        Ok(conn)
+    }
+
+    /// Takes `conn` out of the reactor without notifying the handler, returning its stream.
+    pub fn remove(&mut self, conn: ConnId) -> Option<Box<dyn NonBlocking>> {
+        let connection = self.conns.remove(&conn)?;
+        if let Err(e) = self.poller.delete(connection.fd) {
+            debug!("Unable to deregister {}: {}", conn, e);
+        }
+
+        Some(connection.stream)
+    }
+
+    /// Sends `frame` on `conn`. What could not be written without blocking stays queued, and
+    /// is written once the socket is writable again.
+    ///
+    /// If the stream fails, it is removed, the handler's `on_close` is called before `poll`
+    /// returns, and the error is returned. Fails with `ErrorKind::NotFound` for a stream not
+    /// part of the reactor.
+    pub fn send(&mut self, conn: ConnId, frame: &dyn Frame) -> Result<(), Error> {
+        let connection = self.conns.get_mut(&conn).ok_or_else(|| not_found(conn))?;
+        match connection.stream.nb_send(frame) {
+            Ok(()) => Ok(()),
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                connection.tx_pending = true;
+                Ok(())
+            }
+            Err(e) => {
+                self.fail(conn, &e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Returns the number of streams in the reactor.
+    pub fn len(&self) -> usize {
+        self.conns.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conns.is_empty()
+    }
+
+    /// Waits up to `timeout` for readiness events, forever if `None`, and dispatches every
+    /// event reported to `handler`. The wait ends early once a stream holding receives back
+    /// is due to be received from again. Returns the number of events handled, counting
+    /// each such stream as one, which is zero if the wait timed out or was interrupted by a
+    /// signal.
+    pub fn poll<H: Handler>(
+        &mut self,
+        handler: &mut H,
+        timeout: Option<Duration>,
+    ) -> io::Result<usize> {
+        self.notify_failed(handler);
+
+        let timeout = match self.next_retry() {
+            Some(retry_at) => {
+                let until_retry = retry_at.saturating_duration_since(Instant::now());
+                Some(timeout.map_or(until_retry, |timeout| timeout.min(until_retry)))
+            }
+            None => timeout,
+        };
+        let ready = self.poller.wait(timeout)?;
+        for event in ready.iter() {
+            let conn = ConnId(event.token);
+            if event.writable {
+                self.flush(conn);
+            }
+            if event.readable {
+                self.recv(handler, conn);
+            }
+            self.notify_failed(handler);
+        }
+
+        Ok(ready.len() + self.retry_due(handler))
+    }
+
+    /// Dispatches events to `handler` until no streams are left.
+    pub fn run<H: Handler>(&mut self, handler: &mut H) -> io::Result<()> {
+        while !self.is_empty() {
+            self.poll(handler, None)?;
+        }
+
+        Ok(())
+    }
+
+    /// Delivers every frame `conn` receives until its socket would block.
+    fn recv<H: Handler>(&mut self, handler: &mut H, conn: ConnId) {
+        loop {
+            // The handler may remove the stream while frames are delivered
+            let result = match self.conns.get_mut(&conn) {
+                Some(connection) => connection.stream.nb_recv(),
+                None => return,
+            };
+
+            match result {
+                Ok(frames) => {
+                    for frame in frames {
+                        handler.on_frame(self, conn, frame);
+                    }
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => break,
+                Err(e) => return self.fail(conn, &e),
+            }
+        }
+
+        // Receives held back leave bytes the socket will not report again
+        if let Some(connection) = self.conns.get_mut(&conn) {
+            connection.retry_at = connection.stream.nb_recv_retry_at();
+        }
+    }
+
+    /// Returns the earliest time a stream holding receives back is due to be received from.
+    fn next_retry(&self) -> Option<Instant> {
+        self.conns.values().filter_map(|c| c.retry_at).min()
+    }
+
+    /// Receives from every stream due to be received from again. Returns how many there were.
+    fn retry_due<H: Handler>(&mut self, handler: &mut H) -> usize {
+        let now = Instant::now();
+        let due: Vec<ConnId> = self
+            .conns
+            .iter()
+            .filter(|(_, c)| c.retry_at.is_some_and(|retry_at| retry_at <= now))
+            .map(|(&conn, _)| conn)
+            .collect();
+        for &conn in due.iter() {
+            trace!("Reactor retrying receive on {}", conn);
+            self.recv(handler, conn);
+            self.notify_failed(handler);
+        }
+
+        due.len()
+    }
+
+    /// Writes what `conn` has queued, now that its socket is writable.
+    fn flush(&mut self, conn: ConnId) {
+        let connection = match self.conns.get_mut(&conn) {
+            Some(connection) if connection.tx_pending => connection,
+            _ => return,
+        };
+
+        match connection.stream.nb_flush() {
+            Ok(flushed) => connection.tx_pending = !flushed,
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => self.fail(conn, &e),
+        }
+    }
+
+    /// Removes `conn` after it failed with `e`, leaving the handler to be notified.
+    fn fail(&mut self, conn: ConnId, e: &Error) {
+        debug!("Reactor closing {}: {}", conn, e);
+        if self.remove(conn).is_some() {
+            self.failed.push(conn);
+        }
+    }
+
+    fn notify_failed<H: Handler>(&mut self, handler: &mut H) {
+        while let Some(conn) = self.failed.pop() {
+            handler.on_close(self, conn);
+        }
+    }
+}
+
+fn not_found(conn: ConnId) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!("{} is not part of the reactor", conn),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+    use std::os::unix::net::UnixStream;
+
+    use crate::frame::{SimpleFrame, SimpleFrameBuilder};
+    use crate::ratelimit::{FrameRateLimit, RateLimitAction};
+    use crate::Plain;
+
+    /// Collects the payloads of the frames received.
+    #[derive(Default)]
+    struct Collect(Vec<Vec<u8>>);
+
+    impl Handler for Collect {
+        fn on_frame(&mut self, _reactor: &mut Reactor, _conn: ConnId, frame: Box<dyn Frame>) {
+            self.0.push(frame.payload());
+        }
+
+        fn on_close(&mut self, _reactor: &mut Reactor, _conn: ConnId) {}
+    }
+
+    #[test]
+    fn delayed_frames_arrive_without_another_event() {
+        let (local, mut remote) = UnixStream::pair().unwrap();
+        local.set_nonblocking(true).unwrap();
+        let mut stream = Plain::<_, SimpleFrameBuilder>::new(local);
+        stream.set_rx_frame_limit(Some(FrameRateLimit {
+            frames_per_sec: 50,
+            burst: 1,
+            action: RateLimitAction::Delay,
+        }));
+        let mut reactor = Reactor::new().unwrap();
+        reactor.add(stream).unwrap();
+
+        let payloads = [&b"one"[..], &b"two"[..], &b"three"[..]];
+        for payload in payloads {
+            remote
+                .write_all(&SimpleFrame::new(payload).to_bytes())
+                .unwrap();
+        }
+
+        let mut handler = Collect::default();
+        let started = Instant::now();
+        while handler.0.len() < payloads.len() && started.elapsed() < Duration::from_secs(5) {
+            reactor
+                .poll(&mut handler, Some(Duration::from_secs(1)))
+                .unwrap();
+        }
+        assert_eq!(handler.0, payloads);
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -0,0 +1,183 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Process wide list of the streams that opted in with `register`, for admin and status
+//! endpoints.
+//!
+//! ```ignore
+//! let mut stream = Plain::<TcpStream, SimpleFrameBuilder>::new(socket);
+//! stream.register(Some(peer_addr));
+//!
+//! for s in simple_stream::active_streams() {
+//!     println!("{} {:?} up {:?}, {} frame(s) in", s.id, s.peer_addr, s.age, s.frames_received);
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static REGISTRY: Mutex<BTreeMap<u64, Arc<Entry>>> = Mutex::new(BTreeMap::new());
+
+/// Identifies a registered stream for as long as it stays registered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId(u64);
+
+/// A registered stream as it was when `active_streams` was called.
+#[derive(Clone, Debug)]
+pub struct StreamSnapshot {
+    pub id: StreamId,
+    /// `"plain"` or `"secure"`.
+    pub kind: &'static str,
+    pub peer_addr: Option<SocketAddr>,
+    /// Bytes read but not yet decoded into frames.
+    pub rx_buffered: usize,
+    /// Bytes of queued frames not yet written.
+    pub tx_pending: usize,
+    pub frames_received: u64,
+    pub frames_sent: u64,
+    /// How long ago the stream registered.
+    pub age: Duration,
+}
+
+struct Entry {
+    kind: &'static str,
+    peer_addr: Option<SocketAddr>,
+    registered: Instant,
+    rx_buffered: AtomicUsize,
+    tx_pending: AtomicUsize,
+    frames_received: AtomicU64,
+    frames_sent: AtomicU64,
+}
+
+/// A stream's place in the registry, removed when dropped.
+pub(crate) struct Registration {
+    id: StreamId,
+    entry: Arc<Entry>,
+}
+
+/// Returns every registered stream, oldest first.
+pub fn active_streams() -> Vec<StreamSnapshot> {
+    let registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+    registry
+        .iter()
+        .map(|(id, entry)| StreamSnapshot {
+            id: StreamId(*id),
+            kind: entry.kind,
+            peer_addr: entry.peer_addr,
+            rx_buffered: entry.rx_buffered.load(Ordering::Relaxed),
+            tx_pending: entry.tx_pending.load(Ordering::Relaxed),
+            frames_received: entry.frames_received.load(Ordering::Relaxed),
+            frames_sent: entry.frames_sent.load(Ordering::Relaxed),
+            age: entry.registered.elapsed(),
+        })
+        .collect()
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+impl Registration {
+    pub(crate) fn new(kind: &'static str, peer_addr: Option<SocketAddr>) -> Registration {
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        let entry = Arc::new(Entry {
+            kind,
+            peer_addr,
+            registered: Instant::now(),
+            rx_buffered: AtomicUsize::new(0),
+            tx_pending: AtomicUsize::new(0),
+            frames_received: AtomicU64::new(0),
+            frames_sent: AtomicU64::new(0),
+        });
+        let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+        registry.insert(id, entry.clone());
+        debug!("Stream #{} registered", id);
+
+        Registration {
+            id: StreamId(id),
+            entry,
+        }
+    }
+
+    pub(crate) fn id(&self) -> StreamId {
+        self.id
+    }
+
+    pub(crate) fn received(&self) {
+        self.entry.frames_received.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn sent(&self) {
+        self.entry.frames_sent.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_rx_buffered(&self, len: usize) {
+        self.entry.rx_buffered.store(len, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set_tx_pending(&self, len: usize) {
+        self.entry.tx_pending.store(len, Ordering::Relaxed);
+    }
+}
+
+impl Drop for Registration {
+    fn drop(&mut self) {
+        let mut registry = REGISTRY.lock().unwrap_or_else(|e| e.into_inner());
+        registry.remove(&self.id.0);
+        debug!("Stream {} unregistered", self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Finds `id` among the active streams, which other tests may be registering in parallel.
+    fn snapshot(id: StreamId) -> Option<StreamSnapshot> {
+        active_streams().into_iter().find(|s| s.id == id)
+    }
+
+    #[test]
+    fn registrations_are_listed_until_dropped() {
+        let addr: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let registration = Registration::new("plain", Some(addr));
+        registration.received();
+        registration.received();
+        registration.sent();
+        registration.set_rx_buffered(7);
+        registration.set_tx_pending(3);
+
+        let s = snapshot(registration.id()).unwrap();
+        assert_eq!(s.kind, "plain");
+        assert_eq!(s.peer_addr, Some(addr));
+        assert_eq!((s.frames_received, s.frames_sent), (2, 1));
+        assert_eq!((s.rx_buffered, s.tx_pending), (7, 3));
+
+        let id = registration.id();
+        drop(registration);
+        assert!(snapshot(id).is_none());
+    }
+
+    #[test]
+    fn streams_are_listed_oldest_first() {
+        let first = Registration::new("plain", None);
+        let second = Registration::new("secure", None);
+        assert!(first.id() < second.id());
+
+        let ids: Vec<StreamId> = active_streams().into_iter().map(|s| s.id).collect();
+        let first_pos = ids.iter().position(|&id| id == first.id()).unwrap();
+        let second_pos = ids.iter().position(|&id| id == second.id()).unwrap();
+        assert!(first_pos < second_pos);
+    }
+}
@@ -0,0 +1,228 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! What a stream does once the bytes it received stop lining up with frame boundaries, e.g.
+//! after a bad checksum or guard byte.
+//!
+//! ```ignore
+//! stream.set_decode_policy(Some(DecodePolicy::ScanForStart));
+//! match stream.nb_recv() {
+//!     Ok(frames) => handle(frames),
+//!     Err(Error::BadChecksum) => warn!("Dropped a corrupt frame"),
+//!     Err(e) => return Err(e),
+//! }
+//! ```
+//!
+//! Only frame formats that can tell corrupt bytes apart, by implementing
+//! `FrameBuilder::validate`, are checked. `SimpleFrameBuilder` checks its guard bytes, and
+//! `Checksum32FrameBuilder` its checksum.
+//!
+//! Without a policy, received bytes are only checked once they fail to decode. If they can
+//! never become a frame, e.g. because their length is past what the format accepts, they are
+//! dropped and the stream closes with `CloseReason::ProtocolError`, instead of waiting for
+//! bytes that are never going to complete the frame.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+use crate::buffer::RecvBuffer;
+use crate::frame::{Corruption, Frame, FrameDecoder};
+
+/// How a stream recovers from receiving bytes that can not be a valid frame.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecodePolicy {
+    /// Drops bytes up to the next one a valid frame could start at. Formats without a start
+    /// marker, such as `Checksum32FrameBuilder`, may take a while to line up again.
+    ScanForStart,
+    /// Drops this many bytes, at least one, and tries again.
+    DropBytes(usize),
+    /// Drops everything received and closes the stream with `CloseReason::ProtocolError`.
+    Close,
+}
+
+/// Error carried by the `std::io::Error`, of `ErrorKind::InvalidData`, a receive fails with
+/// once the stream lost sync with frame boundaries. The corrupt bytes were dropped, and unless
+/// the policy is `DecodePolicy::Close` the stream stays open. `Blocking` and `NonBlocking`
+/// return it as `Error::BadChecksum` for a checksum mismatch, and `Error::ProtocolViolation`
+/// otherwise.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Desync {
+    pub corruption: Corruption,
+    /// How many bytes were dropped to get back in sync.
+    pub skipped: usize,
+}
+
+/// Applies a `DecodePolicy`, counting how often the stream lost sync.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Resync {
+    policy: DecodePolicy,
+    desyncs: u64,
+}
+
+impl Resync {
+    pub(crate) fn new(policy: DecodePolicy) -> Resync {
+        Resync { policy, desyncs: 0 }
+    }
+
+    pub(crate) fn desyncs(&self) -> u64 {
+        self.desyncs
+    }
+
+    /// Whether `e` is a desync that should close the stream.
+    pub(crate) fn closes_on(&self, e: &io::Error) -> bool {
+        self.policy == DecodePolicy::Close && is_desync(e)
+    }
+
+    /// Decodes the next frame from `buf` if it starts with one, or drops corrupt bytes as the
+    /// policy says and fails with a `Desync`.
+    pub(crate) fn decode(
+        &mut self,
+        decoder: &mut dyn FrameDecoder,
+        buf: &mut RecvBuffer,
+    ) -> io::Result<Option<Box<dyn Frame>>> {
+        let corruption = match decoder.validate(buf.as_slice()) {
+            Ok(()) => return Ok(decoder.decode_buffer(buf)),
+            Err(corruption) => corruption,
+        };
+
+        let bytes = buf.as_slice();
+        let skipped = match self.policy {
+            DecodePolicy::ScanForStart => (1..bytes.len())
+                .find(|&start| decoder.validate(&bytes[start..]).is_ok())
+                .unwrap_or(bytes.len()),
+            DecodePolicy::DropBytes(len) => len.clamp(1, bytes.len()),
+            DecodePolicy::Close => bytes.len(),
+        };
+        buf.consume(skipped);
+        self.desyncs += 1;
+
+        Err(desync(corruption, skipped))
+    }
+}
+
+/// Decodes the next frame from `buf` for a stream without a `DecodePolicy`. If `buf` holds no
+/// complete frame and can never become one, everything in it is dropped and this fails with a
+/// `Desync`, which the stream closes on.
+pub(crate) fn decode_unsynced(
+    decoder: &mut dyn FrameDecoder,
+    buf: &mut RecvBuffer,
+) -> io::Result<Option<Box<dyn Frame>>> {
+    if let Some(frame) = decoder.decode_buffer(buf) {
+        return Ok(Some(frame));
+    }
+
+    match decoder.validate(buf.as_slice()) {
+        Ok(()) => Ok(None),
+        Err(corruption) => {
+            let skipped = buf.len();
+            buf.clear();
+            Err(desync(corruption, skipped))
+        }
+    }
+}
+
+/// Whether `e` is a `Desync`.
+pub(crate) fn is_desync(e: &io::Error) -> bool {
+    e.get_ref().is_some_and(|e| e.is::<Desync>())
+}
+
+fn desync(corruption: Corruption, skipped: usize) -> io::Error {
+    let desync = Desync {
+        corruption,
+        skipped,
+    };
+    error!("{}", desync);
+    io::Error::new(io::ErrorKind::InvalidData, desync)
+}
+
+impl fmt::Display for Desync {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Lost frame sync: {}. Dropped {} byte(s)",
+            self.corruption, self.skipped
+        )
+    }
+}
+
+impl Error for Desync {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{BuilderDecoder, SimpleFrame, SimpleFrameBuilder};
+
+    /// Three garbage bytes followed by a valid frame.
+    fn corrupt_then_valid() -> RecvBuffer {
+        let mut bytes = vec![0xff, 0xfe, 0xfd];
+        bytes.extend_from_slice(&SimpleFrame::new(b"ok").to_bytes());
+        RecvBuffer::from(bytes)
+    }
+
+    fn desync_of(e: &io::Error) -> Desync {
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        *e.get_ref().unwrap().downcast_ref::<Desync>().unwrap()
+    }
+
+    #[test]
+    fn scanning_skips_to_the_next_frame() {
+        let mut decoder = BuilderDecoder::of::<SimpleFrameBuilder>();
+        let mut buf = corrupt_then_valid();
+        let mut resync = Resync::new(DecodePolicy::ScanForStart);
+
+        let e = resync.decode(&mut decoder, &mut buf).unwrap_err();
+        assert_eq!(
+            desync_of(&e),
+            Desync {
+                corruption: Corruption::BadGuard,
+                skipped: 3
+            }
+        );
+        assert!(!resync.closes_on(&e));
+
+        let frame = resync.decode(&mut decoder, &mut buf).unwrap().unwrap();
+        assert_eq!(frame.payload(), b"ok");
+        assert_eq!(resync.desyncs(), 1);
+    }
+
+    #[test]
+    fn dropping_bytes_takes_one_desync_per_step() {
+        let mut decoder = BuilderDecoder::of::<SimpleFrameBuilder>();
+        let mut buf = corrupt_then_valid();
+        let mut resync = Resync::new(DecodePolicy::DropBytes(2));
+
+        assert_eq!(
+            desync_of(&resync.decode(&mut decoder, &mut buf).unwrap_err()).skipped,
+            2
+        );
+        assert_eq!(
+            desync_of(&resync.decode(&mut decoder, &mut buf).unwrap_err()).skipped,
+            2
+        );
+        // The second step dropped the frame's start guard as well
+        assert!(resync.decode(&mut decoder, &mut buf).is_err());
+        assert_eq!(resync.desyncs(), 3);
+    }
+
+    #[test]
+    fn closing_drops_everything() {
+        let mut decoder = BuilderDecoder::of::<SimpleFrameBuilder>();
+        let mut buf = corrupt_then_valid();
+        let mut resync = Resync::new(DecodePolicy::Close);
+
+        let e = resync.decode(&mut decoder, &mut buf).unwrap_err();
+        assert!(resync.closes_on(&e));
+        assert!(!resync.closes_on(&io::Error::from(io::ErrorKind::InvalidData)));
+        assert!(buf.is_empty());
+
+        let mut buf = corrupt_then_valid();
+        let e = decode_unsynced(&mut decoder, &mut buf).unwrap_err();
+        assert!(is_desync(&e));
+        assert!(buf.is_empty());
+    }
+}
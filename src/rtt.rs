@@ -0,0 +1,121 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::io;
+use std::time::{Duration, Instant};
+
+use crate::frame::{EchoFrame, EchoType, Frame};
+use crate::{Blocking, Error, NonBlocking};
+
+/// Sends an `Echo` and blocks until the matching `EchoReply` is received, returning the
+/// elapsed time.
+///
+/// The stream is expected to be carrying `EchoFrame`s. Echoes from the peer received while
+/// waiting are answered, and any other frames are discarded.
+pub fn measure_rtt<S: Blocking>(stream: &mut S) -> Result<Duration, Error> {
+    let echo = EchoFrame::echo();
+    let sent_at = Instant::now();
+    stream.b_send(&echo)?;
+
+    loop {
+        let frame = stream.b_recv()?;
+        match EchoFrame::from_frame(&*frame) {
+            Some(ref reply)
+                if reply.echo_type() == EchoType::EchoReply && reply.nonce() == echo.nonce() =>
+            {
+                let rtt = sent_at.elapsed();
+                trace!("Echo round trip: {:?}", rtt);
+                return Ok(rtt);
+            }
+            Some(ref request) if request.echo_type() == EchoType::Echo => {
+                stream.b_send(&request.reply())?;
+            }
+            _ => {
+                debug!(
+                    "Discarding {} while waiting for echo reply",
+                    frame.fmt_summary()
+                );
+            }
+        }
+    }
+}
+
+/// An outstanding, non-blocking round trip measurement.
+#[derive(Clone, Debug)]
+pub struct EchoProbe {
+    echo: EchoFrame,
+    sent_at: Instant,
+}
+
+impl EchoProbe {
+    /// Sends an `Echo` through `stream`. A `WouldBlock` from the send is not an error here,
+    /// as the frame has been queued and will go out with the next send.
+    pub fn send<S: NonBlocking>(stream: &mut S) -> Result<EchoProbe, Error> {
+        let echo = EchoFrame::echo();
+        let sent_at = Instant::now();
+        match stream.nb_send(&echo) {
+            Ok(()) => {}
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+            Err(e) => return Err(e),
+        }
+
+        Ok(EchoProbe { echo, sent_at })
+    }
+
+    /// Checks a received frame against this probe, returning the round trip time if it is
+    /// the matching `EchoReply`.
+    pub fn rtt(&self, frame: &dyn Frame) -> Option<Duration> {
+        let reply = EchoFrame::from_frame(frame)?;
+        if reply.echo_type() == EchoType::EchoReply && reply.nonce() == self.echo.nonce() {
+            return Some(self.sent_at.elapsed());
+        }
+
+        None
+    }
+}
+
+/// Answers `frame` with an `EchoReply` if it is an `Echo`. Returns whether a reply was sent.
+pub fn answer_echo<S: NonBlocking>(stream: &mut S, frame: &dyn Frame) -> Result<bool, Error> {
+    match EchoFrame::from_frame(frame) {
+        Some(ref request) if request.echo_type() == EchoType::Echo => {
+            match stream.nb_send(&request.reply()) {
+                Ok(()) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+            Ok(true)
+        }
+        _ => Ok(false),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duplex::Duplex;
+    use crate::frame::EchoFrameBuilder;
+    use crate::Plain;
+
+    #[test]
+    fn probes_match_only_their_own_reply() {
+        let (a, b) = Duplex::pair();
+        let mut local = Plain::<_, EchoFrameBuilder>::new(a);
+        let mut remote = Plain::<_, EchoFrameBuilder>::new(b);
+
+        let probe = EchoProbe::send(&mut local).unwrap();
+        let echo = remote.nb_recv().unwrap().remove(0);
+        assert_eq!(probe.rtt(&*echo), None);
+        assert!(answer_echo(&mut remote, &*echo).unwrap());
+
+        let reply = local.nb_recv().unwrap().remove(0);
+        assert!(probe.rtt(&*reply).is_some());
+        assert!(!answer_echo(&mut local, &*reply).unwrap());
+
+        let other = EchoProbe::send(&mut local).unwrap();
+        assert_eq!(other.rtt(&*reply), None);
+    }
+}
@@ -0,0 +1,473 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, BinaryHeap, VecDeque};
+use std::io::IoSlice;
+use std::time::Instant;
+
+use crate::frame::Frame;
+
+/// A frame waiting in a stream's send queue, along with what a `TxScheduler` may use to
+/// decide when it is written.
+#[derive(Clone, Debug)]
+pub struct QueuedFrame {
+    bytes: Vec<u8>,
+    priority: u8,
+    deadline: Option<Instant>,
+    enqueued_at: Instant,
+}
+
+/// Decides the order queued frames are written to a stream in.
+///
+/// Streams only ask for the next frame once the previous ones have been handed to the
+/// underlying stream, so frames stay reorderable for as long as the peer is not reading.
+pub trait TxScheduler: Send {
+    /// Adds a frame to the queue.
+    fn push(&mut self, frame: QueuedFrame);
+    /// Removes and returns the frame to write next.
+    fn pop(&mut self) -> Option<QueuedFrame>;
+    /// Returns the number of frames queued.
+    fn len(&self) -> usize;
+    /// Returns the queued frames in the order `pop` would return them.
+    fn queued(&self) -> Vec<&QueuedFrame>;
+    /// Returns a copy of this scheduler, including its queued frames.
+    fn box_clone(&self) -> Box<dyn TxScheduler>;
+
+    /// Returns `true` if no frames are queued.
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Describes a frame in a stream's send queue, as returned by `pending_frames`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FrameSummary {
+    /// Encoded length of the frame.
+    pub len: usize,
+    /// Bytes of the frame not written yet.
+    pub remaining: usize,
+    pub priority: u8,
+    pub deadline: Option<Instant>,
+    pub enqueued_at: Instant,
+}
+
+/// Writes frames in the order they were sent.
+#[derive(Clone, Debug, Default)]
+pub struct FifoScheduler {
+    queue: VecDeque<QueuedFrame>,
+}
+
+/// Writes higher priority frames first, and frames of equal priority in the order they were
+/// sent.
+///
+/// ```
+/// use simple_stream::frame::SimpleFrame;
+/// use simple_stream::{PriorityScheduler, QueuedFrame, TxScheduler};
+///
+/// let mut scheduler = PriorityScheduler::default();
+/// for (payload, priority) in [(b"a1", 1), (b"b0", 0), (b"a2", 1), (b"c7", 7), (b"b1", 0)] {
+///     scheduler.push(QueuedFrame::new(&SimpleFrame::new(payload)).with_priority(priority));
+/// }
+///
+/// let order: Vec<u8> = scheduler.queued().iter().map(|f| f.priority()).collect();
+/// assert_eq!(order, [7, 1, 1, 0, 0]);
+///
+/// // Within a priority, frames leave in the order they were sent
+/// let written: Vec<_> = std::iter::from_fn(|| scheduler.pop()).collect();
+/// assert_eq!(written[1].bytes()[3..5], *b"a1");
+/// assert_eq!(written[2].bytes()[3..5], *b"a2");
+/// assert_eq!(written[3].bytes()[3..5], *b"b0");
+/// assert_eq!(written[4].bytes()[3..5], *b"b1");
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct PriorityScheduler {
+    heap: BinaryHeap<Ranked<(u8, Reverse<u64>)>>,
+    next_seq: u64,
+}
+
+/// Writes higher priority frames first, like `PriorityScheduler`, without starving lower
+/// priorities: once the oldest frame of a priority has been passed over for `max_skips`
+/// frames of higher priority, it is written next. Frames of equal priority are written in
+/// the order they were sent.
+///
+/// ```
+/// use simple_stream::frame::SimpleFrame;
+/// use simple_stream::{FairScheduler, QueuedFrame, TxScheduler};
+///
+/// let mut scheduler = FairScheduler::new(2);
+/// for (payload, priority) in [(b"b0", 0), (b"b1", 0), (b"c0", 7), (b"c1", 7), (b"c2", 7)] {
+///     scheduler.push(QueuedFrame::new(&SimpleFrame::new(payload)).with_priority(priority));
+/// }
+///
+/// // Bulk data gets a turn after every two control frames
+/// let order: Vec<u8> = scheduler.queued().iter().map(|f| f.priority()).collect();
+/// assert_eq!(order, [7, 7, 0, 7, 0]);
+/// ```
+#[derive(Clone, Debug)]
+pub struct FairScheduler {
+    /// Frames queued at each priority, oldest first, and how many frames of higher priority
+    /// were written since one of that priority last was.
+    levels: BTreeMap<u8, (VecDeque<QueuedFrame>, usize)>,
+    max_skips: usize,
+    len: usize,
+}
+
+/// Writes frames with the earliest deadline first. Frames without a deadline are written
+/// after every frame with one, in the order they were sent.
+#[derive(Clone, Debug, Default)]
+pub struct DeadlineScheduler {
+    heap: BinaryHeap<Ranked<DeadlineRank>>,
+    next_seq: u64,
+}
+
+/// Whether a frame has no deadline, its deadline, and its sequence number, reversed so the
+/// max-heap pops the smallest first.
+type DeadlineRank = Reverse<(bool, Option<Instant>, u64)>;
+
+/// A queued frame ordered by `rank` alone.
+#[derive(Clone, Debug)]
+struct Ranked<R: Ord> {
+    rank: R,
+    frame: QueuedFrame,
+}
+
+impl QueuedFrame {
+    /// Queues `frame` with the lowest priority and no deadline.
+    pub fn new(frame: &dyn Frame) -> Self {
+        QueuedFrame {
+            bytes: frame.to_bytes(),
+            priority: 0,
+            deadline: None,
+            enqueued_at: Instant::now(),
+        }
+    }
+
+    /// Queues a frame already encoded across `bufs`, with the lowest priority and no deadline.
+    pub(crate) fn from_slices(bufs: &[IoSlice]) -> Self {
+        let mut bytes = Vec::<u8>::with_capacity(bufs.iter().map(|buf| buf.len()).sum());
+        for buf in bufs {
+            bytes.extend_from_slice(buf);
+        }
+
+        QueuedFrame::from_bytes(bytes)
+    }
+
+    /// Queues `bytes`, already encoded as a frame, with the lowest priority and no deadline.
+    pub(crate) fn from_bytes(bytes: Vec<u8>) -> Self {
+        QueuedFrame {
+            bytes,
+            priority: 0,
+            deadline: None,
+            enqueued_at: Instant::now(),
+        }
+    }
+
+    /// Sets the priority used by `PriorityScheduler`, where higher is written sooner.
+    pub fn with_priority(mut self, priority: u8) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Sets the deadline used by `DeadlineScheduler`.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    pub fn priority(&self) -> u8 {
+        self.priority
+    }
+
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    pub fn enqueued_at(&self) -> Instant {
+        self.enqueued_at
+    }
+
+    /// Returns the encoded length of the frame.
+    pub fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bytes.is_empty()
+    }
+
+    /// Returns the encoded frame.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes[..]
+    }
+
+    /// Describes this frame, with none of it written yet.
+    pub fn summary(&self) -> FrameSummary {
+        FrameSummary {
+            len: self.len(),
+            remaining: self.len(),
+            priority: self.priority,
+            deadline: self.deadline,
+            enqueued_at: self.enqueued_at,
+        }
+    }
+
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+}
+
+impl FairScheduler {
+    /// Creates a scheduler writing a frame after it was passed over for `max_skips` frames,
+    /// at least 1, of higher priority.
+    pub fn new(max_skips: usize) -> FairScheduler {
+        FairScheduler {
+            levels: BTreeMap::new(),
+            max_skips: max_skips.max(1),
+            len: 0,
+        }
+    }
+}
+
+impl Default for FairScheduler {
+    /// Creates a scheduler writing a frame after it was passed over for 8 frames of higher
+    /// priority.
+    fn default() -> FairScheduler {
+        FairScheduler::new(8)
+    }
+}
+
+impl TxScheduler for FifoScheduler {
+    fn push(&mut self, frame: QueuedFrame) {
+        self.queue.push_back(frame);
+    }
+
+    fn pop(&mut self) -> Option<QueuedFrame> {
+        self.queue.pop_front()
+    }
+
+    fn len(&self) -> usize {
+        self.queue.len()
+    }
+
+    fn queued(&self) -> Vec<&QueuedFrame> {
+        self.queue.iter().collect()
+    }
+
+    fn box_clone(&self) -> Box<dyn TxScheduler> {
+        Box::new(self.clone())
+    }
+}
+
+impl TxScheduler for PriorityScheduler {
+    fn push(&mut self, frame: QueuedFrame) {
+        let rank = (frame.priority, Reverse(self.next_seq));
+        self.next_seq += 1;
+        self.heap.push(Ranked { rank, frame });
+    }
+
+    fn pop(&mut self) -> Option<QueuedFrame> {
+        self.heap.pop().map(|ranked| ranked.frame)
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    fn queued(&self) -> Vec<&QueuedFrame> {
+        in_pop_order(&self.heap)
+    }
+
+    fn box_clone(&self) -> Box<dyn TxScheduler> {
+        Box::new(self.clone())
+    }
+}
+
+impl TxScheduler for FairScheduler {
+    fn push(&mut self, frame: QueuedFrame) {
+        let (frames, _) = self.levels.entry(frame.priority).or_default();
+        frames.push_back(frame);
+        self.len += 1;
+    }
+
+    fn pop(&mut self) -> Option<QueuedFrame> {
+        let (priority, frames) = take_turn(&mut self.levels, self.max_skips)?;
+        let frame = frames.pop_front();
+        if frames.is_empty() {
+            self.levels.remove(&priority);
+        }
+        self.len -= 1;
+        frame
+    }
+
+    fn len(&self) -> usize {
+        self.len
+    }
+
+    fn queued(&self) -> Vec<&QueuedFrame> {
+        // Plays out the turns pop would give, on a copy of the skip counts
+        let mut levels: BTreeMap<u8, _> = self
+            .levels
+            .iter()
+            .map(|(&priority, (frames, skipped))| (priority, (frames.iter(), *skipped)))
+            .collect();
+        let mut queued = Vec::<&QueuedFrame>::with_capacity(self.len);
+        while let Some((priority, frames)) = take_turn(&mut levels, self.max_skips) {
+            queued.extend(frames.next());
+            if frames.len() == 0 {
+                levels.remove(&priority);
+            }
+        }
+        queued
+    }
+
+    fn box_clone(&self) -> Box<dyn TxScheduler> {
+        Box::new(self.clone())
+    }
+}
+
+impl TxScheduler for DeadlineScheduler {
+    fn push(&mut self, frame: QueuedFrame) {
+        // None sorts before Some, so rank on whether there is no deadline first to put those
+        // frames after every frame with one
+        let rank = Reverse((frame.deadline.is_none(), frame.deadline, self.next_seq));
+        self.next_seq += 1;
+        self.heap.push(Ranked { rank, frame });
+    }
+
+    fn pop(&mut self) -> Option<QueuedFrame> {
+        self.heap.pop().map(|ranked| ranked.frame)
+    }
+
+    fn len(&self) -> usize {
+        self.heap.len()
+    }
+
+    fn queued(&self) -> Vec<&QueuedFrame> {
+        in_pop_order(&self.heap)
+    }
+
+    fn box_clone(&self) -> Box<dyn TxScheduler> {
+        Box::new(self.clone())
+    }
+}
+
+/// Returns the frames in `heap` from the highest ranked down, which is the order `pop`
+/// returns them in as ranks are unique.
+fn in_pop_order<R: Ord>(heap: &BinaryHeap<Ranked<R>>) -> Vec<&QueuedFrame> {
+    let mut ranked: Vec<&Ranked<R>> = heap.iter().collect();
+    ranked.sort_unstable_by(|a, b| b.rank.cmp(&a.rank));
+    ranked.into_iter().map(|ranked| &ranked.frame).collect()
+}
+
+/// Picks the priority of `levels`, none of them empty, to write a frame from next, counting a
+/// skip for every lower priority passed over. Returns it along with its frames.
+fn take_turn<T>(levels: &mut BTreeMap<u8, (T, usize)>, max_skips: usize) -> Option<(u8, &mut T)> {
+    let mut by_priority = levels.iter().rev();
+    let (&highest, _) = by_priority.next()?;
+    // Of the priorities skipped too often, the one skipped the most goes first
+    let priority = by_priority
+        .filter(|(_, &(_, skipped))| skipped >= max_skips)
+        .max_by_key(|(&priority, &(_, skipped))| (skipped, priority))
+        .map_or(highest, |(&priority, _)| priority);
+
+    for (_, (_, skipped)) in levels.range_mut(..priority) {
+        *skipped += 1;
+    }
+    let (frames, skipped) = levels.get_mut(&priority)?;
+    *skipped = 0;
+    Some((priority, frames))
+}
+
+impl Clone for Box<dyn TxScheduler> {
+    fn clone(&self) -> Box<dyn TxScheduler> {
+        self.box_clone()
+    }
+}
+
+impl<R: Ord> PartialEq for Ranked<R> {
+    fn eq(&self, other: &Self) -> bool {
+        self.rank == other.rank
+    }
+}
+
+impl<R: Ord> Eq for Ranked<R> {}
+
+impl<R: Ord> PartialOrd for Ranked<R> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<R: Ord> Ord for Ranked<R> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.rank.cmp(&other.rank)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    use crate::frame::SimpleFrame;
+
+    /// Pops every frame `scheduler` queues, checking each against what `queued` promised, and
+    /// returns their payloads.
+    fn drain(scheduler: &mut dyn TxScheduler) -> Vec<Vec<u8>> {
+        let expected: Vec<Vec<u8>> = scheduler
+            .queued()
+            .iter()
+            .map(|frame| frame.bytes().to_vec())
+            .collect();
+        let popped: Vec<Vec<u8>> = std::iter::from_fn(|| scheduler.pop())
+            .map(|frame| frame.bytes().to_vec())
+            .collect();
+        assert_eq!(popped, expected);
+        assert!(scheduler.is_empty());
+
+        // Payloads sit between the 3 byte header and the end guard byte
+        popped
+            .iter()
+            .map(|bytes| bytes[3..(bytes.len() - 1)].to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn earliest_deadlines_go_first() {
+        let now = Instant::now();
+        let mut scheduler = DeadlineScheduler::default();
+        for (payload, deadline) in [
+            (b"none1", None),
+            (b"late1", Some(now + Duration::from_secs(2))),
+            (b"none2", None),
+            (b"soon1", Some(now + Duration::from_secs(1))),
+            (b"late2", Some(now + Duration::from_secs(2))),
+        ] {
+            let frame = QueuedFrame::new(&SimpleFrame::new(payload));
+            scheduler.push(match deadline {
+                Some(deadline) => frame.with_deadline(deadline),
+                None => frame,
+            });
+        }
+
+        let order = drain(&mut scheduler);
+        assert_eq!(order, [b"soon1", b"late1", b"late2", b"none1", b"none2"]);
+    }
+
+    #[test]
+    fn fair_schedulers_give_every_priority_a_turn() {
+        let mut scheduler = FairScheduler::new(1);
+        for (payload, priority) in [(b"b0", 0), (b"c0", 7), (b"c1", 7), (b"c2", 7)] {
+            scheduler.push(QueuedFrame::new(&SimpleFrame::new(payload)).with_priority(priority));
+        }
+        assert_eq!(scheduler.len(), 4);
+
+        let order = drain(&mut scheduler);
+        assert_eq!(order, [b"c0", b"b0", b"c1", b"c2"]);
+    }
+}
@@ -0,0 +1,531 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::io::{self, Read, Write};
+use std::mem;
+use std::net::{Shutdown, TcpStream};
+#[cfg(unix)]
+use std::os::unix::io::{AsFd, AsRawFd, BorrowedFd, FromRawFd, IntoRawFd, OwnedFd, RawFd};
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+#[cfg(windows)]
+use std::os::windows::io::{
+    AsRawSocket, AsSocket, BorrowedSocket, FromRawSocket, IntoRawSocket, OwnedSocket, RawSocket,
+};
+
+#[cfg(windows)]
+use windows_sys::Win32::Networking::WinSock;
+
+/// First byte of a TLS record carrying a handshake message, such as a ClientHello.
+const TLS_HANDSHAKE_RECORD: u8 = 0x16;
+
+/// The protocol a peer appears to be speaking, as determined by `Socket::sniff_first_byte`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SniffedProtocol {
+    /// The peer opened with a TLS handshake record.
+    Tls,
+    /// Anything that is not a TLS handshake record.
+    Plain,
+}
+
+/// Transports that can be duplicated into a second handle to the same connection, as needed
+/// by `Plain::try_clone`.
+pub trait TryClone: Sized {
+    fn try_clone(&self) -> io::Result<Self>;
+}
+
+/// Owned file descriptor based socket.
+///
+/// The descriptor is closed when the `Socket` is dropped. Use `into_raw_fd`, or convert into
+/// an `OwnedFd`, to take the descriptor back out without closing it.
+#[cfg(unix)]
+#[derive(Debug)]
+pub struct Socket {
+    fd: RawFd,
+}
+
+/// Owned Winsock socket.
+///
+/// The socket is closed when the `Socket` is dropped. Use `into_raw_socket`, or convert into
+/// an `OwnedSocket`, to take the handle back out without closing it.
+#[cfg(windows)]
+#[derive(Debug)]
+pub struct Socket {
+    socket: RawSocket,
+}
+
+impl Socket {
+    /// Peeks at the first pending byte on the socket, without removing it, to determine
+    /// whether the peer is starting a TLS handshake or speaking plain text.
+    ///
+    /// Returns `ErrorKind::UnexpectedEof` if the peer closed the connection before sending
+    /// anything, and `ErrorKind::WouldBlock` on a non-blocking socket with no data pending.
+    pub fn sniff_first_byte(&self) -> io::Result<SniffedProtocol> {
+        let buf = self.peek_socket(1)?;
+        if buf.is_empty() {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+
+        if buf[0] == TLS_HANDSHAKE_RECORD {
+            trace!("TLS handshake record sniffed");
+            Ok(SniffedProtocol::Tls)
+        } else {
+            trace!("Plain text sniffed");
+            Ok(SniffedProtocol::Plain)
+        }
+    }
+}
+
+#[cfg(unix)]
+impl Socket {
+    /// Creates a new `Socket`, taking ownership of `fd`. Use `from_raw_fd` to take ownership
+    /// of a raw descriptor.
+    pub fn new(fd: OwnedFd) -> Socket {
+        Socket {
+            fd: fd.into_raw_fd(),
+        }
+    }
+
+    /// Shuts down the read, write, or both halves of this connection. The descriptor stays
+    /// open until the `Socket` is closed or dropped.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        let how = match how {
+            Shutdown::Read => libc::SHUT_RD,
+            Shutdown::Write => libc::SHUT_WR,
+            Shutdown::Both => libc::SHUT_RDWR,
+        };
+
+        let result = unsafe { libc::shutdown(self.fd, how) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+
+    /// Returns up to `max` bytes pending on the socket without removing them, using
+    /// `recv(2)` with `MSG_PEEK`. An empty buffer means the peer closed the connection.
+    pub fn peek_socket(&self, max: usize) -> io::Result<Vec<u8>> {
+        peek_fd(self.fd, max)
+    }
+
+    /// Returns a new `Socket` owning a duplicate of this descriptor, referring to the same
+    /// connection. The duplicate is closed on exec.
+    pub fn try_clone(&self) -> io::Result<Socket> {
+        let fd = unsafe { libc::fcntl(self.fd, libc::F_DUPFD_CLOEXEC, 0) };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // Safety: fcntl just returned this descriptor, so nothing else owns it
+        Ok(unsafe { Socket::from_raw_fd(fd) })
+    }
+
+    /// Closes the descriptor, reporting any error `close(2)` returns. Dropping a `Socket`
+    /// closes it as well, but silently ignores errors.
+    pub fn close(self) -> io::Result<()> {
+        let fd = self.into_raw_fd();
+        let result = unsafe { libc::close(fd) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl Socket {
+    /// Creates a new `Socket`, taking ownership of `socket`. Use `from_raw_socket` to take
+    /// ownership of a raw socket.
+    pub fn new(socket: OwnedSocket) -> Socket {
+        Socket {
+            socket: socket.into_raw_socket(),
+        }
+    }
+
+    /// Shuts down the read, write, or both halves of this connection. The socket stays
+    /// open until the `Socket` is closed or dropped.
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        shutdown_raw_socket(self.socket, how)
+    }
+
+    /// Returns up to `max` bytes pending on the socket without removing them, using `recv`
+    /// with `MSG_PEEK`. An empty buffer means the peer closed the connection.
+    pub fn peek_socket(&self, max: usize) -> io::Result<Vec<u8>> {
+        peek_raw_socket(self.socket, max)
+    }
+
+    /// Returns a new `Socket` owning a duplicate of this socket, referring to the same
+    /// connection. The duplicate is not inherited by child processes.
+    pub fn try_clone(&self) -> io::Result<Socket> {
+        let mut info: WinSock::WSAPROTOCOL_INFOW = unsafe { mem::zeroed() };
+        let result = unsafe {
+            WinSock::WSADuplicateSocketW(
+                self.socket as WinSock::SOCKET,
+                windows_sys::Win32::System::Threading::GetCurrentProcessId(),
+                &mut info,
+            )
+        };
+        if result == WinSock::SOCKET_ERROR {
+            return Err(last_wsa_error());
+        }
+
+        let socket = unsafe {
+            WinSock::WSASocketW(
+                info.iAddressFamily,
+                info.iSocketType,
+                info.iProtocol,
+                &info,
+                0,
+                WinSock::WSA_FLAG_OVERLAPPED | WinSock::WSA_FLAG_NO_HANDLE_INHERIT,
+            )
+        };
+        if socket == WinSock::INVALID_SOCKET {
+            return Err(last_wsa_error());
+        }
+
+        // Safety: WSASocketW just returned this socket, so nothing else owns it
+        Ok(unsafe { Socket::from_raw_socket(socket as RawSocket) })
+    }
+
+    /// Closes the socket, reporting any error `closesocket` returns. Dropping a `Socket`
+    /// closes it as well, but silently ignores errors.
+    pub fn close(self) -> io::Result<()> {
+        let socket = self.into_raw_socket();
+        let result = unsafe { WinSock::closesocket(socket as WinSock::SOCKET) };
+        if result == WinSock::SOCKET_ERROR {
+            return Err(last_wsa_error());
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let result =
+            unsafe { libc::read(self.fd, buf.as_mut_ptr() as *mut libc::c_void, buf.len()) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(result as usize)
+    }
+}
+
+#[cfg(unix)]
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let result =
+            unsafe { libc::write(self.fd, buf.as_ptr() as *const libc::c_void, buf.len()) };
+        if result < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(result as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(windows)]
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(i32::MAX as usize) as i32;
+        let result =
+            unsafe { WinSock::recv(self.socket as WinSock::SOCKET, buf.as_mut_ptr(), len, 0) };
+        if result == WinSock::SOCKET_ERROR {
+            return Err(last_wsa_error());
+        }
+
+        Ok(result as usize)
+    }
+}
+
+#[cfg(windows)]
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let len = buf.len().min(i32::MAX as usize) as i32;
+        let result = unsafe { WinSock::send(self.socket as WinSock::SOCKET, buf.as_ptr(), len, 0) };
+        if result == WinSock::SOCKET_ERROR {
+            return Err(last_wsa_error());
+        }
+
+        Ok(result as usize)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl TryClone for Socket {
+    fn try_clone(&self) -> io::Result<Socket> {
+        Socket::try_clone(self)
+    }
+}
+
+impl TryClone for TcpStream {
+    fn try_clone(&self) -> io::Result<TcpStream> {
+        TcpStream::try_clone(self)
+    }
+}
+
+#[cfg(unix)]
+impl TryClone for UnixStream {
+    fn try_clone(&self) -> io::Result<UnixStream> {
+        UnixStream::try_clone(self)
+    }
+}
+
+#[cfg(unix)]
+impl AsRawFd for Socket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd
+    }
+}
+
+#[cfg(unix)]
+impl AsFd for Socket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        // Safety: the descriptor stays open for as long as `self` is borrowed
+        unsafe { BorrowedFd::borrow_raw(self.fd) }
+    }
+}
+
+/// Takes ownership of `fd`.
+///
+/// # Safety
+///
+/// `fd` must be an open descriptor that nothing else owns, as it is closed once the `Socket`
+/// is dropped.
+#[cfg(unix)]
+impl FromRawFd for Socket {
+    unsafe fn from_raw_fd(fd: RawFd) -> Socket {
+        Socket { fd }
+    }
+}
+
+#[cfg(unix)]
+impl From<OwnedFd> for Socket {
+    fn from(fd: OwnedFd) -> Socket {
+        Socket::new(fd)
+    }
+}
+
+#[cfg(unix)]
+impl From<Socket> for OwnedFd {
+    fn from(socket: Socket) -> OwnedFd {
+        // Safety: `into_raw_fd` gives up ownership of the open descriptor
+        unsafe { OwnedFd::from_raw_fd(socket.into_raw_fd()) }
+    }
+}
+
+#[cfg(unix)]
+impl IntoRawFd for Socket {
+    fn into_raw_fd(self) -> RawFd {
+        let fd = self.fd;
+        mem::forget(self);
+        fd
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Socket {
+    fn drop(&mut self) {
+        let result = unsafe { libc::close(self.fd) };
+        if result < 0 {
+            debug!(
+                "Error closing fd {}: {}",
+                self.fd,
+                io::Error::last_os_error()
+            );
+        }
+    }
+}
+
+#[cfg(windows)]
+impl AsRawSocket for Socket {
+    fn as_raw_socket(&self) -> RawSocket {
+        self.socket
+    }
+}
+
+#[cfg(windows)]
+impl AsSocket for Socket {
+    fn as_socket(&self) -> BorrowedSocket<'_> {
+        // Safety: the socket stays open for as long as `self` is borrowed
+        unsafe { BorrowedSocket::borrow_raw(self.socket) }
+    }
+}
+
+/// Takes ownership of `socket`.
+///
+/// # Safety
+///
+/// `socket` must be an open socket that nothing else owns, as it is closed once the
+/// `Socket` is dropped.
+#[cfg(windows)]
+impl FromRawSocket for Socket {
+    unsafe fn from_raw_socket(socket: RawSocket) -> Socket {
+        Socket { socket }
+    }
+}
+
+#[cfg(windows)]
+impl From<OwnedSocket> for Socket {
+    fn from(socket: OwnedSocket) -> Socket {
+        Socket::new(socket)
+    }
+}
+
+#[cfg(windows)]
+impl From<Socket> for OwnedSocket {
+    fn from(socket: Socket) -> OwnedSocket {
+        // Safety: `into_raw_socket` gives up ownership of the open socket
+        unsafe { OwnedSocket::from_raw_socket(socket.into_raw_socket()) }
+    }
+}
+
+#[cfg(windows)]
+impl IntoRawSocket for Socket {
+    fn into_raw_socket(self) -> RawSocket {
+        let socket = self.socket;
+        mem::forget(self);
+        socket
+    }
+}
+
+#[cfg(windows)]
+impl Drop for Socket {
+    fn drop(&mut self) {
+        let result = unsafe { WinSock::closesocket(self.socket as WinSock::SOCKET) };
+        if result == WinSock::SOCKET_ERROR {
+            debug!("Error closing socket {}: {}", self.socket, last_wsa_error());
+        }
+    }
+}
+
+/// Copies up to `max` bytes pending on `fd` without consuming them.
+#[cfg(unix)]
+pub(crate) fn peek_fd(fd: RawFd, max: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; max];
+    let result = unsafe {
+        libc::recv(
+            fd,
+            buf.as_mut_ptr() as *mut libc::c_void,
+            buf.len(),
+            libc::MSG_PEEK,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    buf.truncate(result as usize);
+    trace!("Peeked {} byte(s)", buf.len());
+
+    Ok(buf)
+}
+
+/// Copies up to `max` bytes pending on `socket` without consuming them.
+#[cfg(windows)]
+pub(crate) fn peek_raw_socket(socket: RawSocket, max: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; max];
+    let len = buf.len().min(i32::MAX as usize) as i32;
+    let result = unsafe {
+        WinSock::recv(
+            socket as WinSock::SOCKET,
+            buf.as_mut_ptr(),
+            len,
+            WinSock::MSG_PEEK,
+        )
+    };
+    if result == WinSock::SOCKET_ERROR {
+        return Err(last_wsa_error());
+    }
+
+    buf.truncate(result as usize);
+    trace!("Peeked {} byte(s)", buf.len());
+
+    Ok(buf)
+}
+
+/// Shuts down the `how` halves of the connection on `socket`.
+#[cfg(windows)]
+pub(crate) fn shutdown_raw_socket(socket: RawSocket, how: Shutdown) -> io::Result<()> {
+    let how = match how {
+        Shutdown::Read => WinSock::SD_RECEIVE,
+        Shutdown::Write => WinSock::SD_SEND,
+        Shutdown::Both => WinSock::SD_BOTH,
+    };
+
+    let result = unsafe { WinSock::shutdown(socket as WinSock::SOCKET, how) };
+    if result == WinSock::SOCKET_ERROR {
+        return Err(last_wsa_error());
+    }
+
+    Ok(())
+}
+
+/// Returns the error of the last failed Winsock call on this thread.
+#[cfg(windows)]
+fn last_wsa_error() -> io::Error {
+    io::Error::from_raw_os_error(unsafe { WinSock::WSAGetLastError() })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    /// Returns a `Socket` owning one end of a connected pair, and the other end.
+    fn pair() -> (Socket, UnixStream) {
+        let (local, remote) = UnixStream::pair().unwrap();
+        (Socket::new(OwnedFd::from(local)), remote)
+    }
+
+    #[test]
+    fn sniffing_leaves_the_first_byte_pending() {
+        let (mut socket, mut remote) = pair();
+        remote.write_all(&[TLS_HANDSHAKE_RECORD, 0x03]).unwrap();
+        assert_eq!(socket.sniff_first_byte().unwrap(), SniffedProtocol::Tls);
+
+        let mut buf = [0u8; 2];
+        socket.read_exact(&mut buf).unwrap();
+        assert_eq!(buf, [TLS_HANDSHAKE_RECORD, 0x03]);
+
+        remote.write_all(b"GET").unwrap();
+        assert_eq!(socket.sniff_first_byte().unwrap(), SniffedProtocol::Plain);
+        assert_eq!(socket.peek_socket(8).unwrap(), b"GET");
+    }
+
+    #[test]
+    fn sniffing_a_closed_connection_is_eof() {
+        let (socket, remote) = pair();
+        drop(remote);
+
+        let e = socket.sniff_first_byte().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn clones_refer_to_the_same_connection() {
+        let (socket, mut remote) = pair();
+        let mut clone = socket.try_clone().unwrap();
+        assert_ne!(clone.as_raw_fd(), socket.as_raw_fd());
+        socket.close().unwrap();
+
+        clone.write_all(b"hi").unwrap();
+        let mut buf = [0u8; 2];
+        remote.read_exact(&mut buf).unwrap();
+        assert_eq!(&buf, b"hi");
+    }
+}
@@ -0,0 +1,413 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::io;
+use std::mem;
+use std::os::unix::io::{AsRawFd, RawFd};
+use std::time::{Duration, Instant};
+
+use crate::Socket;
+
+/// Values of every socket option this crate knows about, captured at a point in time.
+///
+/// TCP level options are `None` when the socket is not a TCP socket.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SocketOptionsSnapshot {
+    pub nonblocking: bool,
+    pub recv_buffer_size: usize,
+    pub send_buffer_size: usize,
+    pub keepalive: bool,
+    pub reuse_addr: bool,
+    pub linger: Option<Duration>,
+    pub recv_timeout: Option<Duration>,
+    pub send_timeout: Option<Duration>,
+    pub tcp_nodelay: Option<bool>,
+    /// `SO_BUSY_POLL`, `None` when off or not supported by the platform.
+    pub busy_poll: Option<Duration>,
+    /// `TCP_USER_TIMEOUT`, `None` when off, not a TCP socket or not supported by the platform.
+    pub tcp_user_timeout: Option<Duration>,
+}
+
+/// TCP level options a stream applies around the frames it sends and receives. Set them with
+/// `set_tcp_options` on `Plain` or `Secure`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TcpOptions {
+    /// Sends of at least this many bytes are written with `TCP_CORK` set, and uncorked once
+    /// their last byte has been written, so large frames go out in full sized segments.
+    pub cork_threshold: Option<usize>,
+    /// Sets `TCP_QUICKACK` after every read. The kernel clears the option on its own, so it
+    /// has to be reapplied to keep acknowledgements immediate.
+    pub quickack: bool,
+    /// Blocking receives spin on the socket for up to this long before going to sleep, and
+    /// `SO_BUSY_POLL` is set to the same value so the kernel polls the device queue too.
+    /// Trades CPU for latency, so only pays off with a core to spare for every spinning
+    /// stream. See `set_busy_poll` for the privileges needed.
+    pub busy_poll: Option<Duration>,
+    /// Sets `TCP_USER_TIMEOUT`, failing the connection once sent data has gone unacknowledged
+    /// for this long, instead of after the kernel's retransmission limit. Linux only.
+    pub user_timeout: Option<Duration>,
+}
+
+/// A single option that differs between two snapshots.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OptionChange {
+    pub name: &'static str,
+    pub before: String,
+    pub after: String,
+}
+
+impl Socket {
+    /// Captures the current value of every socket option this crate knows about.
+    pub fn options_snapshot(&self) -> io::Result<SocketOptionsSnapshot> {
+        snapshot(self.as_raw_fd())
+    }
+}
+
+impl SocketOptionsSnapshot {
+    /// Returns every option whose value differs between `self` and `other`, with `self` as
+    /// the before value.
+    pub fn diff(&self, other: &SocketOptionsSnapshot) -> Vec<OptionChange> {
+        let mut changes = Vec::new();
+
+        macro_rules! compare {
+            ($($field:ident),*) => {
+                $(
+                    if self.$field != other.$field {
+                        changes.push(OptionChange {
+                            name: stringify!($field),
+                            before: format!("{:?}", self.$field),
+                            after: format!("{:?}", other.$field),
+                        });
+                    }
+                )*
+            };
+        }
+
+        compare!(
+            nonblocking,
+            recv_buffer_size,
+            send_buffer_size,
+            keepalive,
+            reuse_addr,
+            linger,
+            recv_timeout,
+            send_timeout,
+            tcp_nodelay,
+            busy_poll,
+            tcp_user_timeout
+        );
+
+        changes
+    }
+}
+
+/// Sets or clears `TCP_CORK` on `socket`. While set, partial segments are held back until
+/// the option is cleared.
+pub fn set_tcp_cork<F: AsRawFd>(socket: &F, cork: bool) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_CORK,
+            cork as libc::c_int,
+        )
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = (socket, cork);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Sets or clears `TCP_QUICKACK` on `socket`.
+pub fn set_tcp_quickack<F: AsRawFd>(socket: &F, quickack: bool) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_QUICKACK,
+            quickack as libc::c_int,
+        )
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = (socket, quickack);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Sets `TCP_USER_TIMEOUT` on `socket`, so the connection fails once sent data has gone
+/// unacknowledged for `timeout`, or restores the kernel's default if `None`. Linux only.
+pub fn set_tcp_user_timeout<F: AsRawFd>(socket: &F, timeout: Option<Duration>) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let msecs = timeout.map_or(0, |timeout| timeout.as_millis().max(1));
+        setsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_TCP,
+            libc::TCP_USER_TIMEOUT,
+            libc::c_uint::try_from(msecs).unwrap_or(libc::c_uint::MAX),
+        )
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = (socket, timeout);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Sets `SO_BUSY_POLL` on `socket`, making reads with nothing queued poll the device queue
+/// for up to `budget` before sleeping, or turns it off if `None`. Linux only.
+///
+/// Raising the value above its current one, which starts at the `net.core.busy_read` sysctl,
+/// requires `CAP_NET_ADMIN` and fails with `ErrorKind::PermissionDenied` otherwise.
+pub fn set_busy_poll<F: AsRawFd>(socket: &F, budget: Option<Duration>) -> io::Result<()> {
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        let usecs = budget.map_or(0, |budget| budget.as_micros());
+        setsockopt(
+            socket.as_raw_fd(),
+            libc::SOL_SOCKET,
+            libc::SO_BUSY_POLL,
+            libc::c_int::try_from(usecs).unwrap_or(libc::c_int::MAX),
+        )
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = (socket, budget);
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+/// Applies `TcpOptions` to the socket of a stream as it sends and receives.
+#[derive(Clone, Debug)]
+pub(crate) struct TcpTuning {
+    fd: RawFd,
+    options: TcpOptions,
+    corked: bool,
+}
+
+impl TcpTuning {
+    pub(crate) fn new(fd: RawFd, options: TcpOptions) -> TcpTuning {
+        if options.busy_poll.is_some() {
+            if let Err(e) = set_busy_poll(&fd, options.busy_poll) {
+                debug!("Unable to set SO_BUSY_POLL: {}", e);
+            }
+        }
+        if options.user_timeout.is_some() {
+            if let Err(e) = set_tcp_user_timeout(&fd, options.user_timeout) {
+                debug!("Unable to set TCP_USER_TIMEOUT: {}", e);
+            }
+        }
+
+        TcpTuning {
+            fd,
+            options,
+            corked: false,
+        }
+    }
+
+    /// Corks the socket ahead of writing `len` bytes, if that reaches the threshold.
+    pub(crate) fn before_write(&mut self, len: usize) {
+        let threshold = match self.options.cork_threshold {
+            Some(threshold) => threshold,
+            None => return,
+        };
+
+        if !self.corked && len >= threshold {
+            match set_tcp_cork(&self.fd, true) {
+                Ok(()) => self.corked = true,
+                Err(e) => debug!("Unable to set TCP_CORK: {}", e),
+            }
+        }
+    }
+
+    /// Uncorks the socket once everything queued has been written, which is always a frame
+    /// boundary.
+    pub(crate) fn after_write(&mut self, pending: usize) {
+        if self.corked && pending == 0 {
+            if let Err(e) = set_tcp_cork(&self.fd, false) {
+                debug!("Unable to clear TCP_CORK: {}", e);
+            }
+            self.corked = false;
+        }
+    }
+
+    /// Spins until the socket is readable or the busy poll budget runs out, ahead of a read
+    /// that would otherwise put the thread to sleep.
+    pub(crate) fn before_blocking_read(&self) {
+        let budget = match self.options.busy_poll {
+            Some(budget) => budget,
+            None => return,
+        };
+
+        let started = Instant::now();
+        let mut fds = [libc::pollfd {
+            fd: self.fd,
+            events: libc::POLLIN,
+            revents: 0,
+        }];
+        while started.elapsed() < budget {
+            // Errors and hangups are left for the read to report
+            if unsafe { libc::poll(fds.as_mut_ptr(), 1, 0) } != 0 {
+                return;
+            }
+            std::hint::spin_loop();
+        }
+    }
+
+    pub(crate) fn after_read(&self) {
+        if self.options.quickack {
+            if let Err(e) = set_tcp_quickack(&self.fd, true) {
+                debug!("Unable to set TCP_QUICKACK: {}", e);
+            }
+        }
+    }
+}
+
+fn snapshot(fd: RawFd) -> io::Result<SocketOptionsSnapshot> {
+    let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+    if flags < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    let linger: libc::linger = getsockopt(fd, libc::SOL_SOCKET, libc::SO_LINGER)?;
+    let linger = if linger.l_onoff != 0 {
+        Some(Duration::from_secs(linger.l_linger as u64))
+    } else {
+        None
+    };
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let busy_poll = match getsockopt::<libc::c_int>(fd, libc::SOL_SOCKET, libc::SO_BUSY_POLL) {
+        Ok(usecs) if usecs > 0 => Some(Duration::from_micros(usecs as u64)),
+        _ => None,
+    };
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let busy_poll = None;
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    let tcp_user_timeout =
+        match getsockopt::<libc::c_uint>(fd, libc::IPPROTO_TCP, libc::TCP_USER_TIMEOUT) {
+            Ok(msecs) if msecs > 0 => Some(Duration::from_millis(msecs as u64)),
+            _ => None,
+        };
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    let tcp_user_timeout = None;
+
+    let tcp_nodelay = match getsockopt::<libc::c_int>(fd, libc::IPPROTO_TCP, libc::TCP_NODELAY) {
+        Ok(nodelay) => Some(nodelay != 0),
+        Err(_) => None,
+    };
+
+    Ok(SocketOptionsSnapshot {
+        nonblocking: flags & libc::O_NONBLOCK != 0,
+        recv_buffer_size: getsockopt::<libc::c_int>(fd, libc::SOL_SOCKET, libc::SO_RCVBUF)?
+            as usize,
+        send_buffer_size: getsockopt::<libc::c_int>(fd, libc::SOL_SOCKET, libc::SO_SNDBUF)?
+            as usize,
+        keepalive: getsockopt::<libc::c_int>(fd, libc::SOL_SOCKET, libc::SO_KEEPALIVE)? != 0,
+        reuse_addr: getsockopt::<libc::c_int>(fd, libc::SOL_SOCKET, libc::SO_REUSEADDR)? != 0,
+        linger,
+        recv_timeout: timeout(getsockopt(fd, libc::SOL_SOCKET, libc::SO_RCVTIMEO)?),
+        send_timeout: timeout(getsockopt(fd, libc::SOL_SOCKET, libc::SO_SNDTIMEO)?),
+        tcp_nodelay,
+        busy_poll,
+        tcp_user_timeout,
+    })
+}
+
+fn timeout(tv: libc::timeval) -> Option<Duration> {
+    if tv.tv_sec == 0 && tv.tv_usec == 0 {
+        return None;
+    }
+
+    Some(Duration::new(tv.tv_sec as u64, (tv.tv_usec as u32) * 1000))
+}
+
+/// Reads the socket option `name` at `level` into a `T`.
+pub(crate) fn getsockopt<T: Copy>(
+    fd: RawFd,
+    level: libc::c_int,
+    name: libc::c_int,
+) -> io::Result<T> {
+    let mut value: T = unsafe { mem::zeroed() };
+    let mut len = mem::size_of::<T>() as libc::socklen_t;
+    let result = unsafe {
+        libc::getsockopt(
+            fd,
+            level,
+            name,
+            &mut value as *mut T as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(value)
+}
+
+/// Sets the socket option `name` at `level` to `value`.
+pub(crate) fn setsockopt<T: Copy>(
+    fd: RawFd,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: T,
+) -> io::Result<()> {
+    let result = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            &value as *const T as *const libc::c_void,
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        return Err(io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::{TcpListener, TcpStream};
+    use std::os::unix::net::UnixStream;
+
+    #[test]
+    fn diffs_name_the_options_changed() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let before = snapshot(stream.as_raw_fd()).unwrap();
+        assert!(before.tcp_nodelay.is_some());
+
+        stream.set_nodelay(!before.tcp_nodelay.unwrap()).unwrap();
+        stream
+            .set_read_timeout(Some(Duration::from_secs(2)))
+            .unwrap();
+        let after = snapshot(stream.as_raw_fd()).unwrap();
+        assert_eq!(after.recv_timeout, Some(Duration::from_secs(2)));
+
+        let changed: Vec<_> = before.diff(&after).iter().map(|c| c.name).collect();
+        assert_eq!(changed, ["recv_timeout", "tcp_nodelay"]);
+        assert!(after.diff(&after).is_empty());
+    }
+
+    #[test]
+    fn tcp_options_are_absent_on_other_sockets() {
+        let (stream, _) = UnixStream::pair().unwrap();
+        let snapshot = snapshot(stream.as_raw_fd()).unwrap();
+        assert_eq!(snapshot.tcp_nodelay, None);
+        assert_eq!(snapshot.tcp_user_timeout, None);
+    }
+}
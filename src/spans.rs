@@ -0,0 +1,251 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! `tracing` spans around the receives, sends and handshakes of `Plain` and `Secure`, with
+//! the `tracing` feature. Without it, everything here does nothing.
+//!
+//! Receives and sends are `TRACE` spans named `recv` and `send`, and handshakes `DEBUG` spans
+//! named `handshake`, all with the target `simple_stream`. They carry:
+//!
+//! * `call`: the method called, e.g. `nb_recv`.
+//! * `fd`: the stream's file descriptor, once recorded with `trace_fd`.
+//! * `frame`: the kind of the frame sent or received, the first one if several were.
+//! * `frames`: how many frames a non-blocking receive returned, or a batch sent held.
+//! * `bytes`: the encoded length of those frames.
+//! * `error`: why the call failed, unless it would have blocked.
+
+use std::io;
+
+use crate::frame::Frame;
+
+/// Opens the spans of one stream.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Spans {
+    #[cfg(feature = "tracing")]
+    fd: Option<i64>,
+}
+
+/// The span of a single call, entered until dropped.
+pub(crate) struct IoSpan {
+    #[cfg(feature = "tracing")]
+    span: tracing::span::EnteredSpan,
+}
+
+impl Spans {
+    /// Records `fd` in every span opened from now on.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn set_fd(&mut self, fd: i64) {
+        self.fd = Some(fd);
+    }
+
+    /// Opens the span of a receive made with `call`.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn recv(&self, call: &'static str) -> IoSpan {
+        IoSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::trace_span!(
+                target: "simple_stream",
+                "recv",
+                call,
+                fd = self.fd,
+                frame = tracing::field::Empty,
+                frames = tracing::field::Empty,
+                bytes = tracing::field::Empty,
+                error = tracing::field::Empty,
+            )
+            .entered(),
+        }
+    }
+
+    /// Opens the span of sending `frame` with `call`.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn send(&self, call: &'static str, frame: &dyn Frame) -> IoSpan {
+        IoSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::trace_span!(
+                target: "simple_stream",
+                "send",
+                call,
+                fd = self.fd,
+                frame = frame.kind(),
+                bytes = frame.len_as_vec(),
+                error = tracing::field::Empty,
+            )
+            .entered(),
+        }
+    }
+
+    /// Opens the span of sending the batch `frames` with `call`.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn send_all(&self, call: &'static str, frames: &[&dyn Frame]) -> IoSpan {
+        IoSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::trace_span!(
+                target: "simple_stream",
+                "send",
+                call,
+                fd = self.fd,
+                frame = frames.first().map(|frame| frame.kind()),
+                frames = frames.len(),
+                bytes = frames.iter().map(|frame| frame.len_as_vec()).sum::<usize>(),
+                error = tracing::field::Empty,
+            )
+            .entered(),
+        }
+    }
+
+    /// Opens the span of a TLS handshake.
+    pub(crate) fn handshake(&self) -> IoSpan {
+        IoSpan {
+            #[cfg(feature = "tracing")]
+            span: tracing::debug_span!(
+                target: "simple_stream",
+                "handshake",
+                fd = self.fd,
+                error = tracing::field::Empty,
+            )
+            .entered(),
+        }
+    }
+}
+
+impl IoSpan {
+    /// Records the outcome of a receive returning one frame.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn received(&self, result: &io::Result<Box<dyn Frame>>) {
+        #[cfg(feature = "tracing")]
+        if let Ok(ref frame) = *result {
+            self.span.record("frame", frame.kind());
+            self.span.record("bytes", frame.len_as_vec());
+        }
+        self.done(result);
+    }
+
+    /// Records the outcome of a receive that appended `frames`.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn received_many(&self, frames: &[Box<dyn Frame>], result: &io::Result<usize>) {
+        #[cfg(feature = "tracing")]
+        if let Some(first) = frames.first() {
+            let bytes: usize = frames.iter().map(|frame| frame.len_as_vec()).sum();
+            self.span.record("frame", first.kind());
+            self.span.record("frames", frames.len());
+            self.span.record("bytes", bytes);
+        }
+        self.done(result);
+    }
+
+    /// Records the error `result` holds, if any.
+    #[cfg_attr(not(feature = "tracing"), allow(unused_variables))]
+    pub(crate) fn done<T>(&self, result: &io::Result<T>) {
+        #[cfg(feature = "tracing")]
+        match *result {
+            Err(ref e) if e.kind() != io::ErrorKind::WouldBlock => {
+                self.span.record("error", tracing::field::display(e));
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(all(test, feature = "tracing"))]
+mod tests {
+    use super::*;
+    use std::fmt;
+    use std::sync::{Arc, Mutex};
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Attributes, Id, Record};
+    use tracing::{Event, Metadata, Subscriber};
+
+    use crate::frame::SimpleFrame;
+
+    /// Name and recorded fields of every span opened.
+    type Recorded = Arc<Mutex<Vec<(&'static str, Vec<(&'static str, String)>)>>>;
+
+    struct Recorder(Recorded);
+
+    struct Fields<'a>(&'a mut Vec<(&'static str, String)>);
+
+    impl Visit for Fields<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0.push((field.name(), value.to_string()));
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+            self.0.push((field.name(), format!("{:?}", value)));
+        }
+    }
+
+    impl Subscriber for Recorder {
+        fn enabled(&self, _: &Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &Attributes<'_>) -> Id {
+            let mut spans = self.0.lock().unwrap();
+            let mut fields = Vec::new();
+            attrs.record(&mut Fields(&mut fields));
+            spans.push((attrs.metadata().name(), fields));
+            Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &Id, values: &Record<'_>) {
+            let mut spans = self.0.lock().unwrap();
+            let index = span.into_u64() as usize - 1;
+            values.record(&mut Fields(&mut spans[index].1));
+        }
+
+        fn record_follows_from(&self, _: &Id, _: &Id) {}
+
+        fn event(&self, _: &Event<'_>) {}
+
+        fn enter(&self, _: &Id) {}
+
+        fn exit(&self, _: &Id) {}
+    }
+
+    fn field(fields: &[(&'static str, String)], name: &str) -> Option<String> {
+        fields.iter().find(|f| f.0 == name).map(|f| f.1.clone())
+    }
+
+    #[test]
+    fn spans_record_frames_and_errors() {
+        let recorded = Recorded::default();
+        let mut spans = Spans::default();
+        spans.set_fd(3);
+
+        tracing::subscriber::with_default(Recorder(recorded.clone()), || {
+            let frames: Vec<Box<dyn Frame>> = vec![
+                Box::new(SimpleFrame::new(b"one")),
+                Box::new(SimpleFrame::new(b"two")),
+            ];
+            spans.recv("nb_recv").received_many(&frames, &Ok(2));
+
+            let frame = SimpleFrame::new(b"three");
+            let span = spans.send("nb_send", &frame);
+            span.done::<()>(&Err(io::ErrorKind::WouldBlock.into()));
+            span.done::<()>(&Err(io::Error::other("boom")));
+        });
+
+        let recorded = recorded.lock().unwrap();
+        let (name, ref recv) = recorded[0];
+        assert_eq!(name, "recv");
+        assert_eq!(field(recv, "call").as_deref(), Some("nb_recv"));
+        assert_eq!(field(recv, "fd").as_deref(), Some("3"));
+        assert_eq!(field(recv, "frame").as_deref(), Some("SimpleFrame"));
+        assert_eq!(field(recv, "frames").as_deref(), Some("2"));
+        assert_eq!(field(recv, "bytes").as_deref(), Some("14"));
+        assert_eq!(field(recv, "error"), None);
+
+        let (name, ref send) = recorded[1];
+        assert_eq!(name, "send");
+        assert_eq!(field(send, "bytes").as_deref(), Some("9"));
+        let errors: Vec<_> = send.iter().filter(|f| f.0 == "error").collect();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].1, "boom");
+    }
+}
@@ -0,0 +1,186 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::scheduler::FrameSummary;
+
+const NUM_BUCKETS: usize = 32;
+
+/// Histogram of latencies with power of two microsecond buckets. Bucket `0` counts samples
+/// under 1µs, and bucket `n` counts samples in `[2^(n-1), 2^n)` µs.
+#[derive(Clone, Debug, Default)]
+pub struct LatencyHistogram {
+    buckets: [u64; NUM_BUCKETS],
+    count: u64,
+    total: Duration,
+    max: Duration,
+}
+
+impl LatencyHistogram {
+    /// Adds a sample to the histogram.
+    pub fn record(&mut self, latency: Duration) {
+        let micros = latency.as_micros();
+        let bucket = (128 - micros.leading_zeros()) as usize;
+        self.buckets[bucket.min(NUM_BUCKETS - 1)] += 1;
+        self.count += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+    }
+
+    /// Returns the number of samples recorded.
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    /// Returns the mean of all samples, or zero if there are none.
+    pub fn mean(&self) -> Duration {
+        if self.count == 0 {
+            return Duration::ZERO;
+        }
+        Duration::from_nanos((self.total.as_nanos() / self.count as u128) as u64)
+    }
+
+    /// Returns the largest sample recorded.
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    /// Returns the sample count of each bucket.
+    pub fn buckets(&self) -> &[u64] {
+        &self.buckets[..]
+    }
+
+    /// Returns the upper bound of the bucket containing the `p`th percentile, where `p` is
+    /// between `0.0` and `1.0`.
+    pub fn percentile(&self, p: f64) -> Duration {
+        let target = (self.count as f64 * p.clamp(0.0, 1.0)).ceil() as u64;
+        let mut seen = 0u64;
+        for (bucket, &count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target && count > 0 {
+                return Duration::from_micros(1u64 << bucket).min(self.max);
+            }
+        }
+
+        self.max
+    }
+
+    /// Discards all recorded samples.
+    pub fn clear(&mut self) {
+        *self = LatencyHistogram::default();
+    }
+}
+
+/// Reports how much of each large frame has been written as a stream's send queue drains,
+/// e.g. to show upload progress. The callback receives the bytes of the frame written so far
+/// and its total length, every time more of it is written.
+#[derive(Clone)]
+pub struct SendProgress {
+    threshold: usize,
+    callback: Arc<dyn Fn(usize, usize) + Send + Sync>,
+}
+
+/// Tracks when frames were queued for sending, so the time until their last byte is written
+/// to the underlying stream can be recorded.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct SendTimings {
+    pending: VecDeque<FrameSummary>,
+    histogram: LatencyHistogram,
+    progress: Option<SendProgress>,
+}
+
+impl SendProgress {
+    /// Calls `callback` as frames of at least `threshold` bytes are written.
+    pub fn new<F>(threshold: usize, callback: F) -> SendProgress
+    where
+        F: Fn(usize, usize) + Send + Sync + 'static,
+    {
+        SendProgress {
+            threshold,
+            callback: Arc::new(callback),
+        }
+    }
+
+    pub fn threshold(&self) -> usize {
+        self.threshold
+    }
+}
+
+impl fmt::Debug for SendProgress {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SendProgress")
+            .field("threshold", &self.threshold)
+            .finish()
+    }
+}
+
+impl SendTimings {
+    /// Records `frame` being next in line to be written.
+    pub(crate) fn enqueued(&mut self, frame: FrameSummary) {
+        self.pending.push_back(frame);
+    }
+
+    /// Returns the frames being written, in the order they are written in.
+    pub(crate) fn pending(&self) -> impl Iterator<Item = &FrameSummary> {
+        self.pending.iter()
+    }
+
+    /// Records `num_written` bytes, from the front of the queue, being written.
+    pub(crate) fn flushed(&mut self, mut num_written: usize) {
+        while num_written > 0 {
+            let front = match self.pending.front_mut() {
+                Some(front) => front,
+                None => return,
+            };
+
+            let written = front.remaining.min(num_written);
+            front.remaining -= written;
+            num_written -= written;
+            if let Some(ref progress) = self.progress {
+                if front.len >= progress.threshold {
+                    (progress.callback)(front.len - front.remaining, front.len);
+                }
+            }
+
+            if front.remaining > 0 {
+                return;
+            }
+            self.histogram.record(front.enqueued_at.elapsed());
+            self.pending.pop_front();
+        }
+    }
+
+    pub(crate) fn set_progress(&mut self, progress: Option<SendProgress>) {
+        self.progress = progress;
+    }
+
+    pub(crate) fn histogram(&self) -> &LatencyHistogram {
+        &self.histogram
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mean_of_more_samples_than_fit_a_u32() {
+        let mut histogram = LatencyHistogram::default();
+        histogram.record(Duration::from_micros(3));
+        histogram.count = 1 << 32;
+        histogram.total = Duration::from_micros(3 << 32);
+        assert_eq!(histogram.mean(), Duration::from_micros(3));
+
+        histogram.count += 1;
+        histogram.total += Duration::from_micros(3);
+        assert_eq!(histogram.mean(), Duration::from_micros(3));
+    }
+}
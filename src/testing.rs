@@ -0,0 +1,168 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! In-memory transports for unit testing frame handling without sockets.
+//!
+//! `MockStream` is one end of an in-memory pipe whose next reads and writes can be scripted
+//! to be short, block or fail, so each case is hit on purpose rather than by chance over
+//! loopback TCP.
+//!
+//! ```ignore
+//! let (local, mut remote) = MockStream::pair();
+//! let mut stream = Plain::<_, SimpleFrameBuilder>::new(local);
+//!
+//! // The frame arrives one byte at a time, with a WouldBlock in between
+//! remote.write_all(&SimpleFrame::new(b"hi").to_bytes())?;
+//! stream.get_mut().script_reads(&[MockStep::Partial(1), MockStep::WouldBlock]);
+//! assert!(stream.nb_recv().is_err());
+//! assert_eq!(stream.nb_recv()?[0].payload(), b"hi");
+//! ```
+//!
+//! For faults injected at random over long runs, see `FaultyTransport`.
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+use crate::duplex::Duplex;
+
+/// What a single scripted `read` or `write` on a `MockStream` does.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MockStep {
+    /// Transfers no more than this many bytes, at least one, or fewer if fewer are pending.
+    Partial(usize),
+    /// Fails with `ErrorKind::WouldBlock`, even if bytes are pending.
+    WouldBlock,
+    /// Fails with `ErrorKind::Interrupted`.
+    Interrupted,
+    /// Fails with an error of this kind.
+    Fail(io::ErrorKind),
+}
+
+/// One end of an in-memory, bidirectional byte pipe, with scripted reads and writes.
+///
+/// Calls without a step scripted behave like a `Duplex`: as a non-blocking socket, reading
+/// `ErrorKind::WouldBlock` with nothing pending and `Ok(0)` once the other end is dropped.
+#[derive(Debug)]
+pub struct MockStream {
+    pipe: Duplex,
+    reads: VecDeque<MockStep>,
+    writes: VecDeque<MockStep>,
+}
+
+impl MockStream {
+    /// Creates two connected ends. Bytes written to one end are read from the other.
+    pub fn pair() -> (MockStream, MockStream) {
+        let (a, b) = Duplex::pair();
+        (MockStream::new(a), MockStream::new(b))
+    }
+
+    fn new(pipe: Duplex) -> MockStream {
+        MockStream {
+            pipe,
+            reads: VecDeque::new(),
+            writes: VecDeque::new(),
+        }
+    }
+
+    /// Queues `steps` for the next `read` calls, one per call, after any already queued.
+    pub fn script_reads(&mut self, steps: &[MockStep]) {
+        self.reads.extend(steps);
+    }
+
+    /// Queues `steps` for the next `write` calls, one per call, after any already queued.
+    pub fn script_writes(&mut self, steps: &[MockStep]) {
+        self.writes.extend(steps);
+    }
+
+    /// Returns how many scripted steps have not been used yet, reads and writes together.
+    pub fn steps_remaining(&self) -> usize {
+        self.reads.len() + self.writes.len()
+    }
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.reads.pop_front() {
+            None => self.pipe.read(buf),
+            Some(MockStep::Partial(max)) => {
+                let len = buf.len().min(max.max(1));
+                self.pipe.read(&mut buf[..len])
+            }
+            Some(step) => Err(step_error(step)),
+        }
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self.writes.pop_front() {
+            None => self.pipe.write(buf),
+            Some(MockStep::Partial(max)) => {
+                let len = buf.len().min(max.max(1));
+                self.pipe.write(&buf[..len])
+            }
+            Some(step) => Err(step_error(step)),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn step_error(step: MockStep) -> io::Error {
+    match step {
+        MockStep::Partial(_) | MockStep::WouldBlock => io::ErrorKind::WouldBlock.into(),
+        MockStep::Interrupted => io::ErrorKind::Interrupted.into(),
+        MockStep::Fail(kind) => kind.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scripted_steps_run_once_each_in_order() {
+        let (mut local, mut remote) = MockStream::pair();
+        remote.write_all(b"hello").unwrap();
+        local.script_reads(&[
+            MockStep::WouldBlock,
+            MockStep::Partial(2),
+            MockStep::Fail(io::ErrorKind::ConnectionReset),
+        ]);
+        assert_eq!(local.steps_remaining(), 3);
+
+        let mut buf = [0u8; 8];
+        let e = local.read(&mut buf).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+        assert_eq!(local.read(&mut buf).unwrap(), 2);
+        assert_eq!(&buf[..2], b"he");
+        let e = local.read(&mut buf).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::ConnectionReset);
+
+        assert_eq!(local.steps_remaining(), 0);
+        assert_eq!(local.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], b"llo");
+    }
+
+    #[test]
+    fn scripted_writes_are_short_or_fail() {
+        let (mut local, mut remote) = MockStream::pair();
+        local.script_writes(&[MockStep::Interrupted, MockStep::Partial(0)]);
+
+        let e = local.write(b"abc").unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::Interrupted);
+        // A partial step always moves at least one byte
+        assert_eq!(local.write(b"abc").unwrap(), 1);
+        assert_eq!(local.write(b"bc").unwrap(), 2);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(remote.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], b"abc");
+    }
+}
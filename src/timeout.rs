@@ -0,0 +1,175 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+use crate::cancel::{wait_ready, CancellationToken, Interest};
+use crate::sockopt::setsockopt;
+
+/// Error carried by the `Error::Io`, of `ErrorKind::TimedOut`, a blocking receive or send
+/// returns once the timeout set with `set_recv_timeout` or `set_send_timeout` passes without
+/// the socket becoming ready. The stream stays open, and the call can be retried. Retrieve it
+/// with `get_ref()` and `downcast_ref::<IoTimeout>()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IoTimeout {
+    /// Nothing was received for this long.
+    Recv(Duration),
+    /// Nothing could be sent for this long.
+    Send(Duration),
+}
+
+/// The receive and send timeouts of a stream's blocking calls, on the socket `fd`.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Timeouts {
+    fd: RawFd,
+    recv: Option<Duration>,
+    send: Option<Duration>,
+}
+
+impl fmt::Display for IoTimeout {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            IoTimeout::Recv(timeout) => write!(f, "Nothing received for {:?}", timeout),
+            IoTimeout::Send(timeout) => write!(f, "Nothing sent for {:?}", timeout),
+        }
+    }
+}
+
+impl Error for IoTimeout {}
+
+impl Timeouts {
+    pub(crate) fn new(fd: RawFd) -> Timeouts {
+        Timeouts {
+            fd,
+            recv: None,
+            send: None,
+        }
+    }
+
+    /// Sets the timeout for `interest`, or removes it if `None`. Sockets get the same value as
+    /// `SO_RCVTIMEO` or `SO_SNDTIMEO`, which also bounds a single blocking read or write of
+    /// more than the socket can take at once. Other descriptors rely on `poll(2)` alone.
+    pub(crate) fn set(&mut self, interest: Interest, timeout: Option<Duration>) {
+        let name = match interest {
+            Interest::Readable => {
+                self.recv = timeout;
+                libc::SO_RCVTIMEO
+            }
+            Interest::Writable => {
+                self.send = timeout;
+                libc::SO_SNDTIMEO
+            }
+        };
+
+        let timeout = timeout.unwrap_or(Duration::ZERO);
+        let tv = libc::timeval {
+            tv_sec: timeout.as_secs().min(libc::time_t::MAX as u64) as libc::time_t,
+            tv_usec: timeout.subsec_micros() as libc::suseconds_t,
+        };
+        match setsockopt(self.fd, libc::SOL_SOCKET, name, tv) {
+            Ok(()) => {}
+            Err(ref e) if e.raw_os_error() == Some(libc::ENOTSOCK) => {}
+            Err(e) => debug!("Unable to set socket timeout: {}", e),
+        }
+    }
+
+    /// Waits until the socket is ready for `interest`, for no longer than its timeout. Fails
+    /// with an `IoTimeout` once that passes, or with `ErrorKind::Interrupted` if `token` is
+    /// cancelled first.
+    pub(crate) fn wait(
+        &self,
+        interest: Interest,
+        token: Option<&CancellationToken>,
+    ) -> io::Result<()> {
+        let timeout = self.get(interest);
+        if timeout.is_none() && token.is_none() {
+            return Ok(());
+        }
+
+        match wait_ready(self.fd, interest, token, timeout)? {
+            true => Ok(()),
+            false => Err(self.timed_out(interest)),
+        }
+    }
+
+    /// Returns `e` as an `IoTimeout` if it is the `ErrorKind::WouldBlock` a blocking call gets
+    /// once the socket timeout for `interest` passes.
+    pub(crate) fn classify(&self, interest: Interest, e: io::Error) -> io::Error {
+        match e.kind() {
+            io::ErrorKind::WouldBlock if self.get(interest).is_some() => self.timed_out(interest),
+            _ => e,
+        }
+    }
+
+    fn get(&self, interest: Interest) -> Option<Duration> {
+        match interest {
+            Interest::Readable => self.recv,
+            Interest::Writable => self.send,
+        }
+    }
+
+    fn timed_out(&self, interest: Interest) -> io::Error {
+        let timeout = self.get(interest).unwrap_or_default();
+        let e = match interest {
+            Interest::Readable => IoTimeout::Recv(timeout),
+            Interest::Writable => IoTimeout::Send(timeout),
+        };
+        debug!("{}", e);
+        io::Error::new(io::ErrorKind::TimedOut, e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::net::UnixStream;
+
+    #[test]
+    fn waits_time_out_until_the_socket_is_ready() {
+        let (local, mut remote) = UnixStream::pair().unwrap();
+        let mut timeouts = Timeouts::new(local.as_raw_fd());
+        assert!(timeouts.wait(Interest::Readable, None).is_ok());
+
+        timeouts.set(Interest::Readable, Some(Duration::from_millis(20)));
+        let e = timeouts.wait(Interest::Readable, None).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+        let timeout = e.get_ref().unwrap().downcast_ref::<IoTimeout>();
+        assert_eq!(timeout, Some(&IoTimeout::Recv(Duration::from_millis(20))));
+
+        remote.write_all(b"hi").unwrap();
+        assert!(timeouts.wait(Interest::Readable, None).is_ok());
+        assert!(timeouts.wait(Interest::Writable, None).is_ok());
+    }
+
+    #[test]
+    fn socket_timeouts_turn_would_block_into_io_timeouts() {
+        let (mut local, _remote) = UnixStream::pair().unwrap();
+        let mut timeouts = Timeouts::new(local.as_raw_fd());
+        timeouts.set(Interest::Readable, Some(Duration::from_millis(20)));
+        assert_eq!(
+            local.read_timeout().unwrap(),
+            Some(Duration::from_millis(20))
+        );
+
+        let e = local.read(&mut [0u8; 8]).unwrap_err();
+        let e = timeouts.classify(Interest::Readable, e);
+        assert_eq!(e.kind(), io::ErrorKind::TimedOut);
+
+        let would_block = io::Error::from(io::ErrorKind::WouldBlock);
+        let e = timeouts.classify(Interest::Writable, would_block);
+        assert_eq!(e.kind(), io::ErrorKind::WouldBlock);
+
+        timeouts.set(Interest::Readable, None);
+        assert_eq!(local.read_timeout().unwrap(), None);
+    }
+}
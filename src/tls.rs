@@ -0,0 +1,171 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+
+#[cfg(feature = "openssl")]
+use openssl::ssl::{self, ErrorCode, SslStream};
+
+/// Why a `TlsSession` operation did not complete.
+#[derive(Debug)]
+pub enum TlsError {
+    /// The transport has to become readable before the operation can make progress.
+    WantRead,
+    /// The transport has to become writable before the operation can make progress.
+    WantWrite,
+    /// The peer ended the session with a close_notify alert.
+    Closed,
+    /// The transport failed, or reached end of file without a close_notify alert if `None`.
+    Transport(Option<io::Error>),
+    /// The peer violated the TLS protocol.
+    Protocol(String),
+}
+
+/// A TLS session over some transport, as driven by `Secure`. Implement this to back `Secure`
+/// with a TLS library other than OpenSSL.
+///
+/// Implementations should retry internally when the library processed only non-application
+/// data, e.g. a session ticket, and report `WantRead` or `WantWrite` only when the transport
+/// itself would block.
+pub trait TlsSession {
+    /// The transport the session runs over.
+    type Stream;
+
+    /// Drives the handshake to completion, or as far as it gets without blocking. Does nothing
+    /// once the handshake has completed.
+    fn handshake(&mut self) -> Result<(), TlsError>;
+    /// Reads decrypted application data into `buf`.
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, TlsError>;
+    /// Encrypts and writes application data from `buf`, returning how much of it was taken.
+    fn write(&mut self, buf: &[u8]) -> Result<usize, TlsError>;
+    /// Flushes the transport.
+    fn flush(&mut self) -> io::Result<()>;
+    /// Sends a close_notify alert to the peer.
+    fn shutdown(&mut self) -> Result<(), TlsError>;
+    /// Returns the transport the session runs over.
+    fn get_ref(&self) -> &Self::Stream;
+
+    /// Returns how many bytes of application data the session has already decrypted and
+    /// buffered, and can be read without touching the transport.
+    fn pending(&self) -> usize {
+        0
+    }
+}
+
+impl fmt::Display for TlsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TlsError::WantRead => write!(f, "WantRead"),
+            TlsError::WantWrite => write!(f, "WantWrite"),
+            TlsError::Closed => write!(f, "TLS session closed by peer"),
+            TlsError::Transport(Some(ref e)) => write!(f, "Transport error: {}", e),
+            TlsError::Transport(None) => write!(f, "Transport closed without close_notify"),
+            TlsError::Protocol(ref msg) => write!(f, "TLS protocol error: {}", msg),
+        }
+    }
+}
+
+impl Error for TlsError {}
+
+impl From<TlsError> for io::Error {
+    fn from(e: TlsError) -> io::Error {
+        match e {
+            TlsError::WantRead | TlsError::WantWrite => {
+                io::Error::new(io::ErrorKind::WouldBlock, e)
+            }
+            TlsError::Closed | TlsError::Transport(None) => {
+                io::Error::new(io::ErrorKind::UnexpectedEof, e)
+            }
+            TlsError::Transport(Some(e)) => e,
+            TlsError::Protocol(_) => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+#[cfg(feature = "openssl")]
+impl<S: io::Read + io::Write> TlsSession for SslStream<S> {
+    type Stream = S;
+
+    fn handshake(&mut self) -> Result<(), TlsError> {
+        self.do_handshake().map_err(from_ssl_error)
+    }
+
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, TlsError> {
+        loop {
+            match self.ssl_read(buf) {
+                Ok(num_read) => return Ok(num_read),
+                // Only non-application data, such as a session ticket, was processed
+                Err(ref e) if e.code() == ErrorCode::WANT_READ && e.io_error().is_none() => {}
+                Err(e) => return Err(from_ssl_error(e)),
+            }
+        }
+    }
+
+    fn write(&mut self, buf: &[u8]) -> Result<usize, TlsError> {
+        self.ssl_write(buf).map_err(from_ssl_error)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        io::Write::flush(self)
+    }
+
+    fn shutdown(&mut self) -> Result<(), TlsError> {
+        SslStream::shutdown(self)
+            .map(|_| ())
+            .map_err(from_ssl_error)
+    }
+
+    fn get_ref(&self) -> &S {
+        SslStream::get_ref(self)
+    }
+
+    fn pending(&self) -> usize {
+        self.ssl().pending()
+    }
+}
+
+#[cfg(feature = "openssl")]
+fn from_ssl_error(e: ssl::Error) -> TlsError {
+    match e.code() {
+        ErrorCode::ZERO_RETURN => TlsError::Closed,
+        ErrorCode::WANT_READ => TlsError::WantRead,
+        ErrorCode::WANT_WRITE => TlsError::WantWrite,
+        // OpenSSL reports a SYSCALL error without an underlying `std::io::Error` when the peer
+        // closed the socket without a close_notify alert
+        ErrorCode::SYSCALL => TlsError::Transport(e.into_io_error().ok()),
+        _ => TlsError::Protocol(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn errors_map_to_the_io_error_kind_streams_report() {
+        let kind = |e: TlsError| io::Error::from(e).kind();
+        assert_eq!(kind(TlsError::WantRead), io::ErrorKind::WouldBlock);
+        assert_eq!(kind(TlsError::WantWrite), io::ErrorKind::WouldBlock);
+        assert_eq!(kind(TlsError::Closed), io::ErrorKind::UnexpectedEof);
+        assert_eq!(
+            kind(TlsError::Transport(None)),
+            io::ErrorKind::UnexpectedEof
+        );
+        assert_eq!(
+            kind(TlsError::Transport(Some(
+                io::ErrorKind::ConnectionReset.into()
+            ))),
+            io::ErrorKind::ConnectionReset
+        );
+        assert_eq!(
+            kind(TlsError::Protocol("bad record mac".to_string())),
+            io::ErrorKind::InvalidData
+        );
+    }
+}
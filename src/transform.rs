@@ -0,0 +1,102 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Payload transforms applied by streams to every frame sent or received, for envelopes such
+//! as base64, field scrubbing or application level encryption.
+//!
+//! ```ignore
+//! let mut stream = Plain::<TcpStream, SimpleFrameBuilder>::new(socket);
+//! stream.set_tx_transform(Some(Box::new(|payload: Vec<u8>| Ok(seal(&payload)))));
+//! stream.set_rx_transform(Some(Box::new(|payload: Vec<u8>| open(&payload))));
+//! ```
+
+use std::io;
+
+use crate::frame::Frame;
+
+/// Rewrites frame payloads. The transmit transform runs before a frame is encoded and the
+/// receive transform after it is decoded. Frames are rebuilt around the new payload with
+/// `Frame::with_payload`.
+///
+/// Any `FnMut(Vec<u8>) -> io::Result<Vec<u8>>` closure that is `Clone` implements this.
+pub trait PayloadTransform: Send {
+    /// Returns the transformed `payload`.
+    fn apply(&mut self, payload: Vec<u8>) -> io::Result<Vec<u8>>;
+    /// Returns a boxed copy of this transform, including any state it holds, for the stream
+    /// `try_clone` creates.
+    fn box_clone(&self) -> Box<dyn PayloadTransform>;
+}
+
+impl<F> PayloadTransform for F
+where
+    F: FnMut(Vec<u8>) -> io::Result<Vec<u8>> + Clone + Send + 'static,
+{
+    fn apply(&mut self, payload: Vec<u8>) -> io::Result<Vec<u8>> {
+        self(payload)
+    }
+
+    fn box_clone(&self) -> Box<dyn PayloadTransform> {
+        Box::new(self.clone())
+    }
+}
+
+impl Clone for Box<dyn PayloadTransform> {
+    fn clone(&self) -> Box<dyn PayloadTransform> {
+        self.box_clone()
+    }
+}
+
+/// Returns `frame` rebuilt around its payload as rewritten by `transform`.
+pub(crate) fn transform_frame(
+    transform: &mut dyn PayloadTransform,
+    frame: &dyn Frame,
+) -> io::Result<Box<dyn Frame>> {
+    let payload = transform.apply(frame.payload())?;
+    frame.with_payload(&payload[..]).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            format!("{} can not carry a transformed payload", frame.kind()),
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::{PaddedFrame, PaddingPolicy, SimpleFrame};
+
+    #[test]
+    fn frames_are_rebuilt_around_the_new_payload() {
+        let mut reverse = |mut payload: Vec<u8>| {
+            payload.reverse();
+            Ok(payload)
+        };
+        let frame = transform_frame(&mut reverse, &SimpleFrame::new(b"abc")).unwrap();
+        assert_eq!(frame.kind(), "SimpleFrame");
+        assert_eq!(frame.payload(), b"cba");
+
+        let padded = PaddedFrame::new(&SimpleFrame::new(b"abc"), PaddingPolicy::None);
+        let e = transform_frame(&mut reverse, &padded).unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::Unsupported);
+    }
+
+    #[test]
+    fn clones_carry_their_own_state() {
+        let mut num_calls = 0u8;
+        let mut count: Box<dyn PayloadTransform> = Box::new(move |mut payload: Vec<u8>| {
+            num_calls += 1;
+            payload.push(num_calls);
+            Ok(payload)
+        });
+        assert_eq!(count.apply(vec![]).unwrap(), [1]);
+
+        let mut copy = count.clone();
+        assert_eq!(copy.apply(vec![]).unwrap(), [2]);
+        assert_eq!(copy.apply(vec![]).unwrap(), [3]);
+        assert_eq!(count.apply(vec![]).unwrap(), [2]);
+    }
+}
@@ -0,0 +1,169 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Notifies applications when a stream's buffers fill up and drain, for flow control across
+//! layers.
+//!
+//! ```ignore
+//! let producer = Arc::new(AtomicBool::new(true));
+//! let running = producer.clone();
+//! stream.set_tx_watermarks(Some(Watermarks::new(1 << 20, 64 << 10, move |mark, _| {
+//!     // Pause producing once a megabyte is waiting to be written, resume below 64KiB
+//!     running.store(mark == Watermark::Low, Ordering::Release);
+//! })));
+//! ```
+
+use std::error::Error;
+use std::fmt;
+use std::sync::Arc;
+
+/// Which watermark a buffer crossed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Watermark {
+    /// The buffer grew to the high watermark or beyond.
+    High,
+    /// The buffer drained to the low watermark or below, after crossing the high watermark.
+    Low,
+}
+
+/// High and low watermarks for one of a stream's buffers, with the callback told when they
+/// are crossed. The callback receives the watermark and the number of bytes buffered.
+///
+/// Crossings alternate, so the callback sees `High` once the buffer reaches `high` bytes, then
+/// `Low` once it drains to `low`, then `High` again, and so on. The receive buffer counts
+/// bytes read but not yet decoded into frames. The send buffer counts bytes of frames queued
+/// but not yet written.
+#[derive(Clone)]
+pub struct Watermarks {
+    high: usize,
+    low: usize,
+    callback: Arc<dyn Fn(Watermark, usize) + Send + Sync>,
+}
+
+/// Error carried by the `Error::Io` a non-blocking send fails with when the stream's
+/// send queue is at its high-water mark, set with `set_tx_high_water`. The frame was not
+/// queued. Retrieve it with `get_ref()` and `downcast_ref::<TxQueueFull>()`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TxQueueFull {
+    /// Bytes of queued frames not yet written.
+    pub pending: usize,
+    pub high_water: usize,
+}
+
+/// Tracks which side of its watermarks a buffer is on.
+#[derive(Clone, Debug)]
+pub(crate) struct WatermarkMonitor {
+    marks: Watermarks,
+    above: bool,
+}
+
+impl Watermarks {
+    /// Creates watermarks calling `callback` when the buffer reaches `high` bytes and when it
+    /// drains back to `low`. `low` is capped at `high`.
+    pub fn new<F>(high: usize, low: usize, callback: F) -> Watermarks
+    where
+        F: Fn(Watermark, usize) + Send + Sync + 'static,
+    {
+        Watermarks {
+            high,
+            low: low.min(high),
+            callback: Arc::new(callback),
+        }
+    }
+
+    pub fn high(&self) -> usize {
+        self.high
+    }
+
+    pub fn low(&self) -> usize {
+        self.low
+    }
+}
+
+impl fmt::Debug for Watermarks {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Watermarks")
+            .field("high", &self.high)
+            .field("low", &self.low)
+            .finish()
+    }
+}
+
+impl WatermarkMonitor {
+    pub(crate) fn new(marks: Watermarks) -> WatermarkMonitor {
+        WatermarkMonitor {
+            marks,
+            above: false,
+        }
+    }
+
+    /// Calls the callback if `buffered` bytes crosses a watermark.
+    pub(crate) fn update(&mut self, buffered: usize) {
+        let mark = if !self.above && buffered >= self.marks.high {
+            Watermark::High
+        } else if self.above && buffered <= self.marks.low {
+            Watermark::Low
+        } else {
+            return;
+        };
+
+        trace!(
+            "{:?} watermark crossed with {} byte(s) buffered",
+            mark,
+            buffered
+        );
+        self.above = mark == Watermark::High;
+        (self.marks.callback)(mark, buffered);
+    }
+}
+
+impl fmt::Display for TxQueueFull {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Send queue full with {} byte(s) pending, high-water mark is {}",
+            self.pending, self.high_water
+        )
+    }
+}
+
+impl Error for TxQueueFull {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    #[test]
+    fn crossings_alternate() {
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let log = seen.clone();
+        let marks = Watermarks::new(100, 10, move |mark, buffered| {
+            log.lock().unwrap().push((mark, buffered));
+        });
+        let mut monitor = WatermarkMonitor::new(marks);
+
+        for buffered in [50, 100, 150, 50, 10, 5, 99, 120] {
+            monitor.update(buffered);
+        }
+        assert_eq!(
+            *seen.lock().unwrap(),
+            [
+                (Watermark::High, 100),
+                (Watermark::Low, 10),
+                (Watermark::High, 120)
+            ]
+        );
+    }
+
+    #[test]
+    fn low_is_capped_at_high() {
+        let marks = Watermarks::new(10, 20, |_, _| {});
+        assert_eq!(marks.high(), 10);
+        assert_eq!(marks.low(), 10);
+    }
+}
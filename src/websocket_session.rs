@@ -0,0 +1,509 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+//! Handles websocket control frames on behalf of the application, so it only receives data.
+//!
+//! ```ignore
+//! let stream = Plain::<TcpStream, WebSocketMessageBuilder>::new(socket);
+//! let mut session = WebSocketSession::new(stream);
+//!
+//! // Pings are answered and the close handshake completed while receiving
+//! let message = session.b_recv()?;
+//! while let Some(event) = session.next_event() {
+//!     println!("{:?}", event);
+//! }
+//! ```
+
+use std::collections::VecDeque;
+use std::io;
+use std::time::Instant;
+
+use crate::close::CloseReason;
+use crate::frame::{Frame, FrameType, OpType, WebSocketFrame};
+#[cfg(feature = "flate2")]
+use crate::permessage_deflate::{DeflateCodec, PerMessageDeflate};
+use crate::{Blocking, Error, NonBlocking};
+
+/// Close code sent when the peer breaks the protocol, as defined by RFC 6455.
+pub const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+
+/// Most control frame events kept for `next_event`. Older events are dropped first.
+const MAX_EVENTS: usize = 128;
+
+/// A control frame received by a `WebSocketSession`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum WebSocketEvent {
+    /// The peer sent a ping, which was answered with a pong carrying the same payload.
+    Ping(Vec<u8>),
+    /// The peer sent a pong.
+    Pong(Vec<u8>),
+    /// The peer sent a close frame, with the status code and reason it carried, if any.
+    Close { code: Option<u16>, reason: String },
+}
+
+/// Where a `WebSocketSession` is in the close handshake.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WebSocketState {
+    /// Data flows both ways.
+    Open,
+    /// A close frame was sent and the peer's reply has not arrived yet. Data received in the
+    /// meantime is still returned, but nothing more can be sent.
+    CloseSent,
+    /// Close frames were exchanged in both directions.
+    Closed,
+}
+
+/// Wraps a stream carrying websocket frames, answering pings, completing the close handshake
+/// and reporting control frames as `WebSocketEvent`s. Receiving from the session only ever
+/// returns data frames.
+///
+/// Frames that are not `WebSocketFrame`s are passed through as data.
+pub struct WebSocketSession<S> {
+    stream: S,
+    state: WebSocketState,
+    events: VecDeque<WebSocketEvent>,
+    /// Whether control frames sent are masked, as they must be by clients.
+    mask: bool,
+    /// Whether out of order fragments close the connection.
+    strict: bool,
+    /// Whether a fragmented message has started and not yet finished.
+    fragmented: bool,
+    /// Compresses messages, once permessage-deflate was negotiated.
+    #[cfg(feature = "flate2")]
+    deflate: Option<DeflateCodec>,
+}
+
+impl<S> WebSocketSession<S>
+where
+    S: Blocking + NonBlocking,
+{
+    /// Creates a session over an open websocket connection, on the server side.
+    pub fn new(stream: S) -> WebSocketSession<S> {
+        WebSocketSession {
+            stream,
+            state: WebSocketState::Open,
+            events: VecDeque::new(),
+            mask: false,
+            strict: false,
+            fragmented: false,
+            #[cfg(feature = "flate2")]
+            deflate: None,
+        }
+    }
+
+    /// Creates a session over an open websocket connection, on the client side. Control
+    /// frames the session sends are masked, and data frames should be created with
+    /// `WebSocketFrame::new_masked`.
+    pub fn new_client(stream: S) -> WebSocketSession<S> {
+        WebSocketSession {
+            mask: true,
+            ..WebSocketSession::new(stream)
+        }
+    }
+
+    /// Sets whether fragments received out of order are a protocol error. In strict mode, a
+    /// `Continuation` frame without an unfinished `Text` or `Binary` message before it, or a
+    /// new `Text` or `Binary` frame while one is unfinished, starts the close handshake with
+    /// `CLOSE_PROTOCOL_ERROR` and fails the receive with `CloseReason::ProtocolError`.
+    /// Otherwise such frames are returned like any other. Off by default.
+    pub fn set_strict(&mut self, strict: bool) {
+        self.strict = strict;
+    }
+
+    /// Compresses the `Text` and `Binary` messages sent, and decompresses the messages
+    /// received with the RSV1 bit set, with the permessage-deflate parameters negotiated in
+    /// the opening handshake. `None` turns compression off.
+    ///
+    /// Only unfragmented messages are compressed. Received messages have to arrive whole, e.g.
+    /// through a `WebSocketMessageBuilder`, as compressed fragments are a protocol error.
+    #[cfg(feature = "flate2")]
+    pub fn set_permessage_deflate(&mut self, params: Option<PerMessageDeflate>) {
+        self.deflate = params.map(|params| match self.mask {
+            true => DeflateCodec::client(&params),
+            false => DeflateCodec::server(&params),
+        });
+    }
+
+    /// Returns where the session is in the close handshake.
+    pub fn state(&self) -> WebSocketState {
+        self.state
+    }
+
+    /// Removes and returns the oldest control frame event not yet retrieved.
+    pub fn next_event(&mut self) -> Option<WebSocketEvent> {
+        self.events.pop_front()
+    }
+
+    /// Starts the close handshake by sending a close frame with `code` and `reason`. Sending
+    /// data fails from now on, and receiving ends once the peer replies. Does nothing if a
+    /// close frame was already sent.
+    pub fn close(&mut self, code: u16, reason: &str) -> Result<(), Error> {
+        if self.state != WebSocketState::Open {
+            return Ok(());
+        }
+
+        let mut payload = Vec::<u8>::with_capacity(2 + reason.len());
+        payload.extend_from_slice(&code.to_be_bytes());
+        payload.extend_from_slice(reason.as_bytes());
+        self.state = WebSocketState::CloseSent;
+        self.send_control(OpType::Close, &payload[..])
+    }
+
+    /// Returns a reference to the wrapped stream.
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    /// Returns a mutable reference to the wrapped stream. Frames sent or received through it
+    /// bypass the session.
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Handles `frame` if it is a control frame, otherwise returns it.
+    fn handle(&mut self, frame: Box<dyn Frame>) -> Result<Option<Box<dyn Frame>>, Error> {
+        let (op_type, fin) = match frame.downcast_ref::<WebSocketFrame>() {
+            Some(ws_frame) => (ws_frame.op_type(), ws_frame.is_final()),
+            None => return Ok(Some(frame)),
+        };
+
+        let event = match op_type {
+            OpType::Ping => {
+                let payload = frame.into_payload();
+                if self.state == WebSocketState::Open {
+                    self.send_control(OpType::Pong, &payload[..])?;
+                }
+                WebSocketEvent::Ping(payload)
+            }
+            OpType::Pong => WebSocketEvent::Pong(frame.into_payload()),
+            OpType::Close => {
+                let payload = frame.into_payload();
+                if self.state == WebSocketState::Open {
+                    // Echo the status code back, as RFC 6455 suggests
+                    let code_len = payload.len().min(2);
+                    self.send_control(OpType::Close, &payload[..code_len])?;
+                }
+                self.state = WebSocketState::Closed;
+                close_event(&payload[..])
+            }
+            OpType::Continuation | OpType::Text | OpType::Binary => {
+                self.check_order(op_type, fin)?;
+                #[cfg(feature = "flate2")]
+                let frame = self.inflate(frame)?;
+                return Ok(Some(frame));
+            }
+        };
+
+        debug!("WebSocket control frame received: {:?}", event);
+        if self.events.len() == MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+        Ok(None)
+    }
+
+    /// Tracks fragmentation across data frames, failing in strict mode if `op_type` can not
+    /// follow the frames before it.
+    fn check_order(&mut self, op_type: OpType, fin: bool) -> Result<(), Error> {
+        let in_order = (op_type == OpType::Continuation) == self.fragmented;
+        self.fragmented = !fin;
+        if in_order || !self.strict {
+            return Ok(());
+        }
+
+        error!("WebSocket {:?} frame received out of order", op_type);
+        self.fail_protocol()
+    }
+
+    /// Starts the close handshake with `CLOSE_PROTOCOL_ERROR`, and fails with
+    /// `CloseReason::ProtocolError`.
+    fn fail_protocol<T>(&mut self) -> Result<T, Error> {
+        if self.state == WebSocketState::Open {
+            self.state = WebSocketState::CloseSent;
+            self.send_control(OpType::Close, &CLOSE_PROTOCOL_ERROR.to_be_bytes()[..])?;
+        }
+        Err(CloseReason::ProtocolError.to_io_error().into())
+    }
+
+    /// Decompresses `frame` if it carries a compressed message.
+    #[cfg(feature = "flate2")]
+    fn inflate(&mut self, frame: Box<dyn Frame>) -> Result<Box<dyn Frame>, Error> {
+        let (op_type, fin) = match frame.downcast_ref::<WebSocketFrame>() {
+            Some(ws_frame) if ws_frame.is_compressed() => (ws_frame.op_type(), ws_frame.is_final()),
+            _ => return Ok(frame),
+        };
+
+        let codec = match self.deflate {
+            Some(ref mut codec) if fin && op_type != OpType::Continuation => codec,
+            Some(_) => {
+                error!("WebSocket compressed message received in fragments");
+                return self.fail_protocol();
+            }
+            None => {
+                error!("WebSocket compressed message received without permessage-deflate");
+                return self.fail_protocol();
+            }
+        };
+        match codec.decompress(&frame.payload_ref()) {
+            Ok(payload) => Ok(Box::new(WebSocketFrame::new(
+                &payload[..],
+                FrameType::Data,
+                op_type,
+            ))),
+            Err(e) => {
+                error!("WebSocket message failed to decompress: {}", e);
+                self.fail_protocol()
+            }
+        }
+    }
+
+    /// Returns `frame` compressed, if it is a whole `Text` or `Binary` message and
+    /// permessage-deflate is on.
+    #[cfg(feature = "flate2")]
+    fn deflate(&mut self, frame: &dyn Frame) -> io::Result<Option<WebSocketFrame>> {
+        let (codec, ws_frame) = match (&mut self.deflate, frame.downcast_ref::<WebSocketFrame>()) {
+            (Some(codec), Some(ws_frame)) => (codec, ws_frame),
+            _ => return Ok(None),
+        };
+        let op_type = ws_frame.op_type();
+        if !ws_frame.is_final() || !matches!(op_type, OpType::Text | OpType::Binary) {
+            return Ok(None);
+        }
+
+        let payload = codec.compress(&ws_frame.payload_ref())?;
+        let mut compressed =
+            WebSocketFrame::new(&payload[..], FrameType::Data, op_type).with_compressed(true);
+        if ws_frame.is_masked() {
+            compressed = compressed.masked();
+        }
+        Ok(Some(compressed))
+    }
+
+    /// Sends `frame` with `send`, compressing it first if it should be.
+    fn send_with<F>(&mut self, frame: &dyn Frame, send: F) -> Result<(), Error>
+    where
+        F: FnOnce(&mut S, &dyn Frame) -> Result<(), Error>,
+    {
+        self.ensure_can_send()?;
+
+        #[cfg(feature = "flate2")]
+        if let Some(compressed) = self.deflate(frame)? {
+            let result = send(&mut self.stream, &compressed);
+            if let Err(ref e) = result {
+                // The peer never sees this message, so later ones can not refer to it
+                if e.kind() != io::ErrorKind::WouldBlock {
+                    if let Some(ref mut codec) = self.deflate {
+                        codec.reset_compressor();
+                    }
+                }
+            }
+            return result;
+        }
+
+        send(&mut self.stream, frame)
+    }
+
+    /// Sends a control frame, leaving it queued if the stream would block.
+    fn send_control(&mut self, op_type: OpType, payload: &[u8]) -> Result<(), Error> {
+        let mut frame = WebSocketFrame::new(payload, FrameType::Control, op_type);
+        if self.mask {
+            frame = frame.masked();
+        }
+        match self.stream.nb_send(&frame) {
+            Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => Ok(()),
+            result => result,
+        }
+    }
+
+    fn ensure_can_send(&self) -> io::Result<()> {
+        match self.state {
+            WebSocketState::Open => Ok(()),
+            WebSocketState::CloseSent | WebSocketState::Closed => {
+                Err(CloseReason::LocalShutdown.to_io_error())
+            }
+        }
+    }
+
+    fn ensure_can_recv(&self) -> io::Result<()> {
+        match self.state {
+            WebSocketState::Open | WebSocketState::CloseSent => Ok(()),
+            WebSocketState::Closed => Err(CloseReason::PeerClosed.to_io_error()),
+        }
+    }
+}
+
+impl<S> Blocking for WebSocketSession<S>
+where
+    S: Blocking + NonBlocking,
+{
+    fn b_recv(&mut self) -> Result<Box<dyn Frame>, Error> {
+        loop {
+            self.ensure_can_recv()?;
+            let frame = self.stream.b_recv()?;
+            if let Some(frame) = self.handle(frame)? {
+                return Ok(frame);
+            }
+        }
+    }
+
+    fn b_send(&mut self, frame: &dyn Frame) -> Result<(), Error> {
+        self.send_with(frame, |stream, frame| stream.b_send(frame))
+    }
+}
+
+impl<S> NonBlocking for WebSocketSession<S>
+where
+    S: Blocking + NonBlocking,
+{
+    fn nb_recv(&mut self) -> Result<Vec<Box<dyn Frame>>, Error> {
+        self.ensure_can_recv()?;
+        let received = self.stream.nb_recv()?;
+        let mut frames = Vec::<Box<dyn Frame>>::with_capacity(received.len());
+        for frame in received {
+            if let Some(frame) = self.handle(frame)? {
+                frames.push(frame);
+            }
+        }
+
+        if frames.is_empty() {
+            self.ensure_can_recv()?;
+            return Err(io::ErrorKind::WouldBlock.into());
+        }
+        Ok(frames)
+    }
+
+    fn nb_send(&mut self, frame: &dyn Frame) -> Result<(), Error> {
+        self.send_with(frame, |stream, frame| stream.nb_send(frame))
+    }
+
+    fn nb_recv_retry_at(&self) -> Option<Instant> {
+        self.stream.nb_recv_retry_at()
+    }
+
+    fn nb_flush(&mut self) -> Result<bool, Error> {
+        // Queued close frames still need flushing after the session stops sending
+        self.stream.nb_flush()
+    }
+}
+
+fn close_event(payload: &[u8]) -> WebSocketEvent {
+    if payload.len() < 2 {
+        return WebSocketEvent::Close {
+            code: None,
+            reason: String::new(),
+        };
+    }
+
+    WebSocketEvent::Close {
+        code: Some(u16::from_be_bytes([payload[0], payload[1]])),
+        reason: String::from_utf8_lossy(&payload[2..]).into_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::duplex::Duplex;
+    use crate::frame::WebSocketFrameBuilder;
+    use crate::Plain;
+
+    type Stream = Plain<Duplex, WebSocketFrameBuilder>;
+
+    fn session() -> (WebSocketSession<Stream>, Stream) {
+        let (local, remote) = Plain::pair();
+        (WebSocketSession::new(local), remote)
+    }
+
+    fn ws_frame(frame: &dyn Frame) -> &WebSocketFrame {
+        frame.downcast_ref::<WebSocketFrame>().unwrap()
+    }
+
+    #[test]
+    fn pings_are_answered_and_only_data_returned() {
+        let (mut session, mut remote) = session();
+        remote
+            .nb_send(&WebSocketFrame::new(
+                b"hi",
+                FrameType::Control,
+                OpType::Ping,
+            ))
+            .unwrap();
+        remote
+            .nb_send(&WebSocketFrame::new(b"data", FrameType::Data, OpType::Text))
+            .unwrap();
+
+        let frames = session.nb_recv().unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].payload(), b"data");
+        assert_eq!(
+            session.next_event(),
+            Some(WebSocketEvent::Ping(b"hi".to_vec()))
+        );
+        assert_eq!(session.next_event(), None);
+
+        let pong = remote.nb_recv().unwrap().remove(0);
+        assert_eq!(ws_frame(&*pong).op_type(), OpType::Pong);
+        assert_eq!(pong.payload(), b"hi");
+    }
+
+    #[test]
+    fn closing_waits_for_the_peer_reply() {
+        let (mut session, mut remote) = session();
+        session.close(1000, "bye").unwrap();
+        assert_eq!(session.state(), WebSocketState::CloseSent);
+        let data = WebSocketFrame::new(b"late", FrameType::Data, OpType::Text);
+        assert!(session.nb_send(&data).is_err());
+
+        let close = remote.nb_recv().unwrap().remove(0);
+        assert_eq!(ws_frame(&*close).op_type(), OpType::Close);
+        assert_eq!(close.payload(), b"\x03\xe8bye");
+        remote
+            .nb_send(&WebSocketFrame::new(
+                b"\x03\xe8",
+                FrameType::Control,
+                OpType::Close,
+            ))
+            .unwrap();
+
+        let e = session.nb_recv().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(session.state(), WebSocketState::Closed);
+        assert_eq!(
+            session.next_event(),
+            Some(WebSocketEvent::Close {
+                code: Some(1000),
+                reason: String::new()
+            })
+        );
+    }
+
+    #[test]
+    fn strict_sessions_close_on_out_of_order_fragments() {
+        let (mut session, mut remote) = session();
+        session.set_strict(true);
+        remote
+            .nb_send(&WebSocketFrame::fragment(
+                b"orphan",
+                FrameType::Data,
+                OpType::Continuation,
+                true,
+            ))
+            .unwrap();
+
+        let e = session.nb_recv().unwrap_err();
+        assert_eq!(e.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(session.state(), WebSocketState::CloseSent);
+
+        let close = remote.nb_recv().unwrap().remove(0);
+        assert_eq!(close.payload(), CLOSE_PROTOCOL_ERROR.to_be_bytes());
+    }
+}
@@ -0,0 +1,219 @@
+// Copyright 2026 Nathan Sizemore <nathanrsizemore@gmail.com>
+//
+// This Source Code Form is subject to the terms of the
+// Mozilla Public License, v. 2.0. If a copy of the MPL was not
+// distributed with this file, You can obtain one at
+// http://mozilla.org/MPL/2.0/.
+
+use std::fmt::Write;
+use std::io::IoSlice;
+use std::sync::atomic::{AtomicU32, AtomicU64, AtomicU8, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::frame::Frame;
+
+/// Target wire log records are written to, so they can be filtered separately.
+pub const WIRE_LOG_TARGET: &str = "simple_stream::wire";
+
+/// How much of each logged frame is hex dumped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WireLogMode {
+    /// Nothing is logged.
+    Off,
+    /// The first passed number of bytes of each frame, which covers its header.
+    Headers(usize),
+    /// Whole frames, up to `WireLogConfig::max_bytes`.
+    Full,
+}
+
+/// What a stream's wire log records.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct WireLogConfig {
+    pub mode: WireLogMode,
+    /// Logs one in every this many frames, counting both directions. Zero or one logs every
+    /// frame.
+    pub sample_every: u32,
+    /// Most bytes of a single frame dumped, whatever the mode.
+    pub max_bytes: usize,
+}
+
+/// Handle to the wire log of a stream, returned by `wire_log` on `Plain` and `Secure`.
+///
+/// Clones control the same stream, so a handle can be kept by an admin thread to turn
+/// logging on and off while the stream is in use. Records are written at the `info` level
+/// to `WIRE_LOG_TARGET`.
+#[derive(Clone, Debug, Default)]
+pub struct WireLog {
+    inner: Arc<WireLogState>,
+}
+
+#[derive(Debug, Default)]
+struct WireLogState {
+    /// 0 when off, 1 for headers, 2 for full frames.
+    mode: AtomicU8,
+    header_len: AtomicUsize,
+    sample_every: AtomicU32,
+    max_bytes: AtomicUsize,
+    num_frames: AtomicU64,
+}
+
+impl WireLogConfig {
+    /// Logs every frame in full, up to `max_bytes` each.
+    pub fn full(max_bytes: usize) -> WireLogConfig {
+        WireLogConfig {
+            mode: WireLogMode::Full,
+            sample_every: 1,
+            max_bytes,
+        }
+    }
+}
+
+impl Default for WireLogConfig {
+    fn default() -> WireLogConfig {
+        WireLogConfig {
+            mode: WireLogMode::Off,
+            sample_every: 1,
+            max_bytes: 256,
+        }
+    }
+}
+
+impl WireLog {
+    /// Applies `config` to every frame sent or received from now on.
+    pub fn set(&self, config: WireLogConfig) {
+        let state = &self.inner;
+        state
+            .sample_every
+            .store(config.sample_every, Ordering::Relaxed);
+        state.max_bytes.store(config.max_bytes, Ordering::Relaxed);
+        let mode = match config.mode {
+            WireLogMode::Off => 0,
+            WireLogMode::Headers(header_len) => {
+                state.header_len.store(header_len, Ordering::Relaxed);
+                1
+            }
+            WireLogMode::Full => 2,
+        };
+        state.mode.store(mode, Ordering::Release);
+    }
+
+    /// Returns the configuration currently applied.
+    pub fn config(&self) -> WireLogConfig {
+        let state = &self.inner;
+        let mode = match state.mode.load(Ordering::Acquire) {
+            0 => WireLogMode::Off,
+            1 => WireLogMode::Headers(state.header_len.load(Ordering::Relaxed)),
+            _ => WireLogMode::Full,
+        };
+
+        WireLogConfig {
+            mode,
+            sample_every: state.sample_every.load(Ordering::Relaxed),
+            max_bytes: state.max_bytes.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Turns logging off.
+    pub fn disable(&self) {
+        self.inner.mode.store(0, Ordering::Release);
+    }
+
+    pub(crate) fn sent(&self, buf: &[u8]) {
+        if let Some(len) = self.sample() {
+            log_bytes("Sent", "frame", buf, len);
+        }
+    }
+
+    pub(crate) fn sent_vectored(&self, bufs: &[IoSlice]) {
+        if let Some(len) = self.sample() {
+            let bytes: Vec<u8> = bufs.iter().flat_map(|buf| buf.iter()).copied().collect();
+            log_bytes("Sent", "frame", &bytes[..], len);
+        }
+    }
+
+    pub(crate) fn received(&self, frame: &dyn Frame) {
+        if let Some(len) = self.sample() {
+            log_bytes("Received", frame.kind(), &frame.to_bytes()[..], len);
+        }
+    }
+
+    /// Counts a frame and returns how many of its bytes to dump, if it is to be logged.
+    fn sample(&self) -> Option<usize> {
+        let state = &self.inner;
+        let mode = state.mode.load(Ordering::Acquire);
+        if mode == 0 || !log_enabled!(target: WIRE_LOG_TARGET, log::Level::Info) {
+            return None;
+        }
+
+        let sample_every = state.sample_every.load(Ordering::Relaxed).max(1) as u64;
+        let num_frames = state.num_frames.fetch_add(1, Ordering::Relaxed);
+        if !num_frames.is_multiple_of(sample_every) {
+            return None;
+        }
+
+        let max_bytes = state.max_bytes.load(Ordering::Relaxed);
+        match mode {
+            1 => Some(state.header_len.load(Ordering::Relaxed).min(max_bytes)),
+            _ => Some(max_bytes),
+        }
+    }
+}
+
+fn log_bytes(direction: &str, kind: &str, buf: &[u8], max_len: usize) {
+    let shown = &buf[..buf.len().min(max_len)];
+    let mut hex = String::with_capacity(shown.len() * 3);
+    for byte in shown {
+        let _ = write!(hex, "{:02x} ", byte);
+    }
+
+    info!(
+        target: WIRE_LOG_TARGET,
+        "{} {} ({} bytes): {}{}",
+        direction,
+        kind,
+        buf.len(),
+        hex.trim_end(),
+        if shown.len() < buf.len() { " ..." } else { "" }
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_share_the_configuration() {
+        let log = WireLog::default();
+        let admin = log.clone();
+        assert_eq!(log.config().mode, WireLogMode::Off);
+
+        admin.set(WireLogConfig {
+            mode: WireLogMode::Headers(4),
+            sample_every: 10,
+            max_bytes: 2,
+        });
+        assert_eq!(
+            log.config(),
+            WireLogConfig {
+                mode: WireLogMode::Headers(4),
+                sample_every: 10,
+                max_bytes: 2,
+            }
+        );
+
+        admin.set(WireLogConfig::full(64));
+        assert_eq!(log.config(), WireLogConfig::full(64));
+
+        admin.disable();
+        assert_eq!(log.config().mode, WireLogMode::Off);
+        assert_eq!(log.config().max_bytes, 64);
+    }
+
+    #[test]
+    fn nothing_is_sampled_while_off() {
+        let log = WireLog::default();
+        log.sent(&[1, 2, 3]);
+        assert_eq!(log.sample(), None);
+        assert_eq!(log.inner.num_frames.load(Ordering::Relaxed), 0);
+    }
+}